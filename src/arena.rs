@@ -0,0 +1,616 @@
+// An arena-backed form of the AST. Every node lives in one `Vec`
+// owned by an `Arena` and refers to its children by `NodeId`, and
+// every child list is a slice of one shared `Vec<NodeId>`, so nodes
+// and child lists take no allocation of their own, and moving a
+// subtree around is copying an index. The type of a node in the node
+// table is interned as the table's `&'static str`; names, operators,
+// raw text and other strings still take one allocation each.
+//
+// The parser does not build into an arena: a tree is parsed as
+// `node::Node`s first and then lowered with `Arena::fromNode`, which
+// moves its strings rather than copying them and frees the rest of
+// the tree as it goes. Parsing costs the same allocations as `parse`
+// does; the arena pays off in the analyses that run on the tree
+// afterwards.
+//
+// A `NodeId` is stable for the lifetime of its arena, which makes it a
+// cheap key for side tables (inferred types, scopes, taint, ...):
+// see `SideTable`. Ids are assigned in pre-order, so a parent always
+// has a smaller id than its descendants.
+
+use crate::ast;
+use crate::locutil;
+use crate::node;
+use crate::options;
+
+use std::borrow::Cow;
+use std::ops;
+
+/// The index of a node in an `Arena`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A list of children, stored as a range of `Arena.lists`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeList {
+    start: u32,
+    len: u32,
+}
+
+impl NodeList {
+    pub fn len(self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.len == 0
+    }
+}
+
+/// A node stored in an `Arena`. It has the same fields as `node::Node`,
+/// with children replaced by ids. List fields that are absent on the
+/// original node are `None`, so that converting back is lossless.
+#[derive(Clone, Debug, Default)]
+pub struct ArenaNode {
+    /// Borrowed from the node table for the types in it; see
+    /// `internType`.
+    pub r#type: Cow<'static, str>,
+    pub start: usize,
+    pub end: usize,
+    pub loc: Option<locutil::SourceLocation>,
+    pub sourceFile: Option<String>,
+    pub range: Option<(usize, usize)>,
+    pub parent: Option<NodeId>,
+
+    pub sourceType: Option<options::SourceType>,
+    pub operator: Option<String>,
+    pub name: Option<String>,
+    pub raw: Option<String>,
+    pub kind: Option<String>,
+    pub directive: Option<String>,
+    pub cooked: Option<String>,
+    pub prefix: Option<bool>,
+    pub computed: Option<bool>,
+    pub method: Option<bool>,
+    pub shorthand: Option<bool>,
+    pub generator: Option<bool>,
+    pub r#async: Option<bool>,
+    pub r#static: Option<bool>,
+    pub r#await: Option<bool>,
+    pub delegate: Option<bool>,
+    pub tail: Option<bool>,
+
+    pub label: Option<NodeId>,
+    pub id: Option<NodeId>,
+    pub meta: Option<NodeId>,
+    pub key: Option<NodeId>,
+    pub imported: Option<NodeId>,
+    pub tag: Option<NodeId>,
+    pub object: Option<NodeId>,
+    pub property: Option<NodeId>,
+    pub callee: Option<NodeId>,
+    pub arguments: Option<NodeList>,
+    pub superClass: Option<NodeId>,
+    pub params: Option<NodeList>,
+    pub specifiers: Option<NodeList>,
+    pub local: Option<NodeId>,
+    pub exported: Option<NodeId>,
+    pub declaration: Option<NodeId>,
+    pub source: Option<NodeId>,
+    pub discriminant: Option<NodeId>,
+    pub cases: Option<NodeList>,
+    pub block: Option<NodeId>,
+    pub handler: Option<NodeId>,
+    pub param: Option<NodeId>,
+    pub finalizer: Option<NodeId>,
+    pub test: Option<NodeId>,
+    pub left: Option<NodeId>,
+    pub argument: Option<NodeId>,
+    pub expression: Option<NodeId>,
+    pub expressions: NodeList,
+    pub properties: Option<NodeList>,
+    pub elements: Option<NodeList>,
+    pub declarations: Option<NodeList>,
+    pub right: Option<NodeId>,
+    pub value: Option<NodeId>,
+    pub init: Option<NodeId>,
+    pub consequent: Option<NodeId>,
+    pub consequents: Option<NodeList>,
+    pub alternate: Option<NodeId>,
+    pub update: Option<NodeId>,
+    pub quasi: Option<NodeId>,
+    pub quasis: Option<NodeList>,
+    pub body: Option<NodeList>,
+}
+
+#[derive(Default)]
+pub struct Arena {
+    nodes: Vec<ArenaNode>,
+    lists: Vec<NodeId>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Arena::default()
+    }
+
+    /// An arena with room for `nodes` nodes, to avoid regrowing it
+    /// while lowering a tree of known size.
+    pub fn withCapacity(nodes: usize) -> Self {
+        Arena {
+            nodes: Vec::with_capacity(nodes),
+            lists: Vec::with_capacity(nodes),
+        }
+    }
+
+    /// Build an arena from a parsed tree, returning the id of its root.
+    /// The tree is consumed: names, operators and other strings are
+    /// moved into the arena rather than copied.
+    pub fn fromNode(root: node::Node) -> (Self, NodeId) {
+        let mut arena = Arena::new();
+        let id = arena.lower(root, None);
+        (arena, id)
+    }
+
+    /// Move `node` and its subtree into the arena.
+    pub fn lower(&mut self, mut node: node::Node, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        let (loc, sourceFile, range) = node.takeLocation();
+        self.nodes.push(ArenaNode {
+            r#type: internType(std::mem::take(&mut node.r#type)),
+            start: node.start,
+            end: node.end,
+            loc,
+            sourceFile,
+            range,
+            parent,
+            sourceType: node.sourceType.take(),
+            operator: node.operator.take(),
+            name: node.name.take(),
+            raw: node.raw.take(),
+            kind: node.kind.take(),
+            directive: node.directive.take(),
+            cooked: node.cooked.take(),
+            prefix: node.prefix,
+            computed: node.computed,
+            method: node.method,
+            shorthand: node.shorthand,
+            generator: node.generator,
+            r#async: node.r#async,
+            r#static: node.r#static,
+            r#await: node.r#await,
+            delegate: node.delegate,
+            tail: node.tail,
+            ..ArenaNode::default()
+        });
+
+        // Each child is lowered, with its whole subtree, before the
+        // next, so that ids follow a pre-order of the tree. Children are
+        // taken field by field, which is not always source order: use
+        // `children` for that.
+        let parent = Some(id);
+        let child = |arena: &mut Self, child: Option<Box<node::Node>>| {
+            child.map(|child| arena.lower(*child, parent))
+        };
+        let list = |arena: &mut Self, children: Vec<node::Node>| {
+            // The slots of the list come first; each child's subtree
+            // puts its own lists after them.
+            let start = arena.lists.len();
+            let len = children.len();
+            arena.lists.resize(start + len, NodeId(0));
+            for (i, child) in children.into_iter().enumerate() {
+                arena.lists[start + i] = arena.lower(child, parent);
+            }
+            NodeList {
+                start: start as u32,
+                len: len as u32,
+            }
+        };
+        let optList = |arena: &mut Self, children: Option<Vec<node::Node>>| {
+            children.map(|children| list(arena, children))
+        };
+
+        let label = child(self, node.label);
+        let nodeId = child(self, node.id);
+        let meta = child(self, node.meta);
+        let key = child(self, node.key);
+        let imported = child(self, node.imported);
+        let tag = child(self, node.tag);
+        let object = child(self, node.object);
+        let property = child(self, node.property);
+        let callee = child(self, node.callee);
+        let arguments = optList(self, node.arguments);
+        let superClass = child(self, node.superClass);
+        let params = optList(self, node.params);
+        let specifiers = optList(self, node.specifiers);
+        let local = child(self, node.local);
+        let exported = child(self, node.exported);
+        let declaration = child(self, node.declaration);
+        let source = child(self, node.source);
+        let discriminant = child(self, node.discriminant);
+        let cases = optList(self, node.cases);
+        let block = child(self, node.block);
+        let handler = child(self, node.handler);
+        let param = child(self, node.param);
+        let finalizer = child(self, node.finalizer);
+        let test = child(self, node.test);
+        let left = child(self, node.left);
+        let argument = child(self, node.argument);
+        let expression = child(self, node.expression);
+        let expressions = list(self, node.expressions);
+        let properties = optList(self, node.properties);
+        let elements = optList(self, node.elements);
+        let declarations = optList(self, node.declarations);
+        let right = child(self, node.right);
+        let value = child(self, node.value);
+        let init = child(self, node.init);
+        let consequent = child(self, node.consequent);
+        let consequents = optList(self, node.consequents);
+        let alternate = child(self, node.alternate);
+        let update = child(self, node.update);
+        let quasi = child(self, node.quasi);
+        let quasis = optList(self, node.quasis);
+        let body = optList(self, node.body);
+
+        let lowered = &mut self.nodes[id.index()];
+        lowered.label = label;
+        lowered.id = nodeId;
+        lowered.meta = meta;
+        lowered.key = key;
+        lowered.imported = imported;
+        lowered.tag = tag;
+        lowered.object = object;
+        lowered.property = property;
+        lowered.callee = callee;
+        lowered.arguments = arguments;
+        lowered.superClass = superClass;
+        lowered.params = params;
+        lowered.specifiers = specifiers;
+        lowered.local = local;
+        lowered.exported = exported;
+        lowered.declaration = declaration;
+        lowered.source = source;
+        lowered.discriminant = discriminant;
+        lowered.cases = cases;
+        lowered.block = block;
+        lowered.handler = handler;
+        lowered.param = param;
+        lowered.finalizer = finalizer;
+        lowered.test = test;
+        lowered.left = left;
+        lowered.argument = argument;
+        lowered.expression = expression;
+        lowered.expressions = expressions;
+        lowered.properties = properties;
+        lowered.elements = elements;
+        lowered.declarations = declarations;
+        lowered.right = right;
+        lowered.value = value;
+        lowered.init = init;
+        lowered.consequent = consequent;
+        lowered.consequents = consequents;
+        lowered.alternate = alternate;
+        lowered.update = update;
+        lowered.quasi = quasi;
+        lowered.quasis = quasis;
+        lowered.body = body;
+        id
+    }
+
+    /// Store a list of children and return a handle to it.
+    pub fn alloc(&mut self, ids: &[NodeId]) -> NodeList {
+        let start = self.lists.len() as u32;
+        self.lists.extend_from_slice(ids);
+        NodeList {
+            start,
+            len: ids.len() as u32,
+        }
+    }
+
+    /// Add a node to the arena. Its children must already be in it.
+    pub fn push(&mut self, node: ArenaNode) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(node);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, id: NodeId) -> &ArenaNode {
+        &self.nodes[id.index()]
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> &mut ArenaNode {
+        &mut self.nodes[id.index()]
+    }
+
+    /// The ids in a child list.
+    pub fn list(&self, list: NodeList) -> &[NodeId] {
+        &self.lists[list.start as usize..(list.start + list.len) as usize]
+    }
+
+    /// Every node id, in pre-order of the trees they were lowered from.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len() as u32).map(NodeId)
+    }
+
+    /// The direct children of `id`, in source order, as
+    /// `node::Node::children` gives them.
+    pub fn children(&self, id: NodeId) -> Vec<NodeId> {
+        let node = self.get(id);
+        let mut children: Vec<(&'static str, NodeId)> = Vec::new();
+        let list = |list: Option<NodeList>| list.map_or(&[][..], |list| self.list(list));
+        children.extend(node.id.map(|child| ("id", child)));
+        children.extend(node.key.map(|child| ("key", child)));
+        children.extend(node.local.map(|child| ("local", child)));
+        children.extend(node.test.map(|child| ("test", child)));
+        children.extend(node.left.map(|child| ("left", child)));
+        children.extend(node.argument.map(|child| ("argument", child)));
+        children.extend(node.expression.map(|child| ("expression", child)));
+        children.extend(self.list(node.expressions).iter().map(|&child| ("expressions", child)));
+        children.extend(list(node.properties).iter().map(|&child| ("properties", child)));
+        children.extend(list(node.elements).iter().map(|&child| ("elements", child)));
+        children.extend(list(node.declarations).iter().map(|&child| ("declarations", child)));
+        children.extend(node.right.map(|child| ("right", child)));
+        children.extend(node.value.map(|child| ("value", child)));
+        children.extend(node.init.map(|child| ("init", child)));
+        children.extend(node.consequent.map(|child| ("consequent", child)));
+        children.extend(list(node.consequents).iter().map(|&child| ("consequent", child)));
+        children.extend(node.alternate.map(|child| ("alternate", child)));
+        children.extend(node.object.map(|child| ("object", child)));
+        children.extend(node.property.map(|child| ("property", child)));
+        children.extend(node.callee.map(|child| ("callee", child)));
+        children.extend(list(node.arguments).iter().map(|&child| ("arguments", child)));
+        children.extend(list(node.params).iter().map(|&child| ("params", child)));
+        children.extend(node.label.map(|child| ("label", child)));
+        children.extend(node.block.map(|child| ("block", child)));
+        children.extend(node.handler.map(|child| ("handler", child)));
+        children.extend(node.param.map(|child| ("param", child)));
+        children.extend(node.finalizer.map(|child| ("finalizer", child)));
+        children.extend(node.discriminant.map(|child| ("discriminant", child)));
+        children.extend(list(node.cases).iter().map(|&child| ("cases", child)));
+        children.extend(node.update.map(|child| ("update", child)));
+        children.extend(node.tag.map(|child| ("tag", child)));
+        children.extend(node.quasi.map(|child| ("quasi", child)));
+        children.extend(list(node.quasis).iter().map(|&child| ("quasis", child)));
+        children.extend(node.superClass.map(|child| ("superClass", child)));
+        children.extend(node.meta.map(|child| ("meta", child)));
+        children.extend(node.source.map(|child| ("source", child)));
+        children.extend(list(node.specifiers).iter().map(|&child| ("specifiers", child)));
+        children.extend(node.imported.map(|child| ("imported", child)));
+        children.extend(node.exported.map(|child| ("exported", child)));
+        children.extend(node.declaration.map(|child| ("declaration", child)));
+        children.extend(list(node.body).iter().map(|&child| ("body", child)));
+        children.retain(|&(_, child)| !self.get(child).r#type.is_empty());
+        node::inSourceOrder(&node.r#type, children)
+    }
+
+    /// The chain of ancestors of `id`, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.get(id).parent, move |&id| self.get(id).parent)
+    }
+
+    /// Rebuild a `node::Node` tree from the subtree at `id`.
+    pub fn toNode(&self, id: NodeId) -> node::Node {
+        let arena = self.get(id);
+        let child = |child: Option<NodeId>| child.map(|child| Box::new(self.toNode(child)));
+        let list = |list: NodeList| -> Vec<node::Node> {
+            self.list(list).iter().map(|&child| self.toNode(child)).collect()
+        };
+        let mut node = node::Node {
+            r#type: arena.r#type.to_string(),
+            start: arena.start,
+            end: arena.end,
+            sourceType: arena.sourceType.clone(),
+            operator: arena.operator.clone(),
+            name: arena.name.clone(),
+            raw: arena.raw.clone(),
+            kind: arena.kind.clone(),
+            directive: arena.directive.clone(),
+            cooked: arena.cooked.clone(),
+            prefix: arena.prefix,
+            computed: arena.computed,
+            method: arena.method,
+            shorthand: arena.shorthand,
+            generator: arena.generator,
+            r#async: arena.r#async,
+            r#static: arena.r#static,
+            r#await: arena.r#await,
+            delegate: arena.delegate,
+            tail: arena.tail,
+            label: child(arena.label),
+            id: child(arena.id),
+            meta: child(arena.meta),
+            key: child(arena.key),
+            imported: child(arena.imported),
+            tag: child(arena.tag),
+            object: child(arena.object),
+            property: child(arena.property),
+            callee: child(arena.callee),
+            arguments: arena.arguments.map(list),
+            superClass: child(arena.superClass),
+            params: arena.params.map(list),
+            specifiers: arena.specifiers.map(list),
+            local: child(arena.local),
+            exported: child(arena.exported),
+            declaration: child(arena.declaration),
+            source: child(arena.source),
+            discriminant: child(arena.discriminant),
+            cases: arena.cases.map(list),
+            block: child(arena.block),
+            handler: child(arena.handler),
+            param: child(arena.param),
+            finalizer: child(arena.finalizer),
+            test: child(arena.test),
+            left: child(arena.left),
+            argument: child(arena.argument),
+            expression: child(arena.expression),
+            expressions: list(arena.expressions),
+            properties: arena.properties.map(list),
+            elements: arena.elements.map(list),
+            declarations: arena.declarations.map(list),
+            right: child(arena.right),
+            value: child(arena.value),
+            init: child(arena.init),
+            consequent: child(arena.consequent),
+            consequents: arena.consequents.map(list),
+            alternate: child(arena.alternate),
+            update: child(arena.update),
+            quasi: child(arena.quasi),
+            quasis: arena.quasis.map(list),
+            body: arena.body.map(list),
+            ..node::Node::default()
+        };
+        node.setLocation(arena.loc.clone(), arena.sourceFile.clone(), arena.range);
+        node
+    }
+}
+
+impl ops::Index<NodeId> for Arena {
+    type Output = ArenaNode;
+
+    fn index(&self, id: NodeId) -> &ArenaNode {
+        self.get(id)
+    }
+}
+
+impl ops::IndexMut<NodeId> for Arena {
+    fn index_mut(&mut self, id: NodeId) -> &mut ArenaNode {
+        self.get_mut(id)
+    }
+}
+
+/// The node table's own copy of `r#type`, if it has one, so that the
+/// nodes of an arena do not each own a copy of their type.
+fn internType(r#type: String) -> Cow<'static, str> {
+    match ast::fieldsOf(&r#type) {
+        Some(fields) => Cow::Borrowed(fields.r#type),
+        None => Cow::Owned(r#type),
+    }
+}
+
+/// Data attached to the nodes of one arena, indexed by `NodeId`.
+pub struct SideTable<T> {
+    values: Vec<Option<T>>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        SideTable { values: Vec::new() }
+    }
+}
+
+impl<T> SideTable<T> {
+    pub fn new() -> Self {
+        SideTable::default()
+    }
+
+    /// A table sized for every node of `arena`.
+    pub fn forArena(arena: &Arena) -> Self {
+        let mut values = Vec::with_capacity(arena.len());
+        values.resize_with(arena.len(), || None);
+        SideTable { values }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.values.get(id.index())?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.values.get_mut(id.index())?.as_mut()
+    }
+
+    /// Attach `value` to `id`, returning the value it replaces.
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        if id.index() >= self.values.len() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        self.values[id.index()].replace(value)
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.values.get_mut(id.index())?.take()
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, value)| Some((NodeId(i as u32), value.as_ref()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lower(input: &str) -> (node::Node, Arena, NodeId) {
+        let program = match crate::parse(String::from(input), None) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        };
+        let (arena, root) = Arena::fromNode(program.clone());
+        (program, arena, root)
+    }
+
+    #[test]
+    fn loweringIsLossless() {
+        let (program, arena, root) = lower("var [a, , b] = f(x, ...y); if (a) { b(); } else c = `${d}`;");
+        assert!(arena.toNode(root) == program);
+    }
+
+    #[test]
+    fn idsFollowAPreOrder() {
+        let (_, arena, root) = lower("function f(a) { return a + 1; } f(2);");
+        assert_eq!(root, NodeId(0));
+        for id in arena.ids() {
+            for child in arena.children(id) {
+                assert!(child > id);
+                assert_eq!(arena[child].parent, Some(id));
+            }
+        }
+    }
+
+    #[test]
+    fn internsTheTypesOfTheNodeTable() {
+        let (_, arena, _) = lower("f(a, [b, {c}]);");
+        assert!(arena.ids().all(|id| matches!(arena[id].r#type, Cow::Borrowed(_))));
+        let custom = node::Node {
+            r#type: String::from("Custom"),
+            ..node::Node::default()
+        };
+        let (arena, root) = Arena::fromNode(custom);
+        assert_eq!(arena[root].r#type, "Custom");
+        assert_eq!(arena.toNode(root).r#type, "Custom");
+    }
+
+    #[test]
+    fn sideTablesAreKeyedById() {
+        let (_, arena, root) = lower("a + b;");
+        let mut names = SideTable::forArena(&arena);
+        for id in arena.ids() {
+            if let Some(name) = &arena[id].name {
+                names.insert(id, name.clone());
+            }
+        }
+        let found: Vec<&String> = names.iter().map(|(_, name)| name).collect();
+        assert_eq!(found, ["a", "b"]);
+        assert!(!names.contains(root));
+    }
+}
@@ -0,0 +1,1290 @@
+// A strongly typed view of the ESTree AST. `node::Node` is what the
+// parser builds; the types here are converted from it with `FromNode`
+// and give one struct per node type and one enum per syntactic
+// category, so consumers can match on them instead of on `type`
+// strings.
+//
+// Everything is generated by the `ast!` macro from the table at the
+// bottom of this file. To add a node type, add it to `nodes` and to
+// whichever `enums` it belongs to.
+
+use crate::locutil;
+use crate::node;
+use crate::options;
+use crate::visit;
+
+use std::fmt;
+
+/// Why a `node::Node` could not be converted into a typed node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvertError {
+    /// The node has a `type` that is not valid in this position.
+    UnexpectedType {
+        expected: &'static str,
+        found: String,
+    },
+    /// A required field is missing or has the wrong shape.
+    MissingField {
+        nodeType: &'static str,
+        field: &'static str,
+    },
+    /// A field was given to a node type that has no such field.
+    UnexpectedField { nodeType: String, field: String },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::UnexpectedType { expected, found } => {
+                write!(f, "Expected a {} node, found {}", expected, found)
+            }
+            ConvertError::MissingField { nodeType, field } => {
+                write!(f, "{} node is missing its '{}' field", nodeType, field)
+            }
+            ConvertError::UnexpectedField { nodeType, field } => {
+                write!(f, "{} node has no '{}' field", nodeType, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Types that can be built from a whole `node::Node`.
+pub trait FromNode: Sized {
+    fn fromNode(node: &node::Node) -> Result<Self, ConvertError>;
+}
+
+/// A hole in an array (`[a, , b]`) converts to `None`.
+impl<T: FromNode> FromNode for Option<T> {
+    fn fromNode(node: &node::Node) -> Result<Self, ConvertError> {
+        if node.isHole() {
+            return Ok(None);
+        }
+        T::fromNode(node).map(Some)
+    }
+}
+
+/// A field of a `node::Node`, looked up by its ESTree name.
+pub enum FieldRef<'a> {
+    Missing,
+    Node(&'a node::Node),
+    Nodes(&'a [node::Node]),
+    Str(&'a str),
+    Bool(bool),
+    SourceType(&'a options::SourceType),
+}
+
+/// Look up the ESTree field `name` on `node`.
+pub fn field<'a>(node: &'a node::Node, name: &str) -> FieldRef<'a> {
+    let one = |child: &'a Option<Box<node::Node>>| match child {
+        Some(child) => FieldRef::Node(child),
+        None => FieldRef::Missing,
+    };
+    let many = |children: &'a Option<Vec<node::Node>>| match children {
+        Some(children) => FieldRef::Nodes(children),
+        None => FieldRef::Missing,
+    };
+    let string = |value: &'a Option<String>| match value {
+        Some(value) => FieldRef::Str(value),
+        None => FieldRef::Missing,
+    };
+    let flag = |value: Option<bool>| value.map_or(FieldRef::Missing, FieldRef::Bool);
+    let name = name.trim_start_matches("r#");
+    let singleBody = node.body.as_ref().is_some_and(|body| body.len() == 1)
+        && fieldsOf(&node.r#type)
+            .and_then(|fields| fields.shape("body"))
+            .is_some_and(|shape| shape != Shape::List);
+    match name {
+        // `node::Node` has one `body` field for both shapes; where
+        // ESTree's `body` is a single node, it is the only element.
+        "body" if singleBody => FieldRef::Node(&node.body.as_ref().unwrap()[0]),
+        "body" => many(&node.body),
+        // An arrow function's `expression` flag is implied by its body.
+        "expression" if node.r#type == ArrowFunctionExpression::TYPE => FieldRef::Bool(
+            singleBody && node.body.as_ref().unwrap()[0].r#type != BlockStatement::TYPE,
+        ),
+        "local" => one(&node.local),
+        "expressions" => FieldRef::Nodes(&node.expressions),
+        "left" => one(&node.left),
+        "right" => one(&node.right),
+        "operator" => string(&node.operator),
+        "name" => string(&node.name),
+        "raw" => string(&node.raw),
+        "properties" => many(&node.properties),
+        "argument" => one(&node.argument),
+        "kind" => string(&node.kind),
+        "value" => one(&node.value),
+        "key" => one(&node.key),
+        "elements" => many(&node.elements),
+        "expression" => one(&node.expression),
+        "test" => one(&node.test),
+        // A `SwitchCase`'s list of statements has a field of its own.
+        "consequent" if node.r#type == SwitchCase::TYPE => many(&node.consequents),
+        "consequent" => one(&node.consequent),
+        "alternate" => one(&node.alternate),
+        "prefix" => flag(node.prefix),
+        "id" => one(&node.id),
+        "init" => one(&node.init),
+        "declarations" => many(&node.declarations),
+        "object" => one(&node.object),
+        "property" => one(&node.property),
+        "callee" => one(&node.callee),
+        "arguments" => many(&node.arguments),
+        "params" => many(&node.params),
+        "label" => one(&node.label),
+        "block" => one(&node.block),
+        "handler" => one(&node.handler),
+        "finalizer" => one(&node.finalizer),
+        "param" => one(&node.param),
+        "discriminant" => one(&node.discriminant),
+        "cases" => many(&node.cases),
+        "update" => one(&node.update),
+        "tag" => one(&node.tag),
+        "quasi" => one(&node.quasi),
+        "quasis" => many(&node.quasis),
+        "superClass" => one(&node.superClass),
+        "meta" => one(&node.meta),
+        "source" => one(&node.source),
+        "specifiers" => many(&node.specifiers),
+        "imported" => one(&node.imported),
+        "exported" => one(&node.exported),
+        "declaration" => one(&node.declaration),
+        "directive" => string(&node.directive),
+        "cooked" => string(&node.cooked),
+        "computed" => flag(node.computed),
+        "method" => flag(node.method),
+        "shorthand" => flag(node.shorthand),
+        "generator" => flag(node.generator),
+        "async" => flag(node.r#async),
+        "static" => flag(node.r#static),
+        "await" => flag(node.r#await),
+        "delegate" => flag(node.delegate),
+        "tail" => flag(node.tail),
+        "sourceType" => node
+            .sourceType
+            .as_ref()
+            .map_or(FieldRef::Missing, FieldRef::SourceType),
+        _ => FieldRef::Missing,
+    }
+}
+
+/// An owned field value, to be stored on a node with `setField`.
+#[allow(clippy::large_enum_variant)]
+pub enum FieldValue {
+    Node(node::Node),
+    Nodes(Vec<node::Node>),
+    Str(String),
+    Bool(bool),
+    SourceType(options::SourceType),
+}
+
+/// Store `field` as the field `name` of `node`: the inverse of
+/// `field`. For a node type in the node table, `name` must be one of
+/// its fields and `field` must have that field's shape; otherwise,
+/// `node::Node` must have room for it.
+pub fn setField(node: &mut node::Node, name: &str, field: FieldValue) -> Result<(), ConvertError> {
+    let unexpected = |node: &node::Node| ConvertError::UnexpectedField {
+        nodeType: node.r#type.clone(),
+        field: String::from(name),
+    };
+    if let Some(fields) = fieldsOf(&node.r#type) {
+        let shape = fields.shape(name).ok_or_else(|| unexpected(node))?;
+        let fits = matches!(
+            (&field, shape),
+            (FieldValue::Node(_), Shape::Node)
+                | (FieldValue::Node(_), Shape::OptionalNode)
+                | (FieldValue::Nodes(_), Shape::List)
+                | (FieldValue::Str(_), Shape::String)
+                | (FieldValue::Str(_), Shape::OptionalString)
+                | (FieldValue::Bool(_), Shape::Bool)
+                | (FieldValue::SourceType(_), Shape::SourceType)
+        );
+        if !fits {
+            let field = fields.iter().map(|(field, _)| field).find(|&field| field == name);
+            return Err(ConvertError::MissingField {
+                nodeType: fields.r#type,
+                field: field.unwrap_or_default(),
+            });
+        }
+    }
+    match field {
+        FieldValue::Node(child) => {
+            // A single-node `body` is stored as a one-element list.
+            if name == "body" {
+                node.body = Some(vec![child]);
+                return Ok(());
+            }
+            let child = Some(Box::new(child));
+            match name {
+                "local" => node.local = child,
+                "left" => node.left = child,
+                "right" => node.right = child,
+                "argument" => node.argument = child,
+                "value" => node.value = child,
+                "key" => node.key = child,
+                "expression" => node.expression = child,
+                "test" => node.test = child,
+                "consequent" => node.consequent = child,
+                "alternate" => node.alternate = child,
+                "id" => node.id = child,
+                "init" => node.init = child,
+                "object" => node.object = child,
+                "property" => node.property = child,
+                "callee" => node.callee = child,
+                "label" => node.label = child,
+                "block" => node.block = child,
+                "handler" => node.handler = child,
+                "finalizer" => node.finalizer = child,
+                "param" => node.param = child,
+                "discriminant" => node.discriminant = child,
+                "update" => node.update = child,
+                "tag" => node.tag = child,
+                "quasi" => node.quasi = child,
+                "superClass" => node.superClass = child,
+                "meta" => node.meta = child,
+                "source" => node.source = child,
+                "imported" => node.imported = child,
+                "exported" => node.exported = child,
+                "declaration" => node.declaration = child,
+                _ => return Err(unexpected(node)),
+            }
+        }
+        FieldValue::Nodes(children) => match name {
+            "expressions" => node.expressions = children,
+            "body" => node.body = Some(children),
+            "properties" => node.properties = Some(children),
+            "elements" => node.elements = Some(children),
+            "declarations" => node.declarations = Some(children),
+            "arguments" => node.arguments = Some(children),
+            "params" => node.params = Some(children),
+            "cases" => node.cases = Some(children),
+            "consequent" if node.r#type == SwitchCase::TYPE => node.consequents = Some(children),
+            "quasis" => node.quasis = Some(children),
+            "specifiers" => node.specifiers = Some(children),
+            _ => return Err(unexpected(node)),
+        },
+        FieldValue::Str(value) => {
+            let value = Some(value);
+            match name {
+                "operator" => node.operator = value,
+                "name" => node.name = value,
+                "raw" => node.raw = value,
+                "kind" => node.kind = value,
+                "directive" => node.directive = value,
+                "cooked" => node.cooked = value,
+                _ => return Err(unexpected(node)),
+            }
+        }
+        FieldValue::Bool(value) => {
+            let value = Some(value);
+            match name {
+                "prefix" => node.prefix = value,
+                "computed" => node.computed = value,
+                "method" => node.method = value,
+                "shorthand" => node.shorthand = value,
+                "generator" => node.generator = value,
+                "async" => node.r#async = value,
+                "static" => node.r#static = value,
+                "await" => node.r#await = value,
+                "delegate" => node.delegate = value,
+                "tail" => node.tail = value,
+                // An arrow function's `expression` flag is implied by
+                // its body; see `field`.
+                "expression" if node.r#type == ArrowFunctionExpression::TYPE => {}
+                _ => return Err(unexpected(node)),
+            }
+        }
+        FieldValue::SourceType(sourceType) => node.sourceType = Some(sourceType),
+    }
+    Ok(())
+}
+
+/// Every field of `node::Node`, in the order acorn usually sets them.
+pub const NODE_FIELDS: &[&str] = &[
+    "sourceType",
+    "id",
+    "key",
+    "local",
+    "test",
+    "left",
+    "operator",
+    "name",
+    "raw",
+    "argument",
+    "prefix",
+    "expression",
+    "expressions",
+    "properties",
+    "elements",
+    "declarations",
+    "kind",
+    "right",
+    "value",
+    "init",
+    "consequent",
+    "alternate",
+    "object",
+    "property",
+    "callee",
+    "arguments",
+    "params",
+    "label",
+    "block",
+    "handler",
+    "param",
+    "finalizer",
+    "discriminant",
+    "cases",
+    "update",
+    "tag",
+    "quasi",
+    "quasis",
+    "superClass",
+    "meta",
+    "source",
+    "specifiers",
+    "imported",
+    "exported",
+    "declaration",
+    "directive",
+    "computed",
+    "method",
+    "shorthand",
+    "generator",
+    "async",
+    "static",
+    "await",
+    "delegate",
+    "tail",
+    "cooked",
+    "body",
+];
+
+/// Types that can be built from a single field of a `node::Node`.
+pub trait FromField: Sized {
+    fn fromField(
+        field: FieldRef,
+        nodeType: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ConvertError>;
+}
+
+impl<T: FromField> FromField for Option<T> {
+    fn fromField(
+        field: FieldRef,
+        nodeType: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ConvertError> {
+        match field {
+            FieldRef::Missing => Ok(None),
+            field => T::fromField(field, nodeType, name).map(Some),
+        }
+    }
+}
+
+impl<T: FromNode> FromField for Vec<T> {
+    fn fromField(
+        field: FieldRef,
+        nodeType: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ConvertError> {
+        match field {
+            FieldRef::Missing => Ok(Vec::new()),
+            FieldRef::Nodes(nodes) => nodes.iter().map(T::fromNode).collect(),
+            _ => Err(ConvertError::MissingField {
+                nodeType,
+                field: name,
+            }),
+        }
+    }
+}
+
+impl FromField for String {
+    fn fromField(
+        field: FieldRef,
+        nodeType: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ConvertError> {
+        match field {
+            FieldRef::Str(value) => Ok(String::from(value)),
+            _ => Err(ConvertError::MissingField {
+                nodeType,
+                field: name,
+            }),
+        }
+    }
+}
+
+/// Boolean flags (`computed`, `generator`, ...) default to `false`
+/// when the parser did not set them.
+impl FromField for bool {
+    fn fromField(field: FieldRef, _: &'static str, _: &'static str) -> Result<Self, ConvertError> {
+        match field {
+            FieldRef::Bool(value) => Ok(value),
+            _ => Ok(false),
+        }
+    }
+}
+
+impl FromField for options::SourceType {
+    fn fromField(
+        field: FieldRef,
+        nodeType: &'static str,
+        name: &'static str,
+    ) -> Result<Self, ConvertError> {
+        match field {
+            FieldRef::SourceType(sourceType) => Ok(sourceType.clone()),
+            _ => Err(ConvertError::MissingField {
+                nodeType,
+                field: name,
+            }),
+        }
+    }
+}
+
+/// How a field is stored, as declared in the node table. Used by code
+/// that works on `node::Node` generically (serialization, validation,
+/// walking) to know which fields a node type has and what a missing
+/// one means.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// A required child node.
+    Node,
+    /// A child node that may be `null`.
+    OptionalNode,
+    /// A list of child nodes.
+    List,
+    String,
+    /// A string that is left out entirely when absent.
+    OptionalString,
+    Bool,
+    SourceType,
+}
+
+/// The fields of one node type, as declared in the node table.
+#[derive(Clone, Copy, Debug)]
+pub struct NodeFields {
+    pub r#type: &'static str,
+    pub names: &'static [&'static str],
+    pub shapes: &'static [Shape],
+    /// The category (`"Expression"`, `"Pattern"`, ...) or node type
+    /// of each field's children, or `""` for a field without children.
+    pub categories: &'static [&'static str],
+}
+
+impl NodeFields {
+    /// Each field's ESTree name and shape, in ESTree order.
+    pub fn iter(self) -> impl Iterator<Item = (&'static str, Shape)> {
+        self.names
+            .iter()
+            .map(|name| name.trim_start_matches("r#"))
+            .zip(self.shapes.iter().cloned())
+    }
+
+    /// Each child field's ESTree name and the category or node type
+    /// of its children, in ESTree order.
+    pub fn childFields(self) -> impl Iterator<Item = (&'static str, &'static str)> {
+        self.iter()
+            .zip(self.categories.iter().cloned())
+            .filter(|((_, shape), _)| {
+                matches!(shape, Shape::Node | Shape::OptionalNode | Shape::List)
+            })
+            .map(|((name, _), category)| (name, category))
+    }
+
+    /// The shape of the field `name`, if the node type has it.
+    pub fn shape(self, name: &str) -> Option<Shape> {
+        self.iter()
+            .find(|&(field, _)| field == name)
+            .map(|(_, shape)| shape)
+    }
+}
+
+/// The `Shape` of a field type in the node table.
+pub trait FieldShape {
+    const SHAPE: Shape;
+}
+
+impl<T: FieldShape> FieldShape for Option<T> {
+    const SHAPE: Shape = match T::SHAPE {
+        Shape::Node => Shape::OptionalNode,
+        Shape::String => Shape::OptionalString,
+        shape => shape,
+    };
+}
+
+impl<T> FieldShape for Vec<T> {
+    const SHAPE: Shape = Shape::List;
+}
+
+impl FieldShape for String {
+    const SHAPE: Shape = Shape::String;
+}
+
+impl FieldShape for bool {
+    const SHAPE: Shape = Shape::Bool;
+}
+
+impl FieldShape for options::SourceType {
+    const SHAPE: Shape = Shape::SourceType;
+}
+
+/// The category or node type a field type holds, as named in the node
+/// table.
+pub trait FieldCategory {
+    const CATEGORY: &'static str;
+}
+
+impl<T: FieldCategory> FieldCategory for Option<T> {
+    const CATEGORY: &'static str = T::CATEGORY;
+}
+
+impl<T: FieldCategory> FieldCategory for Vec<T> {
+    const CATEGORY: &'static str = T::CATEGORY;
+}
+
+impl FieldCategory for String {
+    const CATEGORY: &'static str = "";
+}
+
+impl FieldCategory for bool {
+    const CATEGORY: &'static str = "";
+}
+
+impl FieldCategory for options::SourceType {
+    const CATEGORY: &'static str = "";
+}
+
+macro_rules! ast {
+    (@typeName $node:ident) => {
+        stringify!($node)
+    };
+    (@typeName $node:ident $typeName:literal) => {
+        $typeName
+    };
+    (
+        nodes {
+            $(
+                $(#[$nodeMeta:meta])*
+                $node:ident $(= $typeName:literal)? {
+                    $( $field:ident : $ty:ty ),* $(,)?
+                }
+            )*
+        }
+        enums {
+            $(
+                $(#[$enumMeta:meta])*
+                $enum:ident {
+                    $( $variant:ident ),* $(,)?
+                    $( ; $( $nested:ident ),* $(,)? )?
+                }
+            )*
+        }
+    ) => {
+        $(
+            $(#[$nodeMeta])*
+            #[derive(Clone, Debug)]
+            pub struct $node {
+                pub start: usize,
+                pub end: usize,
+                pub loc: Option<locutil::SourceLocation>,
+                pub range: Option<(usize, usize)>,
+                $( pub $field: $ty, )*
+            }
+
+            impl $node {
+                /// The ESTree `type` of this node.
+                pub const TYPE: &'static str = ast!(@typeName $node $($typeName)?);
+                /// The ESTree names of this node's fields, in order.
+                pub const FIELDS: &'static [&'static str] = &[$( stringify!($field) ),*];
+                /// The `Shape` of each field, in the order of `FIELDS`.
+                pub const SHAPES: &'static [Shape] = &[$( <$ty as FieldShape>::SHAPE ),*];
+                /// The category of each field, in the order of `FIELDS`.
+                pub const CATEGORIES: &'static [&'static str] =
+                    &[$( <$ty as FieldCategory>::CATEGORY ),*];
+            }
+
+            impl FromNode for $node {
+                fn fromNode(node: &node::Node) -> Result<Self, ConvertError> {
+                    if node.r#type != $node::TYPE {
+                        return Err(ConvertError::UnexpectedType {
+                            expected: $node::TYPE,
+                            found: node.r#type.clone(),
+                        });
+                    }
+                    Ok($node {
+                        start: node.start,
+                        end: node.end,
+                        loc: node.loc().cloned(),
+                        range: node.range(),
+                        $(
+                            $field: FromField::fromField(
+                                field(node, stringify!($field)),
+                                $node::TYPE,
+                                stringify!($field),
+                            )?,
+                        )*
+                    })
+                }
+            }
+
+            impl FromField for $node {
+                fn fromField(
+                    field: FieldRef,
+                    nodeType: &'static str,
+                    name: &'static str,
+                ) -> Result<Self, ConvertError> {
+                    match field {
+                        FieldRef::Node(node) => $node::fromNode(node),
+                        _ => Err(ConvertError::MissingField { nodeType, field: name }),
+                    }
+                }
+            }
+
+            impl FieldShape for $node {
+                const SHAPE: Shape = Shape::Node;
+            }
+
+            impl FieldCategory for $node {
+                const CATEGORY: &'static str = $node::TYPE;
+            }
+        )*
+
+        $(
+            $(#[$enumMeta])*
+            #[derive(Clone, Debug)]
+            pub enum $enum {
+                $( $variant(Box<$variant>), )*
+                $( $( $nested($nested), )* )?
+            }
+
+            impl $enum {
+                /// Whether a node with ESTree type `r#type` belongs to
+                /// this category.
+                pub fn accepts(r#type: &str) -> bool {
+                    $( r#type == $variant::TYPE || )*
+                    $( $( $nested::accepts(r#type) || )* )?
+                    false
+                }
+
+                /// The ESTree `type` of the wrapped node.
+                pub fn r#type(&self) -> &'static str {
+                    match self {
+                        $( $enum::$variant(_) => $variant::TYPE, )*
+                        $( $( $enum::$nested(inner) => inner.r#type(), )* )?
+                    }
+                }
+
+                pub fn start(&self) -> usize {
+                    match self {
+                        $( $enum::$variant(inner) => inner.start, )*
+                        $( $( $enum::$nested(inner) => inner.start(), )* )?
+                    }
+                }
+
+                pub fn end(&self) -> usize {
+                    match self {
+                        $( $enum::$variant(inner) => inner.end, )*
+                        $( $( $enum::$nested(inner) => inner.end(), )* )?
+                    }
+                }
+            }
+
+            impl FromNode for $enum {
+                fn fromNode(node: &node::Node) -> Result<Self, ConvertError> {
+                    $(
+                        if node.r#type == $variant::TYPE {
+                            return Ok($enum::$variant(Box::new($variant::fromNode(node)?)));
+                        }
+                    )*
+                    $( $(
+                        if $nested::accepts(&node.r#type) {
+                            return Ok($enum::$nested($nested::fromNode(node)?));
+                        }
+                    )* )?
+                    Err(ConvertError::UnexpectedType {
+                        expected: stringify!($enum),
+                        found: node.r#type.clone(),
+                    })
+                }
+            }
+
+            impl FromField for $enum {
+                fn fromField(
+                    field: FieldRef,
+                    nodeType: &'static str,
+                    name: &'static str,
+                ) -> Result<Self, ConvertError> {
+                    match field {
+                        FieldRef::Node(node) => $enum::fromNode(node),
+                        _ => Err(ConvertError::MissingField { nodeType, field: name }),
+                    }
+                }
+            }
+
+            impl FieldShape for $enum {
+                const SHAPE: Shape = Shape::Node;
+            }
+
+            impl FieldCategory for $enum {
+                const CATEGORY: &'static str = stringify!($enum);
+            }
+        )*
+
+        /// Hooks for `visit::visitMut`, one per node type in the table,
+        /// named after the type. Each is called with a node of its type
+        /// and returns what to do with it. The default walks the node's
+        /// children and keeps the node; an override that still wants
+        /// the children visited calls `visit::walkMut` itself.
+        ///
+        /// `Property` nodes in object patterns go to `AssignmentProperty`.
+        pub trait VisitMut {
+            $(
+                #[doc = concat!("Called for `", stringify!($node), "` nodes.")]
+                fn $node(&mut self, node: &mut node::Node) -> visit::Change {
+                    visit::walkMut(self, node);
+                    visit::Change::Keep
+                }
+            )*
+
+            /// Called for nodes whose type is not in the node table.
+            fn other(&mut self, node: &mut node::Node) -> visit::Change {
+                visit::walkMut(self, node);
+                visit::Change::Keep
+            }
+        }
+
+        /// Call the `VisitMut` hook named `hook` on `node`.
+        pub(crate) fn dispatchMut<V: VisitMut + ?Sized>(
+            visitor: &mut V,
+            hook: &str,
+            node: &mut node::Node,
+        ) -> visit::Change {
+            $(
+                if hook == stringify!($node) {
+                    return visitor.$node(node);
+                }
+            )*
+            visitor.other(node)
+        }
+
+        /// Whether `name` is a category (`"Statement"`, `"Pattern"`, ...)
+        /// rather than a node type.
+        pub fn isCategory(name: &str) -> bool {
+            $( name == stringify!($enum) || )*
+            false
+        }
+
+        /// Whether a node with ESTree type `r#type` can fill a field
+        /// whose children are of `category`, as `NodeFields::categories`
+        /// names it: a category, or a node type.
+        pub fn belongsTo(r#type: &str, category: &str) -> bool {
+            $(
+                if category == stringify!($enum) {
+                    return $enum::accepts(r#type);
+                }
+            )*
+            r#type == category
+        }
+
+        /// The fields of the node type `r#type`, or `None` for a type
+        /// not in the node table.
+        pub fn fieldsOf(r#type: &str) -> Option<NodeFields> {
+            $(
+                if r#type == $node::TYPE {
+                    return Some(NodeFields {
+                        r#type: $node::TYPE,
+                        names: $node::FIELDS,
+                        shapes: $node::SHAPES,
+                        categories: $node::CATEGORIES,
+                    });
+                }
+            )*
+            None
+        }
+    };
+}
+
+/// The value of a `Literal`, decoded from its source text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LiteralValue {
+    String(String),
+    Boolean(bool),
+    Null,
+    Number(f64),
+    RegExp { pattern: String, flags: String },
+    BigInt(String),
+    /// The raw text is not a well-formed literal, as can happen for a
+    /// tree that was built or deserialized rather than parsed.
+    Invalid,
+}
+
+impl Literal {
+    /// Decode the literal's value from its `raw` source text.
+    pub fn value(&self) -> LiteralValue {
+        literalValue(&self.raw)
+    }
+}
+
+/// Decode the value of a literal from its source text.
+pub fn literalValue(raw: &str) -> LiteralValue {
+    match raw {
+        "null" => return LiteralValue::Null,
+        "true" => return LiteralValue::Boolean(true),
+        "false" => return LiteralValue::Boolean(false),
+        _ => {}
+    }
+    if let Some(quote) = raw.chars().next().filter(|&c| c == '"' || c == '\'') {
+        return match raw[1..].strip_suffix(quote) {
+            Some(body) => LiteralValue::String(unescape(body)),
+            None => LiteralValue::Invalid,
+        };
+    }
+    if let Some(body) = raw.strip_prefix('/') {
+        return match body.rfind('/') {
+            Some(close) => LiteralValue::RegExp {
+                pattern: String::from(&body[..close]),
+                flags: String::from(&body[close + 1..]),
+            },
+            None => LiteralValue::Invalid,
+        };
+    }
+    let digits = raw.replace('_', "");
+    if let Some(digits) = digits.strip_suffix('n') {
+        return LiteralValue::BigInt(String::from(digits));
+    }
+    let radix = |prefix: &[&str], radix| {
+        prefix
+            .iter()
+            .find(|p| digits.starts_with(*p))
+            .and_then(|p| parseRadix(&digits[p.len()..], radix))
+    };
+    let number = radix(&["0x", "0X"], 16)
+        .or_else(|| radix(&["0o", "0O"], 8))
+        // A legacy octal literal, like `0777`. `08` and `09` are decimal.
+        .or_else(|| radix(&["0"], 8))
+        .or_else(|| radix(&["0b", "0B"], 2))
+        .or_else(|| digits.parse().ok());
+    match number {
+        Some(number) => LiteralValue::Number(number),
+        None => LiteralValue::Invalid,
+    }
+}
+
+/// Parse the digits of an integer literal in the given radix, or `None`
+/// if there are none or one is not a digit of that radix. Values too
+/// large for a `u64` are rounded, as in JavaScript.
+fn parseRadix(digits: &str, radix: u32) -> Option<f64> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.chars().try_fold(0.0, |value: f64, c| {
+        Some(value * radix as f64 + c.to_digit(radix)? as f64)
+    })
+}
+
+/// Resolve the escape sequences of a string literal's body.
+pub(crate) fn unescape(body: &str) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('b') => out.push('\u{8}'),
+            Some('v') => out.push('\u{b}'),
+            Some('f') => out.push('\u{c}'),
+            // Legacy octal escapes take up to three digits, as long as
+            // the value still fits in a byte: `\400` is `\40` then `0`.
+            Some(first @ '0'..='7') => {
+                let mut value = first.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) if value * 8 + digit <= 0xff => {
+                            value = value * 8 + digit;
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                out.extend(std::char::from_u32(value));
+            }
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                out.extend(u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32));
+            }
+            Some('u') => {
+                let hex: String = if chars.peek() == Some(&'{') {
+                    chars.next();
+                    chars.by_ref().take_while(|&c| c != '}').collect()
+                } else {
+                    chars.by_ref().take(4).collect()
+                };
+                out.extend(u32::from_str_radix(&hex, 16).ok().and_then(std::char::from_u32));
+            }
+            // Line continuations produce nothing.
+            Some('\r')
+                if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+            Some('\n') | Some('\u{2028}') | Some('\u{2029}') => {}
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+ast! {
+    nodes {
+        Program {
+            sourceType: Option<options::SourceType>,
+            body: Vec<ModuleItem>,
+        }
+
+        // Statements
+        ExpressionStatement {
+            expression: Expression,
+            directive: Option<String>,
+        }
+        BlockStatement { body: Vec<Statement> }
+        EmptyStatement {}
+        DebuggerStatement {}
+        WithStatement { object: Expression, body: Statement }
+        ReturnStatement { argument: Option<Expression> }
+        LabeledStatement { label: Identifier, body: Statement }
+        BreakStatement { label: Option<Identifier> }
+        ContinueStatement { label: Option<Identifier> }
+        IfStatement {
+            test: Expression,
+            consequent: Statement,
+            alternate: Option<Statement>,
+        }
+        SwitchStatement { discriminant: Expression, cases: Vec<SwitchCase> }
+        SwitchCase { test: Option<Expression>, consequent: Vec<Statement> }
+        ThrowStatement { argument: Expression }
+        TryStatement {
+            block: BlockStatement,
+            handler: Option<CatchClause>,
+            finalizer: Option<BlockStatement>,
+        }
+        CatchClause { param: Option<Pattern>, body: BlockStatement }
+        WhileStatement { test: Expression, body: Statement }
+        DoWhileStatement { body: Statement, test: Expression }
+        ForStatement {
+            init: Option<ForInit>,
+            test: Option<Expression>,
+            update: Option<Expression>,
+            body: Statement,
+        }
+        ForInStatement { left: ForInLeft, right: Expression, body: Statement }
+        ForOfStatement {
+            left: ForInLeft,
+            right: Expression,
+            body: Statement,
+            r#await: bool,
+        }
+
+        // Declarations
+        FunctionDeclaration {
+            id: Option<Identifier>,
+            params: Vec<Pattern>,
+            body: BlockStatement,
+            generator: bool,
+            r#async: bool,
+        }
+        VariableDeclaration { declarations: Vec<VariableDeclarator>, kind: String }
+        VariableDeclarator { id: Pattern, init: Option<Expression> }
+        ClassDeclaration {
+            id: Option<Identifier>,
+            superClass: Option<Expression>,
+            body: ClassBody,
+        }
+        ClassBody { body: Vec<MethodDefinition> }
+        MethodDefinition {
+            key: Expression,
+            value: FunctionExpression,
+            kind: String,
+            computed: bool,
+            r#static: bool,
+        }
+
+        // Expressions
+        Identifier { name: String }
+        Literal { raw: String }
+        ThisExpression {}
+        Super {}
+        ArrayExpression { elements: Vec<Option<ExpressionOrSpread>> }
+        ObjectExpression { properties: Vec<ObjectProperty> }
+        Property {
+            key: Expression,
+            value: Expression,
+            kind: String,
+            method: bool,
+            shorthand: bool,
+            computed: bool,
+        }
+        SpreadElement { argument: Expression }
+        FunctionExpression {
+            id: Option<Identifier>,
+            params: Vec<Pattern>,
+            body: BlockStatement,
+            generator: bool,
+            r#async: bool,
+        }
+        ArrowFunctionExpression {
+            params: Vec<Pattern>,
+            body: ArrowBody,
+            expression: bool,
+            r#async: bool,
+        }
+        UnaryExpression { operator: String, prefix: bool, argument: Expression }
+        UpdateExpression { operator: String, prefix: bool, argument: Expression }
+        BinaryExpression { operator: String, left: Expression, right: Expression }
+        LogicalExpression { operator: String, left: Expression, right: Expression }
+        AssignmentExpression { operator: String, left: Pattern, right: Expression }
+        MemberExpression { object: Expression, property: Expression, computed: bool }
+        ConditionalExpression {
+            test: Expression,
+            consequent: Expression,
+            alternate: Expression,
+        }
+        CallExpression { callee: Expression, arguments: Vec<ExpressionOrSpread> }
+        NewExpression { callee: Expression, arguments: Vec<ExpressionOrSpread> }
+        SequenceExpression { expressions: Vec<Expression> }
+        YieldExpression { argument: Option<Expression>, delegate: bool }
+        AwaitExpression { argument: Expression }
+        TemplateLiteral { quasis: Vec<TemplateElement>, expressions: Vec<Expression> }
+        TaggedTemplateExpression { tag: Expression, quasi: TemplateLiteral }
+        TemplateElement { tail: bool, raw: String, cooked: Option<String> }
+        ClassExpression {
+            id: Option<Identifier>,
+            superClass: Option<Expression>,
+            body: ClassBody,
+        }
+        MetaProperty { meta: Identifier, property: Identifier }
+        /// Only produced with the `preserveParens` option.
+        ParenthesizedExpression { expression: Expression }
+
+        // Patterns
+        ObjectPattern { properties: Vec<ObjectPatternProperty> }
+        /// A `Property` inside an `ObjectPattern`, whose value is a
+        /// pattern rather than an expression.
+        AssignmentProperty = "Property" {
+            key: Expression,
+            value: Pattern,
+            shorthand: bool,
+            computed: bool,
+        }
+        ArrayPattern { elements: Vec<Option<Pattern>> }
+        RestElement { argument: Pattern }
+        AssignmentPattern { left: Pattern, right: Expression }
+
+        // Modules
+        ImportDeclaration { specifiers: Vec<ImportDeclarationSpecifier>, source: Literal }
+        ImportSpecifier { imported: Identifier, local: Identifier }
+        ImportDefaultSpecifier { local: Identifier }
+        ImportNamespaceSpecifier { local: Identifier }
+        ExportNamedDeclaration {
+            declaration: Option<Declaration>,
+            specifiers: Vec<ExportSpecifier>,
+            source: Option<Literal>,
+        }
+        ExportSpecifier { local: Identifier, exported: Identifier }
+        ExportDefaultDeclaration { declaration: ExportDefaultKind }
+        ExportAllDeclaration { source: Literal }
+    }
+
+    enums {
+        Statement {
+            ExpressionStatement,
+            BlockStatement,
+            EmptyStatement,
+            DebuggerStatement,
+            WithStatement,
+            ReturnStatement,
+            LabeledStatement,
+            BreakStatement,
+            ContinueStatement,
+            IfStatement,
+            SwitchStatement,
+            ThrowStatement,
+            TryStatement,
+            WhileStatement,
+            DoWhileStatement,
+            ForStatement,
+            ForInStatement,
+            ForOfStatement,
+            FunctionDeclaration,
+            VariableDeclaration,
+            ClassDeclaration,
+        }
+        Declaration { FunctionDeclaration, VariableDeclaration, ClassDeclaration }
+        Expression {
+            Identifier,
+            Literal,
+            ThisExpression,
+            Super,
+            ArrayExpression,
+            ObjectExpression,
+            FunctionExpression,
+            ArrowFunctionExpression,
+            UnaryExpression,
+            UpdateExpression,
+            BinaryExpression,
+            LogicalExpression,
+            AssignmentExpression,
+            MemberExpression,
+            ConditionalExpression,
+            CallExpression,
+            NewExpression,
+            SequenceExpression,
+            YieldExpression,
+            AwaitExpression,
+            TemplateLiteral,
+            TaggedTemplateExpression,
+            ClassExpression,
+            MetaProperty,
+            ParenthesizedExpression,
+        }
+        Pattern {
+            Identifier,
+            MemberExpression,
+            ParenthesizedExpression,
+            ObjectPattern,
+            ArrayPattern,
+            RestElement,
+            AssignmentPattern,
+        }
+        ModuleDeclaration {
+            ImportDeclaration,
+            ExportNamedDeclaration,
+            ExportDefaultDeclaration,
+            ExportAllDeclaration,
+        }
+        /// An entry of `Program.body`.
+        ModuleItem { ; ModuleDeclaration, Statement }
+        ForInit { VariableDeclaration; Expression }
+        ForInLeft { VariableDeclaration; Pattern }
+        ArrowBody { BlockStatement; Expression }
+        ExpressionOrSpread { SpreadElement; Expression }
+        ObjectProperty { Property, SpreadElement }
+        ObjectPatternProperty { AssignmentProperty, RestElement }
+        ImportDeclarationSpecifier {
+            ImportSpecifier,
+            ImportDefaultSpecifier,
+            ImportNamespaceSpecifier,
+        }
+        ExportDefaultKind { FunctionDeclaration, ClassDeclaration; Expression }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodesLiteralValues() {
+        assert_eq!(literalValue("'a\\nb'"), LiteralValue::String(String::from("a\nb")));
+        assert_eq!(literalValue("/a/g"), LiteralValue::RegExp {
+            pattern: String::from("a"),
+            flags: String::from("g"),
+        });
+        assert_eq!(literalValue("0x1F"), LiteralValue::Number(31.0));
+        assert_eq!(literalValue("1_000"), LiteralValue::Number(1000.0));
+        assert_eq!(literalValue("10n"), LiteralValue::BigInt(String::from("10")));
+        assert_eq!(literalValue("0xFFFFFFFFFFFFFFFFF"), LiteralValue::Number(2f64.powi(68)));
+    }
+
+    #[test]
+    fn decodesLegacyOctalEscapes() {
+        assert_eq!(unescape("\\101"), "A");
+        assert_eq!(unescape("\\0"), "\0");
+        assert_eq!(unescape("\\08"), "\08");
+        assert_eq!(unescape("\\7a"), "\u{7}a");
+        assert_eq!(unescape("\\377"), "\u{ff}");
+        assert_eq!(unescape("\\400"), " 0");
+        assert_eq!(unescape("\\1234"), "S4");
+        assert_eq!(unescape("\\8\\9"), "89");
+        assert_eq!(literalValue("'\\101'"), LiteralValue::String(String::from("A")));
+    }
+
+    #[test]
+    fn decodesLegacyOctalLiterals() {
+        assert_eq!(literalValue("0777"), LiteralValue::Number(511.0));
+        assert_eq!(literalValue("0089"), LiteralValue::Number(89.0));
+        assert_eq!(literalValue("0"), LiteralValue::Number(0.0));
+        assert_eq!(literalValue("0.5"), LiteralValue::Number(0.5));
+    }
+
+    #[test]
+    fn malformedLiteralsAreInvalid() {
+        for raw in &["'", "\"", "'a\"", "/", "/a", "0x", "abc", ""] {
+            assert_eq!(literalValue(raw), LiteralValue::Invalid, "{}", raw);
+        }
+    }
+
+    fn nodeOfType(r#type: &str) -> node::Node {
+        node::Node {
+            r#type: String::from(r#type),
+            ..node::Node::default()
+        }
+    }
+
+    #[test]
+    fn setFieldRejectsFieldsTheTypeDoesNotHave() {
+        let mut case = nodeOfType("SwitchCase");
+        let statements = vec![nodeOfType("EmptyStatement"), nodeOfType("EmptyStatement")];
+        assert_eq!(setField(&mut case, "consequent", FieldValue::Nodes(statements)), Ok(()));
+        assert_eq!(case.consequents.as_ref().map(Vec::len), Some(2));
+        assert_eq!(
+            setField(&mut case, "consequent", FieldValue::Node(nodeOfType("EmptyStatement"))),
+            Err(ConvertError::MissingField {
+                nodeType: "SwitchCase",
+                field: "consequent",
+            })
+        );
+
+        let mut test = nodeOfType("IfStatement");
+        assert_eq!(
+            setField(&mut test, "consequent", FieldValue::Nodes(Vec::new())),
+            Err(ConvertError::MissingField {
+                nodeType: "IfStatement",
+                field: "consequent",
+            })
+        );
+        assert!(test.consequents.is_none());
+        assert_eq!(
+            setField(&mut test, "name", FieldValue::Str(String::from("x"))),
+            Err(ConvertError::UnexpectedField {
+                nodeType: String::from("IfStatement"),
+                field: String::from("name"),
+            })
+        );
+        assert!(test.name.is_none());
+    }
+
+    #[test]
+    fn categoriesAcceptTheirMembers() {
+        assert!(belongsTo("Identifier", "Expression"));
+        assert!(belongsTo("ExpressionStatement", "ModuleItem"));
+        assert!(belongsTo("ImportDeclaration", "ModuleItem"));
+        assert!(belongsTo("Property", "ObjectPatternProperty"));
+        assert!(!belongsTo("ExpressionStatement", "Expression"));
+        assert!(!belongsTo("Identifier", "BlockStatement"));
+    }
+
+    #[test]
+    fn parenthesizedExpressionsAreInTheTable() {
+        let fields = fieldsOf("ParenthesizedExpression").unwrap();
+        assert_eq!(fields.iter().collect::<Vec<_>>(), [("expression", Shape::Node)]);
+        assert!(Expression::accepts("ParenthesizedExpression"));
+        assert!(Pattern::accepts("ParenthesizedExpression"));
+    }
+}
@@ -0,0 +1,540 @@
+// A compact binary encoding of a parsed tree and its comments, for
+// persisting parse results (see `cache.rs`). It is lossless: decoding
+// gives back the same nodes, offsets, `loc`, `range` and comments.
+//
+// Layout, with every integer a LEB128 varint unless noted:
+//
+//     magic      b"CAPB"
+//     version    u32, little endian: FORMAT_VERSION
+//     strings    count, then (length, UTF-8 bytes) for each
+//     tree       the root node
+//     comments   count, then each comment
+//
+// A node is its type (a string index), `start`, `end`, a byte of
+// flags saying which of `loc`, `range` and `sourceFile` follow, those,
+// and then its fields: a count, then (index into `ast::NODE_FIELDS`,
+// kind byte, payload) for each field that is set. Strings are interned,
+// so repeated names, operators and types are stored once.
+
+use crate::ast;
+use crate::locutil;
+use crate::node;
+use crate::options;
+use crate::tokenize;
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use ast::{FieldRef, FieldValue};
+
+/// Bumped whenever the layout changes; data written with another
+/// version is rejected rather than misread.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"CAPB";
+
+/// How deeply nodes may nest in data being decoded. Decoding recurses
+/// once per level, so without a limit corrupt or hostile data could
+/// overflow the stack; real trees stay far below it.
+pub const MAX_DEPTH: usize = 512;
+
+const HAS_LOC: u8 = 1;
+const HAS_RANGE: u8 = 2;
+const HAS_SOURCE_FILE: u8 = 4;
+const IS_BLOCK: u8 = 8;
+
+const LOC_START: u8 = 1;
+const LOC_END: u8 = 2;
+const LOC_SOURCE: u8 = 4;
+
+const KIND_NODE: u8 = 0;
+const KIND_NODES: u8 = 1;
+const KIND_STR: u8 = 2;
+const KIND_FALSE: u8 = 3;
+const KIND_TRUE: u8 = 4;
+const KIND_SCRIPT: u8 = 5;
+const KIND_MODULE: u8 = 6;
+const KIND_COMMONJS: u8 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The data does not start with the expected magic bytes.
+    NotEncoded,
+    /// The data was written by another version of the format.
+    Version(u32),
+    /// The data ends in the middle of a value.
+    Truncated,
+    /// The data is not a valid encoding.
+    Corrupt,
+    /// Nodes are nested more than `MAX_DEPTH` deep.
+    TooDeep,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::NotEncoded => write!(f, "Not an encoded tree"),
+            DecodeError::Version(version) => write!(
+                f,
+                "Encoded with format version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            DecodeError::Truncated => write!(f, "Encoded tree is truncated"),
+            DecodeError::Corrupt => write!(f, "Encoded tree is corrupt"),
+            DecodeError::TooDeep => write!(f, "Encoded tree is nested too deeply"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Encode a tree and its comments.
+pub fn encode(root: &node::Node, comments: &[tokenize::Comment]) -> Vec<u8> {
+    let mut encoder = Encoder {
+        out: Vec::new(),
+        strings: Vec::new(),
+        indexes: HashMap::new(),
+    };
+    encoder.node(root);
+    encoder.varint(comments.len());
+    for comment in comments {
+        encoder.comment(comment);
+    }
+
+    let mut out = Vec::with_capacity(encoder.out.len() + 64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    writeVarint(&mut out, encoder.strings.len());
+    for string in &encoder.strings {
+        writeVarint(&mut out, string.len());
+        out.extend_from_slice(string.as_bytes());
+    }
+    out.extend_from_slice(&encoder.out);
+    out
+}
+
+/// Decode a tree and its comments written by `encode`.
+pub fn decode(bytes: &[u8]) -> Result<(node::Node, Vec<tokenize::Comment>), DecodeError> {
+    if bytes.len() < 8 || &bytes[..4] != MAGIC {
+        return Err(DecodeError::NotEncoded);
+    }
+    let mut version = [0; 4];
+    version.copy_from_slice(&bytes[4..8]);
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        return Err(DecodeError::Version(version));
+    }
+
+    let mut decoder = Decoder {
+        bytes,
+        pos: 8,
+        strings: Vec::new(),
+        depth: 0,
+    };
+    let count = decoder.varint()?;
+    for _ in 0..count {
+        let len = decoder.varint()?;
+        let string = std::str::from_utf8(decoder.take(len)?).map_err(|_| DecodeError::Corrupt)?;
+        decoder.strings.push(String::from(string));
+    }
+    let root = *decoder.node()?;
+    let count = decoder.varint()?;
+    let mut comments = Vec::with_capacity(count.min(bytes.len()));
+    for _ in 0..count {
+        comments.push(decoder.comment()?);
+    }
+    if decoder.pos != bytes.len() {
+        return Err(DecodeError::Corrupt);
+    }
+    Ok((root, comments))
+}
+
+fn writeVarint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Encoder<'a> {
+    out: Vec<u8>,
+    strings: Vec<&'a str>,
+    indexes: HashMap<&'a str, usize>,
+}
+
+impl<'a> Encoder<'a> {
+    fn varint(&mut self, value: usize) {
+        writeVarint(&mut self.out, value);
+    }
+
+    fn string(&mut self, string: &'a str) {
+        let next = self.strings.len();
+        let index = *self.indexes.entry(string).or_insert(next);
+        if index == next {
+            self.strings.push(string);
+        }
+        self.varint(index);
+    }
+
+    fn position(&mut self, position: &locutil::Position) {
+        self.varint(position.line);
+        self.varint(position.column);
+    }
+
+    fn location(&mut self, loc: &'a locutil::SourceLocation) {
+        let mut flags = 0;
+        if loc.start.is_some() {
+            flags |= LOC_START;
+        }
+        if loc.end.is_some() {
+            flags |= LOC_END;
+        }
+        if loc.source.is_some() {
+            flags |= LOC_SOURCE;
+        }
+        self.out.push(flags);
+        if let Some(start) = &loc.start {
+            self.position(start);
+        }
+        if let Some(end) = &loc.end {
+            self.position(end);
+        }
+        if let Some(source) = &loc.source {
+            self.string(source);
+        }
+    }
+
+    fn node(&mut self, node: &'a node::Node) {
+        self.string(&node.r#type);
+        self.varint(node.start);
+        self.varint(node.end);
+        let mut flags = 0;
+        if node.loc().is_some() {
+            flags |= HAS_LOC;
+        }
+        if node.range().is_some() {
+            flags |= HAS_RANGE;
+        }
+        if node.sourceFile().is_some() {
+            flags |= HAS_SOURCE_FILE;
+        }
+        self.out.push(flags);
+        if let Some(loc) = node.loc() {
+            self.location(loc);
+        }
+        if let Some((start, end)) = node.range() {
+            self.varint(start);
+            self.varint(end);
+        }
+        if let Some(sourceFile) = node.sourceFile() {
+            self.string(sourceFile);
+        }
+
+        let fields: Vec<(usize, FieldRef<'a>)> = ast::NODE_FIELDS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (i, ast::field(node, name)))
+            .filter(|(i, field)| match field {
+                FieldRef::Missing => false,
+                // `expressions` is the one list that is never absent, so
+                // an empty one need not be stored.
+                FieldRef::Nodes(nodes) => {
+                    !nodes.is_empty() || ast::NODE_FIELDS[*i] != "expressions"
+                }
+                _ => true,
+            })
+            .collect();
+        self.varint(fields.len());
+        for (i, field) in fields {
+            self.varint(i);
+            match field {
+                FieldRef::Missing => unreachable!(),
+                FieldRef::Node(child) => {
+                    self.out.push(KIND_NODE);
+                    self.node(child);
+                }
+                FieldRef::Nodes(children) => {
+                    self.out.push(KIND_NODES);
+                    self.varint(children.len());
+                    for child in children {
+                        self.node(child);
+                    }
+                }
+                FieldRef::Str(value) => {
+                    self.out.push(KIND_STR);
+                    self.string(value);
+                }
+                FieldRef::Bool(value) => self.out.push(if value { KIND_TRUE } else { KIND_FALSE }),
+                FieldRef::SourceType(options::SourceType::Script) => self.out.push(KIND_SCRIPT),
+                FieldRef::SourceType(options::SourceType::Module) => self.out.push(KIND_MODULE),
+                FieldRef::SourceType(options::SourceType::CommonJS) => self.out.push(KIND_COMMONJS),
+            }
+        }
+    }
+
+    fn comment(&mut self, comment: &'a tokenize::Comment) {
+        let mut flags = 0;
+        if comment.block {
+            flags |= IS_BLOCK;
+        }
+        if comment.loc.is_some() {
+            flags |= HAS_LOC;
+        }
+        if comment.range.is_some() {
+            flags |= HAS_RANGE;
+        }
+        self.out.push(flags);
+        self.string(&comment.value);
+        self.varint(comment.start);
+        self.varint(comment.end);
+        if let Some(loc) = &comment.loc {
+            self.location(loc);
+        }
+        if let Some((start, end)) = comment.range {
+            self.varint(start);
+            self.varint(end);
+        }
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    strings: Vec<String>,
+    /// How many nodes enclose the one being decoded.
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.pos).ok_or(DecodeError::Truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self.pos.checked_add(len).ok_or(DecodeError::Corrupt)?;
+        let bytes = self.bytes.get(self.pos..end).ok_or(DecodeError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<usize, DecodeError> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= usize::MAX.count_ones() {
+                return Err(DecodeError::Corrupt);
+            }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let index = self.varint()?;
+        self.strings.get(index).cloned().ok_or(DecodeError::Corrupt)
+    }
+
+    fn position(&mut self) -> Result<locutil::Position, DecodeError> {
+        Ok(locutil::Position::new(self.varint()?, self.varint()?))
+    }
+
+    fn location(&mut self) -> Result<locutil::SourceLocation, DecodeError> {
+        let flags = self.byte()?;
+        let mut loc = locutil::SourceLocation {
+            start: None,
+            end: None,
+            source: None,
+        };
+        if flags & LOC_START != 0 {
+            loc.start = Some(self.position()?);
+        }
+        if flags & LOC_END != 0 {
+            loc.end = Some(self.position()?);
+        }
+        if flags & LOC_SOURCE != 0 {
+            loc.source = Some(self.string()?);
+        }
+        Ok(loc)
+    }
+
+    fn node(&mut self) -> Result<Box<node::Node>, DecodeError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+        self.depth += 1;
+        // The frames of this function, `field` and `child` are on the
+        // stack once for every level of nesting, so they are kept small:
+        // the node is built on the heap, its header is decoded by
+        // `nodeHeader`, and its child lists by `children`.
+        let mut node = self.nodeHeader()?;
+        let count = self.varint()?;
+        for _ in 0..count {
+            self.field(&mut node)?;
+        }
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    /// Decode a node's type, offsets and location.
+    fn nodeHeader(&mut self) -> Result<Box<node::Node>, DecodeError> {
+        let mut node = Box::new(node::Node {
+            r#type: self.string()?,
+            start: self.varint()?,
+            end: self.varint()?,
+            ..node::Node::default()
+        });
+        let flags = self.byte()?;
+        let loc = if flags & HAS_LOC != 0 {
+            Some(self.location()?)
+        } else {
+            None
+        };
+        let range = if flags & HAS_RANGE != 0 {
+            Some((self.varint()?, self.varint()?))
+        } else {
+            None
+        };
+        let sourceFile = if flags & HAS_SOURCE_FILE != 0 {
+            Some(self.string()?)
+        } else {
+            None
+        };
+        node.setLocation(loc, sourceFile, range);
+        Ok(node)
+    }
+
+    /// Decode one field and store it on `node`.
+    fn field(&mut self, node: &mut node::Node) -> Result<(), DecodeError> {
+        let name = *ast::NODE_FIELDS
+            .get(self.varint()?)
+            .ok_or(DecodeError::Corrupt)?;
+        let value = match self.byte()? {
+            KIND_NODE => return self.child(node, name),
+            KIND_NODES => return self.children(node, name),
+            KIND_STR => FieldValue::Str(self.string()?),
+            KIND_FALSE => FieldValue::Bool(false),
+            KIND_TRUE => FieldValue::Bool(true),
+            KIND_SCRIPT => FieldValue::SourceType(options::SourceType::Script),
+            KIND_MODULE => FieldValue::SourceType(options::SourceType::Module),
+            KIND_COMMONJS => FieldValue::SourceType(options::SourceType::CommonJS),
+            _ => return Err(DecodeError::Corrupt),
+        };
+        ast::setField(node, name, value).map_err(|_| DecodeError::Corrupt)
+    }
+
+    fn child(&mut self, node: &mut node::Node, name: &str) -> Result<(), DecodeError> {
+        let child = self.node()?;
+        ast::setField(node, name, FieldValue::Node(*child)).map_err(|_| DecodeError::Corrupt)
+    }
+
+    fn children(&mut self, node: &mut node::Node, name: &str) -> Result<(), DecodeError> {
+        let len = self.varint()?;
+        let mut children = Vec::with_capacity(len.min(self.bytes.len()));
+        for _ in 0..len {
+            children.push(*self.node()?);
+        }
+        ast::setField(node, name, FieldValue::Nodes(children)).map_err(|_| DecodeError::Corrupt)
+    }
+
+    fn comment(&mut self) -> Result<tokenize::Comment, DecodeError> {
+        let flags = self.byte()?;
+        let mut comment = tokenize::Comment {
+            block: flags & IS_BLOCK != 0,
+            value: self.string()?,
+            start: self.varint()?,
+            end: self.varint()?,
+            loc: None,
+            range: None,
+        };
+        if flags & HAS_LOC != 0 {
+            comment.loc = Some(self.location()?);
+        }
+        if flags & HAS_RANGE != 0 {
+            comment.range = Some((self.varint()?, self.varint()?));
+        }
+        Ok(comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options;
+    use crate::state;
+
+    #[test]
+    fn roundTripsParsedTrees() {
+        let input = "class A extends B { static *m(a, ...b) { for (const [x, , y] of z) { \
+                     switch (x) { case 1: a(); b(); default: } } } } \
+                     label: for (;;) break label; `a${b}c`; ({ a, b: [c] } = e); (x) => x;";
+        let options = options::Options::builder()
+            .ecmaVersion(options::EcmaVersion::Latest)
+            .preserveParens(true)
+            .build()
+            .unwrap();
+        let (program, comments) = match state::Parser::parseWithComments(String::from(input), Some(options)) {
+            Ok(parsed) => parsed,
+            Err(err) => panic!("{}", err),
+        };
+        match decode(&encode(&program, &comments)) {
+            Ok((decoded, _)) => assert!(decoded == program),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// A chain of `depth` nested unary expressions.
+    fn nested(depth: usize) -> node::Node {
+        let mut node = node::Node {
+            r#type: String::from("Identifier"),
+            name: Some(String::from("x")),
+            ..node::Node::default()
+        };
+        for _ in 1..depth {
+            node = node::Node {
+                r#type: String::from("UnaryExpression"),
+                operator: Some(String::from("!")),
+                prefix: Some(true),
+                argument: Some(Box::new(node)),
+                ..node::Node::default()
+            };
+        }
+        node
+    }
+
+    #[test]
+    fn limitsNestingDepth() {
+        // Encoding and dropping the tree recurse too, and unoptimized
+        // builds use several kilobytes of stack per level, more than the
+        // default test thread has room for at this depth.
+        std::thread::Builder::new()
+            .stack_size(32 << 20)
+            .spawn(|| {
+                assert!(decode(&encode(&nested(MAX_DEPTH), &[])).is_ok());
+                match decode(&encode(&nested(MAX_DEPTH + 1), &[])) {
+                    Ok(_) => panic!("decoded a tree nested too deeply"),
+                    Err(err) => assert_eq!(err, DecodeError::TooDeep),
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn rejectsTruncatedAndForeignData() {
+        let bytes = encode(&nested(3), &[]);
+        assert_eq!(decode(b"not a tree").err(), Some(DecodeError::NotEncoded));
+        assert_eq!(decode(&bytes[..bytes.len() - 2]).err(), Some(DecodeError::Truncated));
+        let mut newer = bytes.clone();
+        newer[4] = 99;
+        assert_eq!(decode(&newer).err(), Some(DecodeError::Version(99)));
+    }
+}
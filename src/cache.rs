@@ -0,0 +1,178 @@
+// An on-disk cache of parse results, keyed by the SHA-256 digest of
+// the source text and of the options it was parsed with. Entries are
+// stored in the format of `binary.rs`, one file per key named after
+// the digest, so reloading a file that has not changed skips
+// tokenizing and parsing entirely.
+//
+// A missing, stale or unreadable entry is a cache miss, never an
+// error: the caller just parses again.
+
+use crate::binary;
+use crate::location;
+use crate::node;
+use crate::options;
+use crate::state;
+use crate::tokenize;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// A cache storing its entries in `dir`, which is created when the
+    /// first entry is written.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    /// The key under which the result of parsing `input` with
+    /// `options` is stored: the hex SHA-256 digest of the format
+    /// version, the options and the input. It covers the format
+    /// version, so entries written by an older format are never looked
+    /// up.
+    pub fn key(input: &str, options: &options::Options) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(binary::FORMAT_VERSION.to_le_bytes());
+        hasher.update(options.fingerprint().as_bytes());
+        hasher.update([0]);
+        hasher.update(input.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.capb", key))
+    }
+
+    /// The cached result for `input` and `options`, if there is one.
+    pub fn get(
+        &self,
+        input: &str,
+        options: &options::Options,
+    ) -> Option<(node::Node, Vec<tokenize::Comment>)> {
+        self.load(&Cache::key(input, options))
+    }
+
+    fn load(&self, key: &str) -> Option<(node::Node, Vec<tokenize::Comment>)> {
+        let bytes = fs::read(self.path(key)).ok()?;
+        binary::decode(&bytes).ok()
+    }
+
+    /// Store the result of parsing `input` with `options`.
+    pub fn put(
+        &self,
+        input: &str,
+        options: &options::Options,
+        root: &node::Node,
+        comments: &[tokenize::Comment],
+    ) -> io::Result<()> {
+        self.store(&Cache::key(input, options), root, comments)
+    }
+
+    fn store(
+        &self,
+        key: &str,
+        root: &node::Node,
+        comments: &[tokenize::Comment],
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file and rename it into place, so that a
+        // concurrent reader never sees a partial entry.
+        let path = self.path(key);
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp, binary::encode(root, comments))?;
+        fs::rename(&temp, &path)
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Parse `input` as `parseWithComments` does, reusing a cached
+    /// result when there is one and caching the result otherwise.
+    /// Failing to write the cache does not fail the parse.
+    pub fn parse(
+        &self,
+        input: String,
+        options: Option<options::Options>,
+    ) -> Result<(node::Node, Vec<tokenize::Comment>), location::SyntaxError> {
+        let options = options.unwrap_or_default();
+        let key = Cache::key(&input, &options);
+        if let Some(cached) = self.load(&key) {
+            return Ok(cached);
+        }
+        let (root, comments) = state::Parser::parseWithComments(input, Some(options))?;
+        let _ = self.store(&key, &root, &comments);
+        Ok((root, comments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> Cache {
+        let dir = std::env::temp_dir().join(format!("cache-{}-{}", name, std::process::id()));
+        let cache = Cache::new(dir);
+        cache.clear().unwrap();
+        cache
+    }
+
+    #[test]
+    fn returnsWhatWasPut() {
+        let cache = scratch("hit");
+        let options = options::Options::default();
+        assert!(cache.get("a + b;", &options).is_none());
+        let (root, comments) = cache.parse(String::from("a + b; // c"), None).unwrap();
+        match cache.get("a + b; // c", &options) {
+            Some((cached, cachedComments)) => {
+                assert_eq!(binary::encode(&cached, &cachedComments), binary::encode(&root, &comments));
+            }
+            None => panic!("the parse was not cached"),
+        }
+        assert!(cache.get("a - b; // c", &options).is_none());
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn namesEntriesAfterTheirDigest() {
+        let options = options::Options::default();
+        let key = Cache::key("a;", &options);
+        assert_eq!(key.len(), 64);
+        assert!(key.bytes().all(|b| b.is_ascii_hexdigit() && !b.is_ascii_uppercase()));
+        assert_ne!(key, Cache::key("b;", &options));
+        let module = options::Options::builder()
+            .sourceType(options::SourceType::Module)
+            .build()
+            .unwrap();
+        assert_ne!(key, Cache::key("a;", &module));
+    }
+
+    #[test]
+    fn treatsDamagedEntriesAsMisses() {
+        let cache = scratch("damaged");
+        let options = options::Options::default();
+        cache.parse(String::from("c;"), None).unwrap();
+        let path = cache.path(&Cache::key("c;", &options));
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[0] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(cache.get("c;", &options).is_none());
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(cache.get("c;", &options).is_none());
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,1456 @@
+// JavaScript code generation, after astring and escodegen: `generate`
+// prints a tree back to source that parses to the same tree.
+//
+// Parentheses are not kept in the tree, so they are put back from
+// operator precedence: a child is wrapped when it binds more loosely
+// than its position allows, or when it would otherwise change how the
+// statement around it is read (an object literal at the start of an
+// expression statement, a bare `in` in a `for` head, `??` mixed with
+// `||`, ...). Every statement that can end with a semicolon gets one,
+// so the output never depends on automatic semicolon insertion.
+//
+// Comments are not part of the tree either. `generateWithComments`
+// takes the ones `parseWithComments` collected and prints each before
+// the statement, class member or closing brace that follows it in the
+// source, or after a statement that ends on the line it starts on.
+//
+// `generateWithSourceMap` also returns a source map, with a mapping
+// from the start of every statement and expression printed to its
+// `loc` in the source named by `loc.source` (which the parser sets
+// from `Options.sourceFile`); identifiers are mapped with their names.
+// Source maps count columns in UTF-16 units. A tree parsed with UTF-8
+// positions only gets mappings when its source text is given, to
+// convert its columns with.
+
+use crate::ast;
+use crate::locutil;
+use crate::node;
+use crate::options;
+use crate::sourcemap;
+use crate::tokenize;
+
+use ast::LiteralValue;
+
+/// Text to print in place of a node, if any; see `generateReusing`.
+type Reuse<'a> = &'a dyn Fn(&node::Node) -> Option<String>;
+
+/// The quotes string literals are printed with.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum Quotes {
+    Single,
+    #[default]
+    Double,
+    /// Print every literal exactly as it was written.
+    Preserve,
+}
+
+
+pub struct GenerateOptions {
+    /// One level of indentation.
+    pub indent: String,
+    /// Written at the end of every line.
+    pub lineEnd: String,
+    /// The indentation level of the top-level statements.
+    pub startingIndentLevel: usize,
+    pub quotes: Quotes,
+    /// The name of the generated file, recorded in the source map.
+    pub file: Option<String>,
+    /// The map of the source the tree was parsed from, when that is
+    /// itself generated. The source map then points through it at the
+    /// original sources.
+    pub inputSourceMap: Option<sourcemap::SourceMap>,
+    /// The unit of the columns in the tree's locations: the
+    /// `Options.positionEncoding` it was parsed with.
+    pub positionEncoding: options::PositionEncoding,
+    /// The text the tree was parsed from. Needed to map a tree with
+    /// UTF-8 columns; nodes of such a tree are not mapped without it.
+    pub sourceContent: Option<String>,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            indent: String::from("  "),
+            lineEnd: String::from("\n"),
+            startingIndentLevel: 0,
+            quotes: Quotes::default(),
+            file: None,
+            inputSourceMap: None,
+            positionEncoding: options::PositionEncoding::default(),
+            sourceContent: None,
+        }
+    }
+}
+
+/// Print `node`, which can be a `Program`, a statement or an
+/// expression.
+pub fn generate(node: &node::Node, options: &GenerateOptions) -> String {
+    generateWithComments(node, &[], options)
+}
+
+/// Print `node` along with `comments`, which must be in source order,
+/// as `parseWithComments` returns them.
+pub fn generateWithComments(
+    node: &node::Node,
+    comments: &[tokenize::Comment],
+    options: &GenerateOptions,
+) -> String {
+    let mut generator = Generator::new(comments, options, None);
+    generator.root(node);
+    generator.out
+}
+
+/// Print `node`, writing the text `reuse` returns for a statement or
+/// expression instead of printing it, for `lossless` to keep the
+/// original text of the parts of a tree that were not edited.
+pub(crate) fn generateReusing(
+    node: &node::Node,
+    options: &GenerateOptions,
+    reuse: Reuse,
+) -> String {
+    let mut generator = Generator::new(&[], options, None);
+    generator.reuse = Some(reuse);
+    generator.root(node);
+    generator.out
+}
+
+/// Print `node` along with `comments` as `generateWithComments` does,
+/// and return a source map for the output.
+pub fn generateWithSourceMap(
+    node: &node::Node,
+    comments: &[tokenize::Comment],
+    options: &GenerateOptions,
+) -> (String, sourcemap::SourceMap) {
+    let map = sourcemap::SourceMap::new(options.file.clone());
+    let mut generator = Generator::new(comments, options, Some(map));
+    if options.positionEncoding == options::PositionEncoding::Utf8 {
+        generator.source = options
+            .sourceContent
+            .as_deref()
+            .map(|content| locutil::SourceText::new(content, options::PositionEncoding::Utf8));
+    }
+    generator.root(node);
+    let mut map = generator.map.unwrap_or_default();
+    if let Some(input) = &options.inputSourceMap {
+        map = map.compose(input);
+    }
+    (generator.out, map)
+}
+
+// Precedence levels, from loosest to tightest. Binary operators take
+// `BINARY` plus their `binop` value from `tokentype.rs`.
+const SEQUENCE: u8 = 0;
+const ASSIGNMENT: u8 = 1;
+const CONDITIONAL: u8 = 2;
+const BINARY: u8 = 3;
+const UNARY: u8 = 15;
+const POSTFIX: u8 = 16;
+const CALL: u8 = 17;
+const PRIMARY: u8 = 18;
+
+fn binaryPrecedence(operator: &str) -> u8 {
+    BINARY
+        + match operator {
+            "??" | "||" => 1,
+            "&&" => 2,
+            "|" => 3,
+            "^" => 4,
+            "&" => 5,
+            "==" | "!=" | "===" | "!==" => 6,
+            "<" | ">" | "<=" | ">=" | "in" | "instanceof" => 7,
+            "<<" | ">>" | ">>>" => 8,
+            "+" | "-" => 9,
+            "*" | "/" | "%" => 10,
+            "**" => 11,
+            _ => 0,
+        }
+}
+
+pub(crate) fn precedence(node: &node::Node) -> u8 {
+    match node.r#type.as_str() {
+        "SequenceExpression" => SEQUENCE,
+        "AssignmentExpression"
+        | "AssignmentPattern"
+        | "ArrowFunctionExpression"
+        | "YieldExpression"
+        | "SpreadElement"
+        | "RestElement" => ASSIGNMENT,
+        "ConditionalExpression" => CONDITIONAL,
+        "BinaryExpression" | "LogicalExpression" => {
+            binaryPrecedence(node.operator.as_deref().unwrap_or(""))
+        }
+        "UnaryExpression" | "AwaitExpression" => UNARY,
+        "UpdateExpression" if node.prefix == Some(true) => UNARY,
+        "UpdateExpression" => POSTFIX,
+        "CallExpression" | "NewExpression" | "MemberExpression" | "TaggedTemplateExpression" => {
+            CALL
+        }
+        _ => PRIMARY,
+    }
+}
+
+/// `??` cannot be mixed with `||` or `&&` without parentheses.
+fn mixesCoalesce(operator: &str, child: &node::Node) -> bool {
+    if child.r#type != "LogicalExpression" {
+        return false;
+    }
+    let childOperator = child.operator.as_deref().unwrap_or("");
+    (operator == "??") != (childOperator == "??")
+}
+
+/// Whether `callee` has a call in its chain of member objects, which
+/// `new` would otherwise take as its own arguments.
+fn hasCall(callee: &node::Node) -> bool {
+    match callee.r#type.as_str() {
+        "CallExpression" => true,
+        "MemberExpression" | "TaggedTemplateExpression" => callee
+            .object
+            .as_deref()
+            .or(callee.tag.as_deref())
+            .is_some_and(hasCall),
+        _ => false,
+    }
+}
+
+/// Whether `node` is an `if` statement whose last `if` has no `else`,
+/// so an `else` after it would attach to the wrong one.
+fn endsWithBareIf(node: &node::Node) -> bool {
+    match node.r#type.as_str() {
+        "IfStatement" => node.alternate.as_deref().is_none_or(endsWithBareIf),
+        "WithStatement" | "LabeledStatement" | "WhileStatement" | "ForStatement"
+        | "ForInStatement" | "ForOfStatement" => body(node).is_some_and(endsWithBareIf),
+        _ => false,
+    }
+}
+
+/// A body stored as a single node; see `node::Node.body`.
+fn body(node: &node::Node) -> Option<&node::Node> {
+    node.body.as_ref().and_then(|body| body.first())
+}
+
+/// A body stored as a list.
+fn bodyList(node: &node::Node) -> &[node::Node] {
+    node.body.as_deref().unwrap_or(&[])
+}
+
+fn list(nodes: &Option<Vec<node::Node>>) -> &[node::Node] {
+    nodes.as_deref().unwrap_or(&[])
+}
+
+struct Generator<'a> {
+    options: &'a GenerateOptions,
+    out: String,
+    level: usize,
+    comments: &'a [tokenize::Comment],
+    nextComment: usize,
+    /// The output offset of the start of the current expression
+    /// statement, where an expression must not begin with `{`,
+    /// `function` or `class`.
+    statementStart: Option<usize>,
+    /// The output offset of the start of the current arrow function
+    /// expression body, where an expression must not begin with `{`.
+    arrowBodyStart: Option<usize>,
+    /// Set in the head of a `for` statement, where a bare `in` would
+    /// make it a `for`-`in`.
+    noIn: bool,
+    /// The source map being built, if one was asked for.
+    map: Option<sourcemap::SourceMap>,
+    /// Called on every statement and expression before printing it;
+    /// text it returns is written in place of the node.
+    reuse: Option<Reuse<'a>>,
+    /// The position the next write starts at, as a line and a UTF-16
+    /// column; only tracked when building a source map.
+    line: usize,
+    column: usize,
+    /// `GenerateOptions.sourceContent`, to convert UTF-8 columns with.
+    source: Option<locutil::SourceText<'a>>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(
+        comments: &'a [tokenize::Comment],
+        options: &'a GenerateOptions,
+        map: Option<sourcemap::SourceMap>,
+    ) -> Self {
+        Generator {
+            options,
+            out: String::new(),
+            level: options.startingIndentLevel,
+            comments,
+            nextComment: 0,
+            statementStart: None,
+            arrowBodyStart: None,
+            noIn: false,
+            map,
+            reuse: None,
+            line: 1,
+            column: 0,
+            source: None,
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+        if self.map.is_some() {
+            for c in text.chars() {
+                if c == '\n' {
+                    self.line += 1;
+                    self.column = 0;
+                } else {
+                    self.column += c.len_utf16();
+                }
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        let options = self.options;
+        self.write(&options.lineEnd);
+    }
+
+    fn writeIndent(&mut self) {
+        let options = self.options;
+        for _ in 0..self.level {
+            self.write(&options.indent);
+        }
+    }
+
+    /// Write the text `reuse` has for `node`, if any.
+    fn reused(&mut self, node: &node::Node) -> bool {
+        match self.reuse.and_then(|reuse| reuse(node)) {
+            Some(text) => {
+                self.write(&text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Map the current output position to where `node` starts in its
+    /// source, if it has a location.
+    fn mark(&mut self, node: &node::Node) {
+        let map = match &mut self.map {
+            Some(map) => map,
+            None => return,
+        };
+        let loc = match node.loc() {
+            Some(loc) => loc,
+            None => return,
+        };
+        let (start, source) = match (&loc.start, &loc.source) {
+            (Some(start), Some(source)) => (start, source),
+            _ => return,
+        };
+        // Mappings count UTF-16 units.
+        let column = match (self.options.positionEncoding, &self.source) {
+            (options::PositionEncoding::Utf16, _) => start.column,
+            (options::PositionEncoding::Utf8, Some(text)) => {
+                let lineStart = locutil::Position::new(start.line, 0);
+                match (text.utf16Offset(start), text.utf16Offset(&lineStart)) {
+                    (Some(offset), Some(lineStart)) => offset - lineStart,
+                    _ => return,
+                }
+            }
+            (options::PositionEncoding::Utf8, None) => return,
+        };
+        let source = map.addSource(source);
+        let name = match (node.r#type.as_str(), &node.name) {
+            ("Identifier", Some(name)) => Some(map.addName(name)),
+            _ => None,
+        };
+        map.addMapping(sourcemap::Mapping {
+            line: self.line,
+            column: self.column,
+            original: Some(sourcemap::Original {
+                source,
+                line: start.line,
+                column,
+                name,
+            }),
+        });
+    }
+
+    fn root(&mut self, node: &node::Node) {
+        match node.r#type.as_str() {
+            "Program" => {
+                self.statements(bodyList(node), Some(node.end));
+                // Comments after the last statement.
+                self.leadingComments(usize::MAX);
+            }
+            r#type
+                if ast::Statement::accepts(r#type) || ast::ModuleDeclaration::accepts(r#type) =>
+            {
+                self.writeIndent();
+                self.statement(node);
+            }
+            _ => self.expr(node, SEQUENCE),
+        }
+    }
+
+    // Comments
+
+    fn writeComment(&mut self, comment: &tokenize::Comment) {
+        if comment.block {
+            self.write("/*");
+            self.write(&comment.value);
+            self.write("*/");
+        } else {
+            self.write("//");
+            self.write(&comment.value);
+        }
+    }
+
+    /// Whether a comment that has not been printed yet ends before
+    /// `pos`.
+    fn hasCommentBefore(&self, pos: usize) -> bool {
+        self.comments
+            .get(self.nextComment)
+            .is_some_and(|comment| comment.end <= pos)
+    }
+
+    /// Print, each on its own line, the comments that end before `pos`.
+    fn leadingComments(&mut self, pos: usize) {
+        while self.hasCommentBefore(pos) {
+            let comment = &self.comments[self.nextComment];
+            self.nextComment += 1;
+            self.writeIndent();
+            self.writeComment(comment);
+            self.newline();
+        }
+    }
+
+    /// Print the comments that start on the line `node` ends on, after
+    /// it. This needs both to carry locations.
+    fn trailingComments(&mut self, node: &node::Node) {
+        let line = match node.loc().and_then(|loc| loc.end.as_ref()) {
+            Some(end) => end.line,
+            None => return,
+        };
+        while let Some(comment) = self.comments.get(self.nextComment) {
+            let sameLine = comment.start >= node.end
+                && comment
+                    .loc
+                    .as_ref()
+                    .and_then(|loc| loc.start.as_ref())
+                    .is_some_and(|start| start.line == line);
+            if !sameLine {
+                break;
+            }
+            self.nextComment += 1;
+            self.write(" ");
+            self.writeComment(comment);
+        }
+    }
+
+    // Statements
+
+    /// Print `nodes` one per line, then the comments before `end`.
+    fn statements(&mut self, nodes: &[node::Node], end: Option<usize>) {
+        for node in nodes {
+            self.leadingComments(node.start);
+            self.writeIndent();
+            self.statement(node);
+            self.trailingComments(node);
+            self.newline();
+        }
+        if let Some(end) = end {
+            self.leadingComments(end);
+        }
+    }
+
+    fn block(&mut self, node: &node::Node) {
+        let body = bodyList(node);
+        if body.is_empty() && !self.hasCommentBefore(node.end) {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.newline();
+        self.level += 1;
+        self.statements(body, Some(node.end));
+        self.level -= 1;
+        self.writeIndent();
+        self.write("}");
+    }
+
+    /// Print the body of a compound statement, after a space.
+    fn body(&mut self, node: &node::Node, forceBlock: bool) {
+        self.write(" ");
+        if node.r#type == "BlockStatement" {
+            self.block(node);
+        } else if forceBlock {
+            self.write("{");
+            self.newline();
+            self.level += 1;
+            self.writeIndent();
+            self.statement(node);
+            self.newline();
+            self.level -= 1;
+            self.writeIndent();
+            self.write("}");
+        } else {
+            self.statement(node);
+        }
+    }
+
+    fn statement(&mut self, node: &node::Node) {
+        self.mark(node);
+        if self.reused(node) {
+            return;
+        }
+        match node.r#type.as_str() {
+            "ExpressionStatement" => {
+                let expression = match node.expression.as_deref() {
+                    Some(expression) => expression,
+                    None => return,
+                };
+                if node.directive.is_some() {
+                    // A directive keeps its exact text, which is what
+                    // makes it one.
+                    self.write(expression.raw.as_deref().unwrap_or(""));
+                } else if expression.r#type == "Literal"
+                    && expression
+                        .raw
+                        .as_deref()
+                        .is_some_and(|raw| raw.starts_with('"') || raw.starts_with('\''))
+                {
+                    // Not a directive, so it must not print as one.
+                    self.write("(");
+                    self.expression(expression);
+                    self.write(")");
+                } else {
+                    self.statementStart = Some(self.out.len());
+                    self.expr(expression, SEQUENCE);
+                    self.statementStart = None;
+                }
+                self.write(";");
+            }
+            "BlockStatement" => self.block(node),
+            "EmptyStatement" => self.write(";"),
+            "DebuggerStatement" => self.write("debugger;"),
+            "WithStatement" => {
+                self.write("with (");
+                self.child(&node.object, SEQUENCE);
+                self.write(")");
+                self.statementBody(node);
+            }
+            "ReturnStatement" | "ThrowStatement" => {
+                self.write(if node.r#type == "ReturnStatement" {
+                    "return"
+                } else {
+                    "throw"
+                });
+                if let Some(argument) = node.argument.as_deref() {
+                    self.write(" ");
+                    self.expr(argument, SEQUENCE);
+                }
+                self.write(";");
+            }
+            "LabeledStatement" => {
+                self.child(&node.label, PRIMARY);
+                self.write(":");
+                self.statementBody(node);
+            }
+            "BreakStatement" | "ContinueStatement" => {
+                self.write(if node.r#type == "BreakStatement" {
+                    "break"
+                } else {
+                    "continue"
+                });
+                if let Some(label) = node.label.as_deref() {
+                    self.write(" ");
+                    self.expression(label);
+                }
+                self.write(";");
+            }
+            "IfStatement" => {
+                self.write("if (");
+                self.child(&node.test, SEQUENCE);
+                self.write(")");
+                if let Some(consequent) = node.consequent.as_deref() {
+                    let dangling = node.alternate.is_some() && endsWithBareIf(consequent);
+                    self.body(consequent, dangling);
+                }
+                if let Some(alternate) = node.alternate.as_deref() {
+                    self.write(" else");
+                    if alternate.r#type == "IfStatement" {
+                        self.write(" ");
+                        self.statement(alternate);
+                    } else {
+                        self.body(alternate, false);
+                    }
+                }
+            }
+            "SwitchStatement" => {
+                self.write("switch (");
+                self.child(&node.discriminant, SEQUENCE);
+                self.write(") {");
+                self.newline();
+                self.level += 1;
+                for case in list(&node.cases) {
+                    self.leadingComments(case.start);
+                    self.writeIndent();
+                    match case.test.as_deref() {
+                        Some(test) => {
+                            self.write("case ");
+                            self.expr(test, SEQUENCE);
+                            self.write(":");
+                        }
+                        None => self.write("default:"),
+                    }
+                    self.newline();
+                    self.level += 1;
+                    self.statements(list(&case.consequents), None);
+                    self.level -= 1;
+                }
+                self.leadingComments(node.end);
+                self.level -= 1;
+                self.writeIndent();
+                self.write("}");
+            }
+            "TryStatement" => {
+                self.write("try ");
+                if let Some(block) = node.block.as_deref() {
+                    self.block(block);
+                }
+                if let Some(handler) = node.handler.as_deref() {
+                    self.write(" catch");
+                    if let Some(param) = handler.param.as_deref() {
+                        self.write(" (");
+                        self.expr(param, ASSIGNMENT);
+                        self.write(")");
+                    }
+                    self.write(" ");
+                    self.block(body(handler).unwrap_or(handler));
+                }
+                if let Some(finalizer) = node.finalizer.as_deref() {
+                    self.write(" finally ");
+                    self.block(finalizer);
+                }
+            }
+            "WhileStatement" => {
+                self.write("while (");
+                self.child(&node.test, SEQUENCE);
+                self.write(")");
+                self.statementBody(node);
+            }
+            "DoWhileStatement" => {
+                self.write("do");
+                self.statementBody(node);
+                self.write(" while (");
+                self.child(&node.test, SEQUENCE);
+                self.write(");");
+            }
+            "ForStatement" => {
+                self.write("for (");
+                if let Some(init) = node.init.as_deref() {
+                    self.noIn = true;
+                    self.forInit(init);
+                    self.noIn = false;
+                }
+                self.write(";");
+                if let Some(test) = node.test.as_deref() {
+                    self.write(" ");
+                    self.expr(test, SEQUENCE);
+                }
+                self.write(";");
+                if let Some(update) = node.update.as_deref() {
+                    self.write(" ");
+                    self.expr(update, SEQUENCE);
+                }
+                self.write(")");
+                self.statementBody(node);
+            }
+            "ForInStatement" | "ForOfStatement" => {
+                let isOf = node.r#type == "ForOfStatement";
+                self.write("for ");
+                if node.r#await == Some(true) {
+                    self.write("await ");
+                }
+                self.write("(");
+                if let Some(left) = node.left.as_deref() {
+                    self.noIn = true;
+                    self.forInit(left);
+                    self.noIn = false;
+                }
+                if isOf {
+                    self.write(" of ");
+                    self.child(&node.right, ASSIGNMENT);
+                } else {
+                    self.write(" in ");
+                    self.child(&node.right, SEQUENCE);
+                }
+                self.write(")");
+                self.statementBody(node);
+            }
+            "FunctionDeclaration" => self.function(node),
+            "VariableDeclaration" => {
+                self.variableDeclaration(node);
+                self.write(";");
+            }
+            "ClassDeclaration" => self.class(node),
+            "ImportDeclaration" => self.importDeclaration(node),
+            "ExportNamedDeclaration" => {
+                self.write("export ");
+                if let Some(declaration) = node.declaration.as_deref() {
+                    self.statement(declaration);
+                    return;
+                }
+                self.write("{");
+                for (i, specifier) in list(&node.specifiers).iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.child(&specifier.local, PRIMARY);
+                    self.alias(&specifier.local, &specifier.exported);
+                }
+                self.write("}");
+                if let Some(source) = node.source.as_deref() {
+                    self.write(" from ");
+                    self.expression(source);
+                }
+                self.write(";");
+            }
+            "ExportDefaultDeclaration" => {
+                self.write("export default ");
+                let declaration = match node.declaration.as_deref() {
+                    Some(declaration) => declaration,
+                    None => return,
+                };
+                match declaration.r#type.as_str() {
+                    "FunctionDeclaration" | "ClassDeclaration" => self.statement(declaration),
+                    _ => {
+                        // An expression here must not read as a
+                        // declaration either.
+                        self.statementStart = Some(self.out.len());
+                        self.expr(declaration, ASSIGNMENT);
+                        self.statementStart = None;
+                        self.write(";");
+                    }
+                }
+            }
+            "ExportAllDeclaration" => {
+                self.write("export * from ");
+                self.child(&node.source, PRIMARY);
+                self.write(";");
+            }
+            // An expression where a statement was expected, which a
+            // transform can leave behind.
+            _ => {
+                self.statementStart = Some(self.out.len());
+                self.expr(node, SEQUENCE);
+                self.statementStart = None;
+                self.write(";");
+            }
+        }
+    }
+
+    fn statementBody(&mut self, node: &node::Node) {
+        if let Some(child) = body(node) {
+            self.body(child, false);
+        }
+    }
+
+    /// The `init` of a `for` statement or the `left` of a `for`-`in` or
+    /// `for`-`of` statement.
+    fn forInit(&mut self, node: &node::Node) {
+        if node.r#type == "VariableDeclaration" {
+            self.variableDeclaration(node);
+        } else {
+            self.expr(node, SEQUENCE);
+        }
+    }
+
+    fn variableDeclaration(&mut self, node: &node::Node) {
+        self.write(node.kind.as_deref().unwrap_or("var"));
+        self.write(" ");
+        for (i, declarator) in list(&node.declarations).iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.child(&declarator.id, CALL);
+            if let Some(init) = declarator.init.as_deref() {
+                self.write(" = ");
+                self.expr(init, ASSIGNMENT);
+            }
+        }
+    }
+
+    fn importDeclaration(&mut self, node: &node::Node) {
+        self.write("import ");
+        let specifiers = list(&node.specifiers);
+        let mut named = Vec::new();
+        let mut first = true;
+        for specifier in specifiers {
+            match specifier.r#type.as_str() {
+                "ImportDefaultSpecifier" | "ImportNamespaceSpecifier" => {
+                    if !first {
+                        self.write(", ");
+                    }
+                    first = false;
+                    if specifier.r#type == "ImportNamespaceSpecifier" {
+                        self.write("* as ");
+                    }
+                    self.child(&specifier.local, PRIMARY);
+                }
+                _ => named.push(specifier),
+            }
+        }
+        if !named.is_empty() {
+            if !first {
+                self.write(", ");
+            }
+            self.write("{");
+            for (i, specifier) in named.into_iter().enumerate() {
+                if i > 0 {
+                    self.write(", ");
+                }
+                self.child(&specifier.imported, PRIMARY);
+                self.alias(&specifier.imported, &specifier.local);
+            }
+            self.write("}");
+        }
+        if !specifiers.is_empty() {
+            self.write(" from ");
+        }
+        self.child(&node.source, PRIMARY);
+        self.write(";");
+    }
+
+    /// Write ` as alias` when `alias` names something other than
+    /// `name`.
+    fn alias(&mut self, name: &Option<Box<node::Node>>, alias: &Option<Box<node::Node>>) {
+        if let Some(alias) = alias.as_deref() {
+            if name.as_deref().and_then(|name| name.name.as_deref()) != alias.name.as_deref() {
+                self.write(" as ");
+                self.expression(alias);
+            }
+        }
+    }
+
+    fn function(&mut self, node: &node::Node) {
+        if node.r#async == Some(true) {
+            self.write("async ");
+        }
+        self.write("function");
+        if node.generator == Some(true) {
+            self.write("*");
+        }
+        if let Some(id) = node.id.as_deref() {
+            self.write(" ");
+            self.expression(id);
+        }
+        self.functionTail(node);
+    }
+
+    /// The parameters and body of a function.
+    fn functionTail(&mut self, node: &node::Node) {
+        self.params(list(&node.params));
+        self.write(" ");
+        match body(node) {
+            Some(body) => self.block(body),
+            None => self.write("{}"),
+        }
+    }
+
+    fn params(&mut self, params: &[node::Node]) {
+        self.write("(");
+        self.expressions(params, ASSIGNMENT);
+        self.write(")");
+    }
+
+    fn class(&mut self, node: &node::Node) {
+        self.write("class");
+        if let Some(id) = node.id.as_deref() {
+            self.write(" ");
+            self.expression(id);
+        }
+        if let Some(superClass) = node.superClass.as_deref() {
+            self.write(" extends ");
+            self.expr(superClass, CALL);
+        }
+        self.write(" ");
+        let classBody = match body(node) {
+            Some(classBody) => classBody,
+            None => return self.write("{}"),
+        };
+        let members = bodyList(classBody);
+        if members.is_empty() && !self.hasCommentBefore(classBody.end) {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.newline();
+        self.level += 1;
+        for member in members {
+            self.leadingComments(member.start);
+            self.writeIndent();
+            self.methodDefinition(member);
+            self.trailingComments(member);
+            self.newline();
+        }
+        self.leadingComments(classBody.end);
+        self.level -= 1;
+        self.writeIndent();
+        self.write("}");
+    }
+
+    fn methodDefinition(&mut self, node: &node::Node) {
+        if node.r#static == Some(true) {
+            self.write("static ");
+        }
+        let value = node.value.as_deref();
+        match node.kind.as_deref() {
+            Some("get") => self.write("get "),
+            Some("set") => self.write("set "),
+            _ => {
+                if value.is_some_and(|value| value.r#async == Some(true)) {
+                    self.write("async ");
+                }
+                if value.is_some_and(|value| value.generator == Some(true)) {
+                    self.write("*");
+                }
+            }
+        }
+        self.propertyKey(node);
+        match value {
+            Some(value) => self.functionTail(value),
+            None => self.write("() {}"),
+        }
+    }
+
+    fn propertyKey(&mut self, node: &node::Node) {
+        let key = match node.key.as_deref() {
+            Some(key) => key,
+            None => return,
+        };
+        if node.computed == Some(true) {
+            self.write("[");
+            self.expr(key, ASSIGNMENT);
+            self.write("]");
+        } else {
+            self.expression(key);
+        }
+    }
+
+    // Expressions
+
+    fn child(&mut self, node: &Option<Box<node::Node>>, minPrecedence: u8) {
+        if let Some(node) = node.as_deref() {
+            self.expr(node, minPrecedence);
+        }
+    }
+
+    /// Print `nodes` separated by commas, each at `minPrecedence`.
+    /// Holes print as nothing, with a trailing comma when last.
+    fn expressions(&mut self, nodes: &[node::Node], minPrecedence: u8) {
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            if node.isHole() {
+                if i == nodes.len() - 1 {
+                    self.write(",");
+                }
+            } else {
+                self.expr(node, minPrecedence);
+            }
+        }
+    }
+
+    /// Print `node` in a position that takes expressions binding at
+    /// least as tightly as `minPrecedence`, parenthesized if it does
+    /// not or if it would be misread where it starts.
+    fn expr(&mut self, node: &node::Node, minPrecedence: u8) {
+        let here = Some(self.out.len());
+        let misread = match node.r#type.as_str() {
+            "ObjectExpression" | "ObjectPattern" => {
+                here == self.statementStart || here == self.arrowBodyStart
+            }
+            "FunctionExpression" | "ClassExpression" => here == self.statementStart,
+            // `{a} = b` would start a block; the whole assignment is
+            // parenthesized, as `({a}) = b` is not a valid target.
+            "AssignmentExpression" => {
+                node.left.as_ref().is_some_and(|left| left.r#type == "ObjectPattern")
+                    && (here == self.statementStart || here == self.arrowBodyStart)
+            }
+            "BinaryExpression" => self.noIn && node.operator.as_deref() == Some("in"),
+            _ => false,
+        };
+        if precedence(node) < minPrecedence || misread {
+            let noIn = self.noIn;
+            self.noIn = false;
+            self.write("(");
+            self.expression(node);
+            self.write(")");
+            self.noIn = noIn;
+        } else {
+            self.expression(node);
+        }
+    }
+
+    fn expression(&mut self, node: &node::Node) {
+        self.mark(node);
+        if self.reused(node) {
+            return;
+        }
+        match node.r#type.as_str() {
+            "Identifier" => self.write(node.name.as_deref().unwrap_or("")),
+            "Literal" => self.literal(node),
+            "ThisExpression" => self.write("this"),
+            "Super" => self.write("super"),
+            "ArrayExpression" | "ArrayPattern" => {
+                self.write("[");
+                self.expressions(list(&node.elements), ASSIGNMENT);
+                self.write("]");
+            }
+            "ObjectExpression" => {
+                let properties = list(&node.properties);
+                if properties.is_empty() {
+                    self.write("{}");
+                    return;
+                }
+                self.write("{");
+                self.newline();
+                self.level += 1;
+                for (i, property) in properties.iter().enumerate() {
+                    self.writeIndent();
+                    self.property(property);
+                    if i < properties.len() - 1 {
+                        self.write(",");
+                    }
+                    self.newline();
+                }
+                self.level -= 1;
+                self.writeIndent();
+                self.write("}");
+            }
+            "ObjectPattern" => {
+                self.write("{");
+                for (i, property) in list(&node.properties).iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.property(property);
+                }
+                self.write("}");
+            }
+            "Property" => self.property(node),
+            "SpreadElement" | "RestElement" => {
+                self.write("...");
+                self.child(&node.argument, ASSIGNMENT);
+            }
+            "FunctionExpression" => self.function(node),
+            "ArrowFunctionExpression" => {
+                if node.r#async == Some(true) {
+                    self.write("async ");
+                }
+                self.params(list(&node.params));
+                self.write(" => ");
+                match body(node) {
+                    Some(body) if body.r#type == "BlockStatement" => self.block(body),
+                    Some(body) => {
+                        let outer = self.arrowBodyStart;
+                        self.arrowBodyStart = Some(self.out.len());
+                        self.expr(body, ASSIGNMENT);
+                        self.arrowBodyStart = outer;
+                    }
+                    None => self.write("{}"),
+                }
+            }
+            "ClassExpression" => self.class(node),
+            "UnaryExpression" => {
+                let operator = node.operator.as_deref().unwrap_or("");
+                self.write(operator);
+                let argument = match node.argument.as_deref() {
+                    Some(argument) => argument,
+                    None => return,
+                };
+                // `typeof x`, and `- -x` rather than `--x`.
+                let argumentOperator = match argument.r#type.as_str() {
+                    "UnaryExpression" => argument.operator.as_deref(),
+                    "UpdateExpression" if argument.prefix == Some(true) => {
+                        argument.operator.as_deref()
+                    }
+                    _ => None,
+                };
+                let wordy = operator.chars().all(|c| c.is_ascii_alphabetic());
+                let merges = argumentOperator.is_some_and(|argumentOperator| {
+                    (operator == "+" || operator == "-") && argumentOperator.starts_with(operator)
+                });
+                if wordy || merges {
+                    self.write(" ");
+                }
+                self.expr(argument, UNARY);
+            }
+            "UpdateExpression" => {
+                let operator = node.operator.as_deref().unwrap_or("");
+                if node.prefix == Some(true) {
+                    self.write(operator);
+                    self.child(&node.argument, CALL);
+                } else {
+                    self.child(&node.argument, CALL);
+                    self.write(operator);
+                }
+            }
+            "AwaitExpression" => {
+                self.write("await ");
+                self.child(&node.argument, UNARY);
+            }
+            "YieldExpression" => {
+                self.write("yield");
+                if node.delegate == Some(true) {
+                    self.write("*");
+                }
+                if let Some(argument) = node.argument.as_deref() {
+                    self.write(" ");
+                    self.expr(argument, ASSIGNMENT);
+                }
+            }
+            "BinaryExpression" | "LogicalExpression" => {
+                let operator = node.operator.as_deref().unwrap_or("");
+                let precedence = binaryPrecedence(operator);
+                // `**` is right-associative, and its left operand
+                // cannot be a unary expression.
+                let (leftPrecedence, rightPrecedence) = if operator == "**" {
+                    (POSTFIX, precedence)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                for (i, operand) in [&node.left, &node.right].iter().enumerate() {
+                    let operand = match operand.as_deref() {
+                        Some(operand) => operand,
+                        None => continue,
+                    };
+                    if i == 1 {
+                        self.write(" ");
+                        self.write(operator);
+                        self.write(" ");
+                    }
+                    let minPrecedence = if i == 0 {
+                        leftPrecedence
+                    } else {
+                        rightPrecedence
+                    };
+                    if mixesCoalesce(operator, operand) {
+                        self.expr(operand, PRIMARY);
+                    } else {
+                        self.expr(operand, minPrecedence);
+                    }
+                }
+            }
+            "AssignmentExpression" | "AssignmentPattern" => {
+                self.child(&node.left, CALL);
+                self.write(" ");
+                self.write(node.operator.as_deref().unwrap_or("="));
+                self.write(" ");
+                self.child(&node.right, ASSIGNMENT);
+            }
+            "ConditionalExpression" => {
+                self.child(&node.test, CONDITIONAL + 1);
+                self.write(" ? ");
+                self.child(&node.consequent, ASSIGNMENT);
+                self.write(" : ");
+                self.child(&node.alternate, ASSIGNMENT);
+            }
+            "MemberExpression" => {
+                if let Some(object) = node.object.as_deref() {
+                    // `1.toString()` would read as a malformed number.
+                    let integer = object.r#type == "Literal"
+                        && object
+                            .raw
+                            .as_deref()
+                            .is_some_and(|raw| raw.bytes().all(|b| b.is_ascii_digit()));
+                    self.expr(object, if integer { PRIMARY + 1 } else { CALL });
+                }
+                if node.computed == Some(true) {
+                    self.write("[");
+                    self.child(&node.property, SEQUENCE);
+                    self.write("]");
+                } else {
+                    self.write(".");
+                    self.child(&node.property, PRIMARY);
+                }
+            }
+            "CallExpression" => {
+                self.child(&node.callee, CALL);
+                self.arguments(node);
+            }
+            "NewExpression" => {
+                self.write("new ");
+                if let Some(callee) = node.callee.as_deref() {
+                    let minPrecedence = if hasCall(callee) { PRIMARY + 1 } else { CALL };
+                    self.expr(callee, minPrecedence);
+                }
+                self.arguments(node);
+            }
+            "SequenceExpression" => self.expressions(&node.expressions, ASSIGNMENT),
+            "TemplateLiteral" => {
+                self.write("`");
+                let quasis = list(&node.quasis);
+                for (i, quasi) in quasis.iter().enumerate() {
+                    self.write(quasi.raw.as_deref().unwrap_or(""));
+                    if let Some(expression) = node.expressions.get(i) {
+                        self.write("${");
+                        let noIn = self.noIn;
+                        self.noIn = false;
+                        self.expr(expression, SEQUENCE);
+                        self.noIn = noIn;
+                        self.write("}");
+                    }
+                }
+                self.write("`");
+            }
+            "TaggedTemplateExpression" => {
+                self.child(&node.tag, CALL);
+                self.child(&node.quasi, PRIMARY);
+            }
+            "MetaProperty" => {
+                self.child(&node.meta, PRIMARY);
+                self.write(".");
+                self.child(&node.property, PRIMARY);
+            }
+            "ParenthesizedExpression" => {
+                let noIn = self.noIn;
+                self.noIn = false;
+                self.write("(");
+                self.child(&node.expression, SEQUENCE);
+                self.write(")");
+                self.noIn = noIn;
+            }
+            // Not an expression; print it the only way it can be.
+            _ => self.statement(node),
+        }
+    }
+
+    fn arguments(&mut self, node: &node::Node) {
+        let noIn = self.noIn;
+        self.noIn = false;
+        self.write("(");
+        self.expressions(list(&node.arguments), ASSIGNMENT);
+        self.write(")");
+        self.noIn = noIn;
+    }
+
+    /// A property of an object literal or pattern.
+    fn property(&mut self, node: &node::Node) {
+        if node.r#type != "Property" {
+            self.expr(node, ASSIGNMENT);
+            return;
+        }
+        let value = node.value.as_deref();
+        match node.kind.as_deref() {
+            Some("get") | Some("set") => {
+                self.write(node.kind.as_deref().unwrap_or(""));
+                self.write(" ");
+                self.propertyKey(node);
+                if let Some(value) = value {
+                    self.functionTail(value);
+                }
+                return;
+            }
+            _ => {}
+        }
+        if node.method == Some(true) {
+            if let Some(value) = value {
+                if value.r#async == Some(true) {
+                    self.write("async ");
+                }
+                if value.generator == Some(true) {
+                    self.write("*");
+                }
+                self.propertyKey(node);
+                self.functionTail(value);
+            }
+            return;
+        }
+        let value = match value {
+            Some(value) => value,
+            None => return self.propertyKey(node),
+        };
+        // `{a}` and `{a = 1}` only while the value still names the key;
+        // a transform may have renamed one of them.
+        if node.shorthand == Some(true) && node.computed != Some(true) {
+            let target = match value.r#type.as_str() {
+                "AssignmentPattern" => value.left.as_deref(),
+                _ => Some(value),
+            };
+            let keyName = node.key.as_deref().and_then(|key| key.name.as_deref());
+            if target.is_some_and(|target| {
+                target.r#type == "Identifier" && target.name.as_deref() == keyName
+            }) {
+                self.expr(value, ASSIGNMENT);
+                return;
+            }
+        }
+        self.propertyKey(node);
+        self.write(": ");
+        self.expr(value, ASSIGNMENT);
+    }
+
+    fn literal(&mut self, node: &node::Node) {
+        let raw = node.raw.as_deref().unwrap_or("");
+        let quote = match self.options.quotes {
+            Quotes::Single => '\'',
+            Quotes::Double => '"',
+            Quotes::Preserve => return self.write(raw),
+        };
+        if !raw.starts_with('"') && !raw.starts_with('\'') || raw.starts_with(quote) {
+            return self.write(raw);
+        }
+        match ast::literalValue(raw) {
+            LiteralValue::String(value) => {
+                let quoted = quoteString(&value, quote);
+                self.write(&quoted);
+            }
+            _ => self.write(raw),
+        }
+    }
+}
+
+/// `value` as a string literal in `quote`s.
+fn quoteString(value: &str, quote: char) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '\u{b}' => quoted.push_str("\\v"),
+            '\u{c}' => quoted.push_str("\\f"),
+            '\u{2028}' => quoted.push_str("\\u2028"),
+            '\u{2029}' => quoted.push_str("\\u2029"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if (c as u32) < 0x20 || c == '\u{7f}' => {
+                quoted.push_str(&format!("\\x{:02x}", c as u32));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options;
+    use crate::state;
+
+    fn print(input: &str) -> String {
+        printWith(input, &GenerateOptions::default())
+    }
+
+    fn printWith(input: &str, generateOptions: &GenerateOptions) -> String {
+        // Trailing comments are found from `loc`.
+        let options = options::Options::builder().locations(true).build().unwrap();
+        let parsed = state::Parser::parseWithComments(String::from(input), Some(options));
+        let (program, comments) = match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => panic!("{}", err),
+        };
+        generateWithComments(&program, &comments, generateOptions)
+    }
+
+    #[test]
+    fn parenthesizesPatternsAtStatementStart() {
+        assert_eq!(print("({a} = b);"), "({a} = b);\n");
+        assert_eq!(print("({a} = b).c;"), "({a} = b).c;\n");
+        assert_eq!(print("({a} = b, c);"), "({a} = b), c;\n");
+        assert_eq!(print("({a} = {b} = c);"), "({a} = {b} = c);\n");
+        assert_eq!(print("x = {a} = b;"), "x = {a} = b;\n");
+        assert_eq!(print("f = () => ({a} = b);"), "f = () => ({a} = b);\n");
+        assert_eq!(print("[a] = b;"), "[a] = b;\n");
+        // Without semicolons the pattern still starts its own statement.
+        assert_eq!(print("a\n;({b} = c)\n"), "a;\n({b} = c);\n");
+        let tabs = GenerateOptions {
+            indent: String::from("\t"),
+            ..GenerateOptions::default()
+        };
+        assert_eq!(printWith("if (x) { ({a} = b) }", &tabs), "if (x) {\n\t({a} = b);\n}\n");
+        let single = GenerateOptions {
+            quotes: Quotes::Single,
+            ..GenerateOptions::default()
+        };
+        assert_eq!(printWith("({a = \"x\"} = b);", &single), "({a = 'x'} = b);\n");
+        assert_eq!(print("({a = 'x'} = b);"), "({a = \"x\"} = b);\n");
+    }
+
+    #[test]
+    fn printsEveryStatementOfASwitchCase() {
+        assert_eq!(
+            print("switch (x) { case 1: a(); b(); break; default: c(); }"),
+            "switch (x) {\n  case 1:\n    a();\n    b();\n    break;\n  default:\n    c();\n}\n"
+        );
+    }
+
+    #[test]
+    fn printsComments() {
+        assert_eq!(
+            print("// leading\nlet x = 1; // trailing\n/* before */ f();\n// last\n"),
+            "// leading\nlet x = 1; // trailing\n/* before */\nf();\n// last\n"
+        );
+        assert_eq!(
+            print("switch (x) {\n  // first\n  case 1:\n    // body\n    a();\n  // end\n}"),
+            "switch (x) {\n  // first\n  case 1:\n    // body\n    a();\n  // end\n}\n"
+        );
+        assert_eq!(print("function f() {\n  // only\n}"), "function f() {\n  // only\n}\n");
+    }
+
+    /// The generated and original columns of the mapping for each
+    /// identifier printed.
+    fn identifierColumns(
+        input: &str,
+        positionEncoding: options::PositionEncoding,
+        sourceContent: Option<String>,
+    ) -> Vec<(usize, usize)> {
+        let options = options::Options::builder()
+            .locations(true)
+            .sourceFile(String::from("in.js"))
+            .positionEncoding(positionEncoding)
+            .build()
+            .unwrap();
+        let program = match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        };
+        let options = GenerateOptions {
+            positionEncoding,
+            sourceContent,
+            ..GenerateOptions::default()
+        };
+        let (_, map) = generateWithSourceMap(&program, &[], &options);
+        map.mappings
+            .iter()
+            .filter_map(|mapping| {
+                let original = mapping.original.as_ref()?;
+                original.name.map(|_| (mapping.column, original.column))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mapsColumnsInUtf16Units() {
+        let input = "'\u{e9}\u{1f600}' + x;";
+        assert_eq!(
+            identifierColumns(input, options::PositionEncoding::Utf16, None),
+            [(8, 8)]
+        );
+        assert_eq!(
+            identifierColumns(
+                input,
+                options::PositionEncoding::Utf8,
+                Some(String::from(input))
+            ),
+            [(8, 8)]
+        );
+        // UTF-8 columns cannot be converted without the source.
+        assert!(identifierColumns(input, options::PositionEncoding::Utf8, None).is_empty());
+    }
+}
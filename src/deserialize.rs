@@ -0,0 +1,361 @@
+// ESTree JSON deserialization, enabled by the `serde` feature. This is
+// the inverse of `serialize.rs`: it loads the trees acorn, espree or
+// Babel (with its `estree` plugin) produce into `node::Node`.
+//
+// Every node is checked against the node table in `ast.rs`: its `type`
+// must be one the table knows, and every required child and string
+// field must be present. Fields the table does not list (`extra`,
+// `comments`, ...) are ignored.
+
+use crate::ast;
+use crate::locutil;
+use crate::node;
+use crate::options;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use std::fmt;
+
+use ast::{ConvertError, FieldValue, Shape};
+
+/// A JSON value, kept in document order.
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        String::from(match self {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object without a type",
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value as f64))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value as f64))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(String::from(value)))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for node::Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        toNode(&value).map_err(de::Error::custom)
+    }
+}
+
+fn toNode(value: &Value) -> Result<node::Node, ConvertError> {
+    let r#type = match value.get("type") {
+        Some(Value::String(r#type)) => r#type,
+        _ => {
+            return Err(ConvertError::UnexpectedType {
+                expected: "ESTree node",
+                found: value.describe(),
+            })
+        }
+    };
+    let fields = match ast::fieldsOf(r#type) {
+        Some(fields) => fields,
+        None => {
+            return Err(ConvertError::UnexpectedType {
+                expected: "ESTree node",
+                found: r#type.clone(),
+            })
+        }
+    };
+    let nodeType = fields.r#type;
+
+    let mut node = node::Node {
+        r#type: String::from(nodeType),
+        ..node::Node::default()
+    };
+    let range = match value.get("range") {
+        Some(Value::Array(range)) => match range.as_slice() {
+            [Value::Number(start), Value::Number(end)] => Some((*start as usize, *end as usize)),
+            _ => None,
+        },
+        _ => None,
+    };
+    node.start = number(value.get("start")).or(range.map(|r| r.0)).unwrap_or(0);
+    node.end = number(value.get("end")).or(range.map(|r| r.1)).unwrap_or(0);
+    let sourceFile = match value.get("sourceFile") {
+        Some(Value::String(sourceFile)) => Some(sourceFile.clone()),
+        _ => None,
+    };
+    node.setLocation(value.get("loc").and_then(toLocation), sourceFile, range);
+
+    for ((name, shape), &category) in fields.iter().zip(fields.categories) {
+        let missing = ConvertError::MissingField {
+            nodeType,
+            field: name,
+        };
+        let field = match (nodeType, name) {
+            ("Literal", "raw") => Some(FieldValue::Str(literalRaw(value).ok_or(missing)?)),
+            ("TemplateElement", "raw") => {
+                let raw = value.get("value").and_then(|value| value.get("raw"));
+                match raw {
+                    Some(Value::String(raw)) => Some(FieldValue::Str(raw.clone())),
+                    _ => return Err(missing),
+                }
+            }
+            ("TemplateElement", "cooked") => {
+                match value.get("value").and_then(|value| value.get("cooked")) {
+                    Some(Value::String(cooked)) => Some(FieldValue::Str(cooked.clone())),
+                    _ => None,
+                }
+            }
+            // Derived from the body; see `ast::field`.
+            ("ArrowFunctionExpression", "expression") => None,
+            _ => toField(value.get(name), shape, missing)?,
+        };
+        if let Some(field) = field {
+            checkCategory(&field, category)?;
+            ast::setField(&mut node, name, field)?;
+        }
+    }
+    Ok(node)
+}
+
+/// Check that every child in `field` belongs to `category`, the
+/// category or node type the node table gives for the field.
+fn checkCategory(field: &FieldValue, category: &'static str) -> Result<(), ConvertError> {
+    let children = match field {
+        FieldValue::Node(child) => std::slice::from_ref(child),
+        FieldValue::Nodes(children) => children.as_slice(),
+        _ => return Ok(()),
+    };
+    match children
+        .iter()
+        .find(|child| !child.isHole() && !ast::belongsTo(&child.r#type, category))
+    {
+        Some(child) => Err(ConvertError::UnexpectedType {
+            expected: category,
+            found: child.r#type.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Convert the JSON value of a field of shape `shape`. Returns `None`
+/// for an absent optional field, and `missing` for an absent or
+/// ill-typed required one.
+fn toField(
+    value: Option<&Value>,
+    shape: Shape,
+    missing: ConvertError,
+) -> Result<Option<FieldValue>, ConvertError> {
+    let value = match value {
+        None | Some(Value::Null) => {
+            return match shape {
+                Shape::Node | Shape::List | Shape::String => Err(missing),
+                _ => Ok(None),
+            }
+        }
+        Some(value) => value,
+    };
+    Ok(Some(match (shape, value) {
+        (Shape::Node, _) | (Shape::OptionalNode, _) => FieldValue::Node(toNode(value)?),
+        (Shape::List, Value::Array(values)) => FieldValue::Nodes(
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => Ok(node::Node::hole(0)),
+                    value => toNode(value),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        (Shape::String, Value::String(value)) | (Shape::OptionalString, Value::String(value)) => {
+            FieldValue::Str(value.clone())
+        }
+        (Shape::Bool, Value::Bool(value)) => FieldValue::Bool(*value),
+        (Shape::SourceType, Value::String(value)) if value == "script" => {
+            FieldValue::SourceType(options::SourceType::Script)
+        }
+        (Shape::SourceType, Value::String(value)) if value == "module" => {
+            FieldValue::SourceType(options::SourceType::Module)
+        }
+        (Shape::SourceType, Value::String(value)) if value == "commonjs" => {
+            FieldValue::SourceType(options::SourceType::CommonJS)
+        }
+        _ => return Err(missing),
+    }))
+}
+
+/// The source text of a literal. Tools that do not keep `raw` (or keep
+/// it under `extra.raw`, as Babel does) get one rebuilt from `value`,
+/// `regex` or `bigint`.
+fn literalRaw(value: &Value) -> Option<String> {
+    if let Some(Value::String(raw)) = value.get("raw") {
+        return Some(raw.clone());
+    }
+    if let Some(Value::String(raw)) = value.get("extra").and_then(|extra| extra.get("raw")) {
+        return Some(raw.clone());
+    }
+    if let Some(regex) = value.get("regex") {
+        if let (Some(Value::String(pattern)), Some(Value::String(flags))) =
+            (regex.get("pattern"), regex.get("flags"))
+        {
+            return Some(format!("/{}/{}", pattern, flags));
+        }
+    }
+    if let Some(Value::String(bigint)) = value.get("bigint") {
+        return Some(format!("{}n", bigint));
+    }
+    match value.get("value")? {
+        Value::Null => Some(String::from("null")),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::String(value) => Some(format!("{:?}", value)),
+        _ => None,
+    }
+}
+
+fn number(value: Option<&Value>) -> Option<usize> {
+    match value {
+        Some(Value::Number(number)) => Some(*number as usize),
+        _ => None,
+    }
+}
+
+fn toPosition(value: &Value) -> Option<locutil::Position> {
+    Some(locutil::Position::new(
+        number(value.get("line"))?,
+        number(value.get("column"))?,
+    ))
+}
+
+fn toLocation(value: &Value) -> Option<locutil::SourceLocation> {
+    Some(locutil::SourceLocation {
+        start: value.get("start").and_then(toPosition),
+        end: value.get("end").and_then(toPosition),
+        source: match value.get("source") {
+            Some(Value::String(source)) => Some(source.clone()),
+            _ => None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn load(json: &str) -> Result<node::Node, String> {
+        serde_json::from_str(json).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn loadsWhatTheSerializerWrites() {
+        let input = "switch (x) { case 1: a(); b(); } (a) => [, a]; (x);";
+        let options = options::Options::builder().preserveParens(true).build().unwrap();
+        let program = match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        };
+        let json = serde_json::to_string(&program).unwrap();
+        match load(&json) {
+            Ok(loaded) => assert_eq!(serde_json::to_string(&loaded).unwrap(), json),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn rejectsChildrenOfTheWrongCategory() {
+        let statementAsExpression = r#"{"type": "ExpressionStatement",
+            "expression": {"type": "EmptyStatement"}}"#;
+        let err = load(statementAsExpression).err().unwrap();
+        assert!(err.contains("Expected a Expression node, found EmptyStatement"), "{}", err);
+
+        let expressionInBody = r#"{"type": "Program", "sourceType": "script",
+            "body": [{"type": "Identifier", "name": "x"}]}"#;
+        let err = load(expressionInBody).err().unwrap();
+        assert!(err.contains("Expected a ModuleItem node, found Identifier"), "{}", err);
+    }
+
+    #[test]
+    fn rejectsMissingFieldsAndUnknownTypes() {
+        let err = load(r#"{"type": "Identifier"}"#).err().unwrap();
+        assert!(err.contains("Identifier node is missing its 'name' field"), "{}", err);
+        let err = load(r#"{"type": "ChainExpression"}"#).err().unwrap();
+        assert!(err.contains("found ChainExpression"), "{}", err);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::location;
+use crate::locutil;
+use crate::options;
+
+/// How a diagnostic report should be styled.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Style {
+    Plain,
+    Ansi,
+}
+
+const RED: &str = "\x1b[1;31m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Render `error` as a multi-line report in the style of rustc:
+///
+/// ```text
+/// error[E0014]: Identifier 'x' has already been declared
+///  --> input.js:2:5
+///   |
+/// 2 | var x;
+///   |     ^
+///   |
+/// note: 'x' was first declared here
+///  --> input.js:1:5
+///   |
+/// 1 | let x;
+///   |     -
+/// ```
+///
+/// `input` must be the source the error was raised for, and `options`
+/// the options it was parsed with: the file name is taken from
+/// `options.sourceFile`, and offsets are interpreted according to
+/// `options.positionEncoding`.
+pub fn render(
+    error: &location::SyntaxError,
+    input: &str,
+    options: &options::Options,
+    style: Style,
+) -> String {
+    let painter = Painter { style };
+    let file = options.sourceFile.as_deref().unwrap_or("<input>");
+    let offsets = locutil::OffsetMap::new(input);
+    let lines = locutil::LineIndex::new(input);
+    let decode = |pos: usize| match options.positionEncoding {
+        options::PositionEncoding::Utf8 => pos,
+        options::PositionEncoding::Utf16 => offsets.fromUtf16(pos),
+    };
+    let mut out = String::new();
+
+    out += &painter.paint(RED, &format!("error[{}]", error.kind.code()));
+    out += &painter.paint(BOLD, &format!(": {}", error.kind));
+    out += "\n";
+    renderSnippet(&mut out, &painter, input, &lines, file, decode(error.pos), '^', RED);
+
+    for note in &error.details.notes {
+        out += &painter.gutter("");
+        out += "\n";
+        out += &painter.paint(GREEN, "note");
+        out += &painter.paint(BOLD, &format!(": {}", note.message));
+        out += "\n";
+        renderSnippet(&mut out, &painter, input, &lines, file, decode(note.pos), '-', GREEN);
+    }
+    if let Some(help) = &error.details.help {
+        out += &painter.paint(BOLD, "= help");
+        out += &format!(": {}\n", help);
+    }
+    out
+}
+
+struct Painter {
+    style: Style,
+}
+
+impl Painter {
+    fn paint(&self, colour: &str, text: &str) -> String {
+        match self.style {
+            Style::Plain => String::from(text),
+            Style::Ansi => format!("{}{}{}", colour, text, RESET),
+        }
+    }
+
+    fn gutter(&self, lineNumber: &str) -> String {
+        self.paint(BLUE, &format!("{} |", lineNumber))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn renderSnippet(
+    out: &mut String,
+    painter: &Painter,
+    input: &str,
+    lines: &locutil::LineIndex,
+    file: &str,
+    pos: usize,
+    marker: char,
+    colour: &str,
+) {
+    let pos = pos.min(input.len());
+    let loc = lines.position(pos);
+    let isLineBreak = |c: char| c == '\n' || c == '\r' || c == '\u{2028}' || c == '\u{2029}';
+    let lineStart = lines.lineStart(loc.line).unwrap_or(0);
+    let lineEnd = input[pos..].find(isLineBreak).map_or(input.len(), |i| pos + i);
+    let line = &input[lineStart..lineEnd];
+    let prefix = &input[lineStart..pos];
+
+    let number = loc.line.to_string();
+    let blank = " ".repeat(number.len());
+    let column = prefix.chars().count() + 1;
+
+    *out += &format!(
+        "{}{} {}:{}:{}\n",
+        blank,
+        painter.paint(BLUE, "-->"),
+        file,
+        loc.line,
+        column
+    );
+    *out += &painter.gutter(&blank);
+    *out += "\n";
+    *out += &painter.gutter(&number);
+    *out += &format!(" {}\n", line);
+
+    // Keep tabs in the padding so the marker lines up with the source.
+    let padding: String = prefix
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = std::cmp::max(1, wordLength(&input[pos..lineEnd]));
+    *out += &painter.gutter(&blank);
+    *out += &format!(
+        " {}{}\n",
+        padding,
+        painter.paint(colour, &marker.to_string().repeat(width))
+    );
+}
+
+/// Length of the identifier-like word starting the given text, used to
+/// underline a whole name rather than its first character.
+fn wordLength(text: &str) -> usize {
+    text.chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn report(input: &str) -> String {
+        let (_, errors) = state::Parser::parseWithErrors(String::from(input), None)
+            .expect("input should parse");
+        render(&errors[0], input, &options::Options::default(), Style::Plain)
+    }
+
+    #[test]
+    fn rendersTheLineOfEachPosition() {
+        assert_eq!(
+            report("let x;\r\nvar x;"),
+            "error[E0014]: Identifier 'x' has already been declared\n \
+             --> <input>:2:5\n  |\n2 | var x;\n  |     ^\n |\n\
+             note: 'x' was first declared here\n \
+             --> <input>:1:5\n  |\n1 | let x;\n  |     -\n"
+        );
+    }
+
+    #[test]
+    fn splitsLinesLikeTheTokenizer() {
+        let out = report("let x;\u{2028}\tvar x;");
+        assert!(out.contains("2 | \tvar x;\n  | \t    ^\n"), "{}", out);
+    }
+}
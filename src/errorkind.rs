@@ -0,0 +1,365 @@
+use crate::options;
+
+use std::fmt;
+
+/// Every kind of error the parser can raise. The human-readable
+/// message of a `SyntaxError` is derived from its kind; `code` gives an
+/// identifier that tools can match on instead of the message text.
+///
+/// Codes are stable: a code is never renumbered or reused for a
+/// different kind, even if the kind itself is removed or its message
+/// is reworded.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    UnexpectedToken,
+    AssigningToRvalue,
+    BindingRvalue,
+    AwaitIdentifierInAsync,
+    PatternGetterSetter,
+    RestElementDefault,
+    InvalidDefaultOperator,
+    ShorthandAssign,
+    DuplicateProto,
+    RestTrailingComma,
+    ParenthesizedPattern,
+    StrictDelete,
+    UndefinedExport(String),
+    Redeclaration(String),
+    StrictBinding(String),
+    StrictAssignment(String),
+    LetLexicalBinding,
+    ArgumentNameClash,
+    BindingMemberExpression,
+    BindingParenthesized,
+    /// A specific token was required but another one was found. Both
+    /// are given as they appear in the source, or by their token label.
+    Expected {
+        expected: String,
+        found: String,
+    },
+    UnterminatedComment,
+    YieldInDefaultParams,
+    AwaitInDefaultParams,
+    ReturnOutsideFunction,
+    UnterminatedString,
+    UnterminatedTemplate,
+    UnterminatedRegexp,
+    InvalidRegexpFlags,
+    InvalidNumber,
+    IdentifierAfterNumber,
+    StrictOctal,
+    BadEscape,
+    InvalidUnicodeEscape,
+    CodePointOutOfBounds,
+    BadTemplateEscape,
+    UnexpectedCharacter(String),
+    EscapedKeyword(String),
+    UnexpectedKeyword(String),
+    ReservedWord(String),
+    AwaitOutsideAsync,
+    YieldInGenerator,
+    UnsyntacticJump(String),
+    DuplicateLabel(String),
+    MultipleDefaults,
+    NewlineAfterThrow,
+    MissingCatchOrFinally,
+    StrictWith,
+    ForInInitializer(String),
+    MissingConstInitializer,
+    MissingPatternInitializer,
+    DuplicateConstructor,
+    SpecialConstructor,
+    StaticPrototype,
+    GetterParams,
+    SetterParams,
+    SetterRest,
+    NewTargetOutsideFunction,
+    SuperOutsideMethod,
+    SuperCallOutsideConstructor,
+    ImportExportTopLevel,
+    ImportExportOutsideModule,
+    DuplicateExport(String),
+    StrictNonSimpleParams,
+    AsyncConstructor,
+    GeneratorConstructor,
+    PropertyRedefinition,
+    InvalidMetaProperty,
+    OptionalChainingLhs,
+    /// Statements, expressions or patterns are nested more than
+    /// `parseutil::MAX_NESTING_DEPTH` deep.
+    NestingTooDeep,
+    /// The start position given to `parseExpressionAt` lies past the end
+    /// of the input.
+    PositionOutOfRange,
+    /// `Options::validate` rejected the options the parser was given.
+    InvalidOptions(options::OptionsError),
+}
+
+impl ErrorKind {
+    /// The stable code for this kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedToken => "E0001",
+            ErrorKind::AssigningToRvalue => "E0002",
+            ErrorKind::BindingRvalue => "E0003",
+            ErrorKind::AwaitIdentifierInAsync => "E0004",
+            ErrorKind::PatternGetterSetter => "E0005",
+            ErrorKind::RestElementDefault => "E0006",
+            ErrorKind::InvalidDefaultOperator => "E0007",
+            ErrorKind::ShorthandAssign => "E0008",
+            ErrorKind::DuplicateProto => "E0009",
+            ErrorKind::RestTrailingComma => "E0010",
+            ErrorKind::ParenthesizedPattern => "E0011",
+            ErrorKind::StrictDelete => "E0012",
+            ErrorKind::UndefinedExport(_) => "E0013",
+            ErrorKind::Redeclaration(_) => "E0014",
+            ErrorKind::StrictBinding(_) => "E0015",
+            ErrorKind::StrictAssignment(_) => "E0016",
+            ErrorKind::LetLexicalBinding => "E0017",
+            ErrorKind::ArgumentNameClash => "E0018",
+            ErrorKind::BindingMemberExpression => "E0019",
+            ErrorKind::BindingParenthesized => "E0020",
+            ErrorKind::Expected { .. } => "E0021",
+            ErrorKind::UnterminatedComment => "E0022",
+            ErrorKind::YieldInDefaultParams => "E0023",
+            ErrorKind::AwaitInDefaultParams => "E0024",
+            ErrorKind::ReturnOutsideFunction => "E0025",
+            ErrorKind::UnterminatedString => "E0026",
+            ErrorKind::UnterminatedTemplate => "E0027",
+            ErrorKind::UnterminatedRegexp => "E0028",
+            ErrorKind::InvalidRegexpFlags => "E0029",
+            ErrorKind::InvalidNumber => "E0030",
+            ErrorKind::IdentifierAfterNumber => "E0031",
+            ErrorKind::StrictOctal => "E0032",
+            ErrorKind::BadEscape => "E0033",
+            ErrorKind::InvalidUnicodeEscape => "E0034",
+            ErrorKind::CodePointOutOfBounds => "E0035",
+            ErrorKind::BadTemplateEscape => "E0036",
+            ErrorKind::UnexpectedCharacter(_) => "E0037",
+            ErrorKind::EscapedKeyword(_) => "E0038",
+            ErrorKind::UnexpectedKeyword(_) => "E0039",
+            ErrorKind::ReservedWord(_) => "E0040",
+            ErrorKind::AwaitOutsideAsync => "E0041",
+            ErrorKind::YieldInGenerator => "E0042",
+            ErrorKind::UnsyntacticJump(_) => "E0043",
+            ErrorKind::DuplicateLabel(_) => "E0044",
+            ErrorKind::MultipleDefaults => "E0045",
+            ErrorKind::NewlineAfterThrow => "E0046",
+            ErrorKind::MissingCatchOrFinally => "E0047",
+            ErrorKind::StrictWith => "E0048",
+            ErrorKind::ForInInitializer(_) => "E0049",
+            ErrorKind::MissingConstInitializer => "E0050",
+            ErrorKind::MissingPatternInitializer => "E0051",
+            ErrorKind::DuplicateConstructor => "E0052",
+            ErrorKind::SpecialConstructor => "E0053",
+            ErrorKind::StaticPrototype => "E0054",
+            ErrorKind::GetterParams => "E0055",
+            ErrorKind::SetterParams => "E0056",
+            ErrorKind::SetterRest => "E0057",
+            ErrorKind::NewTargetOutsideFunction => "E0058",
+            ErrorKind::SuperOutsideMethod => "E0059",
+            ErrorKind::SuperCallOutsideConstructor => "E0060",
+            ErrorKind::ImportExportTopLevel => "E0061",
+            ErrorKind::ImportExportOutsideModule => "E0062",
+            ErrorKind::DuplicateExport(_) => "E0063",
+            ErrorKind::StrictNonSimpleParams => "E0064",
+            ErrorKind::AsyncConstructor => "E0065",
+            ErrorKind::GeneratorConstructor => "E0066",
+            ErrorKind::PropertyRedefinition => "E0067",
+            ErrorKind::InvalidMetaProperty => "E0068",
+            ErrorKind::InvalidOptions(_) => "E0069",
+            ErrorKind::OptionalChainingLhs => "E0070",
+            ErrorKind::NestingTooDeep => "E0071",
+            ErrorKind::PositionOutOfRange => "E0072",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedToken => write!(f, "Unexpected token"),
+            ErrorKind::AssigningToRvalue => write!(f, "Assigning to rvalue"),
+            ErrorKind::BindingRvalue => write!(f, "Binding rvalue"),
+            ErrorKind::AwaitIdentifierInAsync => write!(
+                f,
+                "Cannot use 'await' as identifier inside an async function"
+            ),
+            ErrorKind::PatternGetterSetter => {
+                write!(f, "Object pattern can't contain getter or setter")
+            }
+            ErrorKind::RestElementDefault => {
+                write!(f, "Rest elements cannot have a default value")
+            }
+            ErrorKind::InvalidDefaultOperator => write!(
+                f,
+                "Only '=' operator can be used for specifying default value."
+            ),
+            ErrorKind::ShorthandAssign => write!(
+                f,
+                "Shorthand property assignments are valid only in destructuring patterns"
+            ),
+            ErrorKind::DuplicateProto => write!(f, "Redefinition of __proto__ property"),
+            ErrorKind::RestTrailingComma => {
+                write!(f, "Comma is not permitted after the rest element")
+            }
+            ErrorKind::ParenthesizedPattern => write!(f, "Parenthesized pattern"),
+            ErrorKind::StrictDelete => write!(f, "Deleting local variable in strict mode"),
+            ErrorKind::UndefinedExport(name) => write!(f, "Export '{}' is not defined", name),
+            ErrorKind::Redeclaration(name) => {
+                write!(f, "Identifier '{}' has already been declared", name)
+            }
+            ErrorKind::StrictBinding(name) => write!(f, "Binding {} in strict mode", name),
+            ErrorKind::StrictAssignment(name) => write!(f, "Assigning to {} in strict mode", name),
+            ErrorKind::LetLexicalBinding => {
+                write!(f, "let is disallowed as a lexically bound name")
+            }
+            ErrorKind::ArgumentNameClash => write!(f, "Argument name clash"),
+            ErrorKind::BindingMemberExpression => write!(f, "Binding member expression"),
+            ErrorKind::BindingParenthesized => write!(f, "Binding parenthesized body"),
+            ErrorKind::Expected { expected, found } => {
+                write!(f, "Expected '{}' but found '{}'", expected, found)
+            }
+            ErrorKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            ErrorKind::YieldInDefaultParams => {
+                write!(f, "Yield expression cannot be a default value")
+            }
+            ErrorKind::AwaitInDefaultParams => {
+                write!(f, "Await expression cannot be a default value")
+            }
+            ErrorKind::ReturnOutsideFunction => write!(f, "'return' outside of function"),
+            ErrorKind::UnterminatedString => write!(f, "Unterminated string constant"),
+            ErrorKind::UnterminatedTemplate => write!(f, "Unterminated template"),
+            ErrorKind::UnterminatedRegexp => write!(f, "Unterminated regular expression"),
+            ErrorKind::InvalidRegexpFlags => write!(f, "Invalid regular expression flag"),
+            ErrorKind::InvalidNumber => write!(f, "Invalid number"),
+            ErrorKind::IdentifierAfterNumber => write!(f, "Identifier directly after number"),
+            ErrorKind::StrictOctal => write!(f, "Octal literal in strict mode"),
+            ErrorKind::BadEscape => write!(f, "Bad character escape sequence"),
+            ErrorKind::InvalidUnicodeEscape => write!(f, "Invalid Unicode escape"),
+            ErrorKind::CodePointOutOfBounds => write!(f, "Code point out of bounds"),
+            ErrorKind::BadTemplateEscape => {
+                write!(f, "Bad escape sequence in untagged template literal")
+            }
+            ErrorKind::UnexpectedCharacter(ch) => write!(f, "Unexpected character '{}'", ch),
+            ErrorKind::EscapedKeyword(word) => write!(f, "Escape sequence in keyword {}", word),
+            ErrorKind::UnexpectedKeyword(word) => write!(f, "Unexpected keyword '{}'", word),
+            ErrorKind::ReservedWord(word) => write!(f, "The keyword '{}' is reserved", word),
+            ErrorKind::AwaitOutsideAsync => {
+                write!(f, "Cannot use keyword 'await' outside an async function")
+            }
+            ErrorKind::YieldInGenerator => {
+                write!(f, "Cannot use 'yield' as identifier inside a generator")
+            }
+            ErrorKind::UnsyntacticJump(keyword) => write!(f, "Unsyntactic {}", keyword),
+            ErrorKind::DuplicateLabel(name) => write!(f, "Label '{}' is already declared", name),
+            ErrorKind::MultipleDefaults => write!(f, "Multiple default clauses"),
+            ErrorKind::NewlineAfterThrow => write!(f, "Illegal newline after throw"),
+            ErrorKind::MissingCatchOrFinally => write!(f, "Missing catch or finally clause"),
+            ErrorKind::StrictWith => write!(f, "'with' in strict mode"),
+            ErrorKind::ForInInitializer(keyword) => write!(
+                f,
+                "for-{} loop variable declaration may not have an initializer",
+                keyword
+            ),
+            ErrorKind::MissingConstInitializer => {
+                write!(f, "Missing initializer in const declaration")
+            }
+            ErrorKind::MissingPatternInitializer => write!(
+                f,
+                "Complex binding patterns require an initialization value"
+            ),
+            ErrorKind::DuplicateConstructor => write!(f, "Duplicate constructor in the same class"),
+            ErrorKind::SpecialConstructor => write!(f, "Constructor can't have get/set modifier"),
+            ErrorKind::StaticPrototype => {
+                write!(f, "Classes may not have a static property named prototype")
+            }
+            ErrorKind::GetterParams => write!(f, "getter should have no params"),
+            ErrorKind::SetterParams => write!(f, "setter should have exactly one param"),
+            ErrorKind::SetterRest => write!(f, "Setter cannot use rest params"),
+            ErrorKind::NewTargetOutsideFunction => {
+                write!(f, "'new.target' can only be used in functions")
+            }
+            ErrorKind::SuperOutsideMethod => write!(f, "'super' keyword outside a method"),
+            ErrorKind::SuperCallOutsideConstructor => {
+                write!(f, "super() call outside constructor of a subclass")
+            }
+            ErrorKind::ImportExportTopLevel => {
+                write!(f, "'import' and 'export' may only appear at the top level")
+            }
+            ErrorKind::ImportExportOutsideModule => write!(
+                f,
+                "'import' and 'export' may appear only with 'sourceType: module'"
+            ),
+            ErrorKind::DuplicateExport(name) => write!(f, "Duplicate export '{}'", name),
+            ErrorKind::StrictNonSimpleParams => write!(
+                f,
+                "Illegal 'use strict' directive in function with non-simple parameter list"
+            ),
+            ErrorKind::AsyncConstructor => write!(f, "Constructor can't be an async method"),
+            ErrorKind::GeneratorConstructor => write!(f, "Constructor can't be a generator"),
+            ErrorKind::PropertyRedefinition => write!(f, "Redefinition of property"),
+            ErrorKind::InvalidMetaProperty => {
+                write!(f, "The only valid meta property for new is new.target")
+            }
+            ErrorKind::InvalidOptions(err) => write!(f, "Invalid options: {}", err),
+            ErrorKind::OptionalChainingLhs => {
+                write!(f, "Optional chaining cannot appear in left-hand side")
+            }
+            ErrorKind::NestingTooDeep => write!(f, "Nesting too deep"),
+            ErrorKind::PositionOutOfRange => write!(f, "Start position is past the end of the input"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn parseError(input: &str) -> crate::location::SyntaxError {
+        match state::Parser::parse(String::from(input), None) {
+            Ok(_) => panic!("{:?} should not parse", input),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn errorsCarryTheirKind() {
+        assert_eq!(parseError("1 = 2;").kind, ErrorKind::AssigningToRvalue);
+        assert_eq!(parseError("return;").kind, ErrorKind::ReturnOutsideFunction);
+        assert_eq!(parseError("'abc").kind, ErrorKind::UnterminatedString);
+        assert_eq!(
+            parseError("a: a: ;").kind,
+            ErrorKind::DuplicateLabel(String::from("a"))
+        );
+    }
+
+    #[test]
+    fn messagesAreDerivedFromTheKind() {
+        for input in ["1 = 2;", "return;", "'abc", "a: a: ;", "/*"] {
+            let err = parseError(input);
+            assert!(
+                err.details.message.starts_with(&err.kind.to_string()),
+                "{:?} does not start with {:?}",
+                err.details.message,
+                err.kind.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn codesAreStable() {
+        // Tools match on these; changing one is a breaking change.
+        assert_eq!(ErrorKind::UnexpectedToken.code(), "E0001");
+        assert_eq!(ErrorKind::AssigningToRvalue.code(), "E0002");
+        assert_eq!(ErrorKind::BindingRvalue.code(), "E0003");
+        assert_eq!(ErrorKind::StrictNonSimpleParams.code(), "E0064");
+        // The payload of a kind does not change its code.
+        assert_eq!(
+            ErrorKind::Redeclaration(String::from("a")).code(),
+            ErrorKind::Redeclaration(String::from("b")).code()
+        );
+    }
+}
@@ -16,20 +16,63 @@
 //
 // [opp]: http://en.wikipedia.org/wiki/Operator-precedence_parser
 
+use crate::ast;
+use crate::errorkind;
+use crate::identifier;
 use crate::location;
 use crate::locutil;
 use crate::lval;
 use crate::node;
+use crate::options;
 use crate::parseutil;
+use crate::scope;
+use crate::scopeflags;
 use crate::state;
+use crate::statement;
+use crate::tokenize;
 use crate::tokentype;
+use crate::whitespace;
 
+use std::collections::{HashMap, HashSet};
+
+use errorkind::ErrorKind;
 use location::ParserLocation;
 use lval::ParserLval;
 use node::ParserNode;
-use parseutil::ParserParseUtil;
+use parseutil::{DestructuringErrors, ParserParseUtil};
+use scope::ParserScope;
+use scopeflags::{Binds, Flags};
+use statement::ParserStatement;
+use tokenize::ParserTokenize;
+use tokentype::TokenType;
+
+/// Called by `parseMaybeAssign` on the left-hand side it parsed, with
+/// the start offset and position of that side.
+pub type AfterLeftParse =
+  fn(&mut state::Parser, node::Node, usize, Option<locutil::Position>) -> node::Node;
+
+/// The properties seen so far in an object literal, for
+/// `checkPropClash`.
+#[derive(Default)]
+pub struct PropHash {
+  proto: bool,
+  /// For ECMAScript 5, whether each name has been defined as a plain
+  /// property, a getter and a setter.
+  kinds: HashMap<String, (bool, bool, bool)>,
+}
 
 pub trait ParserExpression {
+  // Check if property name clashes with already added.
+  // Object/class getters and setters are not allowed to clash —
+  // either with each other or with an init property — and in
+  // strict mode, init properties are also not allowed to be repeated.
+  fn checkPropClash(
+    &mut self,
+    prop: &node::Node,
+    propHash: &mut PropHash,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<(), location::SyntaxError>;
+
   // ### Expression parsing
 
   // These nest, from the most general expression type at the top to
@@ -45,33 +88,33 @@ pub trait ParserExpression {
   /// and object pattern might appear (so it's possible to raise
   /// delayed syntax error at correct position).
   fn parseExpression(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node;
+    &mut self,
+    noIn: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
 
   /// Parse an assignment expression. This includes applications of
   /// operators like `+=`.
   fn parseMaybeAssign(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-    afterLeftParse: Option<fn(state::Parser, node::Node, usize, locutil::Position)>,
-  ) -> node::Node;
+    &mut self,
+    noIn: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+    afterLeftParse: Option<AfterLeftParse>,
+  ) -> Result<node::Node, location::SyntaxError>;
 
   /// Parse a ternary conditional (`?:`) operator.
   fn parseMaybeConditional(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node;
+    &mut self,
+    noIn: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
 
   /// Start the precedence parser.
   fn parseExprOps(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node;
+    &mut self,
+    noIn: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
 
   /// Parse binary operators with the operator precedence parsing
   /// algorithm. `left` is the left-hand side of the operator.
@@ -79,299 +122,1441 @@ pub trait ParserExpression {
   /// defer further parser to one of its callers when it encounters an
   /// operator that has a lower precedence than the set it is parsing.
   fn parseExprOp(
-    self,
+    &mut self,
     left: node::Node,
     leftStartPos: usize,
-    leftStartLoc: locutil::Position,
+    leftStartLoc: Option<locutil::Position>,
     minPrec: isize,
     noIn: bool,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  fn buildBinary(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    left: node::Node,
+    right: node::Node,
+    op: String,
+    logical: bool,
   ) -> node::Node;
 
   /// Parse unary operators, both prefix and postfix.
   fn parseMaybeUnary(
-    self,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
+    &mut self,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
     sawUnary: bool,
-  ) -> node::Node;
+  ) -> Result<node::Node, location::SyntaxError>;
 
-  fn parseYield(
-    self,
-    noIn: Option<bool>
-  ) -> node::Node;
+  /// Parse call, dot, and `[]`-subscript expressions.
+  fn parseExprSubscripts(
+    &mut self,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseSubscripts(
+    &mut self,
+    base: node::Node,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    noCalls: bool,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parse an atomic expression — either a single token that is an
+  /// expression, an expression started by a keyword like `function` or
+  /// `new`, or an expression wrapped in punctuation like `()`, `[]`,
+  /// or `{}`.
+  fn parseExprAtom(
+    &mut self,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseLiteral(&mut self) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseParenExpression(&mut self) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parse a parenthesized expression, which may turn out to be the
+  /// parameter list of an arrow function when `canBeArrow` is set.
+  fn parseParenAndDistinguishExpression(
+    &mut self,
+    canBeArrow: bool,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  /// Hook for plugins to process the items of a parenthesized
+  /// expression; the default leaves them as they are.
+  fn parseParenItem(&mut self, item: node::Node) -> node::Node;
+
+  fn parseParenArrowList(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    exprList: Vec<node::Node>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  // New's precedence is slightly tricky. It must allow its argument to
+  // be a `[]` or dot subscript expression, but not a call — at least,
+  // not without wrapping it in parentheses. Thus, it uses the noCalls
+  // argument to parseSubscripts to prevent it from consuming the
+  // argument list.
+  fn parseNew(&mut self) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parse template expression.
+  fn parseTemplateElement(&mut self, isTagged: bool) -> Result<node::Node, location::SyntaxError>;
+  fn parseTemplate(&mut self, isTagged: bool) -> Result<node::Node, location::SyntaxError>;
+
+  fn isAsyncProp(&self, prop: &node::Node) -> bool;
+
+  /// Parse an object literal or binding pattern.
+  fn parseObj(
+    &mut self,
+    isPattern: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseProperty(
+    &mut self,
+    isPattern: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  #[allow(clippy::too_many_arguments)]
+  fn parsePropertyValue(
+    &mut self,
+    prop: &mut node::Node,
+    isPattern: bool,
+    isGenerator: bool,
+    isAsync: bool,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+    containsEsc: bool,
+  ) -> Result<(), location::SyntaxError>;
+
+  fn parsePropertyName(&mut self, prop: &mut node::Node) -> Result<(), location::SyntaxError>;
+
+  /// Initialize empty function node.
+  fn initFunction(&self, node: &mut node::Node);
+
+  /// Parse object or class method.
+  fn parseMethod(
+    &mut self,
+    isGenerator: bool,
+    isAsync: bool,
+    allowDirectSuper: bool,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parse arrow function expression with given parameters.
+  fn parseArrowExpression(
+    &mut self,
+    node: node::Node,
+    params: Vec<node::Node>,
+    isAsync: bool,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parse function body and check parameters.
+  fn parseFunctionBody(
+    &mut self,
+    node: &mut node::Node,
+    isArrowFunction: bool,
+    isMethod: bool,
+  ) -> Result<(), location::SyntaxError>;
 
-  fn parseAwait(self) -> node::Node;
+  fn isSimpleParamList(&self, params: &[node::Node]) -> bool;
+
+  /// Checks function params for various disallowed patterns such as using "eval"
+  /// or "arguments" and duplicate parameters.
+  fn checkParams(&mut self, node: &node::Node, allowDuplicates: bool) -> Result<(), location::SyntaxError>;
+
+  /// Parses a comma-separated list of expressions, and returns them as
+  /// an array. `close` is the token type that ends the list, and
+  /// `allowEmpty` can be turned on to allow subsequent commas with
+  /// nothing in between them to be parsed as `null` (which is needed
+  /// for array literals).
+  fn parseExprList(
+    &mut self,
+    close: TokenType,
+    allowTrailingComma: bool,
+    allowEmpty: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<Vec<node::Node>, location::SyntaxError>;
+
+  fn checkUnreserved(&mut self, node: &node::Node) -> Result<(), location::SyntaxError>;
+
+  /// Parse the next token as an identifier. If `liberal` is true (used
+  /// when parsing properties), it will also convert keywords into
+  /// identifiers.
+  fn parseIdent(&mut self, liberal: bool) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parses yield expression inside generator.
+  fn parseYield(&mut self, noIn: bool) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseAwait(&mut self) -> Result<node::Node, location::SyntaxError>;
 }
 
 impl ParserExpression for state::Parser {
+  fn checkPropClash(
+    &mut self,
+    prop: &node::Node,
+    propHash: &mut PropHash,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<(), location::SyntaxError> {
+    let ecmaVersion = self.options.ecmaVersion;
+    if ecmaVersion >= options::EcmaVersion::Ecma9 && prop.r#type == "SpreadElement" {
+      return Ok(());
+    }
+    if ecmaVersion >= options::EcmaVersion::Ecma6
+      && (prop.computed == Some(true) || prop.method == Some(true) || prop.shorthand == Some(true))
+    {
+      return Ok(());
+    }
+    let key = match &prop.key {
+      Some(key) => key,
+      None => return Ok(()),
+    };
+    let name = match key.r#type.as_str() {
+      "Identifier" => key.name.clone().unwrap_or_default(),
+      "Literal" => match ast::literalValue(key.raw.as_deref().unwrap_or("")) {
+        ast::LiteralValue::String(value) => value,
+        ast::LiteralValue::Number(value) => value.to_string(),
+        _ => key.raw.clone().unwrap_or_default(),
+      },
+      _ => return Ok(()),
+    };
+    let kind = prop.kind.as_deref().unwrap_or("init");
+    if ecmaVersion >= options::EcmaVersion::Ecma6 {
+      if name == "__proto__" && kind == "init" {
+        if propHash.proto {
+          match refDestructuringErrors {
+            Some(refDestructuringErrors) => {
+              if refDestructuringErrors.doubleProto < 0 {
+                refDestructuringErrors.doubleProto = key.start as isize;
+              }
+            }
+            None => self.raiseRecoverable(key.start, ErrorKind::DuplicateProto)?,
+          }
+        }
+        propHash.proto = true;
+      }
+      return Ok(());
+    }
+    let strict = self.strict;
+    let other = propHash.kinds.entry(name).or_default();
+    let (init, get, set) = *other;
+    let redefinition = match kind {
+      "init" => strict && init || get || set,
+      "get" => init || get,
+      _ => init || set,
+    };
+    match kind {
+      "init" => other.0 = true,
+      "get" => other.1 = true,
+      _ => other.2 = true,
+    }
+    if redefinition {
+      self.raiseRecoverable(key.start, ErrorKind::PropertyRedefinition)?;
+    }
+    Ok(())
+  }
+
   fn parseExpression(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node {
-    let startPos = self.pos;
+    &mut self,
+    noIn: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let startPos = self.start;
     let startLoc = self.startLoc;
-    let expr = self.parseMaybeAssign(noIn, refDestructuringErrors, None);
-    if self.r#type == tokentype::TokenType::comma() {
-      let node = self.startNodeAt(startPos, startLoc);
-      node.expressions = [expr].to_vec();
-      while self.eat(tokentype::TokenType::comma()) {
-        node
-          .expressions
-          .push(self.parseMaybeAssign(noIn, refDestructuringErrors, None))
+    let expr = self.parseMaybeAssign(noIn, refDestructuringErrors.as_deref_mut(), None)?;
+    if self.r#type == TokenType::comma() {
+      let mut node = self.startNodeAt(startPos, startLoc);
+      node.expressions = vec![expr];
+      while self.eat(TokenType::comma())? {
+        let expr = self.parseMaybeAssign(noIn, refDestructuringErrors.as_deref_mut(), None)?;
+        node.expressions.push(expr);
       }
-      return self.finishNode(node, String::from("SequenceExpression"));
+      return Ok(self.finishNode(node, "SequenceExpression"));
     }
-    expr
+    Ok(expr)
   }
 
   fn parseMaybeAssign(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-    afterLeftParse: Option<fn(state::Parser, node::Node, usize, locutil::Position)>,
-  ) -> node::Node {
+    &mut self,
+    noIn: bool,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+    afterLeftParse: Option<AfterLeftParse>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    self.enterNesting()?;
     if self.isContextual("yield") {
       if self.inGenerator() {
-        return self.parseYield(noIn);
-      } else {
-        // The tokenizer will assume an expression is allowed after
-        // `yield`, but this isn't that kind of yield
-        self.exprAllowed = false;
-      }
-    }
-
-    let mut ownDestructuringErrors = false;
-    let mut oldParenAssign = -1;
-    let mut oldTrailingComma = -1;
-    let mut oldShorthandAssign = -1;
-    if refDestructuringErrors.is_some() {
-      oldParenAssign = refDestructuringErrors.unwrap().parenthesizedAssign;
-      oldTrailingComma = refDestructuringErrors.unwrap().trailingComma;
-      oldShorthandAssign = refDestructuringErrors.unwrap().shorthandAssign;
-      refDestructuringErrors.unwrap().parenthesizedAssign = -1;
-      refDestructuringErrors.unwrap().trailingComma = -1;
-      refDestructuringErrors.unwrap().shorthandAssign = -1;
-    } else {
-      refDestructuringErrors = Some(parseutil::DestructuringErrors::new());
-      ownDestructuringErrors = true;
+        let expr = self.parseYield(noIn);
+        self.depth -= 1;
+        return expr;
+      }
+      // The tokenizer will assume an expression is allowed after
+      // `yield`, but this isn't that kind of yield
+      self.exprAllowed = false;
     }
 
+    let ownDestructuringErrors = refDestructuringErrors.is_none();
+    let mut own = DestructuringErrors::new();
+    let refDestructuringErrors = match refDestructuringErrors {
+      Some(refDestructuringErrors) => refDestructuringErrors,
+      None => &mut own,
+    };
+    let oldParenAssign = refDestructuringErrors.parenthesizedAssign;
+    let oldTrailingComma = refDestructuringErrors.trailingComma;
+    let oldShorthandAssign = refDestructuringErrors.shorthandAssign;
+    refDestructuringErrors.parenthesizedAssign = -1;
+    refDestructuringErrors.trailingComma = -1;
+    refDestructuringErrors.shorthandAssign = -1;
+
     let startPos = self.start;
     let startLoc = self.startLoc;
-    if self.r#type == tokentype::TokenType::parenL() || self.r#type == tokentype::TokenType::name()
-    {
+    if self.r#type == TokenType::parenL() || self.r#type == TokenType::name() {
       self.potentialArrowAt = self.start as isize;
     }
-    let mut left = self.parseMaybeConditional(noIn, refDestructuringErrors);
-    if afterLeftParse.is_some() {
-      afterLeftParse.unwrap()(self, left, startPos, startLoc.unwrap());
+    let mut left = self.parseMaybeConditional(noIn, Some(&mut *refDestructuringErrors))?;
+    if let Some(afterLeftParse) = afterLeftParse {
+      left = afterLeftParse(self, left, startPos, startLoc);
     }
     if self.r#type.isAssign {
-      let node = self.startNodeAt(startPos, startLoc);
-      node.operator = self.value;
-      node.left = if self.r#type == tokentype::TokenType::eq() {
-        Some(Box::new(
-          self
-            .toAssignable(Some(left), false, refDestructuringErrors)
-            .unwrap(),
-        ))
-      } else {
-        Some(Box::new(left))
-      };
+      let mut node = self.startNodeAt(startPos, startLoc);
+      node.operator = self.value.clone();
+      let isEq = self.r#type == TokenType::eq();
+      if isEq {
+        left = self.toAssignable(left, false, Some(&*refDestructuringErrors))?;
+      }
       if !ownDestructuringErrors {
-        refDestructuringErrors.unwrap().reset();
+        refDestructuringErrors.parenthesizedAssign = -1;
+        refDestructuringErrors.trailingComma = -1;
+        refDestructuringErrors.doubleProto = -1;
+      }
+      // reset because shorthand default was used correctly
+      if refDestructuringErrors.shorthandAssign >= left.start as isize {
+        refDestructuringErrors.shorthandAssign = -1;
       }
-      refDestructuringErrors.unwrap().shorthandAssign = -1;
-      self.checkLVal(left);
-      self.next();
-      node.right = Some(Box::new(self.parseMaybeAssign(noIn, None, None)));
-      return self.finishNode(node, String::from("AssignmentExpression"));
+      if isEq {
+        self.checkLValPattern(&left, Binds::None, None)?;
+      } else {
+        self.checkLValSimple(&left, Binds::None, None)?;
+      }
+      node.left = Some(Box::new(left));
+      self.next()?;
+      node.right = Some(Box::new(self.parseMaybeAssign(noIn, None, None)?));
+      self.depth -= 1;
+      return Ok(self.finishNode(node, "AssignmentExpression"));
     } else if ownDestructuringErrors {
-      self.checkExpressionErrors(refDestructuringErrors, true);
+      self.checkExpressionErrors(Some(&*refDestructuringErrors), true)?;
     }
     if oldParenAssign > -1 {
-      refDestructuringErrors.unwrap().parenthesizedAssign = oldParenAssign;
+      refDestructuringErrors.parenthesizedAssign = oldParenAssign;
     }
     if oldTrailingComma > -1 {
-      refDestructuringErrors.unwrap().trailingComma = oldTrailingComma;
+      refDestructuringErrors.trailingComma = oldTrailingComma;
     }
     if oldShorthandAssign > -1 {
-      refDestructuringErrors.unwrap().shorthandAssign = oldShorthandAssign;
+      refDestructuringErrors.shorthandAssign = oldShorthandAssign;
     }
-    left
+    self.depth -= 1;
+    Ok(left)
   }
 
   fn parseMaybeConditional(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node {
+    &mut self,
+    noIn: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
     let startPos = self.start;
     let startLoc = self.startLoc;
-    let expr = self.parseExprOps(noIn, refDestructuringErrors);
-    if self.checkExpressionErrors(refDestructuringErrors, false) {
-      return expr;
+    let expr = self.parseExprOps(noIn, refDestructuringErrors.as_deref_mut())?;
+    if self.checkExpressionErrors(refDestructuringErrors.as_deref(), false)? {
+      return Ok(expr);
     }
-    if self.eat(tokentype::TokenType::question()) {
-      let node = self.startNodeAt(startPos, startLoc);
+    if self.eat(TokenType::question())? {
+      let mut node = self.startNodeAt(startPos, startLoc);
       node.test = Some(Box::new(expr));
-      node.consequent = Some(Box::new(self.parseMaybeAssign(None, None, None)));
-      self.expect(tokentype::TokenType::colon());
-      node.alternate = Some(Box::new(self.parseMaybeAssign(noIn, None, None)));
-      return self.finishNode(node, String::from("ConditionalExpression"));
+      node.consequent = Some(Box::new(self.parseMaybeAssign(false, None, None)?));
+      self.expect(TokenType::colon())?;
+      node.alternate = Some(Box::new(self.parseMaybeAssign(noIn, None, None)?));
+      return Ok(self.finishNode(node, "ConditionalExpression"));
     }
-    expr
+    Ok(expr)
   }
 
   fn parseExprOps(
-    self,
-    noIn: Option<bool>,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> node::Node {
+    &mut self,
+    noIn: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
     let startPos = self.start;
     let startLoc = self.startLoc;
-    let expr = self.parseMaybeUnary(refDestructuringErrors, false);
-    if self.checkExpressionErrors(refDestructuringErrors, false) {
-      return expr;
+    let expr = self.parseMaybeUnary(refDestructuringErrors.as_deref_mut(), false)?;
+    if self.checkExpressionErrors(refDestructuringErrors.as_deref(), false)? {
+      return Ok(expr);
     }
     if expr.start == startPos && expr.r#type == "ArrowFunctionExpression" {
-      expr
+      Ok(expr)
     } else {
-      self.parseExprOp(expr, startPos, startLoc.unwrap(), -1, noIn.unwrap())
+      self.parseExprOp(expr, startPos, startLoc, -1, noIn)
     }
   }
 
   fn parseExprOp(
-    self,
-    left: node::Node,
+    &mut self,
+    mut left: node::Node,
     leftStartPos: usize,
-    leftStartLoc: locutil::Position,
+    leftStartLoc: Option<locutil::Position>,
     minPrec: isize,
     noIn: bool,
-  ) -> node::Node {
-    let prec = self.r#type.binop;
-    if prec.is_some()
-      && (!noIn || self.r#type != tokentype::TokenType::_in())
-      && prec.unwrap() as isize > minPrec
-    {
-      let logical = self.r#type == tokentype::TokenType::logicalOR()
-        || self.r#type == tokentype::TokenType::logicalAND();
-      let op = self.value;
-      self.next();
-      let right = self.parseExprOp(
-        self.parseMaybeUnary(None, false),
-        self.start,
-        self.startLoc.unwrap(),
-        prec.unwrap() as isize,
-        noIn,
-      );
-      let node = self.buildBinary(leftStartPos, leftStartLoc, left, right, op, logical);
-      return self.parseExprOp(node, leftStartPos, leftStartLoc, minPrec, noIn);
+  ) -> Result<node::Node, location::SyntaxError> {
+    // Operators that bind no tighter than the one just folded in are
+    // taken by this loop rather than by recursing, so that a long chain
+    // like `a + b + c + ...` does not use stack for every operand.
+    loop {
+      let prec = match self.r#type.binop {
+        Some(prec) if !noIn || self.r#type != TokenType::_in() => prec as isize,
+        _ => return Ok(left),
+      };
+      if prec <= minPrec {
+        return Ok(left);
+      }
+      let logical = self.r#type == TokenType::logicalOR() || self.r#type == TokenType::logicalAND();
+      let op = self.value.clone().unwrap_or_default();
+      self.next()?;
+      let startPos = self.start;
+      let startLoc = self.startLoc;
+      let operand = self.parseMaybeUnary(None, false)?;
+      let right = self.parseExprOp(operand, startPos, startLoc, prec, noIn)?;
+      left = self.buildBinary(leftStartPos, leftStartLoc, left, right, op, logical);
     }
-    left
   }
 
-  fn parseMaybeUnary(
-    self,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-    sawUnary: bool,
+  fn buildBinary(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    left: node::Node,
+    right: node::Node,
+    op: String,
+    logical: bool,
   ) -> node::Node {
+    let mut node = self.startNodeAt(startPos, startLoc);
+    node.left = Some(Box::new(left));
+    node.operator = Some(op);
+    node.right = Some(Box::new(right));
+    self.finishNode(
+      node,
+      if logical {
+        "LogicalExpression"
+      } else {
+        "BinaryExpression"
+      },
+    )
+  }
+
+  fn parseMaybeUnary(
+    &mut self,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+    mut sawUnary: bool,
+  ) -> Result<node::Node, location::SyntaxError> {
+    self.enterNesting()?;
     let startPos = self.start;
     let startLoc = self.startLoc;
     let mut expr: node::Node;
 
     if self.isContextual("await")
-      && (self.inAsync() || (!self.inFunction() && self.options.allowAwaitOutsideFunction))
+      && (self.inAsync() || (!self.inFunction() && self.options.allowAwaitOutsideFunction()))
     {
-      expr = self.parseAwait();
+      expr = self.parseAwait()?;
       sawUnary = true;
     } else if self.r#type.prefix {
-      let node = self.startNode();
-      let update = self.r#type == tokentype::TokenType::incDec();
-      node.operator = self.value;
+      let mut node = self.startNode();
+      let update = self.r#type == TokenType::incDec();
+      node.operator = self.value.clone();
       node.prefix = Some(true);
-      self.next();
-      node.argument = Some(Box::new(self.parseMaybeUnary(None, true)));
-      self.checkExpressionErrors(refDestructuringErrors, true);
+      self.next()?;
+      let argument = self.parseMaybeUnary(None, true)?;
+      self.checkExpressionErrors(refDestructuringErrors.as_deref(), true)?;
       if update {
-        self.checkLVal(node.argument);
+        self.checkLValSimple(&argument, Binds::None, None)?;
       } else if self.strict
-        && node.operator.unwrap() == String::from("delete")
-        && node.argument.unwrap().r#type == "Identifier"
+        && node.operator.as_deref() == Some("delete")
+        && argument.r#type == "Identifier"
       {
-        self.raiseRecoverable(
-          node.start,
-          String::from("Deleting local variable in strict mode"),
-        );
+        self.raiseRecoverable(node.start, ErrorKind::StrictDelete)?;
       } else {
-        sawUnary = false;
+        sawUnary = true;
       }
+      node.argument = Some(Box::new(argument));
       expr = self.finishNode(
         node,
-        String::from(if update {
+        if update {
           "UpdateExpression"
         } else {
           "UnaryExpression"
-        }),
-      )
+        },
+      );
     } else {
-      expr = self.parseExprSubscripts(refDestructuringErrors);
-      if self.checkExpressionErrors(refDestructuringErrors, false) {
-        return expr;
+      expr = self.parseExprSubscripts(refDestructuringErrors.as_deref_mut())?;
+      if self.checkExpressionErrors(refDestructuringErrors.as_deref(), false)? {
+        self.depth -= 1;
+        return Ok(expr);
       }
       while self.r#type.postfix && !self.canInsertSemicolon() {
-        let node = self.startNodeAt(startPos, startLoc);
-        node.operator = self.value;
+        let mut node = self.startNodeAt(startPos, startLoc);
+        node.operator = self.value.clone();
         node.prefix = Some(false);
+        self.checkLValSimple(&expr, Binds::None, None)?;
         node.argument = Some(Box::new(expr));
-        self.checkLVal(expr);
-        self.next();
-        expr = self.finishNode(node, String::from("UpdateExpression"));
+        self.next()?;
+        expr = self.finishNode(node, "UpdateExpression");
       }
     }
 
-    if !sawUnary && self.eat(tokentype::TokenType::starstar()) {
-      self.buildBinary(
-        startPos,
-        startLoc,
-        expr,
-        self.parseMaybeUnary(None, false),
-        "**",
-        false,
-      )
+    if !sawUnary && self.eat(TokenType::starstar())? {
+      let right = self.parseMaybeUnary(None, false)?;
+      expr = self.buildBinary(startPos, startLoc, expr, right, String::from("**"), false);
+    }
+    self.depth -= 1;
+    Ok(expr)
+  }
+
+  fn parseExprSubscripts(
+    &mut self,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let startPos = self.start;
+    let startLoc = self.startLoc;
+    let expr = self.parseExprAtom(refDestructuringErrors.as_deref_mut())?;
+    let skipArrowSubscripts = expr.r#type == "ArrowFunctionExpression"
+      && &self.input[self.lastTokStart..self.lastTokEnd] != ")";
+    if self.checkExpressionErrors(refDestructuringErrors.as_deref(), false)? || skipArrowSubscripts {
+      return Ok(expr);
+    }
+    let result = self.parseSubscripts(expr, startPos, startLoc, false)?;
+    if let Some(refDestructuringErrors) = refDestructuringErrors {
+      if result.r#type == "MemberExpression" {
+        if refDestructuringErrors.parenthesizedAssign >= result.start as isize {
+          refDestructuringErrors.parenthesizedAssign = -1;
+        }
+        if refDestructuringErrors.parenthesizedBind >= result.start as isize {
+          refDestructuringErrors.parenthesizedBind = -1;
+        }
+      }
+    }
+    Ok(result)
+  }
+
+  fn parseSubscripts(
+    &mut self,
+    mut base: node::Node,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    noCalls: bool,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let maybeAsyncArrow = self.options.ecmaVersion >= options::EcmaVersion::Ecma8
+      && base.r#type == "Identifier"
+      && base.name.as_deref() == Some("async")
+      && self.lastTokEnd == base.end
+      && !self.canInsertSemicolon()
+      && &self.input[base.start..base.end] == "async";
+    loop {
+      let computed = self.eat(TokenType::bracketL())?;
+      if computed || self.eat(TokenType::dot())? {
+        let mut node = self.startNodeAt(startPos, startLoc);
+        node.object = Some(Box::new(base));
+        node.property = Some(Box::new(if computed {
+          self.parseExpression(false, None)?
+        } else {
+          self.parseIdent(true)?
+        }));
+        node.computed = Some(computed);
+        if computed {
+          self.expect(TokenType::bracketR())?;
+        }
+        base = self.finishNode(node, "MemberExpression");
+      } else if !noCalls && self.eat(TokenType::parenL())? {
+        let mut refDestructuringErrors = DestructuringErrors::new();
+        let allowTrailingComma = self.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+        let exprList = self.parseExprList(
+          TokenType::parenR(),
+          allowTrailingComma,
+          false,
+          Some(&mut refDestructuringErrors),
+        )?;
+        if maybeAsyncArrow && !self.canInsertSemicolon() && self.eat(TokenType::arrow())? {
+          self.checkPatternErrors(Some(&refDestructuringErrors), false)?;
+          let node = self.startNodeAt(startPos, startLoc);
+          return self.parseArrowExpression(node, exprList, true);
+        }
+        self.checkExpressionErrors(Some(&refDestructuringErrors), true)?;
+        let mut node = self.startNodeAt(startPos, startLoc);
+        node.callee = Some(Box::new(base));
+        node.arguments = Some(exprList);
+        base = self.finishNode(node, "CallExpression");
+      } else if self.r#type == TokenType::backQuote() {
+        let mut node = self.startNodeAt(startPos, startLoc);
+        node.tag = Some(Box::new(base));
+        node.quasi = Some(Box::new(self.parseTemplate(true)?));
+        base = self.finishNode(node, "TaggedTemplateExpression");
+      } else {
+        return Ok(base);
+      }
+    }
+  }
+
+  fn parseExprAtom(
+    &mut self,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    // If a division operator appears in an expression position, the
+    // tokenizer got confused, and we force it to read a regexp instead.
+    if self.r#type == TokenType::slash() || self.r#type == TokenType::assign() && self.value.as_deref() == Some("/=") {
+      self.pos = self.start + 1;
+      self.readRegexp()?;
+    }
+
+    let canBeArrow = self.potentialArrowAt == self.start as isize;
+    let r#type = self.r#type.clone();
+    if r#type == TokenType::_super() {
+      if !self.allowSuper() {
+        self.raise(self.start, ErrorKind::SuperOutsideMethod)?;
+      }
+      let node = self.startNode();
+      self.next()?;
+      if self.r#type == TokenType::parenL() && !self.allowDirectSuper() {
+        self.raise(node.start, ErrorKind::SuperCallOutsideConstructor)?;
+      }
+      // The `super` keyword can appear at below:
+      // SuperProperty:
+      //     super [ Expression ]
+      //     super . IdentifierName
+      // SuperCall:
+      //     super Arguments
+      if self.r#type != TokenType::dot()
+        && self.r#type != TokenType::bracketL()
+        && self.r#type != TokenType::parenL()
+      {
+        return self.unexpected(None);
+      }
+      Ok(self.finishNode(node, "Super"))
+    } else if r#type == TokenType::_this() {
+      let node = self.startNode();
+      self.next()?;
+      Ok(self.finishNode(node, "ThisExpression"))
+    } else if r#type == TokenType::name() {
+      let startPos = self.start;
+      let startLoc = self.startLoc;
+      let containsEsc = self.containsEsc;
+      let id = self.parseIdent(false)?;
+      let isAsync = self.options.ecmaVersion >= options::EcmaVersion::Ecma8
+        && !containsEsc
+        && id.name.as_deref() == Some("async");
+      if isAsync && !self.canInsertSemicolon() && self.eat(TokenType::_function())? {
+        let node = self.startNodeAt(startPos, startLoc);
+        return self.parseFunction(node, 0, false, true);
+      }
+      if canBeArrow && !self.canInsertSemicolon() {
+        if self.eat(TokenType::arrow())? {
+          let node = self.startNodeAt(startPos, startLoc);
+          return self.parseArrowExpression(node, vec![id], false);
+        }
+        if isAsync && self.r#type == TokenType::name() {
+          let id = self.parseIdent(false)?;
+          if self.canInsertSemicolon() || !self.eat(TokenType::arrow())? {
+            return self.unexpected(None);
+          }
+          let node = self.startNodeAt(startPos, startLoc);
+          return self.parseArrowExpression(node, vec![id], true);
+        }
+      }
+      Ok(id)
+    } else if r#type == TokenType::regexp() || r#type == TokenType::num() || r#type == TokenType::string() {
+      self.parseLiteral()
+    } else if r#type == TokenType::_null() || r#type == TokenType::_true() || r#type == TokenType::_false() {
+      let mut node = self.startNode();
+      node.raw = Some(String::from(r#type.keyword));
+      self.next()?;
+      Ok(self.finishNode(node, "Literal"))
+    } else if r#type == TokenType::parenL() {
+      let start = self.start;
+      let expr = self.parseParenAndDistinguishExpression(canBeArrow)?;
+      if let Some(refDestructuringErrors) = refDestructuringErrors {
+        if refDestructuringErrors.parenthesizedAssign < 0 && !self.isSimpleAssignTarget(&expr) {
+          refDestructuringErrors.parenthesizedAssign = start as isize;
+        }
+        if refDestructuringErrors.parenthesizedBind < 0 {
+          refDestructuringErrors.parenthesizedBind = start as isize;
+        }
+      }
+      Ok(expr)
+    } else if r#type == TokenType::bracketL() {
+      let mut node = self.startNode();
+      self.next()?;
+      node.elements = Some(self.parseExprList(TokenType::bracketR(), true, true, refDestructuringErrors)?);
+      Ok(self.finishNode(node, "ArrayExpression"))
+    } else if r#type == TokenType::braceL() {
+      self.parseObj(false, refDestructuringErrors)
+    } else if r#type == TokenType::_function() {
+      let node = self.startNode();
+      self.next()?;
+      self.parseFunction(node, 0, false, false)
+    } else if r#type == TokenType::_class() {
+      let node = self.startNode();
+      self.parseClass(node, false, false)
+    } else if r#type == TokenType::_new() {
+      self.parseNew()
+    } else if r#type == TokenType::backQuote() {
+      self.parseTemplate(false)
     } else {
-      expr
+      self.unexpected(None)
     }
   }
 
-  fn parseYield (
-    self,
-    noIn: Option<bool>
-  ) -> node::Node {
-    if !self.yieldPos {
-        self.yieldPos = self.start;
+  fn parseLiteral(&mut self) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    // The value is derived from the source text when needed; see
+    // `ast::literalValue`.
+    node.raw = Some(String::from(&self.input[self.start..self.end]));
+    self.next()?;
+    Ok(self.finishNode(node, "Literal"))
+  }
+
+  fn parseParenExpression(&mut self) -> Result<node::Node, location::SyntaxError> {
+    self.expect(TokenType::parenL())?;
+    let val = self.parseExpression(false, None)?;
+    self.expect(TokenType::parenR())?;
+    Ok(val)
+  }
+
+  fn parseParenAndDistinguishExpression(
+    &mut self,
+    canBeArrow: bool,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let startPos = self.start;
+    let startLoc = self.startLoc;
+    let val: node::Node;
+    let allowTrailingComma = self.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+      self.next()?;
+
+      let innerStartPos = self.start;
+      let innerStartLoc = self.startLoc;
+      let mut exprList: Vec<node::Node> = Vec::new();
+      let mut first = true;
+      let mut lastIsComma = false;
+      let mut refDestructuringErrors = DestructuringErrors::new();
+      let oldYieldPos = self.yieldPos;
+      let oldAwaitPos = self.awaitPos;
+      let mut spreadStart: Option<usize> = None;
+      self.yieldPos = 0;
+      self.awaitPos = 0;
+      // Do not save awaitIdentPos to allow checking awaits nested in
+      // parameters
+      while self.r#type != TokenType::parenR() {
+        if first {
+          first = false;
+        } else {
+          self.expect(TokenType::comma())?;
+        }
+        if allowTrailingComma && self.afterTrailingComma(TokenType::parenR(), true)? {
+          lastIsComma = true;
+          break;
+        } else if self.r#type == TokenType::ellipsis() {
+          spreadStart = Some(self.start);
+          let rest = self.parseRestBinding()?;
+          let rest = self.parseParenItem(rest);
+          exprList.push(rest);
+          if self.r#type == TokenType::comma() {
+            self.raise(self.start, ErrorKind::RestTrailingComma)?;
+          }
+          break;
+        } else {
+          let item = self.parseMaybeAssign(false, Some(&mut refDestructuringErrors), None)?;
+          let item = self.parseParenItem(item);
+          exprList.push(item);
+        }
+      }
+      let innerEndPos = self.lastTokEnd;
+      let innerEndLoc = self.lastTokEndLoc;
+      self.expect(TokenType::parenR())?;
+
+      if canBeArrow && !self.canInsertSemicolon() && self.eat(TokenType::arrow())? {
+        self.checkPatternErrors(Some(&refDestructuringErrors), false)?;
+        self.checkYieldAwaitInDefaultParams()?;
+        self.yieldPos = oldYieldPos;
+        self.awaitPos = oldAwaitPos;
+        return self.parseParenArrowList(startPos, startLoc, exprList);
+      }
+
+      if exprList.is_empty() || lastIsComma {
+        return self.unexpected(Some(self.lastTokStart));
+      }
+      if let Some(spreadStart) = spreadStart {
+        return self.unexpected(Some(spreadStart));
+      }
+      self.checkExpressionErrors(Some(&refDestructuringErrors), true)?;
+      if oldYieldPos != 0 {
+        self.yieldPos = oldYieldPos;
+      }
+      if oldAwaitPos != 0 {
+        self.awaitPos = oldAwaitPos;
+      }
+
+      if exprList.len() > 1 {
+        let mut sequence = self.startNodeAt(innerStartPos, innerStartLoc);
+        sequence.expressions = exprList;
+        val = self.finishNodeAt(sequence, "SequenceExpression", innerEndPos, innerEndLoc);
+      } else {
+        val = exprList.remove(0);
+      }
+    } else {
+      val = self.parseParenExpression()?;
     }
 
-    let node = self.startNode();
-    self.next();
-    if self.r#type == tokentype::TokenType::semi() || self.canInsertSemiColon() || (self.r#type != tokentype::TokenType::star() && !self.r#type.startsExpr) {
-      node.delegate = false;
-      node.argument = None;
+    if self.options.preserveParens {
+      let mut par = self.startNodeAt(startPos, startLoc);
+      par.expression = Some(Box::new(val));
+      Ok(self.finishNode(par, "ParenthesizedExpression"))
     } else {
-      node.delegate = self.eat(tokentype::TokenType::star());
-      node.argument = Some(Box::new(self.parseMaybeAssign(noIn, None, None)));
+      Ok(val)
     }
-    self.finishNode(node, String::from("YieldExpression"))
   }
 
-  fn parseAwait (self) -> node::Node {
-      if !self.awaitPos {
-          self.awaitPos = self.start;
+  fn parseParenItem(&mut self, item: node::Node) -> node::Node {
+    item
+  }
+
+  fn parseParenArrowList(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    exprList: Vec<node::Node>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let node = self.startNodeAt(startPos, startLoc);
+    self.parseArrowExpression(node, exprList, false)
+  }
+
+  fn parseNew(&mut self) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    let meta = self.parseIdent(true)?;
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && self.eat(TokenType::dot())? {
+      node.meta = Some(Box::new(meta));
+      let containsEsc = self.containsEsc;
+      let property = self.parseIdent(true)?;
+      if property.name.as_deref() != Some("target") || containsEsc {
+        self.raiseRecoverable(property.start, ErrorKind::InvalidMetaProperty)?;
       }
+      let inNonArrowFunction = self
+        .currentThisScope()
+        .is_some_and(|scope| (scope.flags & Flags::Function) != Flags::Zero);
+      if !inNonArrowFunction {
+        self.raiseRecoverable(node.start, ErrorKind::NewTargetOutsideFunction)?;
+      }
+      node.property = Some(Box::new(property));
+      return Ok(self.finishNode(node, "MetaProperty"));
+    }
+    self.enterNesting()?;
+    let startPos = self.start;
+    let startLoc = self.startLoc;
+    let callee = self.parseExprAtom(None)?;
+    node.callee = Some(Box::new(self.parseSubscripts(callee, startPos, startLoc, true)?));
+    node.arguments = Some(if self.eat(TokenType::parenL())? {
+      let allowTrailingComma = self.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+      self.parseExprList(TokenType::parenR(), allowTrailingComma, false, None)?
+    } else {
+      Vec::new()
+    });
+    self.depth -= 1;
+    Ok(self.finishNode(node, "NewExpression"))
+  }
 
-      let node = self.startNode();
-      self.next();
-      node.argument = Some(Box::new(self.parseMaybeUnary(None, true)));
-      self.finishNode(node, String::from("AwaitExpression"))
+  fn parseTemplateElement(&mut self, isTagged: bool) -> Result<node::Node, location::SyntaxError> {
+    let mut elem = self.startNode();
+    if self.r#type == TokenType::invalidTemplate() {
+      if !isTagged {
+        self.raiseRecoverable(self.start, ErrorKind::BadTemplateEscape)?;
+      }
+      elem.raw = self.value.clone();
+      elem.cooked = None;
+    } else {
+      let raw = &self.input[self.start..self.end];
+      elem.raw = Some(raw.replace("\r\n", "\n").replace('\r', "\n"));
+      elem.cooked = self.value.clone();
+    }
+    self.next()?;
+    elem.tail = Some(self.r#type == TokenType::backQuote());
+    Ok(self.finishNode(elem, "TemplateElement"))
+  }
+
+  fn parseTemplate(&mut self, isTagged: bool) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    self.next()?;
+    node.expressions = Vec::new();
+    let mut curElt = self.parseTemplateElement(isTagged)?;
+    let mut quasis = Vec::new();
+    while curElt.tail != Some(true) {
+      quasis.push(curElt);
+      if self.r#type == TokenType::eof() {
+        self.raise(self.pos, ErrorKind::UnterminatedTemplate)?;
+      }
+      self.expect(TokenType::dollarBraceL())?;
+      node.expressions.push(self.parseExpression(false, None)?);
+      self.expect(TokenType::braceR())?;
+      curElt = self.parseTemplateElement(isTagged)?;
+    }
+    quasis.push(curElt);
+    node.quasis = Some(quasis);
+    self.next()?;
+    Ok(self.finishNode(node, "TemplateLiteral"))
+  }
+
+  fn isAsyncProp(&self, prop: &node::Node) -> bool {
+    let key = match &prop.key {
+      Some(key) => key,
+      None => return false,
+    };
+    prop.computed != Some(true)
+      && key.r#type == "Identifier"
+      && key.name.as_deref() == Some("async")
+      && (self.r#type == TokenType::name()
+        || self.r#type == TokenType::num()
+        || self.r#type == TokenType::string()
+        || self.r#type == TokenType::bracketL()
+        || !self.r#type.keyword.is_empty()
+        || (self.options.ecmaVersion >= options::EcmaVersion::Ecma9 && self.r#type == TokenType::star()))
+      && !whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start])
+  }
+
+  fn parseObj(
+    &mut self,
+    isPattern: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    let mut first = true;
+    let mut propHash = PropHash::default();
+    let mut properties = Vec::new();
+    self.next()?;
+    while !self.eat(TokenType::braceR())? {
+      if !first {
+        self.expect(TokenType::comma())?;
+        if self.options.ecmaVersion >= options::EcmaVersion::Ecma5
+          && self.afterTrailingComma(TokenType::braceR(), false)?
+        {
+          break;
+        }
+      } else {
+        first = false;
+      }
+      let prop = self.parseProperty(isPattern, refDestructuringErrors.as_deref_mut())?;
+      if !isPattern {
+        self.checkPropClash(&prop, &mut propHash, refDestructuringErrors.as_deref_mut())?;
+      }
+      properties.push(prop);
+    }
+    node.properties = Some(properties);
+    Ok(self.finishNode(
+      node,
+      if isPattern {
+        "ObjectPattern"
+      } else {
+        "ObjectExpression"
+      },
+    ))
+  }
+
+  fn parseProperty(
+    &mut self,
+    isPattern: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let mut prop = self.startNode();
+    let mut isGenerator = false;
+    let mut startPos = 0;
+    let mut startLoc = None;
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma9 && self.eat(TokenType::ellipsis())? {
+      if isPattern {
+        prop.argument = Some(Box::new(self.parseIdent(false)?));
+        if self.r#type == TokenType::comma() {
+          self.raise(self.start, ErrorKind::RestTrailingComma)?;
+        }
+        return Ok(self.finishNode(prop, "RestElement"));
+      }
+      // To disallow parenthesized identifier via `this.toAssignable()`.
+      if self.r#type == TokenType::parenL() {
+        if let Some(refDestructuringErrors) = refDestructuringErrors.as_deref_mut() {
+          if refDestructuringErrors.parenthesizedAssign < 0 {
+            refDestructuringErrors.parenthesizedAssign = self.start as isize;
+          }
+          if refDestructuringErrors.parenthesizedBind < 0 {
+            refDestructuringErrors.parenthesizedBind = self.start as isize;
+          }
+        }
+      }
+      // Parse argument.
+      let argument = self.parseMaybeAssign(false, refDestructuringErrors.as_deref_mut(), None)?;
+      prop.argument = Some(Box::new(argument));
+      // To disallow trailing comma via `this.toAssignable()`.
+      if self.r#type == TokenType::comma() {
+        if let Some(refDestructuringErrors) = refDestructuringErrors {
+          if refDestructuringErrors.trailingComma < 0 {
+            refDestructuringErrors.trailingComma = self.start as isize;
+          }
+        }
+      }
+      return Ok(self.finishNode(prop, "SpreadElement"));
+    }
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+      prop.method = Some(false);
+      prop.shorthand = Some(false);
+      if isPattern || refDestructuringErrors.is_some() {
+        startPos = self.start;
+        startLoc = self.startLoc;
+      }
+      if !isPattern {
+        isGenerator = self.eat(TokenType::star())?;
+      }
+    }
+    let containsEsc = self.containsEsc;
+    self.parsePropertyName(&mut prop)?;
+    let mut isAsync = false;
+    if !isPattern
+      && !containsEsc
+      && self.options.ecmaVersion >= options::EcmaVersion::Ecma8
+      && !isGenerator
+      && self.isAsyncProp(&prop)
+    {
+      isAsync = true;
+      isGenerator = self.options.ecmaVersion >= options::EcmaVersion::Ecma9 && self.eat(TokenType::star())?;
+      self.parsePropertyName(&mut prop)?;
+    }
+    self.parsePropertyValue(
+      &mut prop,
+      isPattern,
+      isGenerator,
+      isAsync,
+      startPos,
+      startLoc,
+      refDestructuringErrors,
+      containsEsc,
+    )?;
+    Ok(self.finishNode(prop, "Property"))
+  }
+
+  fn parsePropertyValue(
+    &mut self,
+    prop: &mut node::Node,
+    isPattern: bool,
+    isGenerator: bool,
+    isAsync: bool,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    refDestructuringErrors: Option<&mut DestructuringErrors>,
+    containsEsc: bool,
+  ) -> Result<(), location::SyntaxError> {
+    if (isGenerator || isAsync) && self.r#type == TokenType::colon() {
+      return self.unexpected(None);
+    }
+    let ecmaVersion = self.options.ecmaVersion;
+    let keyName = match &prop.key {
+      Some(key) if prop.computed != Some(true) && key.r#type == "Identifier" => key.name.clone(),
+      _ => None,
+    };
+    if self.eat(TokenType::colon())? {
+      prop.value = Some(Box::new(if isPattern {
+        self.parseMaybeDefault(self.start, self.startLoc, None)?
+      } else {
+        self.parseMaybeAssign(false, refDestructuringErrors, None)?
+      }));
+      prop.kind = Some(String::from("init"));
+    } else if ecmaVersion >= options::EcmaVersion::Ecma6 && self.r#type == TokenType::parenL() {
+      if isPattern {
+        return self.unexpected(None);
+      }
+      prop.kind = Some(String::from("init"));
+      prop.method = Some(true);
+      prop.value = Some(Box::new(self.parseMethod(isGenerator, isAsync, false)?));
+    } else if !isPattern
+      && !containsEsc
+      && ecmaVersion >= options::EcmaVersion::Ecma5
+      && (keyName.as_deref() == Some("get") || keyName.as_deref() == Some("set"))
+      && self.r#type != TokenType::comma()
+      && self.r#type != TokenType::braceR()
+    {
+      if isGenerator || isAsync {
+        return self.unexpected(None);
+      }
+      let kind = keyName.unwrap_or_default();
+      self.parsePropertyName(prop)?;
+      let value = self.parseMethod(false, false, false)?;
+      let params = value.params.as_deref().unwrap_or(&[]);
+      let paramCount = if kind == "get" { 0 } else { 1 };
+      if params.len() != paramCount {
+        let kind = if kind == "get" {
+          ErrorKind::GetterParams
+        } else {
+          ErrorKind::SetterParams
+        };
+        self.raiseRecoverable(value.start, kind)?;
+      } else if kind == "set" && params[0].r#type == "RestElement" {
+        self.raiseRecoverable(params[0].start, ErrorKind::SetterRest)?;
+      }
+      prop.kind = Some(kind);
+      prop.value = Some(Box::new(value));
+    } else if ecmaVersion >= options::EcmaVersion::Ecma6 && keyName.is_some() {
+      if isGenerator || isAsync {
+        return self.unexpected(None);
+      }
+      let key = match prop.key.as_deref() {
+        Some(key) => key.clone(),
+        None => return self.unexpected(None),
+      };
+      self.checkUnreserved(&key)?;
+      prop.kind = Some(String::from("init"));
+      if isPattern {
+        prop.value = Some(Box::new(self.parseMaybeDefault(startPos, startLoc, Some(key))?));
+      } else if self.r#type == TokenType::eq() && refDestructuringErrors.is_some() {
+        if let Some(refDestructuringErrors) = refDestructuringErrors {
+          if refDestructuringErrors.shorthandAssign < 0 {
+            refDestructuringErrors.shorthandAssign = self.start as isize;
+          }
+        }
+        prop.value = Some(Box::new(self.parseMaybeDefault(startPos, startLoc, Some(key))?));
+      } else {
+        prop.value = Some(Box::new(key));
+      }
+      prop.shorthand = Some(true);
+    } else {
+      return self.unexpected(None);
+    }
+    Ok(())
+  }
+
+  fn parsePropertyName(&mut self, prop: &mut node::Node) -> Result<(), location::SyntaxError> {
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+      if self.eat(TokenType::bracketL())? {
+        prop.computed = Some(true);
+        prop.key = Some(Box::new(self.parseMaybeAssign(false, None, None)?));
+        return self.expect(TokenType::bracketR());
+      }
+      prop.computed = Some(false);
+    }
+    let key = if self.r#type == TokenType::num() || self.r#type == TokenType::string() {
+      self.parseExprAtom(None)?
+    } else {
+      self.parseIdent(true)?
+    };
+    prop.key = Some(Box::new(key));
+    Ok(())
+  }
+
+  fn initFunction(&self, node: &mut node::Node) {
+    node.id = None;
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+      node.generator = Some(false);
+    }
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+      node.r#async = Some(false);
+    }
+  }
+
+  fn parseMethod(
+    &mut self,
+    isGenerator: bool,
+    isAsync: bool,
+    allowDirectSuper: bool,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    let oldYieldPos = self.yieldPos;
+    let oldAwaitPos = self.awaitPos;
+
+    self.initFunction(&mut node);
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+      node.generator = Some(isGenerator);
+    }
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+      node.r#async = Some(isAsync);
+    }
+
+    let directSuper = if allowDirectSuper {
+      Flags::DirectSuper
+    } else {
+      Flags::Zero
+    };
+    self.yieldPos = 0;
+    self.awaitPos = 0;
+    self.enterScope(scopeflags::function_flags(isAsync, isGenerator) | Flags::Super | directSuper);
+
+    self.expect(TokenType::parenL())?;
+    let allowTrailingComma = self.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+    node.params = Some(self.parseBindingList(TokenType::parenR(), false, allowTrailingComma)?);
+    self.checkYieldAwaitInDefaultParams()?;
+    self.parseFunctionBody(&mut node, false, true)?;
+
+    self.yieldPos = oldYieldPos;
+    self.awaitPos = oldAwaitPos;
+    Ok(self.finishNode(node, "FunctionExpression"))
+  }
+
+  fn parseArrowExpression(
+    &mut self,
+    mut node: node::Node,
+    params: Vec<node::Node>,
+    isAsync: bool,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let oldYieldPos = self.yieldPos;
+    let oldAwaitPos = self.awaitPos;
+
+    self.enterScope(scopeflags::function_flags(isAsync, false) | Flags::Arrow);
+    node.id = None;
+    if self.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+      node.r#async = Some(isAsync);
+    }
+
+    node.params = Some(self.toAssignableList(params, true)?);
+    self.yieldPos = 0;
+    self.awaitPos = 0;
+    self.parseFunctionBody(&mut node, true, false)?;
+
+    self.yieldPos = oldYieldPos;
+    self.awaitPos = oldAwaitPos;
+    Ok(self.finishNode(node, "ArrowFunctionExpression"))
+  }
+
+  fn parseFunctionBody(
+    &mut self,
+    node: &mut node::Node,
+    isArrowFunction: bool,
+    isMethod: bool,
+  ) -> Result<(), location::SyntaxError> {
+    let isExpression = isArrowFunction && self.r#type != TokenType::braceL();
+    let oldStrict = self.strict;
+
+    if isExpression {
+      // An arrow function's expression body is the only element of
+      // `body`; see `node::Node`.
+      node.body = Some(vec![self.parseMaybeAssign(false, None, None)?]);
+      self.checkParams(node, false)?;
+    } else {
+      let params = node.params.as_deref().unwrap_or(&[]);
+      let nonSimple = self.options.ecmaVersion >= options::EcmaVersion::Ecma7 && !self.isSimpleParamList(params);
+      let mut useStrict = false;
+      if !oldStrict || nonSimple {
+        useStrict = self.strictDirective(self.end);
+        if useStrict && nonSimple {
+          self.raiseRecoverable(node.start, ErrorKind::StrictNonSimpleParams)?;
+        }
+      }
+      // Start a new scope with regard to labels and the `inFunction`
+      // flag (restore them to their old value afterwards).
+      let oldLabels = std::mem::take(&mut self.labels);
+      if useStrict {
+        self.strict = true;
+      }
+      // Add the params to varDeclaredNames to ensure that an error is thrown
+      // if a let/const declaration in the function clashes with one of the params.
+      let allowDuplicates = !oldStrict
+        && !useStrict
+        && !isArrowFunction
+        && !isMethod
+        && self.isSimpleParamList(node.params.as_deref().unwrap_or(&[]));
+      self.checkParams(node, allowDuplicates)?;
+      // Ensure the function name isn't a forbidden identifier in strict mode, e.g. 'eval'
+      if self.strict {
+        if let Some(id) = &node.id {
+          self.checkLValSimple(id, Binds::Outside, None)?;
+        }
+      }
+      let body = self.parseBlock(false)?;
+      node.body = Some(vec![body]);
+      self.labels = oldLabels;
+    }
+    self.exitScope();
+    self.strict = oldStrict;
+    Ok(())
+  }
+
+  fn isSimpleParamList(&self, params: &[node::Node]) -> bool {
+    params.iter().all(|param| param.r#type == "Identifier")
+  }
+
+  fn checkParams(&mut self, node: &node::Node, allowDuplicates: bool) -> Result<(), location::SyntaxError> {
+    let mut nameHash = HashSet::new();
+    for param in node.params.iter().flatten() {
+      let checkClashes = if allowDuplicates { None } else { Some(&mut nameHash) };
+      self.checkLValInnerPattern(param, Binds::Var, checkClashes)?;
+    }
+    Ok(())
+  }
+
+  fn parseExprList(
+    &mut self,
+    close: TokenType,
+    allowTrailingComma: bool,
+    allowEmpty: bool,
+    mut refDestructuringErrors: Option<&mut DestructuringErrors>,
+  ) -> Result<Vec<node::Node>, location::SyntaxError> {
+    let mut elts = Vec::new();
+    let mut first = true;
+    while !self.eat(close.clone())? {
+      if !first {
+        self.expect(TokenType::comma())?;
+        if allowTrailingComma && self.afterTrailingComma(close.clone(), false)? {
+          break;
+        }
+      } else {
+        first = false;
+      }
+
+      let elt = if allowEmpty && self.r#type == TokenType::comma() {
+        node::Node::hole(self.start)
+      } else if self.r#type == TokenType::ellipsis() {
+        let elt = self.parseSpread(refDestructuringErrors.as_deref_mut())?;
+        if self.r#type == TokenType::comma() {
+          if let Some(refDestructuringErrors) = refDestructuringErrors.as_deref_mut() {
+            if refDestructuringErrors.trailingComma < 0 {
+              refDestructuringErrors.trailingComma = self.start as isize;
+            }
+          }
+        }
+        elt
+      } else {
+        let elt = self.parseMaybeAssign(false, refDestructuringErrors.as_deref_mut(), None)?;
+        self.parseParenItem(elt)
+      };
+      elts.push(elt);
+    }
+    Ok(elts)
+  }
+
+  fn checkUnreserved(&mut self, node: &node::Node) -> Result<(), location::SyntaxError> {
+    let name = node.name.as_deref().unwrap_or("");
+    if self.inGenerator() && name == "yield" {
+      self.raiseRecoverable(node.start, ErrorKind::YieldInGenerator)?;
+    }
+    if self.inAsync() && name == "await" {
+      self.raiseRecoverable(node.start, ErrorKind::AwaitIdentifierInAsync)?;
+    }
+    let ecmaVersion = self.options.ecmaVersion;
+    if identifier::isKeyword(name, ecmaVersion, self.inModule) {
+      self
+        .raise(node.start, ErrorKind::UnexpectedKeyword(String::from(name)))
+        .map_err(|mut err| {
+          err.details.help = Some(parseutil::reservedWordHint(name));
+          err
+        })?;
+    }
+    if ecmaVersion < options::EcmaVersion::Ecma6 && self.input[node.start..node.end].contains('\\') {
+      return Ok(());
+    }
+    let reserved = identifier::isReservedWord(name, ecmaVersion, self.inModule, self.strict)
+      && (!self.options.allowReserved()
+        || !identifier::isReservedWord(name, ecmaVersion, self.inModule, false));
+    if reserved {
+      if !self.inAsync() && name == "await" {
+        self.raiseRecoverable(node.start, ErrorKind::AwaitOutsideAsync)?;
+      }
+      self.raiseRecoverable(node.start, ErrorKind::ReservedWord(String::from(name)))?;
+    }
+    Ok(())
+  }
+
+  fn parseIdent(&mut self, liberal: bool) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    if self.r#type == TokenType::name() || !self.r#type.keyword.is_empty() {
+      node.name = self.value.clone();
+    } else {
+      return self.unexpected(None);
+    }
+    // `class` and `function` keywords push new context into
+    // `self.context`, but there is no chance to pop the context if the
+    // keyword is consumed as an identifier such as a property name.
+    // If the previous token is a dot, this does not apply because the
+    // context-managing code already ignored the keyword.
+    let name = node.name.as_deref();
+    if (name == Some("class") || name == Some("function"))
+      && self.r#type != TokenType::name()
+      && (self.lastTokEnd != self.lastTokStart + 1 || self.input.as_bytes().get(self.lastTokStart) != Some(&b'.'))
+    {
+      self.context.pop();
+    }
+    self.next()?;
+    let node = self.finishNode(node, "Identifier");
+    if !liberal {
+      self.checkUnreserved(&node)?;
+    }
+    Ok(node)
+  }
+
+  fn parseYield(&mut self, noIn: bool) -> Result<node::Node, location::SyntaxError> {
+    if self.yieldPos == 0 {
+      self.yieldPos = self.start;
+    }
+
+    let mut node = self.startNode();
+    self.next()?;
+    if self.r#type == TokenType::semi()
+      || self.canInsertSemicolon()
+      || (self.r#type != TokenType::star() && !self.r#type.startsExpr)
+    {
+      node.delegate = Some(false);
+      node.argument = None;
+    } else {
+      node.delegate = Some(self.eat(TokenType::star())?);
+      node.argument = Some(Box::new(self.parseMaybeAssign(noIn, None, None)?));
+    }
+    Ok(self.finishNode(node, "YieldExpression"))
+  }
+
+  fn parseAwait(&mut self) -> Result<node::Node, location::SyntaxError> {
+    if self.awaitPos == 0 {
+      self.awaitPos = self.start;
+    }
+
+    let mut node = self.startNode();
+    self.next()?;
+    node.argument = Some(Box::new(self.parseMaybeUnary(None, true)?));
+    Ok(self.finishNode(node, "AwaitExpression"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::errorkind::ErrorKind;
+  use crate::options;
+  use crate::state;
+
+  fn parse(input: &str) -> Result<(), ErrorKind> {
+    let options = options::Options::builder().ecmaVersion(options::EcmaVersion::Latest).build().unwrap();
+    state::Parser::parse(String::from(input), Some(options)).map(|_| ()).map_err(|err| err.kind)
+  }
+
+  #[test]
+  fn rejectsYieldAndAwaitInDefaultParams() {
+    assert_eq!(parse("function* g(a = yield) {}"), Err(ErrorKind::YieldInDefaultParams));
+    assert_eq!(parse("async function f(a = await x) {}"), Err(ErrorKind::AwaitInDefaultParams));
+    assert_eq!(parse("({ *m(a = yield) {} });"), Err(ErrorKind::YieldInDefaultParams));
+    assert_eq!(parse("function* g() { (a = yield) => a; }"), Err(ErrorKind::YieldInDefaultParams));
+  }
+
+  #[test]
+  fn nestedFunctionsDoNotLeakYieldOrAwait() {
+    assert_eq!(parse("function* g() { (x = function* () { yield; }) => x; }"), Ok(()));
+    assert_eq!(parse("function* g() { (x = { *m() { yield; } }) => x; }"), Ok(()));
+    assert_eq!(parse("async function f() { (x = async function () { await 1; }) => x; }"), Ok(()));
+    assert_eq!(parse("async function f() { (x = async () => await 1) => x; }"), Ok(()));
+  }
+
+  #[test]
+  fn preservesParentheses() {
+    let options = options::Options::builder().preserveParens(true).build().unwrap();
+    let program = match state::Parser::parse(String::from("(a);"), Some(options)) {
+      Ok(program) => program,
+      Err(err) => panic!("{}", err),
+    };
+    let statement = &program.body.as_ref().unwrap()[0];
+    let expression = statement.expression.as_ref().unwrap();
+    assert_eq!(expression.r#type, "ParenthesizedExpression");
+    assert_eq!(expression.expression.as_ref().unwrap().r#type, "Identifier");
+    assert_eq!((expression.start, expression.end), (0, 3));
   }
 }
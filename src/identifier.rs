@@ -1,17 +1,145 @@
 // Reserved word lists for various dialects of the language
 
-pub struct ReservedWords {
-    Three: String String::from("abstract boolean byte char class double enum export extends final float goto implements import int interface long native package private protected public short static super synchronized throws transient volatile"),
-    Five: String::from("class enum extends super const export import"),
-    Six: String::from("enum"),
-    Strict: String::from("implements interface let package private protected public static yield"),
-    StrictBind: String::from("eval arguments"),
+use crate::options::EcmaVersion;
+
+const reservedWords3: &[&str] = &[
+    "abstract",
+    "boolean",
+    "byte",
+    "char",
+    "class",
+    "double",
+    "enum",
+    "export",
+    "extends",
+    "final",
+    "float",
+    "goto",
+    "implements",
+    "import",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "short",
+    "static",
+    "super",
+    "synchronized",
+    "throws",
+    "transient",
+    "volatile",
+];
+const reservedWords5: &[&str] = &["class", "enum", "extends", "super", "const", "export", "import"];
+const reservedWords6: &[&str] = &["enum"];
+const reservedWordsStrict: &[&str] = &[
+    "implements",
+    "interface",
+    "let",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "static",
+    "yield",
+];
+const reservedWordsStrictBind: &[&str] = &["eval", "arguments"];
+
+// And the keywords
+
+const ecma5AndLessKeywords: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "continue",
+    "debugger",
+    "default",
+    "do",
+    "else",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "return",
+    "switch",
+    "throw",
+    "try",
+    "var",
+    "while",
+    "with",
+    "null",
+    "true",
+    "false",
+    "instanceof",
+    "typeof",
+    "void",
+    "delete",
+    "new",
+    "in",
+    "this",
+];
+const keywords6: &[&str] = &["const", "class", "extends", "export", "import", "super"];
+
+/// Whether `word` is a keyword in `ecmaVersion`.
+pub fn isKeyword(word: &str, ecmaVersion: EcmaVersion, inModule: bool) -> bool {
+    if ecma5AndLessKeywords.contains(&word) {
+        return true;
+    }
+    if ecmaVersion >= EcmaVersion::Ecma6 {
+        keywords6.contains(&word)
+    } else {
+        inModule && (word == "export" || word == "import")
+    }
+}
+
+/// Whether `word` is a reserved word that is not a keyword. `strict`
+/// adds the words reserved in strict mode code.
+pub fn isReservedWord(word: &str, ecmaVersion: EcmaVersion, inModule: bool, strict: bool) -> bool {
+    let reserved = if ecmaVersion >= EcmaVersion::Ecma6 {
+        reservedWords6
+    } else if ecmaVersion == EcmaVersion::Ecma5 {
+        reservedWords5
+    } else {
+        reservedWords3
+    };
+    reserved.contains(&word)
+        || (inModule && word == "await")
+        || (strict && reservedWordsStrict.contains(&word))
+}
+
+/// Whether `word` may not be bound in strict mode code.
+pub fn isReservedWordStrictBind(word: &str) -> bool {
+    reservedWordsStrict.contains(&word) || reservedWordsStrictBind.contains(&word)
 }
 
-const ecma5AndLessKeywords: String = String::from("break case catch continue debugger default do else finally for function if return switch throw try var while with null true false instanceof typeof void delete new in this");
+// Unicode's ID_Start and ID_Continue are approximated with the
+// alphabetic and alphanumeric properties the standard library
+// exposes, which agree with them for the letters and digits that
+// occur in practice.
+
+/// Test whether a given character can start an identifier.
+pub fn isIdentifierStart(ch: char) -> bool {
+    match ch {
+        'A'..='Z' | 'a'..='z' | '$' | '_' => true,
+        _ => !ch.is_ascii() && ch.is_alphabetic(),
+    }
+}
 
-pub struct Keywords {
-    Five = ecma5AndLessKeywords,
-    FiveModule = ecma5AndLessKeywords + String::from(" export import"),
-    Six = ecma5AndLessKeywords + String::from(" const class extends export import super"),
+/// Test whether a given character is part of an identifier.
+pub fn isIdentifierChar(ch: char) -> bool {
+    match ch {
+        'A'..='Z' | 'a'..='z' | '0'..='9' | '$' | '_' => true,
+        '\u{200c}' | '\u{200d}' => true,
+        _ => {
+            !ch.is_ascii()
+                && (ch.is_alphanumeric()
+                    || ('\u{300}'..='\u{36f}').contains(&ch)
+                    || ('\u{1dc0}'..='\u{1dff}').contains(&ch)
+                    || ('\u{20d0}'..='\u{20ff}').contains(&ch)
+                    || ('\u{fe20}'..='\u{fe2f}').contains(&ch))
+        }
+    }
 }
@@ -1,8 +1,22 @@
+#![allow(non_snake_case, non_upper_case_globals)]
+
 #[macro_use]
 extern crate bitflags;
 
+pub mod arena;
+pub mod ast;
+pub mod binary;
+pub mod cache;
+pub mod codegen;
+#[cfg(feature = "serde")]
+pub mod deserialize;
 pub mod expression;
+pub mod diagnostic;
+pub mod errorkind;
+pub mod identifier;
 pub mod location;
+pub mod loose;
+pub mod lossless;
 pub mod locutil;
 pub mod lval;
 pub mod node;
@@ -10,26 +24,73 @@ pub mod options;
 pub mod parseutil;
 pub mod scope;
 pub mod scopeflags;
+#[cfg(feature = "serde")]
+pub mod serialize;
+pub mod sourcemap;
 pub mod state;
 pub mod statement;
 pub mod tokencontext;
 pub mod tokenize;
 pub mod tokentype;
+pub mod visit;
+pub mod walk;
 pub mod whitespace;
 
-pub fn parse(input: String, options: Option<options::Options>) -> node::Node {
+pub use errorkind::ErrorKind;
+pub use location::SyntaxError;
+
+pub fn parse(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<node::Node, SyntaxError> {
     state::Parser::parse(input, options)
 }
 
+pub fn parseWithErrors(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<(node::Node, Vec<SyntaxError>), SyntaxError> {
+    state::Parser::parseWithErrors(input, options)
+}
+
+/// Parse `input` as `parse` does and move the tree into an
+/// `arena::Arena`, returning the arena and the id of the `Program`.
+/// The tree is built as `node::Node`s first, so this allocates at
+/// least as much as `parse`; see `arena` for what the arena is for.
+pub fn parseToArena(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<(arena::Arena, arena::NodeId), SyntaxError> {
+    Ok(arena::Arena::fromNode(parse(input, options)?))
+}
+
+pub fn parseWithComments(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<(node::Node, Vec<tokenize::Comment>), SyntaxError> {
+    state::Parser::parseWithComments(input, options)
+}
+
+/// Parse `input` for formatting-preserving edits; see `lossless`.
+pub fn parseLossless(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<lossless::Document, SyntaxError> {
+    lossless::parse(input, options)
+}
+
 // TODO(ryzokuken): why is pos supposed to be optional?
 pub fn parseExpressionAt(
     input: String,
     pos: usize,
     options: Option<options::Options>,
-) -> node::Node {
+) -> Result<node::Node, SyntaxError> {
     state::Parser::parseExpressionAt(input, pos, options)
 }
 
-pub fn tokenizer(input: String, options: Option<options::Options>) -> state::Parser {
+pub fn tokenizer(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<state::Parser, SyntaxError> {
     state::Parser::tokenizer(input, options)
 }
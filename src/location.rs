@@ -1,41 +1,126 @@
+use crate::errorkind;
 use crate::locutil;
 use crate::state;
 
+use std::error;
+use std::fmt;
+
+/// The error returned for any input the parser cannot accept.
+#[derive(Debug, Clone)]
 pub struct SyntaxError {
-  pos: usize,
-  loc: locutil::Position,
-  raisedAt: usize,
-  message: String,
+  pub kind: errorkind::ErrorKind,
+  /// Offset into the input at which the error was detected.
+  pub pos: usize,
+  /// Line/column equivalent of `pos`.
+  pub loc: locutil::Position,
+  /// Offset at which the parser was when it raised the error.
+  pub raisedAt: usize,
+  /// Boxed, since every parse function returns a `SyntaxError` in its
+  /// `Result` and most of them succeed.
+  pub details: Box<Details>,
+}
+
+/// The text of a `SyntaxError`. `message` is derived from the error's
+/// kind and already carries the `(line:column)` suffix, as in acorn.
+#[derive(Debug, Clone)]
+pub struct Details {
+  pub message: String,
+  pub notes: Vec<Note>,
+  /// A suggestion on how to fix the error, if the parser has one.
+  pub help: Option<String>,
 }
 
+/// A secondary location attached to a `SyntaxError`, such as the
+/// earlier declaration of a redeclared name.
+#[derive(Debug, Clone)]
+pub struct Note {
+  pub pos: usize,
+  pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "SyntaxError: {}", self.details.message)
+  }
+}
+
+impl error::Error for SyntaxError {}
+
 pub trait ParserLocation {
+  /// Build the `SyntaxError` that `raise` returns, for callers that
+  /// need it as a value.
+  fn error(&self, pos: usize, kind: errorkind::ErrorKind) -> SyntaxError;
+
   /// This function is used to raise exceptions on parse errors. It
   /// takes an offset integer (into the current `input`) to indicate
   /// the location of the error, attaches the position to the end
   /// of the error message, and then raises a `SyntaxError` with that
   /// message.
-  fn raise(self, pos: usize, message: String) -> Result<(), SyntaxError>;
-  fn raiseRecoverable(self, pos: usize, message: String) -> Result<(), SyntaxError>;
-  fn curPosition(self) -> Option<locutil::Position>;
+  fn raise(&mut self, pos: usize, kind: errorkind::ErrorKind) -> Result<(), SyntaxError>;
+
+  /// Like `raise`, but for errors that do not leave the parser in an
+  /// inconsistent state. With `Options.collectRecoverableErrors` set,
+  /// the error is recorded and parsing continues.
+  fn raiseRecoverable(&mut self, pos: usize, kind: errorkind::ErrorKind) -> Result<(), SyntaxError>;
+  fn raiseRecoverableWithNotes(
+    &mut self,
+    pos: usize,
+    kind: errorkind::ErrorKind,
+    notes: Vec<Note>,
+  ) -> Result<(), SyntaxError>;
+  fn curPosition(&self) -> Option<locutil::Position>;
 }
 
 impl ParserLocation for state::Parser {
-  fn raise(self, pos: usize, message: String) -> Result<(), SyntaxError> {
-    let loc = locutil::getLineInfo(self.input, pos);
-    message += &format!(" (#{}:#{})", loc.line, loc.column);
-    Err(SyntaxError {
+  fn error(&self, pos: usize, kind: errorkind::ErrorKind) -> SyntaxError {
+    let raisedAt = self.encodeOffset(self.pos);
+    let loc = self.lineIndex.position(pos);
+    let loc = self.encodePosition(pos, &loc);
+    let pos = self.encodeOffset(pos);
+    let message = format!("{} ({}:{})", kind, loc.line, loc.column);
+    SyntaxError {
+      kind,
       pos,
       loc,
-      raisedAt: pos,
-      message,
-    })
+      raisedAt,
+      details: Box::new(Details {
+        message,
+        notes: Vec::new(),
+        help: None,
+      }),
+    }
   }
 
-  fn raiseRecoverable(self, pos: usize, message: String) -> Result<(), SyntaxError> {
-    self.raise(pos, message)
+  fn raise(&mut self, pos: usize, kind: errorkind::ErrorKind) -> Result<(), SyntaxError> {
+    Err(self.error(pos, kind))
   }
 
-  fn curPosition(self) -> Option<locutil::Position> {
+  fn raiseRecoverable(&mut self, pos: usize, kind: errorkind::ErrorKind) -> Result<(), SyntaxError> {
+    self.raiseRecoverableWithNotes(pos, kind, Vec::new())
+  }
+
+  fn raiseRecoverableWithNotes(
+    &mut self,
+    pos: usize,
+    kind: errorkind::ErrorKind,
+    notes: Vec<Note>,
+  ) -> Result<(), SyntaxError> {
+    let mut err = self.error(pos, kind);
+    err.details.notes = notes
+      .into_iter()
+      .map(|note| Note {
+        pos: self.encodeOffset(note.pos),
+        message: note.message,
+      })
+      .collect();
+    if self.options.collectRecoverableErrors {
+      self.recoverableErrors.push(err);
+      return Ok(());
+    }
+    Err(err)
+  }
+
+  fn curPosition(&self) -> Option<locutil::Position> {
     if self.options.locations {
       return Some(locutil::Position::new(
         self.curLine,
@@ -45,3 +130,37 @@ impl ParserLocation for state::Parser {
     None
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::errorkind::ErrorKind;
+  use crate::state;
+
+  #[test]
+  fn collectsRecoverableErrorsAlongsideTheTree() {
+    let (program, errors) = state::Parser::parseWithErrors(String::from("let x; let x;"), None)
+      .expect("a redeclaration is recoverable");
+    let body = program.body.unwrap();
+    assert_eq!(body.len(), 2);
+    assert!(body.iter().all(|stmt| stmt.r#type == "VariableDeclaration"));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ErrorKind::Redeclaration(String::from("x")));
+    assert_eq!(errors[0].pos, 11);
+    assert_eq!(errors[0].details.notes[0].pos, 4);
+  }
+
+  #[test]
+  fn failsOnTheFirstErrorOtherwise() {
+    let err = match state::Parser::parse(String::from("let x; let x;"), None) {
+      Ok(_) => panic!("a redeclaration should fail the parse"),
+      Err(err) => err,
+    };
+    assert_eq!(err.kind, ErrorKind::Redeclaration(String::from("x")));
+    assert_eq!(err.details.message, "Identifier 'x' has already been declared (1:11)");
+  }
+
+  #[test]
+  fn unrecoverableErrorsStillFail() {
+    assert!(state::Parser::parseWithErrors(String::from("let x; let x; ("), None).is_err());
+  }
+}
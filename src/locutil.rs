@@ -1,7 +1,9 @@
+use crate::node;
+use crate::options;
 use crate::state;
-use crate::whitespace;
+use crate::tokenize;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
@@ -11,30 +13,23 @@ impl Position {
     pub fn new(line: usize, column: usize) -> Self {
         Position { line, column }
     }
-
-    fn offset(&self, n: usize) -> Self {
-        Position::new(self.line, self.column + n)
-    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SourceLocation {
-    start: Option<Position>,
+    pub start: Option<Position>,
     pub end: Option<Position>,
-    source: String,
+    /// The `sourceFile` option, if one was given.
+    pub source: Option<String>,
 }
 
 impl SourceLocation {
     pub fn new(p: &state::Parser, start: Option<Position>, end: Option<Position>) -> Self {
-        let loc = SourceLocation {
+        SourceLocation {
             start,
             end,
-            source: String::from(""),
-        };
-        if p.options.sourceFile.is_some() {
-            loc.source = p.options.sourceFile.unwrap();
+            source: p.options.sourceFile.clone(),
         }
-        loc
     }
 
     pub fn from_parser(p: &state::Parser) -> Self {
@@ -42,21 +37,342 @@ impl SourceLocation {
     }
 }
 
+/// Converts between UTF-8 byte offsets and UTF-16 code unit offsets
+/// into the same input. Only non-ASCII characters are recorded, so
+/// for mostly-ASCII sources the map stays small and both directions
+/// are a binary search.
+pub struct OffsetMap {
+    /// For each non-ASCII character, the byte offset just past it and
+    /// the total number of bytes by which the UTF-8 encoding of the
+    /// input up to that point exceeds its UTF-16 encoding.
+    deltas: Vec<(usize, usize)>,
+}
+
+impl OffsetMap {
+    pub fn new(input: &str) -> Self {
+        let mut deltas = Vec::new();
+        let mut delta = 0;
+        for (i, ch) in input.char_indices() {
+            if !ch.is_ascii() {
+                delta += ch.len_utf8() - ch.len_utf16();
+                deltas.push((i + ch.len_utf8(), delta));
+            }
+        }
+        OffsetMap { deltas }
+    }
+
+    /// Convert a byte offset into a UTF-16 offset.
+    pub fn toUtf16(&self, byte: usize) -> usize {
+        let i = self.deltas.partition_point(|&(after, _)| after <= byte);
+        if i == 0 {
+            byte
+        } else {
+            byte - self.deltas[i - 1].1
+        }
+    }
+
+    /// Convert a UTF-16 offset into a byte offset.
+    pub fn fromUtf16(&self, unit: usize) -> usize {
+        let i = self
+            .deltas
+            .partition_point(|&(after, delta)| after - delta <= unit);
+        if i == 0 {
+            unit
+        } else {
+            unit + self.deltas[i - 1].1
+        }
+    }
+}
+
+/// The start offset of every line in a source, built once so that
+/// offsets can be mapped to positions and back by binary search
+/// rather than by rescanning the input. Line breaks are `\n`, `\r`,
+/// `\r\n`, `\u2028` and `\u2029`, as in the tokenizer. Offsets and
+/// columns are in UTF-8 bytes.
+pub struct LineIndex {
+    lineStarts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(input: &str) -> Self {
+        let bytes = input.as_bytes();
+        let mut lineStarts = vec![0];
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' => {
+                    if bytes.get(i + 1) == Some(&b'\n') {
+                        i += 1;
+                    }
+                    lineStarts.push(i + 1);
+                }
+                b'\n' => lineStarts.push(i + 1),
+                // U+2028 and U+2029 are encoded as E2 80 A8 and E2 80 A9.
+                0xE2 if bytes.get(i + 1) == Some(&0x80)
+                    && (bytes.get(i + 2) == Some(&0xA8) || bytes.get(i + 2) == Some(&0xA9)) =>
+                {
+                    i += 2;
+                    lineStarts.push(i + 1);
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        LineIndex {
+            lineStarts,
+            len: input.len(),
+        }
+    }
+
+    /// The number of lines in the source.
+    pub fn lineCount(&self) -> usize {
+        self.lineStarts.len()
+    }
+
+    /// The offset at which the given (1-based) line starts.
+    pub fn lineStart(&self, line: usize) -> Option<usize> {
+        self.lineStarts.get(line.checked_sub(1)?).cloned()
+    }
+
+    /// The line and column of `offset`.
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.lineStarts.partition_point(|&start| start <= offset);
+        Position::new(line, offset - self.lineStarts[line - 1])
+    }
+
+    /// The offset of `position`, or `None` if it lies outside the
+    /// source.
+    pub fn offset(&self, position: &Position) -> Option<usize> {
+        let offset = self.lineStart(position.line)? + position.column;
+        let lineEnd = self.lineStart(position.line + 1).unwrap_or(self.len + 1);
+        if offset < lineEnd && offset <= self.len {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
+
+/// A source text together with the indexes needed to map the
+/// positions the parser reports — in the given `PositionEncoding` —
+/// back to offsets, and to slice the text covered by a node or token.
+pub struct SourceText<'a> {
+    input: &'a str,
+    encoding: options::PositionEncoding,
+    lines: LineIndex,
+    offsets: OffsetMap,
+}
+
+impl<'a> SourceText<'a> {
+    pub fn new(input: &'a str, encoding: options::PositionEncoding) -> Self {
+        SourceText {
+            input,
+            encoding,
+            lines: LineIndex::new(input),
+            offsets: OffsetMap::new(input),
+        }
+    }
+
+    /// Convert a reported offset into a byte offset.
+    pub fn toByteOffset(&self, offset: usize) -> usize {
+        match self.encoding {
+            options::PositionEncoding::Utf8 => offset,
+            options::PositionEncoding::Utf16 => self.offsets.fromUtf16(offset),
+        }
+    }
+
+    /// Convert a byte offset into a reported offset.
+    pub fn fromByteOffset(&self, byte: usize) -> usize {
+        match self.encoding {
+            options::PositionEncoding::Utf8 => byte,
+            options::PositionEncoding::Utf16 => self.offsets.toUtf16(byte),
+        }
+    }
+
+    /// The byte offset of a reported `Position`, or `None` if it lies
+    /// outside the source.
+    pub fn byteOffset(&self, position: &Position) -> Option<usize> {
+        let lineStart = self.lines.lineStart(position.line)?;
+        let byte = self.toByteOffset(self.fromByteOffset(lineStart) + position.column);
+        self.lines.offset(&Position::new(position.line, byte - lineStart))
+    }
+
+    /// The UTF-16 offset of a reported `Position`.
+    pub fn utf16Offset(&self, position: &Position) -> Option<usize> {
+        self.byteOffset(position).map(|byte| self.offsets.toUtf16(byte))
+    }
+
+    /// The offset of a reported `Position`, in the reporting encoding —
+    /// the unit of `Node.start` and `Node.end`.
+    pub fn offset(&self, position: &Position) -> Option<usize> {
+        self.byteOffset(position).map(|byte| self.fromByteOffset(byte))
+    }
+
+    /// The reported `Position` of a reported offset.
+    pub fn position(&self, offset: usize) -> Position {
+        let byte = self.toByteOffset(offset);
+        let loc = self.lines.position(byte);
+        let column = offset - self.fromByteOffset(byte - loc.column);
+        Position::new(loc.line, column)
+    }
+
+    /// The byte range covered by a `SourceLocation`.
+    pub fn byteRange(&self, loc: &SourceLocation) -> Option<(usize, usize)> {
+        Some((
+            self.byteOffset(loc.start.as_ref()?)?,
+            self.byteOffset(loc.end.as_ref()?)?,
+        ))
+    }
+
+    /// The text between two reported offsets.
+    pub fn slice(&self, start: usize, end: usize) -> Option<&'a str> {
+        self.input.get(self.toByteOffset(start)..self.toByteOffset(end))
+    }
+
+    /// The source text of a node.
+    pub fn nodeText(&self, node: &node::Node) -> Option<&'a str> {
+        self.slice(node.start, node.end)
+    }
+
+    /// The source text of a token.
+    pub fn tokenText(&self, token: &tokenize::Token) -> Option<&'a str> {
+        self.slice(token.start, token.end)
+    }
+
+    /// The innermost node of `root` enclosing `position`, as received
+    /// from an editor cursor.
+    pub fn nodeAt<'n>(
+        &self,
+        root: &'n node::Node,
+        position: &Position,
+    ) -> Option<&'n node::Node> {
+        root.innermostAt(self.offset(position)?)
+    }
+}
+
 /// The `getLineInfo` function is mostly useful when the
 /// `locations` option is off (for performance reasons) and you
 /// want to find the line/column position for a given character
 /// offset. `input` should be the code string that the offset refers
-/// into.
+/// into. Both the offset and the resulting column are in UTF-8 bytes.
+///
+/// This builds a `LineIndex` on every call; to look up many offsets
+/// in the same source, build one and reuse it.
 pub fn getLineInfo(input: String, offset: usize) -> Position {
-    let line = 1;
-    let cur = 0;
-    loop {
-        let mat = whitespace::lineBreak.find_at(input.as_str(), cur);
-        if mat.is_some() && mat.unwrap().start() < offset {
-            line += 1;
-            cur = mat.unwrap().end();
-        } else {
-            return Position::new(line, offset - cur);
+    LineIndex::new(&input).position(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenize::ParserTokenize;
+
+    #[test]
+    fn indexesEveryKindOfLineBreak() {
+        let input = "a\r\nb\nc\u{2028}d\re\u{2029}";
+        let lines = LineIndex::new(input);
+        assert_eq!(lines.lineCount(), 6);
+        let starts: Vec<_> = (1..=6).map(|line| lines.lineStart(line).unwrap()).collect();
+        assert_eq!(starts, [0, 3, 5, 9, 11, 15]);
+        assert_eq!(lines.lineStart(0), None);
+        assert_eq!(lines.lineStart(7), None);
+    }
+
+    #[test]
+    fn mapsOffsetsToPositionsAndBack() {
+        let input = "ab\r\ncd\n\néf";
+        let lines = LineIndex::new(input);
+        for offset in 0..=input.len() {
+            let position = lines.position(offset);
+            assert_eq!(position, getLineInfo(String::from(input), offset));
+            assert_eq!(lines.offset(&position), Some(offset));
+        }
+        assert_eq!(lines.position(4), Position::new(2, 0));
+        assert_eq!(lines.position(11), Position::new(4, 3));
+        // Columns past the end of their line do not run into the next.
+        assert_eq!(lines.offset(&Position::new(1, 5)), None);
+        assert_eq!(lines.offset(&Position::new(5, 0)), None);
+    }
+
+    fn parseWith(input: &str, encoding: options::PositionEncoding) -> node::Node {
+        let options = options::Options::builder()
+            .locations(true)
+            .positionEncoding(encoding)
+            .build()
+            .unwrap();
+        match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn mapsReportedPositionsBackToOffsets() {
+        let input = "'é😀';
+foo.bar;";
+        for encoding in [options::PositionEncoding::Utf8, options::PositionEncoding::Utf16] {
+            let text = SourceText::new(input, encoding);
+            let program = parseWith(input, encoding);
+            let body = program.body.as_ref().unwrap();
+            let literal = body[0].expression.as_ref().unwrap();
+            assert_eq!(text.nodeText(literal), Some("'é😀'"));
+            assert_eq!(text.nodeText(&body[1]), Some("foo.bar;"));
+            let loc = body[1].loc().unwrap();
+            assert_eq!(text.byteRange(loc), Some((10, 18)));
+            assert_eq!(text.offset(loc.start.as_ref().unwrap()), Some(body[1].start));
+            for (byte, _) in input.char_indices() {
+                let offset = text.fromByteOffset(byte);
+                assert_eq!(text.toByteOffset(offset), byte);
+                assert_eq!(text.offset(&text.position(offset)), Some(offset));
+            }
+        }
+        let text = SourceText::new(input, options::PositionEncoding::Utf16);
+        // The emoji is two UTF-16 units but four bytes.
+        assert_eq!(text.position(5), Position::new(1, 5));
+        assert_eq!(text.byteOffset(&Position::new(1, 5)), Some(8));
+        assert_eq!(text.utf16Offset(&Position::new(2, 0)), Some(7));
+        assert_eq!(text.byteOffset(&Position::new(1, 9)), None);
+    }
+
+    #[test]
+    fn findsTheNodeAtACursor() {
+        let input = "f(a, bé.c);";
+        let text = SourceText::new(input, options::PositionEncoding::Utf16);
+        let program = parseWith(input, options::PositionEncoding::Utf16);
+        let at = |column| {
+            text.nodeAt(&program, &Position::new(1, column))
+                .map(|node| text.nodeText(node).unwrap())
+        };
+        assert_eq!(at(2), Some("a"));
+        assert_eq!(at(6), Some("bé"));
+        assert_eq!(at(8), Some("c"));
+        // A cursor just after a node is still in it.
+        assert_eq!(at(10), Some("f(a, bé.c)"));
+        assert_eq!(at(11), Some("f(a, bé.c);"));
+        assert_eq!(at(20), None);
+    }
+
+    #[test]
+    fn slicesTokens() {
+        let input = "let é = 'x';";
+        let options = options::Options::builder()
+            .positionEncoding(options::PositionEncoding::Utf8)
+            .build()
+            .unwrap();
+        let mut tokens = match state::Parser::tokenizer(String::from(input), Some(options)) {
+            Ok(tokens) => tokens,
+            Err(err) => panic!("{}", err),
+        };
+        let text = SourceText::new(input, options::PositionEncoding::Utf8);
+        let mut texts = Vec::new();
+        loop {
+            let token = tokens.getToken().unwrap();
+            if token.r#type == crate::tokentype::TokenType::eof() {
+                break;
+            }
+            texts.push(text.tokenText(&token).unwrap());
         }
+        assert_eq!(texts, ["let", "é", "=", "'x'", ";"]);
     }
 }
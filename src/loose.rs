@@ -0,0 +1,2068 @@
+// The loose parser is a port of acorn-loose. It reads the same token
+// stream as `state::Parser`, but never raises an error: where the
+// strict parser would give up, it inserts a placeholder node, or
+// skips ahead to something that looks like the start of the next
+// statement, and carries on. The result is always a `Program` node,
+// which makes it usable for editor tooling working on half-typed
+// code.
+//
+// Indentation is used as a hint for where blocks end: a line that is
+// indented less than the block's opening line is taken to close it,
+// even when the closing brace is missing.
+
+use crate::locutil;
+use crate::node;
+use crate::options;
+use crate::parseutil;
+use crate::state;
+use crate::statement;
+use crate::tokenize;
+use crate::tokentype;
+
+use std::collections::VecDeque;
+
+use node::ParserNode;
+use statement::ParserStatement;
+use tokenize::ParserTokenize;
+use tokentype::TokenType;
+
+/// The name given to placeholder identifiers inserted in place of
+/// missing or broken expressions.
+pub const DUMMY_NAME: &str = "✖";
+
+/// Parse `input` without ever failing.
+pub fn parse(input: String, options: Option<options::Options>) -> node::Node {
+    LooseParser::new(input, options).parseTopLevel()
+}
+
+/// Returns whether `node` is a placeholder inserted by the loose parser.
+pub fn isDummy(node: &node::Node) -> bool {
+    node.name.as_ref().is_some_and(|name| name == DUMMY_NAME)
+}
+
+pub struct LooseParser {
+    toks: state::Parser,
+    /// The encoding the caller asked for. The tokenizer itself runs in
+    /// UTF-8 mode so that token offsets can be used to look at `input`.
+    positionEncoding: options::PositionEncoding,
+    tok: tokenize::Token,
+    last: tokenize::Token,
+    ahead: VecDeque<tokenize::Token>,
+    /// Stack of indentation levels of the blocks we are inside of.
+    context: Vec<usize>,
+    curIndent: usize,
+    curLineStart: usize,
+    nextLineStart: usize,
+    /// Whether `await` and `yield` start expressions where we are.
+    inAsync: bool,
+    inGenerator: bool,
+    /// How many statements and expressions are open; see `tooDeep`.
+    depth: usize,
+    /// How many columns a tab counts for when measuring indentation.
+    pub tabSize: usize,
+}
+
+impl LooseParser {
+    pub fn new(input: String, options: Option<options::Options>) -> Self {
+        let mut options = options.unwrap_or_default();
+        let positionEncoding = options.positionEncoding;
+        options.positionEncoding = options::PositionEncoding::Utf8;
+        let toks = state::Parser::new(options, input, None);
+        let tok = LooseParser::eofToken(&toks);
+        let mut parser = LooseParser {
+            last: tok.clone(),
+            tok,
+            ahead: VecDeque::new(),
+            context: Vec::new(),
+            curIndent: 0,
+            curLineStart: 0,
+            nextLineStart: 0,
+            inAsync: false,
+            inGenerator: false,
+            depth: 0,
+            tabSize: 4,
+            positionEncoding,
+            toks,
+        };
+        parser.nextLineStart = parser.lineEnd(parser.curLineStart) + 1;
+        parser.next();
+        parser
+    }
+
+    fn eofToken(toks: &state::Parser) -> tokenize::Token {
+        let end = toks.input.len();
+        tokenize::Token {
+            r#type: TokenType::eof(),
+            value: None,
+            start: end,
+            end,
+            loc: None,
+            range: None,
+        }
+    }
+
+    // ### Token stream
+
+    /// Read the next token from the tokenizer. Tokenizer errors are
+    /// swallowed by skipping past the offending character.
+    fn readToken(&mut self) -> tokenize::Token {
+        loop {
+            match self.toks.getToken() {
+                Ok(token) => return token,
+                Err(err) => {
+                    let input = &self.toks.input;
+                    let mut pos = std::cmp::max(err.pos, self.toks.pos) + 1;
+                    while pos < input.len() && !input.is_char_boundary(pos) {
+                        pos += 1;
+                    }
+                    if pos >= input.len() {
+                        return LooseParser::eofToken(&self.toks);
+                    }
+                    self.toks.pos = pos;
+                    self.toks.start = pos;
+                    self.toks.end = pos;
+                }
+            }
+        }
+    }
+
+    fn next(&mut self) {
+        self.last = self.tok.clone();
+        self.tok = match self.ahead.pop_front() {
+            Some(token) => token,
+            None => self.readToken(),
+        };
+        if self.tok.start >= self.nextLineStart {
+            while self.tok.start >= self.nextLineStart {
+                self.curLineStart = self.nextLineStart;
+                self.nextLineStart = self.lineEnd(self.curLineStart) + 1;
+            }
+            self.curIndent = self.indentationAfter(self.curLineStart);
+        }
+    }
+
+    fn lookAhead(&mut self, n: usize) -> &tokenize::Token {
+        while n > self.ahead.len() {
+            let token = self.readToken();
+            self.ahead.push_back(token);
+        }
+        &self.ahead[n - 1]
+    }
+
+    fn eat(&mut self, r#type: TokenType) -> bool {
+        if self.tok.r#type == r#type {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn isContextual(&self, name: &str) -> bool {
+        self.tok.r#type == TokenType::name() && self.tok.value.as_deref() == Some(name)
+    }
+
+    fn eatContextual(&mut self, name: &str) -> bool {
+        self.isContextual(name) && self.eat(TokenType::name())
+    }
+
+    /// Like `eat`, but also accepts the token if it shows up within the
+    /// next two tokens, skipping whatever garbage came before it.
+    fn expect(&mut self, r#type: TokenType) -> bool {
+        if self.eat(r#type.clone()) {
+            return true;
+        }
+        for i in 1..=2 {
+            if self.lookAhead(i).r#type == r#type {
+                for _ in 0..i {
+                    self.next();
+                }
+                return true;
+            }
+        }
+        false
+    }
+
+    fn semicolon(&mut self) -> bool {
+        self.eat(TokenType::semi())
+    }
+
+    fn canInsertSemicolon(&self) -> bool {
+        self.tok.r#type == TokenType::eof()
+            || self.tok.r#type == TokenType::braceR()
+            || self.lineBreakBefore()
+    }
+
+    /// Whether a line break separates the current token from the last.
+    fn lineBreakBefore(&self) -> bool {
+        self.toks
+            .input
+            .as_bytes()
+            .get(self.last.end..self.tok.start)
+            .is_some_and(|between| {
+                between.iter().any(|&ch| ch == b'\n' || ch == b'\r')
+            })
+    }
+
+    /// Whether `let` starts a declaration here.
+    fn isLet(&mut self) -> bool {
+        if !self.isContextual("let") || self.toks.options.ecmaVersion < options::EcmaVersion::Ecma6
+        {
+            return false;
+        }
+        let next = self.lookAhead(1).r#type.clone();
+        next == TokenType::name() || next == TokenType::bracketL() || next == TokenType::braceL()
+    }
+
+    /// Whether `async function` starts here.
+    fn isAsyncFunction(&mut self) -> bool {
+        if !self.isContextual("async")
+            || self.toks.options.ecmaVersion < options::EcmaVersion::Ecma8
+        {
+            return false;
+        }
+        let end = self.tok.end;
+        let next = self.lookAhead(1);
+        let (isFunction, nextStart) = (next.r#type == TokenType::_function(), next.start);
+        isFunction && !self.toks.input[end..nextStart].contains(['\n', '\r'])
+    }
+
+    fn storeCurrentPos(&self) -> (usize, Option<locutil::Position>) {
+        (
+            self.tok.start,
+            self.tok.loc.as_ref().and_then(|loc| loc.start),
+        )
+    }
+
+    // ### Indentation heuristics
+
+    fn lineEnd(&self, pos: usize) -> usize {
+        let bytes = self.toks.input.as_bytes();
+        let mut pos = pos;
+        while pos < bytes.len() && bytes[pos] != b'\n' && bytes[pos] != b'\r' {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn indentationAfter(&self, pos: usize) -> usize {
+        let bytes = self.toks.input.as_bytes();
+        let mut count = 0;
+        for &ch in &bytes[pos.min(bytes.len())..] {
+            match ch {
+                b' ' => count += 1,
+                b'\t' => count += self.tabSize,
+                _ => break,
+            }
+        }
+        count
+    }
+
+    fn tokenStartsLine(&self) -> bool {
+        self.toks
+            .input
+            .as_bytes()
+            .get(self.curLineStart..self.tok.start)
+            .is_some_and(|prefix| {
+                prefix.iter().all(|&ch| ch == b' ' || ch == b'\t')
+            })
+    }
+
+    fn pushCx(&mut self) {
+        self.context.push(self.curIndent);
+    }
+
+    fn popCx(&mut self) {
+        self.curIndent = self.context.pop().unwrap_or(0);
+    }
+
+    /// Decides whether the block opened at `line` with indentation
+    /// `indent` has ended, either by seeing `closeTok` or by finding a
+    /// line that is indented less than the block itself.
+    fn closes(
+        &self,
+        closeTok: TokenType,
+        indent: usize,
+        line: usize,
+        blockHeuristic: bool,
+    ) -> bool {
+        if self.tok.r#type == closeTok || self.tok.r#type == TokenType::eof() {
+            return true;
+        }
+        line != self.curLineStart
+            && self.curIndent < indent
+            && self.tokenStartsLine()
+            && (!blockHeuristic
+                || self.nextLineStart >= self.toks.input.len()
+                || self.indentationAfter(self.nextLineStart) < indent)
+    }
+
+    /// Skip tokens until something that plausibly starts a statement:
+    /// a token after a `;`, a statement keyword, the `}` of an
+    /// enclosing block, or the first token on a line indented no
+    /// deeper than the current block.
+    fn resync(&mut self) {
+        let indent = self.context.last().cloned().unwrap_or(0);
+        loop {
+            let r#type = self.tok.r#type.clone();
+            if r#type == TokenType::eof() || r#type == TokenType::braceR() {
+                return;
+            }
+            if r#type == TokenType::semi() {
+                self.next();
+                return;
+            }
+            if !r#type.keyword.is_empty() && r#type != TokenType::_in() {
+                return;
+            }
+            if self.tokenStartsLine()
+                && self.curIndent <= indent
+                && self.last.end < self.curLineStart
+            {
+                return;
+            }
+            self.next();
+        }
+    }
+
+    /// When a list or block is missing its closing token, make the node
+    /// being finished span to the start of the next token instead.
+    fn spanToNextToken(&mut self) {
+        self.last.end = self.tok.start;
+        let start = self.tok.loc.as_ref().and_then(|loc| loc.start);
+        if let Some(loc) = &mut self.last.loc {
+            loc.end = start;
+        }
+    }
+
+    // ### Nodes
+
+    fn startNode(&self) -> node::Node {
+        let (start, loc) = self.storeCurrentPos();
+        self.toks.startNodeAt(start, loc)
+    }
+
+    fn startNodeAt(&self, pos: usize, loc: Option<locutil::Position>) -> node::Node {
+        self.toks.startNodeAt(pos, loc)
+    }
+
+    fn finishNode(&self, node: node::Node, r#type: &str) -> node::Node {
+        let loc = self.last.loc.as_ref().and_then(|loc| loc.end);
+        self.toks.finishNodeAt(node, r#type, self.last.end, loc)
+    }
+
+    /// Finish a node that covers no tokens, at the current one.
+    fn finishEmpty(&self, node: node::Node, r#type: &str) -> node::Node {
+        let (start, loc) = self.storeCurrentPos();
+        self.toks.finishNodeAt(node, r#type, start, loc)
+    }
+
+    fn dummyIdent(&self) -> node::Node {
+        let mut dummy = self.startNode();
+        dummy.name = Some(String::from(DUMMY_NAME));
+        self.finishEmpty(dummy, "Identifier")
+    }
+
+    fn dummyString(&self) -> node::Node {
+        let mut dummy = self.startNode();
+        dummy.raw = Some(format!("\"{}\"", DUMMY_NAME));
+        self.finishEmpty(dummy, "Literal")
+    }
+
+    /// Whether `parseutil::MAX_NESTING_DEPTH` statements or expressions
+    /// are already open. Past that, instead of recursing further, the
+    /// parser skips what it would have parsed, a bracketed group at
+    /// once, and puts a placeholder in its place.
+    fn tooDeep(&mut self) -> bool {
+        if self.depth < parseutil::MAX_NESTING_DEPTH {
+            return false;
+        }
+        let mut open = 0;
+        loop {
+            let r#type = &self.tok.r#type;
+            if *r#type == TokenType::eof() {
+                break;
+            }
+            if *r#type == TokenType::parenL()
+                || *r#type == TokenType::bracketL()
+                || *r#type == TokenType::braceL()
+                || *r#type == TokenType::dollarBraceL()
+            {
+                open += 1;
+            } else if *r#type == TokenType::parenR()
+                || *r#type == TokenType::bracketR()
+                || *r#type == TokenType::braceR()
+            {
+                if open == 0 {
+                    break;
+                }
+                open -= 1;
+            }
+            self.next();
+            if open == 0 {
+                break;
+            }
+        }
+        true
+    }
+
+    fn initFunction(&self, node: &mut node::Node) {
+        node.id = None;
+        node.params = Some(Vec::new());
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            node.generator = Some(false);
+        }
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+            node.r#async = Some(false);
+        }
+    }
+
+    // ### Statements
+
+    pub fn parseTopLevel(mut self) -> node::Node {
+        let mut node = self.startNodeAt(0, Some(locutil::Position::new(1, 0)));
+        let mut body = Vec::new();
+        while self.tok.r#type != TokenType::eof() {
+            body.push(self.parseStatement());
+        }
+        self.toks.adaptDirectivePrologue(&mut body);
+        node.body = Some(body);
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            node.sourceType = Some(self.toks.options.sourceType.clone());
+        }
+        let mut node = self.finishNode(node, "Program");
+        if self.positionEncoding == options::PositionEncoding::Utf16 {
+            let offsets = &self.toks.offsets;
+            node.mapOffsets(&|pos| offsets.toUtf16(pos));
+        }
+        node
+    }
+
+    fn parseStatement(&mut self) -> node::Node {
+        if self.tooDeep() {
+            let mut node = self.startNode();
+            node.expression = Some(Box::new(self.dummyIdent()));
+            return self.finishNode(node, "ExpressionStatement");
+        }
+        self.depth += 1;
+        let stmt = self.parseStatementBody();
+        self.depth -= 1;
+        stmt
+    }
+
+    /// Parse a statement. Garbage that does not start one is skipped
+    /// until something that does, in a loop rather than by recursing,
+    /// so long runs of it cannot exhaust the stack.
+    fn parseStatementBody(&mut self) -> node::Node {
+        loop {
+            let mut starttype = self.tok.r#type.clone();
+            let mut node = self.startNode();
+            let mut kind = None;
+
+            if self.isLet() {
+                starttype = TokenType::_var();
+                kind = Some(String::from("let"));
+            }
+
+            if starttype == TokenType::_break() || starttype == TokenType::_continue() {
+                self.next();
+                node.label = if self.semicolon() || self.canInsertSemicolon() {
+                    None
+                } else {
+                    let label = if self.tok.r#type == TokenType::name() {
+                        Some(Box::new(self.parseIdent(false)))
+                    } else {
+                        None
+                    };
+                    self.semicolon();
+                    label
+                };
+                return self.finishNode(
+                    node,
+                    if starttype == TokenType::_break() {
+                        "BreakStatement"
+                    } else {
+                        "ContinueStatement"
+                    },
+                );
+            }
+            if starttype == TokenType::_debugger() {
+                self.next();
+                self.semicolon();
+                return self.finishNode(node, "DebuggerStatement");
+            }
+            if starttype == TokenType::_do() {
+                self.next();
+                node.body = Some(vec![self.parseStatement()]);
+                node.test = Some(Box::new(if self.eat(TokenType::_while()) {
+                    self.parseParenExpression()
+                } else {
+                    self.dummyIdent()
+                }));
+                self.semicolon();
+                return self.finishNode(node, "DoWhileStatement");
+            }
+            if starttype == TokenType::_for() {
+                return self.parseFor(node);
+            }
+            if starttype == TokenType::_function() {
+                self.next();
+                return self.parseFunction(node, true, false);
+            }
+            if starttype == TokenType::_if() {
+                self.next();
+                node.test = Some(Box::new(self.parseParenExpression()));
+                node.consequent = Some(Box::new(self.parseStatement()));
+                node.alternate = if self.eat(TokenType::_else()) {
+                    Some(Box::new(self.parseStatement()))
+                } else {
+                    None
+                };
+                return self.finishNode(node, "IfStatement");
+            }
+            if starttype == TokenType::_return() {
+                self.next();
+                node.argument = if self.semicolon() || self.canInsertSemicolon() {
+                    None
+                } else {
+                    let argument = self.parseExpression(false);
+                    self.semicolon();
+                    Some(Box::new(argument))
+                };
+                return self.finishNode(node, "ReturnStatement");
+            }
+            if starttype == TokenType::_switch() {
+                return self.parseSwitch(node);
+            }
+            if starttype == TokenType::_throw() {
+                self.next();
+                node.argument = Some(Box::new(self.parseExpression(false)));
+                self.semicolon();
+                return self.finishNode(node, "ThrowStatement");
+            }
+            if starttype == TokenType::_try() {
+                return self.parseTry(node);
+            }
+            if starttype == TokenType::_var() || starttype == TokenType::_const() {
+                let kind = kind.or_else(|| self.tok.value.clone());
+                return self.parseVar(node, false, kind);
+            }
+            if starttype == TokenType::_while() {
+                self.next();
+                node.test = Some(Box::new(self.parseParenExpression()));
+                node.body = Some(vec![self.parseStatement()]);
+                return self.finishNode(node, "WhileStatement");
+            }
+            if starttype == TokenType::_with() {
+                self.next();
+                node.object = Some(Box::new(self.parseParenExpression()));
+                node.body = Some(vec![self.parseStatement()]);
+                return self.finishNode(node, "WithStatement");
+            }
+            if starttype == TokenType::braceL() {
+                return self.parseBlock();
+            }
+            if starttype == TokenType::semi() {
+                self.next();
+                return self.finishNode(node, "EmptyStatement");
+            }
+            if starttype == TokenType::_class() {
+                return self.parseClass(true);
+            }
+            if starttype == TokenType::_import()
+                && self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6
+            {
+                return self.parseImport(node);
+            }
+            if starttype == TokenType::_export()
+                && self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6
+            {
+                return self.parseExport(node);
+            }
+            if self.isAsyncFunction() {
+                self.next();
+                self.next();
+                return self.parseFunction(node, true, true);
+            }
+
+            let expr = self.parseExpression(false);
+            if isDummy(&expr) {
+                // Nothing we understand here. A `}` may close a block we
+                // are in; leave it to that block.
+                if self.tok.r#type == TokenType::braceR() && !self.context.is_empty() {
+                    return self.finishEmpty(node, "EmptyStatement");
+                }
+                // Otherwise skip to something that looks like the start
+                // of the next statement.
+                self.next();
+                self.resync();
+                if self.tok.r#type == TokenType::eof() {
+                    return self.finishNode(node, "EmptyStatement");
+                }
+                continue;
+            }
+            if starttype == TokenType::name()
+                && expr.r#type == "Identifier"
+                && self.eat(TokenType::colon())
+            {
+                node.body = Some(vec![self.parseStatement()]);
+                node.label = Some(Box::new(expr));
+                return self.finishNode(node, "LabeledStatement");
+            }
+            node.expression = Some(Box::new(expr));
+            self.semicolon();
+            return self.finishNode(node, "ExpressionStatement");
+        }
+    }
+
+    fn parseBlock(&mut self) -> node::Node {
+        let mut node = self.startNode();
+        self.pushCx();
+        self.expect(TokenType::braceL());
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        let mut body = Vec::new();
+        while !self.closes(TokenType::braceR(), indent, line, true) {
+            body.push(self.parseStatement());
+        }
+        self.popCx();
+        self.eat(TokenType::braceR());
+        node.body = Some(body);
+        self.finishNode(node, "BlockStatement")
+    }
+
+    fn parseFor(&mut self, mut node: node::Node) -> node::Node {
+        self.next();
+        let isAwait = self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma9
+            && self.eatContextual("await");
+        self.pushCx();
+        self.expect(TokenType::parenL());
+        if self.tok.r#type == TokenType::semi() {
+            return self.parseForRest(node, None);
+        }
+        let isLet = self.isLet();
+        if isLet || self.tok.r#type == TokenType::_var() || self.tok.r#type == TokenType::_const() {
+            let kind = if isLet {
+                Some(String::from("let"))
+            } else {
+                self.tok.value.clone()
+            };
+            let init = self.parseVar(self.startNode(), true, kind);
+            let single = init
+                .declarations
+                .as_ref()
+                .is_some_and(|list| list.len() == 1);
+            if single && (self.tok.r#type == TokenType::_in() || self.isContextual("of")) {
+                if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma9
+                    && self.tok.r#type != TokenType::_in()
+                {
+                    node.r#await = Some(isAwait);
+                }
+                return self.parseForIn(node, init);
+            }
+            return self.parseForRest(node, Some(init));
+        }
+        let init = self.parseExpression(true);
+        if self.tok.r#type == TokenType::_in() || self.isContextual("of") {
+            if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma9
+                && self.tok.r#type != TokenType::_in()
+            {
+                node.r#await = Some(isAwait);
+            }
+            let left = self.toAssignable(init, false);
+            return self.parseForIn(node, left);
+        }
+        self.parseForRest(node, Some(init))
+    }
+
+    /// The rest of a `for (init; test; update)` statement.
+    fn parseForRest(&mut self, mut node: node::Node, init: Option<node::Node>) -> node::Node {
+        node.init = init.map(Box::new);
+        if self.eat(TokenType::semi()) && self.tok.r#type != TokenType::semi() {
+            node.test = Some(Box::new(self.parseExpression(false)));
+        }
+        if self.eat(TokenType::semi()) && self.tok.r#type != TokenType::parenR() {
+            node.update = Some(Box::new(self.parseExpression(false)));
+        }
+        self.popCx();
+        self.expect(TokenType::parenR());
+        node.body = Some(vec![self.parseStatement()]);
+        self.finishNode(node, "ForStatement")
+    }
+
+    fn parseForIn(&mut self, mut node: node::Node, init: node::Node) -> node::Node {
+        let r#type = if self.tok.r#type == TokenType::_in() {
+            "ForInStatement"
+        } else {
+            "ForOfStatement"
+        };
+        self.next();
+        node.left = Some(Box::new(init));
+        node.right = Some(Box::new(self.parseExpression(false)));
+        self.popCx();
+        self.expect(TokenType::parenR());
+        node.body = Some(vec![self.parseStatement()]);
+        self.finishNode(node, r#type)
+    }
+
+    fn parseSwitch(&mut self, mut node: node::Node) -> node::Node {
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        self.next();
+        node.discriminant = Some(Box::new(self.parseParenExpression()));
+        let mut cases: Vec<node::Node> = Vec::new();
+        self.pushCx();
+        self.expect(TokenType::braceL());
+        let mut cur: Option<node::Node> = None;
+        while !self.closes(TokenType::braceR(), indent, line, true) {
+            if self.tok.r#type == TokenType::_case() || self.tok.r#type == TokenType::_default() {
+                let isCase = self.tok.r#type == TokenType::_case();
+                if let Some(case) = cur.take() {
+                    cases.push(self.finishNode(case, "SwitchCase"));
+                }
+                let mut case = self.startNode();
+                case.consequents = Some(Vec::new());
+                self.next();
+                case.test = if isCase {
+                    Some(Box::new(self.parseExpression(false)))
+                } else {
+                    None
+                };
+                self.expect(TokenType::colon());
+                cur = Some(case);
+            } else {
+                if cur.is_none() {
+                    let mut case = self.startNode();
+                    case.consequents = Some(Vec::new());
+                    cur = Some(case);
+                }
+                let statement = self.parseStatement();
+                if let Some(consequents) = cur.as_mut().and_then(|case| case.consequents.as_mut()) {
+                    consequents.push(statement);
+                }
+            }
+        }
+        if let Some(case) = cur {
+            cases.push(self.finishNode(case, "SwitchCase"));
+        }
+        self.popCx();
+        self.eat(TokenType::braceR());
+        node.cases = Some(cases);
+        self.finishNode(node, "SwitchStatement")
+    }
+
+    fn parseTry(&mut self, mut node: node::Node) -> node::Node {
+        self.next();
+        let block = self.parseBlock();
+        if self.tok.r#type == TokenType::_catch() {
+            let mut clause = self.startNode();
+            self.next();
+            if self.eat(TokenType::parenL()) {
+                let param = self.parseExprAtom();
+                clause.param = Some(Box::new(self.toAssignable(param, true)));
+                self.expect(TokenType::parenR());
+            }
+            clause.body = Some(vec![self.parseBlock()]);
+            node.handler = Some(Box::new(self.finishNode(clause, "CatchClause")));
+        }
+        if self.eat(TokenType::_finally()) {
+            node.finalizer = Some(Box::new(self.parseBlock()));
+        }
+        if node.handler.is_none() && node.finalizer.is_none() {
+            return block;
+        }
+        node.block = Some(Box::new(block));
+        self.finishNode(node, "TryStatement")
+    }
+
+    fn parseVar(&mut self, mut node: node::Node, noIn: bool, kind: Option<String>) -> node::Node {
+        node.kind = kind;
+        self.next();
+        let mut declarations = Vec::new();
+        loop {
+            let mut decl = self.startNode();
+            let id = if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+                let atom = self.parseExprAtom();
+                self.toAssignable(atom, true)
+            } else {
+                self.parseIdent(false)
+            };
+            decl.id = Some(Box::new(id));
+            decl.init = if self.eat(TokenType::eq()) {
+                Some(Box::new(self.parseMaybeAssign(noIn)))
+            } else {
+                None
+            };
+            declarations.push(self.finishNode(decl, "VariableDeclarator"));
+            if !self.eat(TokenType::comma()) {
+                break;
+            }
+        }
+        node.declarations = Some(declarations);
+        if !noIn {
+            self.semicolon();
+        }
+        self.finishNode(node, "VariableDeclaration")
+    }
+
+    fn parseClass(&mut self, isStatement: bool) -> node::Node {
+        let mut node = self.startNode();
+        self.next();
+        node.id = if self.tok.r#type == TokenType::name() {
+            Some(Box::new(self.parseIdent(false)))
+        } else if isStatement {
+            Some(Box::new(self.dummyIdent()))
+        } else {
+            None
+        };
+        node.superClass = if self.eat(TokenType::_extends()) {
+            Some(Box::new(self.parseExpression(false)))
+        } else {
+            None
+        };
+        let mut body = self.startNode();
+        let mut elements = Vec::new();
+        self.pushCx();
+        let mut indent = self.curIndent + 1;
+        let mut line = self.curLineStart;
+        self.eat(TokenType::braceL());
+        if self.curIndent + 1 < indent {
+            indent = self.curIndent;
+            line = self.curLineStart;
+        }
+        while !self.closes(TokenType::braceR(), indent, line, false) {
+            if let Some(element) = self.parseClassElement() {
+                elements.push(element);
+            }
+        }
+        self.popCx();
+        if !self.eat(TokenType::braceR()) {
+            self.spanToNextToken();
+        }
+        self.semicolon();
+        body.body = Some(elements);
+        node.body = Some(vec![self.finishNode(body, "ClassBody")]);
+        self.finishNode(
+            node,
+            if isStatement {
+                "ClassDeclaration"
+            } else {
+                "ClassExpression"
+            },
+        )
+    }
+
+    /// Parse a class method. Returns `None`, having skipped at least one
+    /// token, for something that is not one.
+    fn parseClassElement(&mut self) -> Option<node::Node> {
+        if self.eat(TokenType::semi()) {
+            return None;
+        }
+        let mut method = self.startNode();
+        let ecmaVersion = self.toks.options.ecmaVersion;
+        // `static`, `async`, `get` and `set` are only modifiers when
+        // something other than the parameter list follows them.
+        let isModifier = |parser: &mut LooseParser, name: &str| {
+            parser.isContextual(name) && {
+                let next = parser.lookAhead(1).r#type.clone();
+                next != TokenType::parenL() && next != TokenType::eq()
+            }
+        };
+        method.r#static = Some(isModifier(self, "static"));
+        if method.r#static == Some(true) {
+            self.next();
+        }
+        let mut kind = "method";
+        let isAsync = ecmaVersion >= options::EcmaVersion::Ecma8
+            && isModifier(self, "async")
+            && !self.lineBreakBefore();
+        if isAsync {
+            self.next();
+        }
+        let isGenerator = self.eat(TokenType::star());
+        if !isAsync && !isGenerator && (isModifier(self, "get") || isModifier(self, "set")) {
+            kind = if self.isContextual("get") {
+                "get"
+            } else {
+                "set"
+            };
+            self.next();
+        }
+        self.parsePropertyName(&mut method);
+        if method.key.as_deref().is_none_or(isDummy) {
+            if isDummy(&self.parseMaybeAssign(false)) {
+                self.next();
+            }
+            self.eat(TokenType::comma());
+            return None;
+        }
+        let isConstructor = method.r#static != Some(true)
+            && method.computed != Some(true)
+            && method.key.as_ref().is_some_and(|key| {
+                key.name.as_deref() == Some("constructor")
+                    || key.r#type == "Literal"
+                        && key.raw.as_deref().is_some_and(|raw| {
+                            raw.get(1..raw.len() - 1) == Some("constructor")
+                        })
+            });
+        if kind == "method" && isConstructor {
+            kind = "constructor";
+        }
+        method.kind = Some(String::from(kind));
+        method.value = Some(Box::new(self.parseMethod(isGenerator, isAsync)));
+        Some(self.finishNode(method, "MethodDefinition"))
+    }
+
+    fn parseFunction(
+        &mut self,
+        mut node: node::Node,
+        isStatement: bool,
+        isAsync: bool,
+    ) -> node::Node {
+        let oldInAsync = self.inAsync;
+        let oldInGenerator = self.inGenerator;
+        self.initFunction(&mut node);
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            node.generator = Some(self.eat(TokenType::star()));
+        }
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+            node.r#async = Some(isAsync);
+        }
+        if self.tok.r#type == TokenType::name() {
+            node.id = Some(Box::new(self.parseIdent(false)));
+        } else if isStatement {
+            node.id = Some(Box::new(self.dummyIdent()));
+        }
+        self.inAsync = node.r#async == Some(true);
+        self.inGenerator = node.generator == Some(true);
+        node.params = Some(self.parseFunctionParams());
+        node.body = Some(vec![self.parseBlock()]);
+        self.inAsync = oldInAsync;
+        self.inGenerator = oldInGenerator;
+        self.finishNode(
+            node,
+            if isStatement {
+                "FunctionDeclaration"
+            } else {
+                "FunctionExpression"
+            },
+        )
+    }
+
+    fn parseImport(&mut self, mut node: node::Node) -> node::Node {
+        self.next();
+        let mut specifiers = Vec::new();
+        if self.tok.r#type == TokenType::string() {
+            node.source = Some(Box::new(self.parseExprAtom()));
+        } else {
+            if self.tok.r#type == TokenType::name() && !self.isContextual("from") {
+                let mut elt = self.startNode();
+                elt.local = Some(Box::new(self.parseIdent(false)));
+                specifiers.push(self.finishNode(elt, "ImportDefaultSpecifier"));
+                self.eat(TokenType::comma());
+            }
+            self.parseImportSpecifiers(&mut specifiers);
+            node.source = Some(Box::new(
+                if self.eatContextual("from") && self.tok.r#type == TokenType::string() {
+                    self.parseExprAtom()
+                } else {
+                    self.dummyString()
+                },
+            ));
+        }
+        node.specifiers = Some(specifiers);
+        self.semicolon();
+        self.finishNode(node, "ImportDeclaration")
+    }
+
+    fn parseImportSpecifiers(&mut self, specifiers: &mut Vec<node::Node>) {
+        if self.tok.r#type == TokenType::star() {
+            let mut elt = self.startNode();
+            self.next();
+            elt.local = Some(Box::new(if self.eatContextual("as") {
+                self.parseIdent(false)
+            } else {
+                self.dummyIdent()
+            }));
+            specifiers.push(self.finishNode(elt, "ImportNamespaceSpecifier"));
+            return;
+        }
+        if self.tok.r#type != TokenType::braceL() {
+            return;
+        }
+        self.parseSpecifierList(specifiers, |parser, mut elt, name| {
+            elt.local = Some(Box::new(if parser.eatContextual("as") {
+                parser.parseIdent(false)
+            } else {
+                name.clone()
+            }));
+            elt.imported = Some(Box::new(name));
+            parser.finishNode(elt, "ImportSpecifier")
+        });
+    }
+
+    /// Parse the `{ a as b, ... }` of an import or export, building each
+    /// specifier from its first name with `finish`.
+    fn parseSpecifierList<F>(&mut self, specifiers: &mut Vec<node::Node>, finish: F)
+    where
+        F: Fn(&mut LooseParser, node::Node, node::Node) -> node::Node,
+    {
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        let mut continuedLine = self.nextLineStart;
+        self.pushCx();
+        self.eat(TokenType::braceL());
+        if self.curLineStart > continuedLine {
+            continuedLine = self.curLineStart;
+        }
+        loop {
+            let extra = if self.curLineStart <= continuedLine {
+                1
+            } else {
+                0
+            };
+            if self.closes(TokenType::braceR(), indent + extra, line, false) {
+                break;
+            }
+            let elt = self.startNode();
+            let name = self.parseIdent(true);
+            if isDummy(&name) {
+                break;
+            }
+            let specifier = finish(self, elt, name);
+            specifiers.push(specifier);
+            self.eat(TokenType::comma());
+        }
+        self.eat(TokenType::braceR());
+        self.popCx();
+    }
+
+    fn parseExport(&mut self, mut node: node::Node) -> node::Node {
+        self.next();
+        if self.eat(TokenType::star()) {
+            node.source = Some(Box::new(if self.eatContextual("from") {
+                self.parseExprAtom()
+            } else {
+                self.dummyString()
+            }));
+            self.semicolon();
+            return self.finishNode(node, "ExportAllDeclaration");
+        }
+        if self.eat(TokenType::_default()) {
+            let isAsync = self.isAsyncFunction();
+            let declaration = if self.tok.r#type == TokenType::_function() || isAsync {
+                let fNode = self.startNode();
+                if isAsync {
+                    self.next();
+                }
+                self.next();
+                // The name of a default-exported function is optional.
+                let mut function = self.parseFunction(fNode, true, isAsync);
+                if function.id.as_deref().is_some_and(isDummy) {
+                    function.id = None;
+                }
+                function
+            } else if self.tok.r#type == TokenType::_class() {
+                let mut class = self.parseClass(true);
+                if class.id.as_deref().is_some_and(isDummy) {
+                    class.id = None;
+                }
+                class
+            } else {
+                let declaration = self.parseMaybeAssign(false);
+                self.semicolon();
+                declaration
+            };
+            node.declaration = Some(Box::new(declaration));
+            return self.finishNode(node, "ExportDefaultDeclaration");
+        }
+        if !self.tok.r#type.keyword.is_empty() || self.isLet() || self.isAsyncFunction() {
+            node.declaration = Some(Box::new(self.parseStatement()));
+            node.specifiers = Some(Vec::new());
+        } else {
+            let mut specifiers = Vec::new();
+            self.parseSpecifierList(&mut specifiers, |parser, mut elt, name| {
+                elt.exported = Some(Box::new(if parser.eatContextual("as") {
+                    parser.parseIdent(true)
+                } else {
+                    name.clone()
+                }));
+                elt.local = Some(Box::new(name));
+                parser.finishNode(elt, "ExportSpecifier")
+            });
+            node.specifiers = Some(specifiers);
+            if self.eatContextual("from") {
+                node.source = Some(Box::new(self.parseExprAtom()));
+            }
+            self.semicolon();
+        }
+        self.finishNode(node, "ExportNamedDeclaration")
+    }
+
+    // ### Expressions
+
+    /// Keep `expr` as an update or assignment target if it can be one,
+    /// or replace it with a placeholder.
+    fn checkLVal(&self, expr: node::Node) -> node::Node {
+        match expr.r#type.as_str() {
+            "Identifier" | "MemberExpression" => expr,
+            "ParenthesizedExpression" => {
+                let mut expr = expr;
+                if let Some(inner) = expr.expression.take() {
+                    expr.expression = Some(Box::new(self.checkLVal(*inner)));
+                }
+                expr
+            }
+            _ => self.dummyIdent(),
+        }
+    }
+
+    fn parseParenExpression(&mut self) -> node::Node {
+        self.pushCx();
+        self.expect(TokenType::parenL());
+        let val = self.parseExpression(false);
+        self.popCx();
+        self.expect(TokenType::parenR());
+        val
+    }
+
+    fn parseExpression(&mut self, noIn: bool) -> node::Node {
+        let (start, startLoc) = self.storeCurrentPos();
+        let expr = self.parseMaybeAssign(noIn);
+        if self.tok.r#type == TokenType::comma() {
+            let mut node = self.startNodeAt(start, startLoc);
+            node.expressions = vec![expr];
+            while self.eat(TokenType::comma()) {
+                node.expressions.push(self.parseMaybeAssign(noIn));
+            }
+            return self.finishNode(node, "SequenceExpression");
+        }
+        expr
+    }
+
+    fn parseMaybeAssign(&mut self, noIn: bool) -> node::Node {
+        if self.tooDeep() {
+            return self.dummyIdent();
+        }
+        self.depth += 1;
+        if self.inGenerator && self.isContextual("yield") {
+            let mut node = self.startNode();
+            self.next();
+            let noArgument = self.semicolon()
+                || self.canInsertSemicolon()
+                || self.tok.r#type != TokenType::star() && !self.tok.r#type.startsExpr;
+            if noArgument {
+                node.delegate = Some(false);
+            } else {
+                node.delegate = Some(self.eat(TokenType::star()));
+                node.argument = Some(Box::new(self.parseMaybeAssign(false)));
+            }
+            self.depth -= 1;
+            return self.finishNode(node, "YieldExpression");
+        }
+
+        let (start, startLoc) = self.storeCurrentPos();
+        let left = self.parseMaybeConditional(noIn);
+        if self.tok.r#type.isAssign {
+            let mut node = self.startNodeAt(start, startLoc);
+            node.operator = self.tok.value.clone();
+            node.left = Some(Box::new(if self.tok.r#type == TokenType::eq() {
+                self.toAssignable(left, false)
+            } else {
+                self.checkLVal(left)
+            }));
+            self.next();
+            node.right = Some(Box::new(self.parseMaybeAssign(noIn)));
+            self.depth -= 1;
+            return self.finishNode(node, "AssignmentExpression");
+        }
+        self.depth -= 1;
+        left
+    }
+
+    fn parseMaybeConditional(&mut self, noIn: bool) -> node::Node {
+        let (start, startLoc) = self.storeCurrentPos();
+        let expr = self.parseExprOps(noIn);
+        if self.eat(TokenType::question()) {
+            let mut node = self.startNodeAt(start, startLoc);
+            node.test = Some(Box::new(expr));
+            node.consequent = Some(Box::new(self.parseMaybeAssign(false)));
+            node.alternate = Some(Box::new(if self.expect(TokenType::colon()) {
+                self.parseMaybeAssign(noIn)
+            } else {
+                self.dummyIdent()
+            }));
+            return self.finishNode(node, "ConditionalExpression");
+        }
+        expr
+    }
+
+    fn parseExprOps(&mut self, noIn: bool) -> node::Node {
+        let (start, startLoc) = self.storeCurrentPos();
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        let left = self.parseMaybeUnary(false);
+        self.parseExprOp(left, start, startLoc, -1, noIn, indent, line)
+    }
+
+    /// Operators that bind no tighter than the one before them are folded
+    /// into `left` in a loop, so long chains of them take no stack.
+    #[allow(clippy::too_many_arguments)]
+    fn parseExprOp(
+        &mut self,
+        mut left: node::Node,
+        start: usize,
+        startLoc: Option<locutil::Position>,
+        minPrec: isize,
+        noIn: bool,
+        indent: usize,
+        line: usize,
+    ) -> node::Node {
+        loop {
+            if self.curLineStart != line && self.curIndent < indent && self.tokenStartsLine() {
+                return left;
+            }
+            if noIn && self.tok.r#type == TokenType::_in() {
+                return left;
+            }
+            let prec = match self.tok.r#type.binop {
+                Some(prec) if prec as isize > minPrec => prec,
+                _ => return left,
+            };
+            let logical = self.tok.r#type == TokenType::logicalOR()
+                || self.tok.r#type == TokenType::logicalAND();
+            let mut node = self.startNodeAt(start, startLoc);
+            node.left = Some(Box::new(left));
+            node.operator = self
+                .tok
+                .value
+                .clone()
+                .or_else(|| Some(String::from(self.tok.r#type.label)));
+            self.next();
+            let right =
+                if self.curLineStart != line && self.curIndent < indent && self.tokenStartsLine() {
+                    self.dummyIdent()
+                } else {
+                    let (rightStart, rightStartLoc) = self.storeCurrentPos();
+                    let operand = self.parseMaybeUnary(false);
+                    self.parseExprOp(
+                        operand,
+                        rightStart,
+                        rightStartLoc,
+                        prec as isize,
+                        noIn,
+                        indent,
+                        line,
+                    )
+                };
+            node.right = Some(Box::new(right));
+            left = self.finishNode(
+                node,
+                if logical {
+                    "LogicalExpression"
+                } else {
+                    "BinaryExpression"
+                },
+            );
+        }
+    }
+
+    fn parseMaybeUnary(&mut self, sawUnary: bool) -> node::Node {
+        if self.tooDeep() {
+            return self.dummyIdent();
+        }
+        self.depth += 1;
+        let (start, startLoc) = self.storeCurrentPos();
+        let mut sawUnary = sawUnary;
+        let mut expr = if self.inAsync && self.isContextual("await") {
+            sawUnary = true;
+            let mut node = self.startNode();
+            self.next();
+            node.argument = Some(Box::new(self.parseMaybeUnary(true)));
+            self.finishNode(node, "AwaitExpression")
+        } else if self.tok.r#type.prefix {
+            let mut node = self.startNode();
+            let update = self.tok.r#type == TokenType::incDec();
+            sawUnary |= !update;
+            node.operator = self.tok.value.clone();
+            node.prefix = Some(true);
+            self.next();
+            let argument = self.parseMaybeUnary(true);
+            node.argument = Some(Box::new(if update {
+                self.checkLVal(argument)
+            } else {
+                argument
+            }));
+            self.finishNode(
+                node,
+                if update {
+                    "UpdateExpression"
+                } else {
+                    "UnaryExpression"
+                },
+            )
+        } else if self.tok.r#type == TokenType::ellipsis() {
+            let mut node = self.startNode();
+            self.next();
+            node.argument = Some(Box::new(self.parseMaybeUnary(sawUnary)));
+            self.finishNode(node, "SpreadElement")
+        } else {
+            let mut expr = self.parseExprSubscripts();
+            while self.tok.r#type.postfix && !self.canInsertSemicolon() {
+                let mut node = self.startNodeAt(start, startLoc);
+                node.operator = self.tok.value.clone();
+                node.prefix = Some(false);
+                node.argument = Some(Box::new(self.checkLVal(expr)));
+                self.next();
+                expr = self.finishNode(node, "UpdateExpression");
+            }
+            expr
+        };
+        if !sawUnary && self.eat(TokenType::starstar()) {
+            let mut node = self.startNodeAt(start, startLoc);
+            node.left = Some(Box::new(expr));
+            node.operator = Some(String::from("**"));
+            node.right = Some(Box::new(self.parseMaybeUnary(false)));
+            expr = self.finishNode(node, "BinaryExpression");
+        }
+        self.depth -= 1;
+        expr
+    }
+
+    fn parseExprSubscripts(&mut self) -> node::Node {
+        let (start, startLoc) = self.storeCurrentPos();
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        let base = self.parseExprAtom();
+        self.parseSubscripts(base, start, startLoc, false, indent, line)
+    }
+
+    fn parseSubscripts(
+        &mut self,
+        base: node::Node,
+        start: usize,
+        startLoc: Option<locutil::Position>,
+        noCalls: bool,
+        startIndent: usize,
+        line: usize,
+    ) -> node::Node {
+        let mut base = base;
+        let mut startIndent = startIndent;
+        loop {
+            // A dedented line ends the expression, unless it continues a
+            // chain of `.` accesses at the same indentation.
+            if self.curLineStart != line && self.curIndent <= startIndent && self.tokenStartsLine()
+            {
+                if self.tok.r#type == TokenType::dot() && self.curIndent == startIndent {
+                    startIndent = startIndent.saturating_sub(1);
+                } else {
+                    break;
+                }
+            }
+
+            if self.eat(TokenType::dot()) {
+                let mut node = self.startNodeAt(start, startLoc);
+                node.object = Some(Box::new(base));
+                let dedented = self.curLineStart != line
+                    && self.curIndent <= startIndent
+                    && self.tokenStartsLine();
+                node.property = Some(Box::new(if dedented {
+                    self.dummyIdent()
+                } else {
+                    self.parseIdent(true)
+                }));
+                node.computed = Some(false);
+                base = self.finishNode(node, "MemberExpression");
+            } else if self.tok.r#type == TokenType::bracketL() {
+                self.pushCx();
+                self.next();
+                let mut node = self.startNodeAt(start, startLoc);
+                node.object = Some(Box::new(base));
+                node.property = Some(Box::new(self.parseExpression(false)));
+                node.computed = Some(true);
+                self.popCx();
+                self.expect(TokenType::bracketR());
+                base = self.finishNode(node, "MemberExpression");
+            } else if !noCalls && self.tok.r#type == TokenType::parenL() {
+                let maybeAsyncArrow = base.r#type == "Identifier"
+                    && base.name.as_deref() == Some("async")
+                    && !self.canInsertSemicolon()
+                    && self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+                let exprList = self.parseExprList(TokenType::parenR(), false);
+                if maybeAsyncArrow && self.eat(TokenType::arrow()) {
+                    let node = self.startNodeAt(start, startLoc);
+                    return self.parseArrowExpression(node, exprList, true);
+                }
+                let mut node = self.startNodeAt(start, startLoc);
+                node.callee = Some(Box::new(base));
+                node.arguments = Some(exprList);
+                base = self.finishNode(node, "CallExpression");
+            } else if self.tok.r#type == TokenType::backQuote() {
+                let mut node = self.startNodeAt(start, startLoc);
+                node.tag = Some(Box::new(base));
+                node.quasi = Some(Box::new(self.parseTemplate()));
+                base = self.finishNode(node, "TaggedTemplateExpression");
+            } else {
+                return base;
+            }
+        }
+        base
+    }
+
+    fn parseExprAtom(&mut self) -> node::Node {
+        let r#type = self.tok.r#type.clone();
+        if r#type == TokenType::_this() || r#type == TokenType::_super() {
+            let node = self.startNode();
+            self.next();
+            return self.finishNode(
+                node,
+                if r#type == TokenType::_this() {
+                    "ThisExpression"
+                } else {
+                    "Super"
+                },
+            );
+        }
+        if r#type == TokenType::name() {
+            let (start, startLoc) = self.storeCurrentPos();
+            let mut id = self.parseIdent(false);
+            let mut isAsync = false;
+            if id.name.as_deref() == Some("async")
+                && !self.canInsertSemicolon()
+                && self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8
+            {
+                if self.eat(TokenType::_function()) {
+                    let node = self.startNodeAt(start, startLoc);
+                    return self.parseFunction(node, false, true);
+                }
+                if self.tok.r#type == TokenType::name() {
+                    id = self.parseIdent(false);
+                    isAsync = true;
+                }
+            }
+            if self.eat(TokenType::arrow()) {
+                let node = self.startNodeAt(start, startLoc);
+                return self.parseArrowExpression(node, vec![id], isAsync);
+            }
+            return id;
+        }
+        if r#type == TokenType::num()
+            || r#type == TokenType::string()
+            || r#type == TokenType::regexp()
+            || r#type == TokenType::_null()
+            || r#type == TokenType::_true()
+            || r#type == TokenType::_false()
+        {
+            let mut node = self.startNode();
+            // The value is derived from the source text when needed; see
+            // `ast::literalValue`.
+            node.raw = Some(String::from(&self.toks.input[self.tok.start..self.tok.end]));
+            self.next();
+            return self.finishNode(node, "Literal");
+        }
+        if r#type == TokenType::parenL() {
+            let (start, startLoc) = self.storeCurrentPos();
+            self.next();
+            let inner = self.parseExpression(false);
+            self.expect(TokenType::parenR());
+            if self.eat(TokenType::arrow()) {
+                let mut params = if inner.r#type == "SequenceExpression" {
+                    inner.expressions
+                } else {
+                    vec![inner]
+                };
+                if params.last().is_some_and(isDummy) {
+                    params.pop();
+                }
+                let node = self.startNodeAt(start, startLoc);
+                return self.parseArrowExpression(node, params, false);
+            }
+            if self.toks.options.preserveParens {
+                let mut node = self.startNodeAt(start, startLoc);
+                node.expression = Some(Box::new(inner));
+                return self.finishNode(node, "ParenthesizedExpression");
+            }
+            return inner;
+        }
+        if r#type == TokenType::bracketL() {
+            let mut node = self.startNode();
+            node.elements = Some(self.parseExprList(TokenType::bracketR(), true));
+            return self.finishNode(node, "ArrayExpression");
+        }
+        if r#type == TokenType::braceL() {
+            return self.parseObj();
+        }
+        if r#type == TokenType::_class() {
+            return self.parseClass(false);
+        }
+        if r#type == TokenType::_function() {
+            let node = self.startNode();
+            self.next();
+            return self.parseFunction(node, false, false);
+        }
+        if r#type == TokenType::_new() {
+            return self.parseNew();
+        }
+        if r#type == TokenType::backQuote() {
+            return self.parseTemplate();
+        }
+        self.dummyIdent()
+    }
+
+    fn parseNew(&mut self) -> node::Node {
+        if self.tooDeep() {
+            return self.dummyIdent();
+        }
+        let mut node = self.startNode();
+        let startIndent = self.curIndent;
+        let line = self.curLineStart;
+        let meta = self.parseIdent(true);
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6
+            && self.eat(TokenType::dot())
+        {
+            node.meta = Some(Box::new(meta));
+            node.property = Some(Box::new(self.parseIdent(true)));
+            return self.finishNode(node, "MetaProperty");
+        }
+        let (start, startLoc) = self.storeCurrentPos();
+        self.depth += 1;
+        let atom = self.parseExprAtom();
+        self.depth -= 1;
+        let callee = self.parseSubscripts(atom, start, startLoc, true, startIndent, line);
+        node.callee = Some(Box::new(callee));
+        node.arguments = Some(if self.tok.r#type == TokenType::parenL() {
+            self.parseExprList(TokenType::parenR(), false)
+        } else {
+            Vec::new()
+        });
+        self.finishNode(node, "NewExpression")
+    }
+
+    fn parseTemplateElement(&mut self) -> node::Node {
+        let mut elem = self.startNode();
+        if self.tok.r#type == TokenType::invalidTemplate() {
+            elem.raw = self.tok.value.clone();
+            elem.cooked = None;
+        } else {
+            let raw = &self.toks.input[self.tok.start..self.tok.end];
+            elem.raw = Some(raw.replace("\r\n", "\n").replace('\r', "\n"));
+            elem.cooked = self.tok.value.clone();
+        }
+        self.next();
+        elem.tail = Some(self.tok.r#type == TokenType::backQuote());
+        self.finishNode(elem, "TemplateElement")
+    }
+
+    fn parseTemplate(&mut self) -> node::Node {
+        let mut node = self.startNode();
+        self.next();
+        let mut curElt = self.parseTemplateElement();
+        let mut quasis = Vec::new();
+        while curElt.tail != Some(true) {
+            quasis.push(curElt);
+            self.next();
+            node.expressions.push(self.parseExpression(false));
+            curElt = if self.expect(TokenType::braceR()) {
+                self.parseTemplateElement()
+            } else {
+                let mut elem = self.startNode();
+                elem.raw = Some(String::new());
+                elem.cooked = Some(String::new());
+                elem.tail = Some(true);
+                self.finishEmpty(elem, "TemplateElement")
+            };
+        }
+        quasis.push(curElt);
+        node.quasis = Some(quasis);
+        self.expect(TokenType::backQuote());
+        self.finishNode(node, "TemplateLiteral")
+    }
+
+    fn parseObj(&mut self) -> node::Node {
+        let mut node = self.startNode();
+        let mut properties = Vec::new();
+        self.pushCx();
+        let mut indent = self.curIndent + 1;
+        let mut line = self.curLineStart;
+        self.eat(TokenType::braceL());
+        if self.curIndent + 1 < indent {
+            indent = self.curIndent;
+            line = self.curLineStart;
+        }
+        let ecmaVersion = self.toks.options.ecmaVersion;
+        while !self.closes(TokenType::braceR(), indent, line, false) {
+            let mut prop = self.startNode();
+            let (start, startLoc) = self.storeCurrentPos();
+            if ecmaVersion >= options::EcmaVersion::Ecma9 && self.eat(TokenType::ellipsis()) {
+                prop.argument = Some(Box::new(self.parseMaybeAssign(false)));
+                properties.push(self.finishNode(prop, "SpreadElement"));
+                self.eat(TokenType::comma());
+                continue;
+            }
+            let mut isGenerator = false;
+            if ecmaVersion >= options::EcmaVersion::Ecma6 {
+                prop.method = Some(false);
+                prop.shorthand = Some(false);
+                isGenerator = self.eat(TokenType::star());
+            }
+            self.parsePropertyName(&mut prop);
+            let mut isAsync = false;
+            if self.isAsyncProp(&prop) {
+                isAsync = true;
+                isGenerator =
+                    ecmaVersion >= options::EcmaVersion::Ecma9 && self.eat(TokenType::star());
+                self.parsePropertyName(&mut prop);
+            }
+            if prop.key.as_deref().is_none_or(isDummy) {
+                if isDummy(&self.parseMaybeAssign(false)) {
+                    self.next();
+                }
+                self.eat(TokenType::comma());
+                continue;
+            }
+            let accessor = prop.key.as_ref().and_then(|key| match key.name.as_deref() {
+                Some(name @ "get") | Some(name @ "set") if key.r#type == "Identifier" => {
+                    Some(String::from(name))
+                }
+                _ => None,
+            });
+            if self.eat(TokenType::colon()) {
+                prop.kind = Some(String::from("init"));
+                prop.value = Some(Box::new(self.parseMaybeAssign(false)));
+            } else if ecmaVersion >= options::EcmaVersion::Ecma6
+                && (self.tok.r#type == TokenType::parenL()
+                    || self.tok.r#type == TokenType::braceL())
+            {
+                prop.kind = Some(String::from("init"));
+                prop.method = Some(true);
+                prop.value = Some(Box::new(self.parseMethod(isGenerator, isAsync)));
+            } else if ecmaVersion >= options::EcmaVersion::Ecma5
+                && accessor.is_some()
+                && prop.computed != Some(true)
+                && self.tok.r#type != TokenType::comma()
+                && self.tok.r#type != TokenType::braceR()
+                && self.tok.r#type != TokenType::eq()
+            {
+                prop.kind = accessor;
+                self.parsePropertyName(&mut prop);
+                prop.value = Some(Box::new(self.parseMethod(false, false)));
+            } else {
+                prop.kind = Some(String::from("init"));
+                let key = prop.key.as_deref().cloned().unwrap_or_default();
+                prop.value = Some(Box::new(if ecmaVersion < options::EcmaVersion::Ecma6 {
+                    self.dummyIdent()
+                } else if self.eat(TokenType::eq()) {
+                    let mut assign = self.startNodeAt(start, startLoc);
+                    assign.operator = Some(String::from("="));
+                    assign.left = Some(Box::new(key));
+                    assign.right = Some(Box::new(self.parseMaybeAssign(false)));
+                    self.finishNode(assign, "AssignmentExpression")
+                } else {
+                    key
+                }));
+                prop.shorthand = Some(true);
+            }
+            properties.push(self.finishNode(prop, "Property"));
+            self.eat(TokenType::comma());
+        }
+        self.popCx();
+        if !self.eat(TokenType::braceR()) {
+            self.spanToNextToken();
+        }
+        node.properties = Some(properties);
+        self.finishNode(node, "ObjectExpression")
+    }
+
+    /// Whether `prop` is `async` followed by the name of an async method.
+    fn isAsyncProp(&self, prop: &node::Node) -> bool {
+        let r#type = &self.tok.r#type;
+        self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8
+            && prop.computed != Some(true)
+            && prop.key.as_ref().is_some_and(|key| {
+                key.r#type == "Identifier" && key.name.as_deref() == Some("async")
+            })
+            && (*r#type == TokenType::name()
+                || *r#type == TokenType::num()
+                || *r#type == TokenType::string()
+                || *r#type == TokenType::bracketL()
+                || !r#type.keyword.is_empty()
+                || *r#type == TokenType::star())
+            && !self.lineBreakBefore()
+    }
+
+    fn parsePropertyName(&mut self, prop: &mut node::Node) {
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            if self.eat(TokenType::bracketL()) {
+                prop.computed = Some(true);
+                prop.key = Some(Box::new(self.parseExpression(false)));
+                self.expect(TokenType::bracketR());
+                return;
+            }
+            prop.computed = Some(false);
+        }
+        let key = if self.tok.r#type == TokenType::num() || self.tok.r#type == TokenType::string() {
+            self.parseExprAtom()
+        } else {
+            self.parseIdent(true)
+        };
+        prop.key = Some(Box::new(key));
+    }
+
+    /// Parse an identifier, or with `liberal` a keyword used as a name.
+    /// Anything else gives a placeholder, without being skipped.
+    fn parseIdent(&mut self, liberal: bool) -> node::Node {
+        let name = if self.tok.r#type == TokenType::name() {
+            self.tok.value.clone()
+        } else if liberal && !self.tok.r#type.keyword.is_empty() {
+            Some(String::from(self.tok.r#type.keyword))
+        } else {
+            None
+        };
+        if name.is_none() {
+            return self.dummyIdent();
+        }
+        let mut node = self.startNode();
+        node.name = name;
+        self.next();
+        self.finishNode(node, "Identifier")
+    }
+
+    fn toAssignable(&self, node: node::Node, binding: bool) -> node::Node {
+        let mut node = node;
+        match node.r#type.as_str() {
+            "Identifier" => {}
+            "MemberExpression" if !binding => {}
+            "ParenthesizedExpression" => {
+                if let Some(inner) = node.expression.take() {
+                    node.expression = Some(Box::new(self.toAssignable(*inner, binding)));
+                }
+            }
+            _ if self.toks.options.ecmaVersion < options::EcmaVersion::Ecma6 => {
+                return self.dummyIdent();
+            }
+            "ObjectExpression" => {
+                node.r#type = String::from("ObjectPattern");
+                if let Some(properties) = node.properties.take() {
+                    node.properties = Some(
+                        properties
+                            .into_iter()
+                            .map(|prop| self.toAssignable(prop, binding))
+                            .collect(),
+                    );
+                }
+            }
+            "ArrayExpression" => {
+                node.r#type = String::from("ArrayPattern");
+                if let Some(elements) = node.elements.take() {
+                    node.elements = Some(self.toAssignableList(elements, binding));
+                }
+            }
+            "Property" => {
+                if let Some(value) = node.value.take() {
+                    node.value = Some(Box::new(self.toAssignable(*value, binding)));
+                }
+            }
+            "SpreadElement" => {
+                node.r#type = String::from("RestElement");
+                if let Some(argument) = node.argument.take() {
+                    node.argument = Some(Box::new(self.toAssignable(*argument, binding)));
+                }
+            }
+            "AssignmentExpression" => {
+                node.r#type = String::from("AssignmentPattern");
+                node.operator = None;
+                if let Some(left) = node.left.take() {
+                    node.left = Some(Box::new(self.toAssignable(*left, binding)));
+                }
+            }
+            _ => return self.dummyIdent(),
+        }
+        node
+    }
+
+    fn toAssignableList(&self, list: Vec<node::Node>, binding: bool) -> Vec<node::Node> {
+        list.into_iter()
+            .map(|elt| {
+                if elt.isHole() {
+                    elt
+                } else {
+                    self.toAssignable(elt, binding)
+                }
+            })
+            .collect()
+    }
+
+    fn parseFunctionParams(&mut self) -> Vec<node::Node> {
+        if self.tok.r#type != TokenType::parenL() {
+            return Vec::new();
+        }
+        let params = self.parseExprList(TokenType::parenR(), false);
+        self.toAssignableList(params, true)
+    }
+
+    fn parseMethod(&mut self, isGenerator: bool, isAsync: bool) -> node::Node {
+        let mut node = self.startNode();
+        let oldInAsync = self.inAsync;
+        let oldInGenerator = self.inGenerator;
+        self.initFunction(&mut node);
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            node.generator = Some(isGenerator);
+        }
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+            node.r#async = Some(isAsync);
+        }
+        self.inAsync = isAsync;
+        self.inGenerator = isGenerator;
+        node.params = Some(self.parseFunctionParams());
+        node.body = Some(vec![self.parseBlock()]);
+        self.inAsync = oldInAsync;
+        self.inGenerator = oldInGenerator;
+        self.finishNode(node, "FunctionExpression")
+    }
+
+    fn parseArrowExpression(
+        &mut self,
+        mut node: node::Node,
+        params: Vec<node::Node>,
+        isAsync: bool,
+    ) -> node::Node {
+        let oldInAsync = self.inAsync;
+        let oldInGenerator = self.inGenerator;
+        self.initFunction(&mut node);
+        node.generator = None;
+        if self.toks.options.ecmaVersion >= options::EcmaVersion::Ecma8 {
+            node.r#async = Some(isAsync);
+        }
+        self.inAsync = isAsync;
+        self.inGenerator = false;
+        node.params = Some(self.toAssignableList(params, true));
+        // An arrow function's expression body is the only element of
+        // `body`; see `node::Node`.
+        node.body = Some(vec![if self.tok.r#type == TokenType::braceL() {
+            self.parseBlock()
+        } else {
+            self.parseMaybeAssign(false)
+        }]);
+        self.inAsync = oldInAsync;
+        self.inGenerator = oldInGenerator;
+        self.finishNode(node, "ArrowFunctionExpression")
+    }
+
+    /// Parse a bracketed list, with the current token as its opening
+    /// bracket. `allowEmpty` keeps elisions as holes.
+    fn parseExprList(&mut self, close: TokenType, allowEmpty: bool) -> Vec<node::Node> {
+        self.pushCx();
+        let indent = self.curIndent;
+        let line = self.curLineStart;
+        let mut elts = Vec::new();
+        self.next(); // Opening bracket
+        while !self.closes(close.clone(), indent + 1, line, false) {
+            if self.eat(TokenType::comma()) {
+                elts.push(if allowEmpty {
+                    node::Node::hole(self.last.start)
+                } else {
+                    self.dummyIdent()
+                });
+                continue;
+            }
+            let elt = self.parseMaybeAssign(false);
+            if isDummy(&elt) {
+                if self.closes(close.clone(), indent, line, false) {
+                    break;
+                }
+                self.next();
+            } else {
+                elts.push(elt);
+            }
+            self.eat(TokenType::comma());
+        }
+        self.popCx();
+        if !self.eat(close) {
+            self.spanToNextToken();
+        }
+        elts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parseModule(input: &str) -> node::Node {
+        let options = options::Options::builder()
+            .sourceType(options::SourceType::Module)
+            .build()
+            .unwrap();
+        parse(String::from(input), Some(options))
+    }
+
+    /// The types of every node in the tree, children first.
+    fn types(node: &node::Node) -> Vec<String> {
+        let mut types: Vec<String> = node.children().into_iter().flat_map(types).collect();
+        types.push(node.r#type.clone());
+        types
+    }
+
+    /// The types of the top-level statements.
+    fn statements(input: &str) -> Vec<String> {
+        let program = parseModule(input);
+        let body = program.body.unwrap_or_default();
+        body.iter().map(|node| node.r#type.clone()).collect()
+    }
+
+    /// Every node must have `start <= end` and lie within the input.
+    fn assertWellFormed(input: &str) -> node::Node {
+        fn wellFormed(node: &node::Node, len: usize) -> bool {
+            node.start <= node.end
+                && node.end <= len
+                && node.children().into_iter().all(|child| wellFormed(child, len))
+        }
+        let program = parseModule(input);
+        assert!(wellFormed(&program, input.len()), "bad positions for {:?}", input);
+        program
+    }
+
+    #[test]
+    fn parsesTheFullGrammar() {
+        assert_eq!(
+            statements(
+                "import a, {b as c} from 'm';\n\
+                 export const d = 1;\n\
+                 export default class E extends F { constructor() { super(); } get g() {} }\n\
+                 function* h(i, [j], {k = 2}) { yield i; }\n\
+                 async function l() { await m(); }\n\
+                 for (let n of o) {}\n\
+                 for (p in q) {}\n\
+                 for (let r = 0; r < 1; r++) continue;\n\
+                 while (s) break;\n\
+                 do t(); while (u)\n\
+                 try { v(); } catch (w) {} finally {}\n\
+                 switch (x) { case 1: y; default: z; }\n\
+                 label: ;\n\
+                 throw new Error(`a${b}c`);\n"
+            ),
+            [
+                "ImportDeclaration",
+                "ExportNamedDeclaration",
+                "ExportDefaultDeclaration",
+                "FunctionDeclaration",
+                "FunctionDeclaration",
+                "ForOfStatement",
+                "ForInStatement",
+                "ForStatement",
+                "WhileStatement",
+                "DoWhileStatement",
+                "TryStatement",
+                "SwitchStatement",
+                "LabeledStatement",
+                "ThrowStatement",
+            ]
+        );
+        let all = types(&parseModule(
+            "x = {a: [1, , 2], b() {}, ...c, d}; y = (e, f) => e.f[g](h); z = async i => i;",
+        ));
+        for expected in [
+            "ObjectExpression",
+            "ArrayExpression",
+            "SpreadElement",
+            "ArrowFunctionExpression",
+            "MemberExpression",
+            "CallExpression",
+        ] {
+            assert!(
+                all.iter().any(|t| t == expected),
+                "no {} in {:?}",
+                expected,
+                all
+            );
+        }
+    }
+
+    #[test]
+    fn fillsInMissingPieces() {
+        // `foo.` at the end of a line gets a placeholder property.
+        let program = assertWellFormed("foo.\nbar();\n");
+        let body = program.body.unwrap();
+        assert_eq!(body.len(), 2);
+        let member = body[0].expression.as_ref().unwrap();
+        assert_eq!(member.r#type, "MemberExpression");
+        assert!(isDummy(member.property.as_ref().unwrap()));
+
+        assert_eq!(statements("f(a, b"), ["ExpressionStatement"]);
+        assert_eq!(statements("import {a} from"), ["ImportDeclaration"]);
+        assert_eq!(
+            statements("if (a) {\n  b();\nc();\n"),
+            ["IfStatement", "ExpressionStatement"]
+        );
+        assert_eq!(
+            statements("class A {\n  m() {\n    x;\n}\nfunction f() {}\n"),
+            ["ClassDeclaration", "FunctionDeclaration"]
+        );
+    }
+
+    #[test]
+    fn strayBracesStayWithTheirBlock() {
+        // The `)` garbage in the inner block must not swallow the `}`
+        // that closes it, or `b` would end up inside the block.
+        let program = assertWellFormed("{ ) }\nb;\n");
+        let body = program.body.unwrap();
+        assert_eq!(body.len(), 2);
+        assert_eq!(body[0].r#type, "BlockStatement");
+        assert_eq!(body[1].r#type, "ExpressionStatement");
+        assert_eq!(statements("}}}\na;"), ["ExpressionStatement"]);
+    }
+
+    #[test]
+    fn neverPanicsOnGarbage() {
+        for input in [
+            "\"é",
+            "#é",
+            "@€ x",
+            "a ++ é ¤ b",
+            "`${",
+            "/[",
+            "({",
+            "class { static",
+            "for (;;",
+            "export {",
+            "switch (a) { case",
+            "a => { => }",
+            "\u{feff}😀 = 1",
+        ] {
+            assertWellFormed(input);
+        }
+    }
+
+    #[test]
+    fn skipsLongRunsOfGarbageWithoutRecursing() {
+        let input = ")".repeat(200_000);
+        assert!(parseModule(&input).body.unwrap().len() <= 1);
+        let input = format!("a;{}", "@".repeat(200_000));
+        assert_eq!(parseModule(&input).body.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn skipsWhatIsNestedTooDeeply() {
+        // Unoptimized builds use far more stack per level than release
+        // builds, more than the default test thread has room for.
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(|| {
+                for (open, close) in [("(", ")"), ("{", "}"), ("[", "]"), ("!", ""), ("new ", "")] {
+                    let input = format!("{}x{};\ny;", open.repeat(5000), close.repeat(5000));
+                    let program = assertWellFormed(&input);
+                    assert!(types(&program).iter().any(|t| t == "Identifier"));
+                    let body = program.body.unwrap();
+                    let last = body.last().unwrap().expression.as_ref().unwrap();
+                    assert_eq!(last.name.as_deref(), Some("y"), "for {:?}", open);
+                }
+                let chain = format!("{}a", "a + ".repeat(100_000));
+                assert_eq!(parseModule(&chain).body.unwrap().len(), 1);
+                let ifs = format!("{}x;", "if (a) ".repeat(5000));
+                assert!(!parseModule(&ifs).body.unwrap().is_empty());
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
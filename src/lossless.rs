@@ -0,0 +1,407 @@
+// Formatting-preserving printing, after recast. `parseLossless` keeps
+// the source text next to the tree, and `Document::print` writes the
+// edited tree back out by copying the source for everything that was
+// not edited: whitespace, comments, parentheses and the exact text of
+// every token survive, so an unmodified tree prints byte-for-byte as it
+// was read and an edit only changes the text of what it touched.
+//
+// A node is printed by the first of these that applies:
+//
+// - If it is equal to the node parsed at the same offsets, its source
+//   text is copied.
+// - If it has the same type and flags as that node and the same child
+//   positions filled, the source text is copied with the text of each
+//   changed child replaced by that child's printed text. A list that
+//   grew or shrank keeps the original separators between the elements
+//   that are still next to each other.
+// - Otherwise it is printed by `codegen`, which still copies the
+//   source text of any unedited node inside it.
+//
+// Comments inside a node that has to be printed by `codegen` are lost.
+
+use crate::ast;
+use crate::codegen;
+use crate::location;
+use crate::node;
+use crate::options;
+use crate::state;
+use crate::tokenize;
+
+use std::collections::HashMap;
+
+use ast::{FieldRef, Shape};
+
+/// A tree together with the source it was parsed from.
+pub struct Document {
+    source: String,
+    original: node::Node,
+    comments: Vec<tokenize::Comment>,
+    /// The tree to edit. It starts out as a copy of `original`.
+    pub tree: node::Node,
+}
+
+/// Parse `input` for editing. Offsets in the tree are UTF-8 byte
+/// offsets whatever `Options.positionEncoding` says, so that they can
+/// slice `Document::source`.
+pub fn parse(
+    input: String,
+    options: Option<options::Options>,
+) -> Result<Document, location::SyntaxError> {
+    let mut options = options.unwrap_or_default();
+    options.positionEncoding = options::PositionEncoding::Utf8;
+    let (tree, comments) = state::Parser::parseWithComments(input.clone(), Some(options))?;
+    Ok(Document {
+        source: input,
+        original: tree.clone(),
+        comments,
+        tree,
+    })
+}
+
+impl Document {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The tree as it was parsed.
+    pub fn original(&self) -> &node::Node {
+        &self.original
+    }
+
+    pub fn comments(&self) -> &[tokenize::Comment] {
+        &self.comments
+    }
+
+    /// Print `tree`, printing new and rewritten nodes with the default
+    /// `codegen` options.
+    pub fn print(&self) -> String {
+        self.printWith(&codegen::GenerateOptions::default())
+    }
+
+    /// Print `tree`, printing new and rewritten nodes with `options`.
+    pub fn printWith(&self, options: &codegen::GenerateOptions) -> String {
+        let mut originals = HashMap::new();
+        index(&self.original, &mut originals);
+        let printer = Printer {
+            source: &self.source,
+            comments: &self.comments,
+            originals,
+            options,
+        };
+        let text = printer.print(&self.tree);
+        if self.tree.r#type == "Program" {
+            // Whatever surrounds the program, such as a trailing
+            // newline outside its range.
+            let root = &self.original;
+            format!(
+                "{}{}{}",
+                &self.source[..root.start.min(self.source.len())],
+                text,
+                &self.source[root.end.min(self.source.len())..]
+            )
+        } else {
+            text
+        }
+    }
+}
+
+type Key<'a> = (usize, usize, &'a str);
+
+fn key(node: &node::Node) -> Key<'_> {
+    (node.start, node.end, &node.r#type)
+}
+
+/// Index every node of `node` by its offsets and type. The first node
+/// wins, which only matters for the key and value of a shorthand
+/// property, and those are equal.
+fn index<'a>(node: &'a node::Node, originals: &mut HashMap<Key<'a>, &'a node::Node>) {
+    if node.start < node.end {
+        originals.entry(key(node)).or_insert(node);
+    }
+    for child in node.children() {
+        index(child, originals);
+    }
+}
+
+/// Statement lists, whose elements are separated by line breaks rather
+/// than commas.
+fn isStatementList(field: &str) -> bool {
+    field == "body" || field == "consequent" || field == "cases"
+}
+
+fn sameScalar(a: FieldRef, b: FieldRef) -> bool {
+    match (a, b) {
+        (FieldRef::Missing, FieldRef::Missing) => true,
+        (FieldRef::Str(a), FieldRef::Str(b)) => a == b,
+        (FieldRef::Bool(a), FieldRef::Bool(b)) => a == b,
+        (FieldRef::SourceType(a), FieldRef::SourceType(b)) => a == b,
+        _ => false,
+    }
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    comments: &'a [tokenize::Comment],
+    originals: HashMap<Key<'a>, &'a node::Node>,
+    options: &'a codegen::GenerateOptions,
+}
+
+impl<'a> Printer<'a> {
+    fn original(&self, node: &node::Node) -> Option<&'a node::Node> {
+        if node.start >= node.end || node.end > self.source.len() {
+            return None;
+        }
+        self.originals.get(&key(node)).copied()
+    }
+
+    fn text(&self, node: &node::Node) -> &'a str {
+        &self.source[node.start..node.end]
+    }
+
+    fn print(&self, node: &node::Node) -> String {
+        self.printAt(node, node.start).0
+    }
+
+    /// Print `node`, which goes where the source has the text at `pos`.
+    /// Also returns whether the text is copied unchanged.
+    fn printAt(&self, node: &node::Node, pos: usize) -> (String, bool) {
+        let original = self.original(node);
+        if let Some(original) = original {
+            if original == node {
+                return (String::from(self.text(node)), true);
+            }
+            if let Some(text) = self.patch(node, original) {
+                return (text, false);
+            }
+        }
+        (self.reindent(self.generate(node), pos), false)
+    }
+
+    /// Print `node` with `codegen`, reusing what can be reused inside
+    /// it.
+    fn generate(&self, node: &node::Node) -> String {
+        let reuse = |child: &node::Node| {
+            if std::ptr::eq(child, node) || self.original(child).is_none() {
+                None
+            } else {
+                Some(self.print(child))
+            }
+        };
+        codegen::generateReusing(node, self.options, &reuse)
+    }
+
+    /// The source text of `original` with the text of each child that
+    /// differs in `node` replaced, or `None` if `node` differs from
+    /// `original` in more than its children.
+    fn patch(&self, node: &node::Node, original: &node::Node) -> Option<String> {
+        let fields = ast::fieldsOf(&node.r#type)?;
+        // The key and value of `{a}` share their text; a change to
+        // either needs `a: b`.
+        if node.shorthand == Some(true) {
+            return None;
+        }
+        let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+        for (name, shape) in fields.iter() {
+            let (new, old) = (ast::field(node, name), ast::field(original, name));
+            match shape {
+                Shape::String | Shape::OptionalString | Shape::Bool | Shape::SourceType => {
+                    if !sameScalar(new, old) {
+                        return None;
+                    }
+                }
+                Shape::Node | Shape::OptionalNode | Shape::List => match (new, old) {
+                    (FieldRef::Missing, FieldRef::Missing) => {}
+                    (FieldRef::Node(new), FieldRef::Node(old)) => {
+                        if new != old {
+                            let text = self.child(new, old, node);
+                            replacements.push((old.start, old.end, text));
+                        }
+                    }
+                    (FieldRef::Nodes(new), FieldRef::Nodes(old)) => {
+                        if new != old {
+                            let first = old.first()?;
+                            let last = old.last()?;
+                            let text = self.list(new, old, name)?;
+                            replacements.push((first.start, last.end, text));
+                        }
+                    }
+                    _ => return None,
+                },
+            }
+        }
+        replacements.sort_by_key(|&(start, _, _)| start);
+        let mut out = String::new();
+        let mut pos = original.start;
+        for (start, end, text) in replacements {
+            if start < pos || end > original.end {
+                return None;
+            }
+            out.push_str(&self.source[pos..start]);
+            out.push_str(&text);
+            pos = end;
+        }
+        out.push_str(&self.source[pos..original.end]);
+        Some(out)
+    }
+
+    /// Print `node`, which takes the place of `old` in `parent`.
+    fn child(&self, node: &node::Node, old: &node::Node, parent: &node::Node) -> String {
+        let (text, unchanged) = self.printAt(node, old.start);
+        if unchanged {
+            return text;
+        }
+        let statementStart = match parent.r#type.as_str() {
+            "ExpressionStatement" | "ArrowFunctionExpression" => match node.r#type.as_str() {
+                "ObjectExpression" | "ObjectPattern" => true,
+                "FunctionExpression" | "ClassExpression" => parent.r#type == "ExpressionStatement",
+                _ => false,
+            },
+            _ => false,
+        };
+        let isExpression = ast::Expression::accepts(&node.r#type);
+        if isExpression && (statementStart || codegen::precedence(node) < codegen::precedence(old))
+        {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+
+    /// Print the list `nodes`, which replaces `old`, the `field` list of
+    /// a node. Elements that were next to each other in `old` keep the
+    /// text between them.
+    fn list(&self, nodes: &[node::Node], old: &[node::Node], field: &str) -> Option<String> {
+        if old.iter().chain(nodes).any(node::Node::isHole) {
+            return None;
+        }
+        let statements = isStatementList(field);
+        let separator = match old {
+            [first, second, ..] => self.separatorBetween(first.end, second.start),
+            [first, ..] if statements => format!("\n{}", self.indentAt(first.start)),
+            _ => String::from(", "),
+        };
+        let position = |node: &node::Node| old.iter().position(|old| key(old) == key(node));
+        let mut out = String::new();
+        let mut previous: Option<usize> = None;
+        for (i, node) in nodes.iter().enumerate() {
+            let at = position(node);
+            if i > 0 {
+                match (previous, at) {
+                    (Some(previous), Some(at)) if at == previous + 1 => {
+                        out.push_str(&self.source[old[previous].end..old[at].start]);
+                    }
+                    _ => out.push_str(&separator),
+                }
+            }
+            let anchor = old[at.unwrap_or(0)].start;
+            let (text, _) = self.printAt(node, anchor);
+            if !statements && node.r#type == "SequenceExpression" {
+                out.push_str(&format!("({})", text));
+            } else {
+                out.push_str(&text);
+            }
+            previous = at;
+        }
+        Some(out)
+    }
+
+    /// The source between `start` and `end` without the comments in it
+    /// and the blanks before each, to put between elements that were
+    /// not next to each other without repeating those comments.
+    fn separatorBetween(&self, start: usize, end: usize) -> String {
+        let mut out = String::new();
+        let mut pos = start;
+        let inside = |comment: &&tokenize::Comment| comment.start >= start && comment.end <= end;
+        for comment in self.comments.iter().filter(inside) {
+            out.push_str(self.source[pos..comment.start].trim_end_matches([' ', '\t']));
+            pos = comment.end;
+        }
+        out.push_str(&self.source[pos..end]);
+        out
+    }
+
+    /// The whitespace at the start of the line `pos` is on.
+    fn indentAt(&self, pos: usize) -> &'a str {
+        let lineStart = self.source[..pos].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.source[lineStart..];
+        let width = line.len() - line.trim_start_matches([' ', '\t']).len();
+        &line[..width.min(pos - lineStart)]
+    }
+
+    /// Indent the lines after the first of `text`, printed by `codegen`
+    /// from column zero, to match the line `pos` is on.
+    fn reindent(&self, text: String, pos: usize) -> String {
+        let indent = self.indentAt(pos);
+        if indent.is_empty() || !text.contains('\n') {
+            return text;
+        }
+        text.replace('\n', &format!("\n{}", indent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(input: &str) -> Document {
+        match parse(String::from(input), None) {
+            Ok(document) => document,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn body(document: &mut Document) -> &mut Vec<node::Node> {
+        document.tree.body.as_mut().unwrap()
+    }
+
+    #[test]
+    fn printsAnUneditedTreeAsItWasRead() {
+        let input = "/* head */\nconst  a = ( 1 +\t2 ) ; // tail\r\n\nf( 'é' , `x${ y }` )\n";
+        assert_eq!(document(input).print(), input);
+    }
+
+    #[test]
+    fn onlyRewritesWhatWasEdited() {
+        let mut doc = document("let a = (b  +  c); // keep\nf( a );\n");
+        let declarator = &mut body(&mut doc)[0].declarations.as_mut().unwrap()[0];
+        declarator.id.as_mut().unwrap().name = Some(String::from("renamed"));
+        assert_eq!(doc.print(), "let renamed = (b  +  c); // keep\nf( a );\n");
+    }
+
+    #[test]
+    fn keepsSeparatorsWhenAListShrinks() {
+        let mut doc = document("f(a,  b,\n  c);\n");
+        let call = body(&mut doc)[0].expression.as_mut().unwrap();
+        call.arguments.as_mut().unwrap().remove(1);
+        // The separator after `a` is kept; the one after `b` goes with it.
+        assert_eq!(doc.print(), "f(a,  c);\n");
+    }
+
+    #[test]
+    fn doesNotRepeatCommentsBetweenElements() {
+        let mut doc = document("a; // one\nb;\n");
+        let mut added = document("x; y;").tree.body.unwrap();
+        for statement in &mut added {
+            statement.start += 100;
+            statement.end += 100;
+        }
+        body(&mut doc).extend(added);
+        assert_eq!(doc.print(), "a; // one\nb;\nx;\ny;\n");
+
+        let mut doc = document("f(a, /* one */ b);\n");
+        let call = body(&mut doc)[0].expression.as_mut().unwrap();
+        call.arguments.as_mut().unwrap().reverse();
+        assert_eq!(doc.print(), "f(b, a);\n");
+    }
+
+    #[test]
+    fn printsNewNodesWithCodegen() {
+        let mut doc = document("a;  b;\n");
+        let mut replacement = document("x = [1,2]").tree.body.unwrap().remove(0);
+        // Move it off any offset of the original, so no text is copied.
+        replacement.start = 100;
+        replacement.end = 100;
+        body(&mut doc)[1] = replacement;
+        assert_eq!(doc.print(), "a;  x = [1, 2];\n");
+    }
+}
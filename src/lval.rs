@@ -1,115 +1,539 @@
+use crate::errorkind;
+use crate::expression;
 use crate::location;
+use crate::locutil;
 use crate::node;
 use crate::options;
 use crate::parseutil;
+use crate::scope;
+use crate::scopeflags;
 use crate::state;
+use crate::tokenize;
+use crate::tokentype;
 
+use std::collections::HashSet;
+
+use errorkind::ErrorKind;
+use expression::ParserExpression;
 use location::ParserLocation;
+use node::ParserNode;
 use parseutil::ParserParseUtil;
+use scope::ParserScope;
+use scopeflags::Binds;
+use tokenize::ParserTokenize;
+use tokentype::TokenType;
 
 pub trait ParserLval {
+  /// Convert existing expression atom to assignable pattern
+  /// if possible.
   fn toAssignable(
-    self,
-    node: Option<node::Node>,
+    &mut self,
+    node: node::Node,
+    isBinding: bool,
+    refDestructuringErrors: Option<&parseutil::DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  /// Convert list of expression atoms to binding list.
+  fn toAssignableList(
+    &mut self,
+    exprList: Vec<node::Node>,
     isBinding: bool,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> Option<node::Node>;
+  ) -> Result<Vec<node::Node>, location::SyntaxError>;
+
+  /// Parses spread element.
+  fn parseSpread(
+    &mut self,
+    refDestructuringErrors: Option<&mut parseutil::DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseRestBinding(&mut self) -> Result<node::Node, location::SyntaxError>;
+
+  /// Parses lvalue (assignable) atom.
+  fn parseBindingAtom(&mut self) -> Result<node::Node, location::SyntaxError>;
+
+  fn parseBindingList(
+    &mut self,
+    close: TokenType,
+    allowEmpty: bool,
+    allowTrailingComma: bool,
+  ) -> Result<Vec<node::Node>, location::SyntaxError>;
+
+  /// Parses assignment pattern around given atom if possible.
+  fn parseMaybeDefault(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    left: Option<node::Node>,
+  ) -> Result<node::Node, location::SyntaxError>;
+
+  // The following three functions all verify that a node is an lvalue —
+  // something that can be bound, or assigned to. In order to do so, they perform
+  // a variety of checks:
+  //
+  // - Check that none of the bound/assigned-to identifiers are reserved words.
+  // - Record name declarations for bindings in the appropriate scope.
+  // - Check duplicate argument names, if checkClashes is set.
+  //
+  // If a complex binding pattern is encountered (e.g., object and array
+  // destructuring), the entire pattern is recursively checked.
+  //
+  // There are three versions of checkLVal*() appropriate for different
+  // circumstances:
+  //
+  // - checkLValSimple() shall be used if the syntactic construct supports
+  //   nothing other than identifiers and member expressions. Parenthesized
+  //   expressions are also correctly handled. This is generally appropriate for
+  //   constructs for which the spec says
+  //
+  //   > It is a Syntax Error if AssignmentTargetType of [the production] is not
+  //   > simple.
+  //
+  //   It is also appropriate for checking if an identifier is valid and not
+  //   defined elsewhere, like import declarations or function/class identifiers.
+  //
+  //   Examples where this is used include:
+  //     a += …;
+  //     import a from '…';
+  //   where a is the node to be checked.
+  //
+  // - checkLValPattern() shall be used if the syntactic construct supports
+  //   anything checkLValSimple() supports, as well as object and array
+  //   destructuring patterns. This is generally appropriate for constructs for
+  //   which the spec says
+  //
+  //   > It is a Syntax Error if [the production] is neither an ObjectLiteral nor
+  //   > an ArrayLiteral and AssignmentTargetType of [the production] is not
+  //   > simple.
+  //
+  //   Examples where this is used include:
+  //     (a = …);
+  //     const a = …;
+  //     try { … } catch (a) { … }
+  //   where a is the node to be checked.
+  //
+  // - checkLValInnerPattern() shall be used if the syntactic construct supports
+  //   anything checkLValPattern() supports, as well as default assignment
+  //   patterns, rest elements, and other constructs that may appear within an
+  //   object or array destructuring pattern.
+  //
+  //   As a special case, function parameters also use checkLValInnerPattern(),
+  //   as they also support defaults and rest constructs.
+  //
+  // These functions deliberately support both assignment and binding constructs,
+  // as the logic for both is exceedingly similar. If the node is the target of
+  // an assignment, then bindingType should be set to Binds::None. Otherwise, it
+  // should be set to the appropriate Binds::* constant, like Binds::Var or
+  // Binds::Lexical.
+  //
+  // If the function is called with a non-Binds::None bindingType, then
+  // additionally a checkClashes set may be specified to allow checking for
+  // duplicate argument names. checkClashes is ignored if the provided construct
+  // is an assignment (i.e., bindingType is Binds::None).
+
+  fn checkLValSimple(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError>;
+
+  fn checkLValPattern(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError>;
+
+  fn checkLValInnerPattern(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError>;
 }
 
 impl ParserLval for state::Parser {
   fn toAssignable(
-    self,
-    node: Option<node::Node>,
+    &mut self,
+    mut node: node::Node,
     isBinding: bool,
-    refDestructuringErrors: Option<parseutil::DestructuringErrors>,
-  ) -> Option<node::Node> {
-    if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && node.is_some() {
-      let node = node.unwrap();
-      match node.r#type.as_str() {
-        "Identifier" => {
-          if self.inAsync() && node.name.unwrap() == "await" {
-            self.raise(
-              node.start,
-              String::from("Cannot use 'await' as identifier inside an async function"),
-            );
-          }
+    refDestructuringErrors: Option<&parseutil::DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    if self.options.ecmaVersion < options::EcmaVersion::Ecma6 {
+      self.checkPatternErrors(refDestructuringErrors, true)?;
+      return Ok(node);
+    }
+    match node.r#type.as_str() {
+      "Identifier" => {
+        if self.inAsync() && node.name.as_deref() == Some("await") {
+          self.raise(node.start, ErrorKind::AwaitIdentifierInAsync)?;
         }
-        "ObjectPattern" | "ArrayPattern" | "RestElement" => {}
-        "ObjectExpression" => {
-          node.r#type = String::from("ObjectPattern");
-          if refDestructuringErrors.is_some() {
-            self.checkPatternErrors(refDestructuringErrors, true);
-          }
-          for prop in node.properties.unwrap() {
-            let arg = prop.argument.unwrap();
-            self.toAssignable(Some(prop), isBinding, None);
-            // Early error:
-            //   AssignmentRestProperty[Yield, Await] :
-            //     `...` DestructuringAssignmentTarget[Yield, Await]
-            //
-            //   It is a Syntax Error if |DestructuringAssignmentTarget| is an |ArrayLiteral| or an |ObjectLiteral|.
-            if prop.r#type == "RestElement"
-              && (arg.r#type == "ArrayPattern" || arg.r#type == "ObjectPattern")
-            {
-              self.raise(arg.start, String::from("Unexpected token"));
+      }
+      "ObjectPattern" | "ArrayPattern" | "RestElement" | "AssignmentPattern" => {}
+      "ObjectExpression" => {
+        node.r#type = String::from("ObjectPattern");
+        self.checkPatternErrors(refDestructuringErrors, true)?;
+        let mut properties = Vec::new();
+        for prop in node.properties.take().unwrap_or_default() {
+          let prop = self.toAssignable(prop, isBinding, None)?;
+          // Early error:
+          //   AssignmentRestProperty[Yield, Await] :
+          //     `...` DestructuringAssignmentTarget[Yield, Await]
+          //
+          //   It is a Syntax Error if |DestructuringAssignmentTarget| is an |ArrayLiteral| or an |ObjectLiteral|.
+          if prop.r#type == "RestElement" {
+            if let Some(arg) = &prop.argument {
+              if arg.r#type == "ArrayPattern" || arg.r#type == "ObjectPattern" {
+                self.raise(arg.start, ErrorKind::UnexpectedToken)?;
+              }
             }
           }
+          properties.push(prop);
         }
-        "Property" => {
-          // AssignmentProperty has type === "Property"
-          if node.kind != Some(String::from("init")) {
-            self.raise(
-              node.key.unwrap().start,
-              String::from("Object pattern can't contain getter or setter"),
-            );
-          }
-          self.toAssignable(Some(*node.value.unwrap()), isBinding, None);
+        node.properties = Some(properties);
+      }
+      "Property" => {
+        // AssignmentProperty has type === "Property"
+        if node.kind.as_deref() != Some("init") {
+          let pos = node.key.as_ref().map_or(node.start, |key| key.start);
+          self.raise(pos, ErrorKind::PatternGetterSetter)?;
         }
-        "ArrayExpression" => {
-          node.r#type = String::from("ArrayPattern");
-          if refDestructuringErrors.is_some() {
-            self.checkPatternErrors(refDestructuringErrors, true);
+        if let Some(value) = node.value.take() {
+          node.value = Some(Box::new(self.toAssignable(*value, isBinding, None)?));
+        }
+      }
+      "ArrayExpression" => {
+        node.r#type = String::from("ArrayPattern");
+        self.checkPatternErrors(refDestructuringErrors, true)?;
+        let elements = node.elements.take().unwrap_or_default();
+        node.elements = Some(self.toAssignableList(elements, isBinding)?);
+      }
+      "SpreadElement" => {
+        node.r#type = String::from("RestElement");
+        if let Some(argument) = node.argument.take() {
+          let argument = self.toAssignable(*argument, isBinding, None)?;
+          if argument.r#type == "AssignmentPattern" {
+            self.raise(argument.start, ErrorKind::RestElementDefault)?;
           }
-          self.toAssignableList(node.elements, isBinding);
-        }
-        "SpreadElement" => {
-          node.r#type = String::from("RestElement");
-          self.toAssignable(Some(*node.argument.unwrap()), isBinding, None);
-          if node.argument.unwrap().r#type == "AssignmentPattern" {
-            self.raise(
-              node.argument.unwrap().start,
-              String::from("Rest elements cannot have a default value"),
-            );
+          node.argument = Some(Box::new(argument));
+        }
+      }
+      "AssignmentExpression" => {
+        if node.operator.as_deref() != Some("=") {
+          let pos = node.left.as_ref().map_or(node.start, |left| left.end);
+          self.raise(pos, ErrorKind::InvalidDefaultOperator)?;
+        }
+        node.r#type = String::from("AssignmentPattern");
+        node.operator = None;
+        if let Some(left) = node.left.take() {
+          node.left = Some(Box::new(self.toAssignable(*left, isBinding, None)?));
+        }
+      }
+      "ParenthesizedExpression" => {
+        if let Some(expression) = node.expression.take() {
+          let expression = self.toAssignable(*expression, isBinding, refDestructuringErrors)?;
+          node.expression = Some(Box::new(expression));
+        }
+      }
+      "ChainExpression" => {
+        self.raiseRecoverable(node.start, ErrorKind::OptionalChainingLhs)?;
+      }
+      "MemberExpression" if !isBinding => {}
+      _ => {
+        self.raise(node.start, ErrorKind::AssigningToRvalue)?;
+      }
+    }
+    Ok(node)
+  }
+
+  fn toAssignableList(
+    &mut self,
+    exprList: Vec<node::Node>,
+    isBinding: bool,
+  ) -> Result<Vec<node::Node>, location::SyntaxError> {
+    let mut list = Vec::with_capacity(exprList.len());
+    for elt in exprList {
+      list.push(if elt.isHole() {
+        elt
+      } else {
+        self.toAssignable(elt, isBinding, None)?
+      });
+    }
+    if let Some(last) = list.last() {
+      if self.options.ecmaVersion == options::EcmaVersion::Ecma6 && isBinding && last.r#type == "RestElement" {
+        if let Some(argument) = &last.argument {
+          if argument.r#type != "Identifier" {
+            return self.unexpected(Some(argument.start));
           }
         }
-        "AssignmentExpression" => {
-          if node.operator != Some(String::from("=")) {
-            self.raise(
-              node.left.unwrap().end,
-              String::from("Only '=' operator can be used for specifying default value."),
-            );
+      }
+    }
+    Ok(list)
+  }
+
+  fn parseSpread(
+    &mut self,
+    refDestructuringErrors: Option<&mut parseutil::DestructuringErrors>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    self.next()?;
+    node.argument = Some(Box::new(self.parseMaybeAssign(false, refDestructuringErrors, None)?));
+    Ok(self.finishNode(node, "SpreadElement"))
+  }
+
+  fn parseRestBinding(&mut self) -> Result<node::Node, location::SyntaxError> {
+    let mut node = self.startNode();
+    self.next()?;
+
+    // RestElement inside of a function parameter must be an identifier
+    if self.options.ecmaVersion == options::EcmaVersion::Ecma6 && self.r#type != TokenType::name() {
+      return self.unexpected(None);
+    }
+    node.argument = Some(Box::new(self.parseBindingAtom()?));
+    Ok(self.finishNode(node, "RestElement"))
+  }
+
+  fn parseBindingAtom(&mut self) -> Result<node::Node, location::SyntaxError> {
+    self.enterNesting()?;
+    let destructuring = self.options.ecmaVersion >= options::EcmaVersion::Ecma6;
+    let atom = if destructuring && self.r#type == TokenType::bracketL() {
+      let mut node = self.startNode();
+      self.next()?;
+      node.elements = Some(self.parseBindingList(TokenType::bracketR(), true, true)?);
+      Ok(self.finishNode(node, "ArrayPattern"))
+    } else if destructuring && self.r#type == TokenType::braceL() {
+      self.parseObj(true, None)
+    } else {
+      self.parseIdent(false)
+    };
+    self.depth -= 1;
+    atom
+  }
+
+  fn parseBindingList(
+    &mut self,
+    close: TokenType,
+    allowEmpty: bool,
+    allowTrailingComma: bool,
+  ) -> Result<Vec<node::Node>, location::SyntaxError> {
+    let mut elts = Vec::new();
+    let mut first = true;
+    while !self.eat(close.clone())? {
+      if first {
+        first = false;
+      } else {
+        self.expect(TokenType::comma())?;
+      }
+      if allowEmpty && self.r#type == TokenType::comma() {
+        elts.push(node::Node::hole(self.start));
+      } else if allowTrailingComma && self.afterTrailingComma(close.clone(), false)? {
+        break;
+      } else if self.r#type == TokenType::ellipsis() {
+        let rest = self.parseRestBinding()?;
+        elts.push(rest);
+        if self.r#type == TokenType::comma() {
+          self.raise(self.start, ErrorKind::RestTrailingComma)?;
+        }
+        self.expect(close)?;
+        break;
+      } else {
+        let elem = self.parseMaybeDefault(self.start, self.startLoc, None)?;
+        elts.push(elem);
+      }
+    }
+    Ok(elts)
+  }
+
+  fn parseMaybeDefault(
+    &mut self,
+    startPos: usize,
+    startLoc: Option<locutil::Position>,
+    left: Option<node::Node>,
+  ) -> Result<node::Node, location::SyntaxError> {
+    let left = match left {
+      Some(left) => left,
+      None => self.parseBindingAtom()?,
+    };
+    if self.options.ecmaVersion < options::EcmaVersion::Ecma6 || !self.eat(TokenType::eq())? {
+      return Ok(left);
+    }
+    let mut node = self.startNodeAt(startPos, startLoc);
+    node.left = Some(Box::new(left));
+    node.right = Some(Box::new(self.parseMaybeAssign(false, None, None)?));
+    Ok(self.finishNode(node, "AssignmentPattern"))
+  }
+
+  fn checkLValSimple(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError> {
+    let isBind = bindingType != Binds::None;
+    match expr.r#type.as_str() {
+      "Identifier" => {
+        let name = expr.name.clone().unwrap_or_default();
+        if self.strict && (name == "eval" || name == "arguments") {
+          self.raiseRecoverable(
+            expr.start,
+            if isBind {
+              ErrorKind::StrictBinding(name.clone())
+            } else {
+              ErrorKind::StrictAssignment(name.clone())
+            },
+          )?;
+        }
+        if isBind {
+          if bindingType == Binds::Lexical && name == "let" {
+            self.raiseRecoverable(expr.start, ErrorKind::LetLexicalBinding)?;
           }
-          node.r#type = String::from("AssignmentPattern");
-          node.operator = None;
-          self.toAssignable(Some(*node.left.unwrap()), isBinding, None);
-          // TODO: clarify on the fallthrough here?
-        }
-        "AssignmentPattern" => {}
-        "ParenthesizedExpression" => {
-          self.toAssignable(
-            Some(*node.expression.unwrap()),
-            isBinding,
-            refDestructuringErrors,
-          );
-        }
-        "MemberExpression" if !isBinding => {}
-        _ => {
-          self.raise(node.start, String::from("Assigning to rvalue"));
-        }
-      }
-    } else if refDestructuringErrors.is_some() {
-      self.checkPatternErrors(refDestructuringErrors, true);
+          if let Some(checkClashes) = checkClashes {
+            if checkClashes.contains(&name) {
+              self.raiseRecoverable(expr.start, ErrorKind::ArgumentNameClash)?;
+            }
+            checkClashes.insert(name.clone());
+          }
+          if bindingType != Binds::Outside {
+            self.declareName(&name, bindingType, expr.start)?;
+          }
+        }
+        Ok(())
+      }
+      "MemberExpression" => {
+        if isBind {
+          self.raiseRecoverable(expr.start, ErrorKind::BindingMemberExpression)?;
+        }
+        Ok(())
+      }
+      "ChainExpression" => self.raiseRecoverable(expr.start, ErrorKind::OptionalChainingLhs),
+      "ParenthesizedExpression" => {
+        if isBind {
+          self.raiseRecoverable(expr.start, ErrorKind::BindingParenthesized)?;
+        }
+        match &expr.expression {
+          Some(expression) => self.checkLValSimple(expression, bindingType, checkClashes),
+          None => Ok(()),
+        }
+      }
+      _ => self.raise(
+        expr.start,
+        if isBind {
+          ErrorKind::BindingRvalue
+        } else {
+          ErrorKind::AssigningToRvalue
+        },
+      ),
+    }
+  }
+
+  fn checkLValPattern(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    mut checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError> {
+    match expr.r#type.as_str() {
+      "ObjectPattern" => {
+        for prop in expr.properties.iter().flatten() {
+          self.checkLValInnerPattern(prop, bindingType, checkClashes.as_deref_mut())?;
+        }
+        Ok(())
+      }
+      "ArrayPattern" => {
+        for elem in expr.elements.iter().flatten().filter(|elem| !elem.isHole()) {
+          self.checkLValInnerPattern(elem, bindingType, checkClashes.as_deref_mut())?;
+        }
+        Ok(())
+      }
+      _ => self.checkLValSimple(expr, bindingType, checkClashes),
+    }
+  }
+
+  fn checkLValInnerPattern(
+    &mut self,
+    expr: &node::Node,
+    bindingType: Binds,
+    checkClashes: Option<&mut HashSet<String>>,
+  ) -> Result<(), location::SyntaxError> {
+    match expr.r#type.as_str() {
+      // AssignmentProperty has type === "Property"
+      "Property" => match &expr.value {
+        Some(value) => self.checkLValInnerPattern(value, bindingType, checkClashes),
+        None => Ok(()),
+      },
+      "AssignmentPattern" => match &expr.left {
+        Some(left) => self.checkLValPattern(left, bindingType, checkClashes),
+        None => Ok(()),
+      },
+      "RestElement" => match &expr.argument {
+        Some(argument) => self.checkLValPattern(argument, bindingType, checkClashes),
+        None => Ok(()),
+      },
+      _ => self.checkLValPattern(expr, bindingType, checkClashes),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::ParserLval;
+  use crate::errorkind::ErrorKind;
+  use crate::node;
+  use crate::options;
+  use crate::scopeflags::Binds;
+  use crate::state;
+
+  fn errors(input: &str) -> Vec<ErrorKind> {
+    let (_, errors) = state::Parser::parseWithErrors(String::from(input), None).expect("input should parse");
+    errors.into_iter().map(|err| err.kind).collect()
+  }
+
+  #[test]
+  fn skipsArrayPatternHoles() {
+    assert!(errors("var [, a] = [];").is_empty());
+    assert!(errors("let [a, , b] = [];").is_empty());
+    assert!(errors("var a, b; [, a, , b] = [];").is_empty());
+    assert!(errors("function f([, a]) {}").is_empty());
+  }
+
+  #[test]
+  fn checksParameterClashes() {
+    assert!(errors("function f(a, a) {}").is_empty());
+    assert_eq!(errors("'use strict'; function f(a, a) {}"), [ErrorKind::ArgumentNameClash]);
+    assert_eq!(errors("function f(a, a) { 'use strict'; }"), [ErrorKind::ArgumentNameClash]);
+    assert_eq!(errors("function f(a, [a]) {}"), [ErrorKind::ArgumentNameClash]);
+    assert_eq!(errors("(a, a) => {};"), [ErrorKind::ArgumentNameClash]);
+    assert_eq!(errors("(a, a) => a;"), [ErrorKind::ArgumentNameClash]);
+    assert_eq!(errors("({ m(a, a) {} });"), [ErrorKind::ArgumentNameClash]);
+  }
+
+  #[test]
+  fn declaresParametersAsVar() {
+    assert!(errors("function f(a) { var a; }").is_empty());
+    assert_eq!(errors("function f(a) { let a; }"), [ErrorKind::Redeclaration(String::from("a"))]);
+  }
+
+  #[test]
+  fn functionExpressionNamesBindOutside() {
+    assert!(errors("(function f() { let f; });").is_empty());
+    assert_eq!(
+      errors("(function eval() { 'use strict'; });"),
+      [ErrorKind::StrictBinding(String::from("eval"))]
+    );
+  }
+
+  #[test]
+  fn rejectsOptionalChainingTargets() {
+    let chain = node::Node {
+      r#type: String::from("ChainExpression"),
+      ..node::Node::default()
+    };
+    let mut parser = state::Parser::new(options::Options::default(), String::from("a?.b"), None);
+    let err = parser.checkLValSimple(&chain, Binds::None, None).expect_err("chain is not assignable");
+    assert_eq!(err.kind, ErrorKind::OptionalChainingLhs);
+    match parser.toAssignable(chain, false, None) {
+      Ok(_) => panic!("chain is not assignable"),
+      Err(err) => assert_eq!(err.kind, ErrorKind::OptionalChainingLhs),
     }
-    node
   }
 }
@@ -1,15 +1,18 @@
+use crate::ast;
 use crate::locutil;
 use crate::options;
 use crate::state;
 
-#[derive(Clone)]
+#[derive(Clone, Default, PartialEq)]
 pub struct Node {
     pub r#type: String,
     pub start: usize,
     pub end: usize,
-    loc: Option<locutil::SourceLocation>,
-    sourceFile: Option<String>,
-    range: Option<(usize, usize)>,
+    pub(crate) loc: Option<locutil::SourceLocation>,
+    pub(crate) sourceFile: Option<String>,
+    pub(crate) range: Option<(usize, usize)>,
+    /// For node types whose ESTree `body` is a single node (functions,
+    /// loops, `ClassDeclaration`, ...), that node is the only element.
     pub body: Option<Vec<Node>>,
     pub local: Option<Box<Node>>,
     pub sourceType: Option<options::SourceType>,
@@ -19,6 +22,7 @@ pub struct Node {
     pub right: Option<Box<Node>>,
     pub operator: Option<String>,
     pub name: Option<String>,
+    pub raw: Option<String>,
     pub properties: Option<Vec<Node>>,
     pub argument: Option<Box<Node>>,
     pub kind: Option<String>,
@@ -29,99 +33,470 @@ pub struct Node {
 
     pub test: Option<Box<Node>>,
     pub consequent: Option<Box<Node>>,
+    /// The statements of a `SwitchCase`, whose ESTree `consequent` is a
+    /// list rather than the single node `consequent` holds.
+    pub consequents: Option<Vec<Node>>,
     pub alternate: Option<Box<Node>>,
     pub prefix: Option<bool>,
+
+    pub id: Option<Box<Node>>,
+    pub init: Option<Box<Node>>,
+    pub declarations: Option<Vec<Node>>,
+
+    pub object: Option<Box<Node>>,
+    pub property: Option<Box<Node>>,
+    pub callee: Option<Box<Node>>,
+    pub arguments: Option<Vec<Node>>,
+    pub params: Option<Vec<Node>>,
+    pub label: Option<Box<Node>>,
+    pub block: Option<Box<Node>>,
+    pub handler: Option<Box<Node>>,
+    pub finalizer: Option<Box<Node>>,
+    pub param: Option<Box<Node>>,
+    pub discriminant: Option<Box<Node>>,
+    pub cases: Option<Vec<Node>>,
+    pub update: Option<Box<Node>>,
+    pub tag: Option<Box<Node>>,
+    pub quasi: Option<Box<Node>>,
+    pub quasis: Option<Vec<Node>>,
+    pub superClass: Option<Box<Node>>,
+    pub meta: Option<Box<Node>>,
+    pub source: Option<Box<Node>>,
+    pub specifiers: Option<Vec<Node>>,
+    pub imported: Option<Box<Node>>,
+    pub exported: Option<Box<Node>>,
+    pub declaration: Option<Box<Node>>,
+
+    pub directive: Option<String>,
+    /// The cooked value of a `TemplateElement`; its raw value is `raw`.
+    pub cooked: Option<String>,
+    pub computed: Option<bool>,
+    pub method: Option<bool>,
+    pub shorthand: Option<bool>,
+    pub generator: Option<bool>,
+    pub r#async: Option<bool>,
+    pub r#static: Option<bool>,
+    pub r#await: Option<bool>,
+    pub delegate: Option<bool>,
+    pub tail: Option<bool>,
 }
 
 impl Node {
-    fn new(parser: state::Parser, pos: usize, loc: Option<locutil::Position>) -> Self {
-        let node = Node {
-            r#type: String::from(""),
+    fn new(parser: &state::Parser, pos: usize, loc: Option<locutil::Position>) -> Self {
+        let mut node = Node {
             start: pos,
-            end: 0,
-            loc: None,
-            sourceFile: None,
-            range: None,
-            body: None,
-            local: None,
-            sourceType: None,
-            expressions: Vec::new(),
-            left: None,
-            right: None,
-            operator: None,
-            name: None,
-            properties: None,
-            argument: None,
-            kind: None,
-            value: None,
-            key: None,
-            elements: None,
-            expression: None,
-            test: None,
-            consequent: None,
-            alternate: None,
-            prefix: None,
+            ..Node::default()
         };
         if parser.options.locations {
-            node.loc = Some(locutil::SourceLocation::new(&parser, loc, None));
+            node.loc = Some(locutil::SourceLocation::new(parser, loc, None));
+        }
+        if parser.options.directSourceFile.is_some() {
+            node.sourceFile = parser.options.directSourceFile.clone();
+        }
+        if parser.options.ranges {
+            node.range = Some((pos, 0));
         }
-        // TODO: finish this.
         node
     }
+
+    /// A placeholder for an elided element of an array literal or
+    /// pattern (`[a, , b]`), which ESTree represents as `null`.
+    pub fn hole(pos: usize) -> Self {
+        Node {
+            start: pos,
+            end: pos,
+            ..Node::default()
+        }
+    }
+
+    /// Whether this node is a `hole`.
+    pub fn isHole(&self) -> bool {
+        self.r#type.is_empty()
+    }
+
+    /// The node's line/column extent. Only present when the
+    /// `locations` option is on.
+    pub fn loc(&self) -> Option<&locutil::SourceLocation> {
+        self.loc.as_ref()
+    }
+
+    /// The node's `[start, end]` offsets. Only present when the
+    /// `ranges` option is on.
+    pub fn range(&self) -> Option<(usize, usize)> {
+        self.range
+    }
+
+    /// The `directSourceFile` option the node was parsed with.
+    pub fn sourceFile(&self) -> Option<&str> {
+        self.sourceFile.as_deref()
+    }
+
+    /// Move out `loc`, `sourceFile` and `range`, for `arena::Arena`.
+    pub(crate) fn takeLocation(
+        &mut self,
+    ) -> (
+        Option<locutil::SourceLocation>,
+        Option<String>,
+        Option<(usize, usize)>,
+    ) {
+        (self.loc.take(), self.sourceFile.take(), self.range.take())
+    }
+
+    pub(crate) fn setLocation(
+        &mut self,
+        loc: Option<locutil::SourceLocation>,
+        sourceFile: Option<String>,
+        range: Option<(usize, usize)>,
+    ) {
+        self.loc = loc;
+        self.sourceFile = sourceFile;
+        self.range = range;
+    }
+}
+
+impl Node {
+    /// Every direct child of this node, in source order. Which fields
+    /// hold children, and their order, come from the node table in
+    /// `ast.rs`; holes are skipped.
+    pub fn children(&self) -> Vec<&Node> {
+        let mut children: Vec<(&'static str, &Node)> = Vec::new();
+        children.extend(self.id.as_deref().map(|child| ("id", child)));
+        children.extend(self.key.as_deref().map(|child| ("key", child)));
+        children.extend(self.local.as_deref().map(|child| ("local", child)));
+        children.extend(self.test.as_deref().map(|child| ("test", child)));
+        children.extend(self.left.as_deref().map(|child| ("left", child)));
+        children.extend(self.argument.as_deref().map(|child| ("argument", child)));
+        children.extend(self.expression.as_deref().map(|child| ("expression", child)));
+        children.extend(self.expressions.iter().map(|child| ("expressions", child)));
+        children.extend(self.properties.iter().flatten().map(|child| ("properties", child)));
+        children.extend(self.elements.iter().flatten().map(|child| ("elements", child)));
+        children.extend(self.declarations.iter().flatten().map(|child| ("declarations", child)));
+        children.extend(self.right.as_deref().map(|child| ("right", child)));
+        children.extend(self.value.as_deref().map(|child| ("value", child)));
+        children.extend(self.init.as_deref().map(|child| ("init", child)));
+        children.extend(self.consequent.as_deref().map(|child| ("consequent", child)));
+        children.extend(self.consequents.iter().flatten().map(|child| ("consequent", child)));
+        children.extend(self.alternate.as_deref().map(|child| ("alternate", child)));
+        children.extend(self.object.as_deref().map(|child| ("object", child)));
+        children.extend(self.property.as_deref().map(|child| ("property", child)));
+        children.extend(self.callee.as_deref().map(|child| ("callee", child)));
+        children.extend(self.arguments.iter().flatten().map(|child| ("arguments", child)));
+        children.extend(self.params.iter().flatten().map(|child| ("params", child)));
+        children.extend(self.label.as_deref().map(|child| ("label", child)));
+        children.extend(self.block.as_deref().map(|child| ("block", child)));
+        children.extend(self.handler.as_deref().map(|child| ("handler", child)));
+        children.extend(self.param.as_deref().map(|child| ("param", child)));
+        children.extend(self.finalizer.as_deref().map(|child| ("finalizer", child)));
+        children.extend(self.discriminant.as_deref().map(|child| ("discriminant", child)));
+        children.extend(self.cases.iter().flatten().map(|child| ("cases", child)));
+        children.extend(self.update.as_deref().map(|child| ("update", child)));
+        children.extend(self.tag.as_deref().map(|child| ("tag", child)));
+        children.extend(self.quasi.as_deref().map(|child| ("quasi", child)));
+        children.extend(self.quasis.iter().flatten().map(|child| ("quasis", child)));
+        children.extend(self.superClass.as_deref().map(|child| ("superClass", child)));
+        children.extend(self.meta.as_deref().map(|child| ("meta", child)));
+        children.extend(self.source.as_deref().map(|child| ("source", child)));
+        children.extend(self.specifiers.iter().flatten().map(|child| ("specifiers", child)));
+        children.extend(self.imported.as_deref().map(|child| ("imported", child)));
+        children.extend(self.exported.as_deref().map(|child| ("exported", child)));
+        children.extend(self.declaration.as_deref().map(|child| ("declaration", child)));
+        children.extend(self.body.iter().flatten().map(|child| ("body", child)));
+        children.retain(|(_, child)| !child.isHole());
+        inSourceOrder(&self.r#type, children)
+    }
+
+    /// The innermost node in this subtree whose extent contains
+    /// `pos`. A position just past the end of a node counts as inside
+    /// it, so that a cursor right after an identifier finds it.
+    pub fn innermostAt(&self, pos: usize) -> Option<&Node> {
+        if pos < self.start || pos > self.end {
+            return None;
+        }
+        for child in self.children() {
+            if let Some(found) = child.innermostAt(pos) {
+                return Some(found);
+            }
+        }
+        Some(self)
+    }
+
+    /// Mutable references to every direct child of this node, in the
+    /// order of `children`.
+    pub fn childrenMut(&mut self) -> Vec<&mut Node> {
+        let r#type = self.r#type.clone();
+        let mut children: Vec<(&'static str, &mut Node)> = Vec::new();
+        children.extend(self.id.as_deref_mut().map(|child| ("id", child)));
+        children.extend(self.key.as_deref_mut().map(|child| ("key", child)));
+        children.extend(self.local.as_deref_mut().map(|child| ("local", child)));
+        children.extend(self.test.as_deref_mut().map(|child| ("test", child)));
+        children.extend(self.left.as_deref_mut().map(|child| ("left", child)));
+        children.extend(self.argument.as_deref_mut().map(|child| ("argument", child)));
+        children.extend(self.expression.as_deref_mut().map(|child| ("expression", child)));
+        children.extend(self.expressions.iter_mut().map(|child| ("expressions", child)));
+        children.extend(self.properties.iter_mut().flatten().map(|child| ("properties", child)));
+        children.extend(self.elements.iter_mut().flatten().map(|child| ("elements", child)));
+        children.extend(self.declarations.iter_mut().flatten().map(|child| ("declarations", child)));
+        children.extend(self.right.as_deref_mut().map(|child| ("right", child)));
+        children.extend(self.value.as_deref_mut().map(|child| ("value", child)));
+        children.extend(self.init.as_deref_mut().map(|child| ("init", child)));
+        children.extend(self.consequent.as_deref_mut().map(|child| ("consequent", child)));
+        children.extend(self.consequents.iter_mut().flatten().map(|child| ("consequent", child)));
+        children.extend(self.alternate.as_deref_mut().map(|child| ("alternate", child)));
+        children.extend(self.object.as_deref_mut().map(|child| ("object", child)));
+        children.extend(self.property.as_deref_mut().map(|child| ("property", child)));
+        children.extend(self.callee.as_deref_mut().map(|child| ("callee", child)));
+        children.extend(self.arguments.iter_mut().flatten().map(|child| ("arguments", child)));
+        children.extend(self.params.iter_mut().flatten().map(|child| ("params", child)));
+        children.extend(self.label.as_deref_mut().map(|child| ("label", child)));
+        children.extend(self.block.as_deref_mut().map(|child| ("block", child)));
+        children.extend(self.handler.as_deref_mut().map(|child| ("handler", child)));
+        children.extend(self.param.as_deref_mut().map(|child| ("param", child)));
+        children.extend(self.finalizer.as_deref_mut().map(|child| ("finalizer", child)));
+        children.extend(self.discriminant.as_deref_mut().map(|child| ("discriminant", child)));
+        children.extend(self.cases.iter_mut().flatten().map(|child| ("cases", child)));
+        children.extend(self.update.as_deref_mut().map(|child| ("update", child)));
+        children.extend(self.tag.as_deref_mut().map(|child| ("tag", child)));
+        children.extend(self.quasi.as_deref_mut().map(|child| ("quasi", child)));
+        children.extend(self.quasis.iter_mut().flatten().map(|child| ("quasis", child)));
+        children.extend(self.superClass.as_deref_mut().map(|child| ("superClass", child)));
+        children.extend(self.meta.as_deref_mut().map(|child| ("meta", child)));
+        children.extend(self.source.as_deref_mut().map(|child| ("source", child)));
+        children.extend(self.specifiers.iter_mut().flatten().map(|child| ("specifiers", child)));
+        children.extend(self.imported.as_deref_mut().map(|child| ("imported", child)));
+        children.extend(self.exported.as_deref_mut().map(|child| ("exported", child)));
+        children.extend(self.declaration.as_deref_mut().map(|child| ("declaration", child)));
+        children.extend(self.body.iter_mut().flatten().map(|child| ("body", child)));
+        children.retain(|(_, child)| !child.isHole());
+        inSourceOrder(&r#type, children)
+    }
+
+    /// Rewrite every offset in this subtree — `start`, `end`, `range`
+    /// and the columns of `loc` — through `f`.
+    pub fn mapOffsets(&mut self, f: &dyn Fn(usize) -> usize) {
+        if let Some(loc) = &mut self.loc {
+            if let Some(start) = &mut loc.start {
+                start.column = f(self.start) - f(self.start - start.column);
+            }
+            if let Some(end) = &mut loc.end {
+                end.column = f(self.end) - f(self.end - end.column);
+            }
+        }
+        self.start = f(self.start);
+        self.end = f(self.end);
+        if let Some((start, end)) = self.range {
+            self.range = Some((f(start), f(end)));
+        }
+        for child in self.childrenMut() {
+            child.mapOffsets(f);
+        }
+    }
+}
+
+/// Order `children`, each given with the ESTree name of the field it
+/// is in, as the node table lists the fields of `r#type`, dropping
+/// fields the type does not have. The quasis and expressions of a
+/// `TemplateLiteral` alternate, as they do in the source. For a type
+/// not in the table, the order is kept.
+pub(crate) fn inSourceOrder<T>(r#type: &str, mut children: Vec<(&'static str, T)>) -> Vec<T> {
+    let fields = match ast::fieldsOf(r#type) {
+        Some(fields) => fields,
+        None => return children.into_iter().map(|(_, child)| child).collect(),
+    };
+    let rank = |name: &str| fields.childFields().position(|(field, _)| field == name);
+    children.retain(|(name, _)| rank(name).is_some());
+    children.sort_by_key(|(name, _)| rank(name));
+    if r#type != ast::TemplateLiteral::TYPE {
+        return children.into_iter().map(|(_, child)| child).collect();
+    }
+    let (quasis, expressions): (Vec<_>, Vec<_>) =
+        children.into_iter().partition(|(name, _)| *name == "quasis");
+    let mut expressions = expressions.into_iter();
+    let mut ordered = Vec::new();
+    for (_, quasi) in quasis {
+        ordered.push(quasi);
+        ordered.extend(expressions.next().map(|(_, expression)| expression));
+    }
+    ordered.extend(expressions.map(|(_, expression)| expression));
+    ordered
 }
 
 pub trait ParserNode {
-    fn startNode(self) -> Node;
-    fn startNodeAt(self, pos: usize, loc: Option<locutil::Position>) -> Node;
-    fn finishNode(self, node: Node, r#type: String) -> Node;
+    fn startNode(&self) -> Node;
+    fn startNodeAt(&self, pos: usize, loc: Option<locutil::Position>) -> Node;
+    fn finishNode(&self, node: Node, r#type: &str) -> Node;
     fn finishNodeAt(
-        self,
+        &self,
         node: Node,
-        r#type: String,
+        r#type: &str,
         pos: usize,
         loc: Option<locutil::Position>,
     ) -> Node;
 }
 
 impl ParserNode for state::Parser {
-    fn startNode(self) -> Node {
+    fn startNode(&self) -> Node {
         Node::new(self, self.start, self.startLoc)
     }
 
-    fn startNodeAt(self, pos: usize, loc: Option<locutil::Position>) -> Node {
+    fn startNodeAt(&self, pos: usize, loc: Option<locutil::Position>) -> Node {
         Node::new(self, pos, loc)
     }
 
-    fn finishNode(self, node: Node, r#type: String) -> Node {
-        finishNodeAt(self, node, r#type, self.lastTokEnd, self.lastTokEndLoc)
+    fn finishNode(&self, node: Node, r#type: &str) -> Node {
+        finishNodeAt(node, r#type, self.lastTokEnd, self.lastTokEndLoc)
     }
 
     fn finishNodeAt(
-        self,
+        &self,
         node: Node,
-        r#type: String,
+        r#type: &str,
         pos: usize,
         loc: Option<locutil::Position>,
     ) -> Node {
-        finishNodeAt(self, node, r#type, pos, loc)
+        finishNodeAt(node, r#type, pos, loc)
     }
 }
 
 // Finish an AST node, adding `type` and `end` properties.
 fn finishNodeAt(
-    parser: state::Parser,
-    node: Node,
-    r#type: String,
+    mut node: Node,
+    r#type: &str,
     pos: usize,
     loc: Option<locutil::Position>,
 ) -> Node {
-    node.r#type = r#type;
+    node.r#type = String::from(r#type);
     node.end = pos;
-    if parser.options.locations {
-        node.loc.unwrap().end = loc;
+    if let Some(nodeLoc) = &mut node.loc {
+        nodeLoc.end = loc;
     }
-    if parser.options.ranges {
-        node.range.unwrap().1 = pos;
+    if let Some(range) = &mut node.range {
+        range.1 = pos;
     }
     node
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::arena;
+    use crate::locutil;
+    use crate::options;
+    use crate::state;
+
+    fn parse(input: &str) -> super::Node {
+        match state::Parser::parse(String::from(input), None) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn starts(children: &[&super::Node]) -> Vec<usize> {
+        children.iter().map(|child| child.start).collect()
+    }
+
+    fn assertSourceOrder(node: &super::Node) {
+        let children = node.children();
+        let mut sorted = starts(&children);
+        sorted.sort_unstable();
+        assert_eq!(starts(&children), sorted, "children of {}", node.r#type);
+        for child in children {
+            assertSourceOrder(child);
+        }
+    }
+
+    #[test]
+    fn childrenAreInSourceOrder() {
+        let program = parse(
+            "do x(); while (y); for (var i = 0; i < n; i++) f(i); `a${b}c${d}e`; \
+             label: a ? b : c; [, a, , b]; class C extends D { m() {} } \
+             switch (x) { case 1: a(); b(); } tag`x${y}`;",
+        );
+        assertSourceOrder(&program);
+
+        let template = &program.body.as_ref().unwrap()[2];
+        let literal = template.expression.as_deref().unwrap();
+        let types: Vec<&str> = literal.children().iter().map(|child| child.r#type.as_str()).collect();
+        assert_eq!(
+            types,
+            ["TemplateElement", "Identifier", "TemplateElement", "Identifier", "TemplateElement"]
+        );
+    }
+
+    #[test]
+    fn childrenSkipHoles() {
+        let program = parse("[, a, , b];");
+        let array = program.body.as_ref().unwrap()[0].expression.as_deref().unwrap();
+        assert_eq!(starts(&array.children()), [3, 8]);
+    }
+
+    #[test]
+    fn childrenMutAndTheArenaAgreeWithChildren() {
+        let mut program = parse("for (let x of `a${b}c`) { if (x) y(); else z(); }");
+        let expected: Vec<(usize, String)> = program
+            .children()
+            .iter()
+            .flat_map(|statement| statement.children())
+            .map(|child| (child.start, child.r#type.clone()))
+            .collect();
+
+        let statement = &mut program.body.as_mut().unwrap()[0];
+        let found: Vec<(usize, String)> = statement
+            .childrenMut()
+            .iter()
+            .map(|child| (child.start, child.r#type.clone()))
+            .collect();
+        assert_eq!(found, expected);
+
+        let (arena, root) = arena::Arena::fromNode(program);
+        let statement = arena.children(root)[0];
+        let found: Vec<(usize, String)> = arena
+            .children(statement)
+            .into_iter()
+            .map(|child| (arena[child].start, arena[child].r#type.to_string()))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    fn parseWith(input: &str, options: options::Options) -> super::Node {
+        match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn fillsInLocationsRangesAndSourceFiles() {
+        let options = options::Options::builder()
+            .locations(true)
+            .ranges(true)
+            .sourceFile(String::from("a.js"))
+            .directSourceFile(String::from("b.js"))
+            .build()
+            .unwrap();
+        let program = parseWith("a;
+  b + c;", options);
+        let statement = &program.body.as_ref().unwrap()[1];
+        let loc = statement.loc().unwrap();
+        assert_eq!(loc.start, Some(locutil::Position::new(2, 2)));
+        assert_eq!(loc.end, Some(locutil::Position::new(2, 8)));
+        assert_eq!(loc.source.as_deref(), Some("a.js"));
+        assert_eq!(statement.range(), Some((5, 11)));
+        assert_eq!(statement.sourceFile(), Some("b.js"));
+        assert_eq!(program.range(), Some((0, 11)));
+    }
+
+    #[test]
+    fn leavesThemOutUnlessAskedFor() {
+        let program = parse("a;
+  b + c;");
+        let statement = &program.body.as_ref().unwrap()[1];
+        assert!(statement.loc().is_none());
+        assert!(statement.range().is_none());
+        assert!(statement.sourceFile().is_none());
+
+        // Each option works without the others.
+        let options = options::Options::builder().ranges(true).build().unwrap();
+        let program = parseWith("a;", options);
+        assert!(program.loc().is_none());
+        assert_eq!(program.range(), Some((0, 2)));
+    }
+}
@@ -1,13 +1,21 @@
 use crate::locutil;
 use crate::node;
 
-#[derive(PartialEq, PartialOrd)]
+use std::cmp::Ordering;
+use std::error;
+use std::fmt;
+
+/// The ECMAScript version to parse. Editions can be named by number or
+/// by year: `Ecma6` and `Ecma2015` are the same version, and compare
+/// equal.
+#[derive(Clone, Copy, Debug, Default)]
 pub enum EcmaVersion {
     Ecma3,
     Ecma5,
     Ecma6,
     Ecma7,
     Ecma8,
+    #[default]
     Ecma9,
     Ecma10,
     Ecma2015,
@@ -15,28 +23,83 @@ pub enum EcmaVersion {
     Ecma2017,
     Ecma2018,
     Ecma2019,
+    /// The newest version the parser supports: `Ecma2019` plus BigInt
+    /// literals. It compares greater than every named version, so that
+    /// features newer than those are enabled by it alone. Later syntax
+    /// such as `?.`, `??`, `import()`, `import.meta`, class fields and
+    /// `||=` is not supported yet.
+    Latest,
+}
+
+impl EcmaVersion {
+    /// The edition number: 3, 5, 6 for ES2015, and so on.
+    pub fn edition(self) -> u32 {
+        match self {
+            EcmaVersion::Ecma3 => 3,
+            EcmaVersion::Ecma5 => 5,
+            EcmaVersion::Ecma6 | EcmaVersion::Ecma2015 => 6,
+            EcmaVersion::Ecma7 | EcmaVersion::Ecma2016 => 7,
+            EcmaVersion::Ecma8 | EcmaVersion::Ecma2017 => 8,
+            EcmaVersion::Ecma9 | EcmaVersion::Ecma2018 => 9,
+            EcmaVersion::Ecma10 | EcmaVersion::Ecma2019 => 10,
+            EcmaVersion::Latest => u32::MAX,
+        }
+    }
+}
+
+impl PartialEq for EcmaVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.edition() == other.edition()
+    }
+}
+
+impl Eq for EcmaVersion {}
+
+impl PartialOrd for EcmaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-impl Default for EcmaVersion {
-    fn default() -> Self {
-        EcmaVersion::Ecma9
+impl Ord for EcmaVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.edition().cmp(&other.edition())
     }
 }
 
-#[derive(PartialEq, Clone)]
+
+#[derive(PartialEq, Clone, Debug, Default)]
 pub enum SourceType {
+    #[default]
     Script,
     Module,
+    /// A Node.js CommonJS module: a script whose top level is the body
+    /// of the module wrapper function, so `return` is allowed there and
+    /// `require`, `module`, `exports`, `__filename` and `__dirname` are
+    /// already declared.
+    CommonJS,
 }
 
-impl Default for SourceType {
-    fn default() -> Self {
-        SourceType::Script
-    }
+
+/// The unit in which `start`/`end` offsets and `Position.column` are
+/// reported. The parser works on UTF-8 byte offsets internally and
+/// converts them on the way out.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum PositionEncoding {
+    /// Byte offsets into the UTF-8 input, suitable for slicing it from
+    /// Rust.
+    Utf8,
+    /// UTF-16 code units, matching the offsets acorn reports for the
+    /// same input.
+    #[default]
+    Utf16,
 }
 
+
 // TODO(ryzokuken): onToken
-// TODO(ryzokuken): onComment
+/// Parser options. Options without a public field are set through
+/// `Options::builder()`; those left unset get acorn's defaults, which
+/// the accessor of the same name resolves.
 #[derive(Default)]
 pub struct Options {
     pub ecmaVersion: EcmaVersion,
@@ -46,12 +109,300 @@ pub struct Options {
     allowReserved: Option<bool>,
     allowReturnOutsideFunction: bool,
     allowImportExportEverywhere: bool,
-    pub allowAwaitOutsideFunction: bool,
+    allowAwaitOutsideFunction: bool,
     allowHashBang: bool,
     pub locations: bool,
     pub ranges: bool,
     pub program: Option<node::Node>,
     pub sourceFile: Option<String>,
-    directSourceFile: Option<String>,
-    preserveParens: bool,
+    /// When set, every node gets a `sourceFile` property with this
+    /// value. Unlike `sourceFile`, it does not require `locations`.
+    pub directSourceFile: Option<String>,
+    /// When `true`, parenthesized expressions are represented by
+    /// (non-standard) `ParenthesizedExpression` nodes instead of
+    /// being dropped from the tree.
+    pub preserveParens: bool,
+    /// When `true`, errors raised through `raiseRecoverable` are pushed
+    /// onto `Parser.recoverableErrors` instead of aborting the parse.
+    /// Only `Parser::parseWithErrors` sets it, since it is the only
+    /// entry point that returns what was collected.
+    pub(crate) collectRecoverableErrors: bool,
+    pub positionEncoding: PositionEncoding,
+    /// When `true`, every comment is recorded in `Parser.comments`, as
+    /// passing an array as acorn's `onComment` does.
+    pub collectComments: bool,
+}
+
+impl Options {
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder::default()
+    }
+
+    /// Whether reserved words may be used as identifiers. Defaults to
+    /// `true` for ECMAScript 3 only.
+    pub fn allowReserved(&self) -> bool {
+        self.allowReserved
+            .unwrap_or(self.ecmaVersion < EcmaVersion::Ecma5)
+    }
+
+    /// Whether `return` is allowed at the top level. Always `true` for
+    /// CommonJS.
+    pub fn allowReturnOutsideFunction(&self) -> bool {
+        self.allowReturnOutsideFunction || self.sourceType == SourceType::CommonJS
+    }
+
+    /// Whether `import` and `export` are allowed anywhere, rather than
+    /// only at the top level of a module.
+    pub fn allowImportExportEverywhere(&self) -> bool {
+        self.allowImportExportEverywhere
+    }
+
+    /// Whether `await` is allowed outside of async functions. acorn
+    /// turns this on for modules from ES2022, which has top-level
+    /// `await`; no version the parser supports does, so it is only on
+    /// when asked for.
+    pub fn allowAwaitOutsideFunction(&self) -> bool {
+        self.allowAwaitOutsideFunction
+    }
+
+    /// Whether a `#!` line at the start of the input is skipped as a
+    /// comment. acorn turns this on from ES2023, which has hashbang
+    /// comments; no version the parser supports does, so it is only on
+    /// when asked for.
+    pub fn allowHashBang(&self) -> bool {
+        self.allowHashBang
+    }
+
+    pub fn onInsertedSemicolon(&self) -> Option<fn(u32, Option<locutil::Position>) -> ()> {
+        self.onInsertedSemicolon
+    }
+
+    pub fn onTrailingComma(&self) -> Option<fn(u32, Option<locutil::Position>) -> ()> {
+        self.onTrailingComma
+    }
+
+    /// Check that the options make sense together.
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.sourceType == SourceType::Module && self.ecmaVersion < EcmaVersion::Ecma6 {
+            return Err(OptionsError::ModuleBeforeEcma6);
+        }
+        if self.allowAwaitOutsideFunction && self.ecmaVersion < EcmaVersion::Ecma8 {
+            return Err(OptionsError::AwaitBeforeEcma8);
+        }
+        if self.sourceFile.is_some() && !self.locations {
+            return Err(OptionsError::SourceFileWithoutLocations);
+        }
+        if let Some(program) = &self.program {
+            if program.r#type != "Program" {
+                return Err(OptionsError::ProgramNotProgram(program.r#type.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// A string that differs between any two sets of options that can
+    /// produce different trees for the same input, for keying caches.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "{} {:?} {} {} {} {} {} {} {} {} {} {:?} {:?} {:?}",
+            self.ecmaVersion.edition(),
+            self.sourceType,
+            self.allowReserved(),
+            self.allowReturnOutsideFunction,
+            self.allowImportExportEverywhere,
+            self.allowAwaitOutsideFunction(),
+            self.allowHashBang(),
+            self.locations,
+            self.ranges,
+            self.preserveParens,
+            self.program.is_some(),
+            self.sourceFile,
+            self.directSourceFile,
+            self.positionEncoding,
+        )
+    }
+}
+
+/// Why `OptionsBuilder::build` rejected a set of options.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionsError {
+    /// `sourceType` is `Module` but `ecmaVersion` predates modules.
+    ModuleBeforeEcma6,
+    /// `allowAwaitOutsideFunction` is set but `ecmaVersion` predates
+    /// `await`.
+    AwaitBeforeEcma8,
+    /// `sourceFile` is only recorded in `loc`, which `locations` turns
+    /// on.
+    SourceFileWithoutLocations,
+    /// `program` is a node of another type.
+    ProgramNotProgram(String),
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptionsError::ModuleBeforeEcma6 => {
+                write!(f, "sourceType module requires ecmaVersion 6 or later")
+            }
+            OptionsError::AwaitBeforeEcma8 => {
+                write!(
+                    f,
+                    "allowAwaitOutsideFunction requires ecmaVersion 8 or later"
+                )
+            }
+            OptionsError::SourceFileWithoutLocations => {
+                write!(f, "sourceFile has no effect without locations")
+            }
+            OptionsError::ProgramNotProgram(r#type) => {
+                write!(f, "program must be a Program node, not {}", r#type)
+            }
+        }
+    }
+}
+
+impl error::Error for OptionsError {}
+
+/// Builds `Options`, checking them with `Options::validate`.
+///
+/// ```ignore
+/// let options = Options::builder()
+///     .ecmaVersion(EcmaVersion::Latest)
+///     .sourceType(SourceType::Module)
+///     .locations(true)
+///     .build()?;
+/// ```
+#[derive(Default)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl OptionsBuilder {
+    pub fn ecmaVersion(mut self, ecmaVersion: EcmaVersion) -> Self {
+        self.options.ecmaVersion = ecmaVersion;
+        self
+    }
+
+    pub fn sourceType(mut self, sourceType: SourceType) -> Self {
+        self.options.sourceType = sourceType;
+        self
+    }
+
+    pub fn onInsertedSemicolon(mut self, callback: fn(u32, Option<locutil::Position>)) -> Self {
+        self.options.onInsertedSemicolon = Some(callback);
+        self
+    }
+
+    pub fn onTrailingComma(mut self, callback: fn(u32, Option<locutil::Position>)) -> Self {
+        self.options.onTrailingComma = Some(callback);
+        self
+    }
+
+    pub fn allowReserved(mut self, allow: bool) -> Self {
+        self.options.allowReserved = Some(allow);
+        self
+    }
+
+    pub fn allowReturnOutsideFunction(mut self, allow: bool) -> Self {
+        self.options.allowReturnOutsideFunction = allow;
+        self
+    }
+
+    pub fn allowImportExportEverywhere(mut self, allow: bool) -> Self {
+        self.options.allowImportExportEverywhere = allow;
+        self
+    }
+
+    pub fn allowAwaitOutsideFunction(mut self, allow: bool) -> Self {
+        self.options.allowAwaitOutsideFunction = allow;
+        self
+    }
+
+    pub fn allowHashBang(mut self, allow: bool) -> Self {
+        self.options.allowHashBang = allow;
+        self
+    }
+
+    pub fn locations(mut self, locations: bool) -> Self {
+        self.options.locations = locations;
+        self
+    }
+
+    pub fn ranges(mut self, ranges: bool) -> Self {
+        self.options.ranges = ranges;
+        self
+    }
+
+    pub fn program(mut self, program: node::Node) -> Self {
+        self.options.program = Some(program);
+        self
+    }
+
+    pub fn sourceFile(mut self, sourceFile: String) -> Self {
+        self.options.sourceFile = Some(sourceFile);
+        self
+    }
+
+    pub fn directSourceFile(mut self, directSourceFile: String) -> Self {
+        self.options.directSourceFile = Some(directSourceFile);
+        self
+    }
+
+    pub fn preserveParens(mut self, preserveParens: bool) -> Self {
+        self.options.preserveParens = preserveParens;
+        self
+    }
+
+    pub fn positionEncoding(mut self, positionEncoding: PositionEncoding) -> Self {
+        self.options.positionEncoding = positionEncoding;
+        self
+    }
+
+    pub fn collectComments(mut self, collect: bool) -> Self {
+        self.options.collectComments = collect;
+        self
+    }
+
+    pub fn build(self) -> Result<Options, OptionsError> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builderValidatesOptions() {
+        let built = Options::builder()
+            .ecmaVersion(EcmaVersion::Ecma5)
+            .sourceType(SourceType::Module)
+            .build();
+        assert_eq!(built.err(), Some(OptionsError::ModuleBeforeEcma6));
+        let built = Options::builder().sourceFile(String::from("a.js")).build();
+        assert_eq!(built.err(), Some(OptionsError::SourceFileWithoutLocations));
+        assert!(Options::builder().ecmaVersion(EcmaVersion::Latest).build().is_ok());
+    }
+
+    #[test]
+    fn latestComparesAboveEveryEdition() {
+        assert!(EcmaVersion::Latest > EcmaVersion::Ecma2019);
+        assert_eq!(EcmaVersion::Ecma6, EcmaVersion::Ecma2015);
+        let latest = Options::builder()
+            .ecmaVersion(EcmaVersion::Latest)
+            .sourceType(SourceType::Module)
+            .build()
+            .unwrap();
+        // Nothing `Latest` parses has hashbangs or top-level `await`.
+        assert!(!latest.allowHashBang());
+        assert!(!latest.allowAwaitOutsideFunction());
+        let opted = Options::builder()
+            .ecmaVersion(EcmaVersion::Latest)
+            .allowHashBang(true)
+            .allowAwaitOutsideFunction(true)
+            .build()
+            .unwrap();
+        assert!(opted.allowHashBang());
+        assert!(opted.allowAwaitOutsideFunction());
+    }
 }
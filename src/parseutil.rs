@@ -1,116 +1,358 @@
+use crate::errorkind;
 use crate::location;
+use crate::node;
 use crate::state;
+use crate::tokenize;
 use crate::tokentype;
+use crate::whitespace;
 
+use errorkind::ErrorKind;
 use location::ParserLocation;
+use tokenize::ParserTokenize;
+
+/// The hint given when an arrow function's `=>` starts a new line.
+pub const ARROW_ON_NEW_LINE: &str =
+  "'=>' must be on the same line as the arrow function's parameters";
+
+/// The hint given when a keyword is used where a name is needed.
+pub fn reservedWordHint(word: &str) -> String {
+  format!("'{}' is a reserved word and cannot be used as an identifier", word)
+}
+
+/// How deeply statements, expressions and binding patterns may nest.
+/// The parser recurses once per level, and an optimized build uses up
+/// to about 15 KiB of stack for each, so this stays well inside the
+/// default 8 MiB main-thread stack; real programs nest far less.
+pub const MAX_NESTING_DEPTH: usize = 256;
 
 pub trait ParserParseUtil {
   /// Tests whether parsed token is a contextual keyword.
-  fn isContextual(self, name: &str) -> bool;
+  fn isContextual(&self, name: &str) -> bool;
+
+  /// Consumes contextual keyword if possible.
+  fn eatContextual(&mut self, name: &str) -> Result<bool, location::SyntaxError>;
+
+  /// Asserts that following token is given contextual keyword.
+  fn expectContextual(&mut self, name: &str) -> Result<(), location::SyntaxError>;
 
   /// Predicate that tests whether the next token is of the given
   /// type, and if yes, consumes it as a side effect.
-  fn eat(self, r#type: tokentype::TokenType) -> bool;
+  fn eat(&mut self, r#type: tokentype::TokenType) -> Result<bool, location::SyntaxError>;
 
   /// Expect a token of a given type. If found, consume it, otherwise,
-  /// raise an unexpected token error.
-  fn expect(self, r#type: tokentype::TokenType) -> Result<(), location::SyntaxError>;
+  /// raise an error naming both the expected and the found token.
+  fn expect(&mut self, r#type: tokentype::TokenType) -> Result<(), location::SyntaxError>;
+
+  /// Describe the current token the way it is written in the source,
+  /// for use in error messages.
+  fn describeToken(&self) -> String;
+
+  /// Suggest a fix for a failed `expect`, for mistakes common enough to
+  /// be worth recognising.
+  fn expectedHint(&self, expected: &tokentype::TokenType) -> Option<String>;
+
+  /// Test whether a semicolon can be inserted at the current
+  /// position.
+  fn canInsertSemicolon(&self) -> bool;
+
+  /// Like `canInsertSemicolon`, but also reports the inserted
+  /// semicolon to `onInsertedSemicolon`.
+  fn insertSemicolon(&self) -> bool;
+
+  /// Consume a semicolon, or, failing that, see if we are allowed to
+  /// pretend that there is one.
+  fn semicolon(&mut self) -> Result<(), location::SyntaxError>;
 
-  /// Raise an unexpected token error.
-  fn unexpected(self, pos: Option<usize>) -> Result<(), location::SyntaxError>;
+  fn afterTrailingComma(
+    &mut self,
+    tokType: tokentype::TokenType,
+    notNext: bool,
+  ) -> Result<bool, location::SyntaxError>;
 
-  fn checkPatternErrors(self, refDetructuringErrors: Option<DestructuringErrors>, isAssign: bool);
+  /// Raise an unexpected token error. Generic over the success type so
+  /// that it can stand in for whatever the caller returns.
+  fn unexpected<T>(&mut self, pos: Option<usize>) -> Result<T, location::SyntaxError>;
+
+  fn checkPatternErrors(
+    &mut self,
+    refDestructuringErrors: Option<&DestructuringErrors>,
+    isAssign: bool,
+  ) -> Result<(), location::SyntaxError>;
 
   fn checkExpressionErrors(
-    self,
-    refDetructuringErrors: Option<DestructuringErrors>,
+    &mut self,
+    refDestructuringErrors: Option<&DestructuringErrors>,
     andThrow: bool,
-  ) -> bool;
+  ) -> Result<bool, location::SyntaxError>;
+
+  fn checkYieldAwaitInDefaultParams(&mut self) -> Result<(), location::SyntaxError>;
+
+  fn isSimpleAssignTarget(&self, expr: &node::Node) -> bool;
+
+  /// Open one more level of nesting, failing instead of recursing
+  /// further once `MAX_NESTING_DEPTH` levels are open. The caller
+  /// closes it with `self.depth -= 1` once it is done; after an error
+  /// the parse is abandoned, so the count is not restored then.
+  fn enterNesting(&mut self) -> Result<(), location::SyntaxError>;
+
+  /// Whether the directive prologue starting at `start` contains a
+  /// `"use strict"` directive.
+  fn strictDirective(&self, start: usize) -> bool;
 }
 
 impl ParserParseUtil for state::Parser {
-  fn isContextual(self, name: &str) -> bool {
+  fn isContextual(&self, name: &str) -> bool {
     self.r#type == tokentype::TokenType::name()
-      && self.value.unwrap().as_str() == name
+      && self.value.as_deref() == Some(name)
       && !self.containsEsc
   }
 
-  fn eat(self, tt: tokentype::TokenType) -> bool {
+  fn eatContextual(&mut self, name: &str) -> Result<bool, location::SyntaxError> {
+    if !self.isContextual(name) {
+      return Ok(false);
+    }
+    self.next()?;
+    Ok(true)
+  }
+
+  fn expectContextual(&mut self, name: &str) -> Result<(), location::SyntaxError> {
+    if !self.eatContextual(name)? {
+      return self.unexpected(None);
+    }
+    Ok(())
+  }
+
+  fn eat(&mut self, tt: tokentype::TokenType) -> Result<bool, location::SyntaxError> {
     if self.r#type == tt {
-      self.next();
-      true
+      self.next()?;
+      Ok(true)
     } else {
-      false
+      Ok(false)
     }
   }
 
-  fn expect(self, tt: tokentype::TokenType) -> Result<(), location::SyntaxError> {
-    if self.eat(tt) {
-      Ok(())
-    } else {
-      self.unexpected(None)
+  fn expect(&mut self, tt: tokentype::TokenType) -> Result<(), location::SyntaxError> {
+    if self.eat(tt.clone())? {
+      return Ok(());
     }
+    let help = self.expectedHint(&tt);
+    let kind = ErrorKind::Expected {
+      expected: String::from(tt.label),
+      found: self.describeToken(),
+    };
+    self.raise(self.start, kind).map_err(|mut err| {
+      err.details.help = help;
+      err
+    })
   }
 
-  fn unexpected(self, pos: Option<usize>) -> Result<(), location::SyntaxError> {
-    self.raise(pos.unwrap_or(self.start), String::from("Unexpected token"))
+  fn describeToken(&self) -> String {
+    if self.r#type == tokentype::TokenType::eof() {
+      return String::from("end of input");
+    }
+    match &self.value {
+      Some(value) => value.clone(),
+      None => String::from(self.r#type.label),
+    }
   }
 
-  fn checkExpressionErrors(
-    self,
-    refDetructuringErrors: Option<DestructuringErrors>,
-    andThrow: bool,
-  ) -> bool {
-    if refDetructuringErrors.is_none() {
+  fn expectedHint(&self, expected: &tokentype::TokenType) -> Option<String> {
+    let found = &self.r#type;
+    if *found == tokentype::TokenType::arrow()
+      && whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start])
+    {
+      return Some(String::from(ARROW_ON_NEW_LINE));
+    }
+    if *expected == tokentype::TokenType::name() && !found.keyword.is_empty() {
+      return Some(reservedWordHint(found.keyword));
+    }
+    if *expected == tokentype::TokenType::parenR() {
+      return Some(String::from("an opening '(' is missing its closing ')'"));
+    }
+    if *expected == tokentype::TokenType::braceR() {
+      return Some(String::from("an opening '{' is missing its closing '}'"));
+    }
+    if *expected == tokentype::TokenType::bracketR() {
+      return Some(String::from("an opening '[' is missing its closing ']'"));
+    }
+    None
+  }
+
+  fn canInsertSemicolon(&self) -> bool {
+    self.r#type == tokentype::TokenType::eof()
+      || self.r#type == tokentype::TokenType::braceR()
+      || whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start])
+  }
+
+  fn insertSemicolon(&self) -> bool {
+    if !self.canInsertSemicolon() {
       return false;
     }
-    let shorthandAssign = refDetructuringErrors.unwrap().shorthandAssign;
-    let doubleProto = refDetructuringErrors.unwrap().doubleProto;
+    if let Some(onInsertedSemicolon) = self.options.onInsertedSemicolon() {
+      onInsertedSemicolon(self.lastTokEnd as u32, self.lastTokEndLoc);
+    }
+    true
+  }
+
+  fn semicolon(&mut self) -> Result<(), location::SyntaxError> {
+    if !self.eat(tokentype::TokenType::semi())? && !self.insertSemicolon() {
+      return self.unexpected(None);
+    }
+    Ok(())
+  }
+
+  fn afterTrailingComma(
+    &mut self,
+    tokType: tokentype::TokenType,
+    notNext: bool,
+  ) -> Result<bool, location::SyntaxError> {
+    if self.r#type != tokType {
+      return Ok(false);
+    }
+    if let Some(onTrailingComma) = self.options.onTrailingComma() {
+      onTrailingComma(self.lastTokStart as u32, self.lastTokStartLoc);
+    }
+    if !notNext {
+      self.next()?;
+    }
+    Ok(true)
+  }
+
+  fn unexpected<T>(&mut self, pos: Option<usize>) -> Result<T, location::SyntaxError> {
+    let mut err = self.error(pos.unwrap_or(self.start), ErrorKind::UnexpectedToken);
+    if pos.is_none()
+      && self.r#type == tokentype::TokenType::arrow()
+      && whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start])
+    {
+      err.details.help = Some(String::from(ARROW_ON_NEW_LINE));
+    }
+    Err(err)
+  }
+
+  fn checkExpressionErrors(
+    &mut self,
+    refDestructuringErrors: Option<&DestructuringErrors>,
+    andThrow: bool,
+  ) -> Result<bool, location::SyntaxError> {
+    let (shorthandAssign, doubleProto) = match refDestructuringErrors {
+      Some(errors) => (errors.shorthandAssign, errors.doubleProto),
+      None => return Ok(false),
+    };
     if !andThrow {
-      return shorthandAssign >= 0 || doubleProto >= 0;
+      return Ok(shorthandAssign >= 0 || doubleProto >= 0);
     }
     if shorthandAssign >= 0 {
-      self.raise(
-        shorthandAssign as usize,
-        String::from("Shorthand property assignments are valid only in destructuring patterns"),
-      );
+      self.raise(shorthandAssign as usize, ErrorKind::ShorthandAssign)?;
     }
     if doubleProto >= 0 {
-      self.raiseRecoverable(
-        doubleProto as usize,
-        String::from("Redefinition of __proto__ property"),
-      );
+      self.raiseRecoverable(doubleProto as usize, ErrorKind::DuplicateProto)?;
     }
-    false
+    Ok(false)
   }
 
-  fn checkPatternErrors(self, refDetructuringErrors: Option<DestructuringErrors>, isAssign: bool) {
-    if refDetructuringErrors.is_some() {
-      let refDetructuringErrors = refDetructuringErrors.unwrap();
-      if refDetructuringErrors.trailingComma > -1 {
+  fn checkPatternErrors(
+    &mut self,
+    refDestructuringErrors: Option<&DestructuringErrors>,
+    isAssign: bool,
+  ) -> Result<(), location::SyntaxError> {
+    if let Some(refDestructuringErrors) = refDestructuringErrors {
+      if refDestructuringErrors.trailingComma > -1 {
         self.raiseRecoverable(
-          refDetructuringErrors.trailingComma as usize,
-          String::from("Comma is not permitted after the rest element"),
-        );
+          refDestructuringErrors.trailingComma as usize,
+          ErrorKind::RestTrailingComma,
+        )?;
       }
       let parens = if isAssign {
-        refDetructuringErrors.parenthesizedAssign
+        refDestructuringErrors.parenthesizedAssign
       } else {
-        refDetructuringErrors.parenthesizedBind
+        refDestructuringErrors.parenthesizedBind
       };
       if parens > -1 {
-        self.raiseRecoverable(parens as usize, String::from("Parenthesized pattern"));
+        self.raiseRecoverable(parens as usize, ErrorKind::ParenthesizedPattern)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn checkYieldAwaitInDefaultParams(&mut self) -> Result<(), location::SyntaxError> {
+    if self.yieldPos != 0 && (self.awaitPos == 0 || self.yieldPos < self.awaitPos) {
+      self.raise(self.yieldPos, ErrorKind::YieldInDefaultParams)?;
+    }
+    if self.awaitPos != 0 {
+      self.raise(self.awaitPos, ErrorKind::AwaitInDefaultParams)?;
+    }
+    Ok(())
+  }
+
+  fn isSimpleAssignTarget(&self, expr: &node::Node) -> bool {
+    match expr.r#type.as_str() {
+      "ParenthesizedExpression" => expr
+        .expression
+        .as_deref()
+        .is_some_and(|expr| self.isSimpleAssignTarget(expr)),
+      "Identifier" | "MemberExpression" => true,
+      _ => false,
+    }
+  }
+
+  fn enterNesting(&mut self) -> Result<(), location::SyntaxError> {
+    if self.depth >= MAX_NESTING_DEPTH {
+      return self.raise(self.start, ErrorKind::NestingTooDeep);
+    }
+    self.depth += 1;
+    Ok(())
+  }
+
+  fn strictDirective(&self, mut start: usize) -> bool {
+    loop {
+      start = whitespace::skipWhiteSpace(&self.input, start);
+      let (length, body) = match stringLiteral(&self.input[start..]) {
+        Some(literal) => literal,
+        None => return false,
+      };
+      if body == "use strict" {
+        let end = whitespace::skipWhiteSpace(&self.input, start + length);
+        let spaceAfter = &self.input[start + length..end];
+        let rest = &self.input[end..];
+        let next = rest.chars().next();
+        return next == Some(';')
+          || next == Some('}')
+          || whitespace::hasLineBreak(spaceAfter)
+            && !(next.is_some_and(|next| "(`.[+-/*%<>=,?^&".contains(next))
+              || rest.starts_with("!="));
+      }
+      start = whitespace::skipWhiteSpace(&self.input, start + length);
+      if self.input[start..].starts_with(';') {
+        start += 1;
       }
     }
   }
 }
 
+/// Match a string literal at the start of `input`, returning its
+/// length and its body without the quotes.
+fn stringLiteral(input: &str) -> Option<(usize, &str)> {
+  let quote = input.chars().next().filter(|&ch| ch == '\'' || ch == '"')?;
+  let mut escaped = false;
+  for (i, ch) in input.char_indices().skip(1) {
+    if escaped {
+      escaped = false;
+    } else if ch == '\\' {
+      escaped = true;
+    } else if ch == quote {
+      return Some((i + 1, &input[1..i]));
+    }
+  }
+  None
+}
+
+#[derive(Clone, Copy)]
 pub struct DestructuringErrors {
   pub shorthandAssign: isize,
   pub trailingComma: isize,
   pub parenthesizedAssign: isize,
-  parenthesizedBind: isize,
-  doubleProto: isize,
+  pub parenthesizedBind: isize,
+  pub doubleProto: isize,
 }
 
 impl DestructuringErrors {
@@ -124,7 +366,7 @@ impl DestructuringErrors {
     }
   }
 
-  pub fn reset(self) {
+  pub fn reset(&mut self) {
     self.shorthandAssign = -1;
     self.trailingComma = -1;
     self.parenthesizedAssign = -1;
@@ -132,3 +374,50 @@ impl DestructuringErrors {
     self.doubleProto = -1;
   }
 }
+
+impl Default for DestructuringErrors {
+  fn default() -> Self {
+    DestructuringErrors::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parseError(input: &str) -> location::SyntaxError {
+    match state::Parser::parse(String::from(input), None) {
+      Ok(_) => panic!("{:?} should not parse", input),
+      Err(err) => err,
+    }
+  }
+
+  #[test]
+  fn expectNamesTheExpectedAndFoundTokens() {
+    let err = parseError("f(a b)");
+    assert_eq!(
+      err.kind,
+      ErrorKind::Expected { expected: String::from(","), found: String::from("b") }
+    );
+    assert_eq!(err.details.message, "Expected ',' but found 'b' (1:4)");
+    assert_eq!(
+      parseError("if (a").kind,
+      ErrorKind::Expected { expected: String::from(")"), found: String::from("end of input") }
+    );
+  }
+
+  #[test]
+  fn hintsAtCommonMistakes() {
+    assert_eq!(
+      parseError("if (a {}").details.help.as_deref(),
+      Some("an opening '(' is missing its closing ')'")
+    );
+    assert_eq!(
+      parseError("a[1;").details.help.as_deref(),
+      Some("an opening '[' is missing its closing ']'")
+    );
+    assert_eq!(parseError("(a)\n=> a").details.help.as_deref(), Some(ARROW_ON_NEW_LINE));
+    assert_eq!(parseError("var if = 1;").details.help, Some(reservedWordHint("if")));
+    assert_eq!(parseError("a b").details.help, None);
+  }
+}
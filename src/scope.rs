@@ -1,7 +1,14 @@
+use crate::errorkind;
+use crate::location;
+use crate::node;
 use crate::scopeflags;
 use crate::state;
 
-use scopeflags::Flags;
+use std::collections::HashMap;
+
+use errorkind::ErrorKind;
+use location::ParserLocation;
+use scopeflags::{Binds, Flags};
 
 pub struct Scope {
   pub flags: Flags,
@@ -11,6 +18,9 @@ pub struct Scope {
   lexical: Vec<String>,
   /// A list of lexically-declared FunctionDeclaration names in the current lexical scope
   functions: Vec<String>,
+  /// Offset of the first declaration of each name in this scope, used
+  /// to point at it when reporting a redeclaration
+  declaredAt: HashMap<String, usize>,
 }
 
 impl Scope {
@@ -20,21 +30,276 @@ impl Scope {
       var: Vec::new(),
       lexical: Vec::new(),
       functions: Vec::new(),
+      declaredAt: HashMap::new(),
     }
   }
 }
 
 pub trait ParserScope {
-  fn currentVarScope(self) -> Option<Scope>;
+  fn enterScope(&mut self, flags: Flags);
+  fn exitScope(&mut self);
+
+  /// The spec says:
+  /// > At the top level of a function, or script, function declarations are
+  /// > treated like var declarations rather than like lexical declarations.
+  fn treatFunctionsAsVarInScope(&self, scope: &Scope) -> bool;
+  fn treatFunctionsAsVar(&self) -> bool;
+
+  fn declareName(
+    &mut self,
+    name: &str,
+    bindingType: Binds,
+    pos: usize,
+  ) -> Result<(), location::SyntaxError>;
+  fn checkLocalExport(&mut self, id: &node::Node);
+
+  /// Declare `names` as vars of the current scope, like the parameters
+  /// of an implicit function. They have no declaration in the source,
+  /// so redeclarations of them point at `pos`.
+  fn declareImplicit(&mut self, names: &[&str], pos: usize);
+
+  fn currentScope(&mut self) -> Option<&mut Scope>;
+  fn currentVarScope(&self) -> Option<&Scope>;
+  /// Could be useful for `this`, `new.target`, `super()`, `super.property`, and `super[property]`.
+  fn currentThisScope(&self) -> Option<&Scope>;
 }
 
 impl ParserScope for state::Parser {
-  fn currentVarScope(self) -> Option<Scope> {
-    for &scope in self.scopeStack.iter().rev() {
-      if (scope.flags & Flags::Var) != Flags::Zero {
-        return Some(scope);
+  fn enterScope(&mut self, flags: Flags) {
+    self.scopeStack.push(Scope::new(flags));
+  }
+
+  fn exitScope(&mut self) {
+    self.scopeStack.pop();
+  }
+
+  fn treatFunctionsAsVarInScope(&self, scope: &Scope) -> bool {
+    (scope.flags & Flags::Function) != Flags::Zero
+      || !self.inModule && (scope.flags & Flags::Top) != Flags::Zero
+  }
+
+  fn treatFunctionsAsVar(&self) -> bool {
+    self
+      .scopeStack
+      .last()
+      .is_some_and(|scope| self.treatFunctionsAsVarInScope(scope))
+  }
+
+  fn declareName(
+    &mut self,
+    name: &str,
+    bindingType: Binds,
+    pos: usize,
+  ) -> Result<(), location::SyntaxError> {
+    let mut redeclared = false;
+    let name = String::from(name);
+    match bindingType {
+      Binds::Lexical => {
+        let inModule = self.inModule;
+        if let Some(scope) = self.currentScope() {
+          redeclared = scope.lexical.contains(&name)
+            || scope.functions.contains(&name)
+            || scope.var.contains(&name);
+          scope.lexical.push(name.clone());
+          scope.declaredAt.entry(name.clone()).or_insert(pos);
+          if inModule && (scope.flags & Flags::Top) != Flags::Zero {
+            self.undefinedExports.remove(&name);
+          }
+        }
+      }
+      Binds::SimpleCatch => {
+        if let Some(scope) = self.currentScope() {
+          scope.lexical.push(name.clone());
+          scope.declaredAt.entry(name.clone()).or_insert(pos);
+        }
+      }
+      Binds::Function => {
+        let treatFunctionsAsVar = self.treatFunctionsAsVar();
+        if let Some(scope) = self.currentScope() {
+          redeclared = if treatFunctionsAsVar {
+            scope.lexical.contains(&name)
+          } else {
+            scope.lexical.contains(&name) || scope.var.contains(&name)
+          };
+          scope.functions.push(name.clone());
+          scope.declaredAt.entry(name.clone()).or_insert(pos);
+        }
+      }
+      _ => {
+        // Only note where the name was declared once it is known not to
+        // clash, so a redeclaration points at the earlier declaration.
+        let mut declaredIn = Vec::new();
+        for i in (0..self.scopeStack.len()).rev() {
+          let treatFunctionsAsVar = self.treatFunctionsAsVarInScope(&self.scopeStack[i]);
+          let scope = &mut self.scopeStack[i];
+          if scope.lexical.contains(&name)
+            && !((scope.flags & Flags::SimpleCatch) != Flags::Zero && scope.lexical[0] == name)
+            || !treatFunctionsAsVar && scope.functions.contains(&name)
+          {
+            redeclared = true;
+            break;
+          }
+          scope.var.push(name.clone());
+          declaredIn.push(i);
+          let flags = scope.flags;
+          if self.inModule && (flags & Flags::Top) != Flags::Zero {
+            self.undefinedExports.remove(&name);
+          }
+          if (flags & Flags::Var) != Flags::Zero {
+            break;
+          }
+        }
+        if !redeclared {
+          for i in declaredIn {
+            self.scopeStack[i].declaredAt.entry(name.clone()).or_insert(pos);
+          }
+        }
+      }
+    }
+    if redeclared {
+      let notes = self
+        .scopeStack
+        .iter()
+        .rev()
+        .find_map(|scope| scope.declaredAt.get(&name))
+        .map(|&earlier| location::Note {
+          pos: earlier,
+          message: format!("'{}' was first declared here", name),
+        })
+        .into_iter()
+        .collect();
+      return self.raiseRecoverableWithNotes(pos, ErrorKind::Redeclaration(name), notes);
+    }
+    Ok(())
+  }
+
+  fn checkLocalExport(&mut self, id: &node::Node) {
+    let name = match &id.name {
+      Some(name) => name.clone(),
+      None => return,
+    };
+    let declared = self
+      .scopeStack
+      .first()
+      .is_some_and(|top| top.lexical.contains(&name) || top.var.contains(&name));
+    if !declared {
+      self.undefinedExports.insert(name, id.clone());
+    }
+  }
+
+  fn declareImplicit(&mut self, names: &[&str], pos: usize) {
+    if let Some(scope) = self.currentScope() {
+      for &name in names {
+        scope.var.push(String::from(name));
+        scope.declaredAt.entry(String::from(name)).or_insert(pos);
       }
     }
-    None
+  }
+
+  fn currentScope(&mut self) -> Option<&mut Scope> {
+    self.scopeStack.last_mut()
+  }
+
+  fn currentVarScope(&self) -> Option<&Scope> {
+    self
+      .scopeStack
+      .iter()
+      .rev()
+      .find(|scope| (scope.flags & Flags::Var) != Flags::Zero)
+  }
+
+  fn currentThisScope(&self) -> Option<&Scope> {
+    self
+      .scopeStack
+      .iter()
+      .rev()
+      .find(|scope| {
+        (scope.flags & Flags::Var) != Flags::Zero && (scope.flags & Flags::Arrow) == Flags::Zero
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::errorkind::ErrorKind;
+  use crate::options;
+  use crate::state;
+
+  fn errors(input: &str, sourceType: options::SourceType) -> Vec<ErrorKind> {
+    let mut options = options::Options::default();
+    options.sourceType = sourceType;
+    let (_, errors) = state::Parser::parseWithErrors(String::from(input), Some(options))
+      .expect("input should parse");
+    errors.into_iter().map(|err| err.kind).collect()
+  }
+
+  fn redeclarations(input: &str) -> Vec<ErrorKind> {
+    errors(input, options::SourceType::Script)
+  }
+
+  fn redeclared(name: &str) -> ErrorKind {
+    ErrorKind::Redeclaration(String::from(name))
+  }
+
+  #[test]
+  fn rejectsLexicalRedeclarations() {
+    assert_eq!(redeclarations("let x; var x;"), [redeclared("x")]);
+    assert_eq!(redeclarations("var x; let x;"), [redeclared("x")]);
+    assert_eq!(redeclarations("const x = 1; let x;"), [redeclared("x")]);
+    assert_eq!(redeclarations("let x; function x() {}"), [redeclared("x")]);
+    assert_eq!(redeclarations("class C {} let C;"), [redeclared("C")]);
+    assert_eq!(redeclarations("try {} catch ([e]) { let e; }"), [redeclared("e")]);
+  }
+
+  #[test]
+  fn allowsVarRedeclarations() {
+    assert!(redeclarations("var x; var x;").is_empty());
+    assert!(redeclarations("var f; function f() {}").is_empty());
+    assert!(redeclarations("try {} catch (e) { var e; }").is_empty());
+  }
+
+  #[test]
+  fn blocksAndLoopsGetTheirOwnScope() {
+    assert!(redeclarations("let x; { let x; }").is_empty());
+    assert!(redeclarations("{ let x; } { let x; }").is_empty());
+    assert!(redeclarations("for (let i = 0;;) {} for (let i of []) {} let i;").is_empty());
+    assert!(redeclarations("switch (0) { case 0: let x; } let x;").is_empty());
+    assert!(redeclarations("function f(a) { let x; } let x;").is_empty());
+    assert_eq!(redeclarations("{ let x; var x; }"), [redeclared("x")]);
+    assert_eq!(redeclarations("let x; { var x; }"), [redeclared("x")]);
+  }
+
+  fn firstDeclaredAt(input: &str, sourceType: options::SourceType) -> Vec<usize> {
+    let mut options = options::Options::default();
+    options.sourceType = sourceType;
+    let (_, errors) = state::Parser::parseWithErrors(String::from(input), Some(options))
+      .expect("input should parse");
+    errors
+      .iter()
+      .flat_map(|err| err.details.notes.iter().map(|note| note.pos))
+      .collect()
+  }
+
+  #[test]
+  fn notesPointAtTheEarlierDeclaration() {
+    assert_eq!(firstDeclaredAt("let x; { var x; }", options::SourceType::Script), [4]);
+    assert_eq!(firstDeclaredAt("{ var x; } let x;", options::SourceType::Script), [6]);
+    assert_eq!(firstDeclaredAt("var x; var x; let x;", options::SourceType::Script), [4]);
+    assert_eq!(firstDeclaredAt(" let require;", options::SourceType::CommonJS), [0]);
+  }
+
+  #[test]
+  fn moduleTopLevelFunctionsAreLexical() {
+    // `inModule` follows `sourceType`, so top-level functions only
+    // behave like `var` in scripts.
+    assert!(errors("function f() {} var f;", options::SourceType::Script).is_empty());
+    assert_eq!(
+      errors("function f() {} var f;", options::SourceType::Module),
+      [redeclared("f")]
+    );
+    assert_eq!(
+      errors("import x from 'x'; let x;", options::SourceType::Module),
+      [redeclared("x")]
+    );
   }
 }
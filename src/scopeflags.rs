@@ -25,7 +25,8 @@ pub fn function_flags(is_async: bool, is_generator: bool) -> Flags {
 }
 
 /// Used in checkLVal and declareName to determine the type of a binding
-enum Binds {
+#[derive(Clone, Copy, PartialEq)]
+pub enum Binds {
     /// Not a binding
     None,
     /// Var-style binding
@@ -0,0 +1,369 @@
+// ESTree JSON serialization, enabled by the `serde` feature. The output
+// for a tree, token or comment is the same object acorn produces for
+// the same input and options: the same fields, with `null`, `false`
+// and `[]` for missing ones where acorn sets them, and `loc`, `range`
+// and `sourceFile` only when the corresponding option is on.
+//
+// Which fields a node type has comes from the node table in `ast.rs`.
+// Node types that are not in the table are serialized with whatever
+// fields are set on them.
+
+use crate::ast;
+use crate::locutil;
+use crate::node;
+use crate::options;
+use crate::tokenize;
+use crate::tokentype;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use ast::{FieldRef, LiteralValue, Shape};
+
+impl Serialize for locutil::Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("line", &self.line)?;
+        map.serialize_entry("column", &self.column)?;
+        map.end()
+    }
+}
+
+impl Serialize for locutil::SourceLocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("start", &self.start)?;
+        map.serialize_entry("end", &self.end)?;
+        if let Some(source) = &self.source {
+            map.serialize_entry("source", source)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for options::SourceType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match self {
+            options::SourceType::Script => "script",
+            options::SourceType::Module => "module",
+            options::SourceType::CommonJS => "commonjs",
+        })
+    }
+}
+
+impl Serialize for node::Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.isHole() {
+            return serializer.serialize_unit();
+        }
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", &self.r#type)?;
+        map.serialize_entry("start", &self.start)?;
+        map.serialize_entry("end", &self.end)?;
+        if let Some(loc) = self.loc() {
+            map.serialize_entry("loc", loc)?;
+        }
+        if let Some((start, end)) = self.range() {
+            map.serialize_entry("range", &[start, end])?;
+        }
+        if let Some(sourceFile) = self.sourceFile() {
+            map.serialize_entry("sourceFile", sourceFile)?;
+        }
+
+        match self.r#type.as_str() {
+            "Literal" => serializeLiteral(&mut map, self)?,
+            "TemplateElement" => {
+                let raw = self.raw.as_deref().unwrap_or("");
+                // The cooked value is the raw text with escapes
+                // resolved, the same as a string literal's body.
+                let cooked = self.cooked.clone().unwrap_or_else(|| ast::unescape(raw));
+                map.serialize_entry("value", &TemplateValue(raw, &cooked))?;
+                serializeField(&mut map, self, "tail", Shape::Bool)?;
+            }
+            r#type => match ast::fieldsOf(r#type) {
+                Some(fields) => {
+                    for (name, shape) in fields.iter() {
+                        serializeField(&mut map, self, name, shape)?;
+                    }
+                }
+                None => {
+                    for &name in ast::NODE_FIELDS {
+                        serializeField(&mut map, self, name, Shape::OptionalString)?;
+                    }
+                }
+            },
+        }
+        map.end()
+    }
+}
+
+/// Write the field `name` of `node`. A missing field is written as
+/// acorn would leave it: `null` for a child node, `[]` for a list,
+/// `false` for a flag, and not at all otherwise.
+fn serializeField<M: SerializeMap>(
+    map: &mut M,
+    node: &node::Node,
+    name: &'static str,
+    shape: Shape,
+) -> Result<(), M::Error> {
+    match ast::field(node, name) {
+        FieldRef::Node(child) => map.serialize_entry(name, child),
+        FieldRef::Nodes(children) => map.serialize_entry(name, children),
+        FieldRef::Str(value) => map.serialize_entry(name, value),
+        FieldRef::Bool(value) => map.serialize_entry(name, &value),
+        FieldRef::SourceType(sourceType) => map.serialize_entry(name, sourceType),
+        FieldRef::Missing => match shape {
+            Shape::Node | Shape::OptionalNode => map.serialize_entry(name, &()),
+            Shape::List => map.serialize_entry(name, &[(); 0]),
+            Shape::Bool => map.serialize_entry(name, &false),
+            Shape::String | Shape::OptionalString | Shape::SourceType => Ok(()),
+        },
+    }
+}
+
+fn serializeLiteral<M: SerializeMap>(map: &mut M, node: &node::Node) -> Result<(), M::Error> {
+    let raw = node.raw.as_deref().unwrap_or("");
+    let value = ast::literalValue(raw);
+    match &value {
+        LiteralValue::String(value) => map.serialize_entry("value", value)?,
+        LiteralValue::Boolean(value) => map.serialize_entry("value", value)?,
+        LiteralValue::Null => map.serialize_entry("value", &())?,
+        LiteralValue::Number(value) => map.serialize_entry("value", &Number(*value))?,
+        // `JSON.stringify` turns a `RegExp` into `{}`.
+        LiteralValue::RegExp { .. } => map.serialize_entry("value", &Empty)?,
+        // `JSON.stringify` cannot represent a `BigInt`; tools that
+        // serialize acorn's output replace it with `null`.
+        LiteralValue::BigInt(_) => map.serialize_entry("value", &())?,
+        LiteralValue::Invalid => map.serialize_entry("value", &())?,
+    }
+    map.serialize_entry("raw", raw)?;
+    match &value {
+        LiteralValue::RegExp { pattern, flags } => {
+            map.serialize_entry("regex", &Regex { pattern, flags })
+        }
+        LiteralValue::BigInt(digits) => map.serialize_entry("bigint", digits),
+        _ => Ok(()),
+    }
+}
+
+/// A number, written as an integer when it is one, as
+/// `JSON.stringify` does.
+struct Number(f64);
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0;
+        if value.fract() == 0.0 && value.abs() < 9007199254740992.0 {
+            serializer.serialize_i64(value as i64)
+        } else {
+            serializer.serialize_f64(value)
+        }
+    }
+}
+
+struct Empty;
+
+impl Serialize for Empty {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_map(Some(0))?.end()
+    }
+}
+
+struct Regex<'a> {
+    pattern: &'a str,
+    flags: &'a str,
+}
+
+impl<'a> Serialize for Regex<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("pattern", self.pattern)?;
+        map.serialize_entry("flags", self.flags)?;
+        map.end()
+    }
+}
+
+/// The `value` of a `TemplateElement`: its raw and cooked text.
+struct TemplateValue<'a>(&'a str, &'a str);
+
+impl<'a> Serialize for TemplateValue<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("raw", self.0)?;
+        map.serialize_entry("cooked", self.1)?;
+        map.end()
+    }
+}
+
+impl Serialize for tokentype::TokenType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("label", &self.label)?;
+        if !self.keyword.is_empty() {
+            map.serialize_entry("keyword", &self.keyword)?;
+        }
+        map.serialize_entry("beforeExpr", &self.beforeExpr)?;
+        map.serialize_entry("startsExpr", &self.startsExpr)?;
+        map.serialize_entry("isLoop", &self.isLoop)?;
+        map.serialize_entry("isAssign", &self.isAssign)?;
+        map.serialize_entry("prefix", &self.prefix)?;
+        map.serialize_entry("postfix", &self.postfix)?;
+        map.serialize_entry("binop", &self.binop)?;
+        map.serialize_entry("updateContext", &())?;
+        map.end()
+    }
+}
+
+impl Serialize for tokenize::Token {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", &self.r#type)?;
+        if let Some(value) = &self.value {
+            if self.r#type == tokentype::TokenType::num() {
+                match ast::literalValue(value) {
+                    LiteralValue::Number(number) => map.serialize_entry("value", &Number(number))?,
+                    _ => map.serialize_entry("value", value)?,
+                }
+            } else {
+                map.serialize_entry("value", value)?;
+            }
+        }
+        map.serialize_entry("start", &self.start)?;
+        map.serialize_entry("end", &self.end)?;
+        if let Some(loc) = &self.loc {
+            map.serialize_entry("loc", loc)?;
+        }
+        if let Some((start, end)) = self.range {
+            map.serialize_entry("range", &[start, end])?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for tokenize::Comment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", self.r#type())?;
+        map.serialize_entry("value", &self.value)?;
+        map.serialize_entry("start", &self.start)?;
+        map.serialize_entry("end", &self.end)?;
+        if let Some(loc) = &self.loc {
+            map.serialize_entry("loc", loc)?;
+        }
+        if let Some((start, end)) = self.range {
+            map.serialize_entry("range", &[start, end])?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+    use crate::tokenize::ParserTokenize;
+
+    use serde_json::{json, Value};
+
+    fn toJson(input: &str, options: options::Options) -> Value {
+        match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => serde_json::to_value(&program).unwrap(),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn matchesAcornForNodes() {
+        assert_eq!(
+            toJson("x = 1;", options::Options::default()),
+            json!({
+                "type": "Program", "start": 0, "end": 6, "sourceType": "script",
+                "body": [{
+                    "type": "ExpressionStatement", "start": 0, "end": 6,
+                    "expression": {
+                        "type": "AssignmentExpression", "start": 0, "end": 5, "operator": "=",
+                        "left": {"type": "Identifier", "start": 0, "end": 1, "name": "x"},
+                        "right": {"type": "Literal", "start": 4, "end": 5, "value": 1, "raw": "1"}
+                    }
+                }]
+            })
+        );
+        let program = toJson("/a/g; 'a\\n'; null; 1.5; [, a];", options::Options::default());
+        let expressions: Vec<&Value> = program["body"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|statement| &statement["expression"])
+            .collect();
+        assert_eq!(expressions[0]["value"], json!({}));
+        assert_eq!(expressions[0]["regex"], json!({"pattern": "a", "flags": "g"}));
+        assert_eq!(expressions[1]["value"], json!("a\n"));
+        assert_eq!(expressions[1]["raw"], json!("'a\\n'"));
+        assert_eq!(expressions[2]["value"], Value::Null);
+        assert_eq!(expressions[3]["value"], json!(1.5));
+        assert_eq!(expressions[4]["elements"][0], Value::Null);
+    }
+
+    #[test]
+    fn writesBigIntsAsAcornDoes() {
+        let literal = node::Node {
+            r#type: String::from("Literal"),
+            raw: Some(String::from("10n")),
+            ..node::Node::default()
+        };
+        let json = serde_json::to_value(&literal).unwrap();
+        assert_eq!(json["value"], Value::Null);
+        assert_eq!(json["bigint"], json!("10"));
+    }
+
+    #[test]
+    fn includesLocationsAndRangesWhenAskedFor() {
+        let options = options::Options::builder()
+            .locations(true)
+            .ranges(true)
+            .sourceFile(String::from("a.js"))
+            .build()
+            .unwrap();
+        let statement = &toJson("x;", options)["body"][0];
+        assert_eq!(
+            statement["loc"],
+            json!({
+                "start": {"line": 1, "column": 0},
+                "end": {"line": 1, "column": 2},
+                "source": "a.js"
+            })
+        );
+        assert_eq!(statement["range"], json!([0, 2]));
+        let statement = &toJson("x;", options::Options::default())["body"][0];
+        assert!(statement.get("loc").is_none() && statement.get("range").is_none());
+    }
+
+    #[test]
+    fn matchesAcornForTokensAndComments() {
+        let (_, comments) =
+            match state::Parser::parseWithComments(String::from("var x; // c"), None) {
+                Ok(parsed) => parsed,
+                Err(err) => panic!("{}", err),
+            };
+        assert_eq!(
+            serde_json::to_value(&comments).unwrap(),
+            json!([{"type": "Line", "value": " c", "start": 7, "end": 11}])
+        );
+
+        let mut tokens = match state::Parser::tokenizer(String::from("var x"), None) {
+            Ok(tokens) => tokens,
+            Err(err) => panic!("{}", err),
+        };
+        let var = tokens.getToken().unwrap();
+        assert_eq!(
+            serde_json::to_value(&var).unwrap(),
+            json!({
+                "type": {
+                    "label": "var", "keyword": "var", "beforeExpr": false, "startsExpr": false,
+                    "isLoop": false, "isAssign": false, "prefix": false, "postfix": false,
+                    "binop": null, "updateContext": null
+                },
+                "value": "var", "start": 0, "end": 3
+            })
+        );
+    }
+}
@@ -0,0 +1,723 @@
+// Source Map v3 (https://sourcemaps.info/spec.html), as produced by
+// `codegen::generateWithSourceMap`. A map can also be read back from
+// JSON, so that the map of one transform can be composed with the map
+// of the transform before it and point at the original sources.
+//
+// Lines are 1-based and columns 0-based, as in `locutil::Position`;
+// columns count UTF-16 code units, as the specification requires.
+
+use std::error;
+use std::fmt;
+
+/// How deeply arrays and objects may nest in a map's JSON. A source map
+/// needs two levels; the limit keeps hostile input from overflowing the
+/// stack of the recursive parser.
+const MAX_JSON_DEPTH: usize = 64;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A position in one of the map's sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Original {
+    /// An index into `SourceMap.sources`.
+    pub source: usize,
+    pub line: usize,
+    pub column: usize,
+    /// An index into `SourceMap.names`.
+    pub name: Option<usize>,
+}
+
+/// A position in the generated code, and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub line: usize,
+    pub column: usize,
+    pub original: Option<Original>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    /// The name of the generated file.
+    pub file: Option<String>,
+    pub sourceRoot: Option<String>,
+    pub sources: Vec<String>,
+    /// The content of each source, when known; parallel to `sources`.
+    pub sourcesContent: Vec<Option<String>>,
+    pub names: Vec<String>,
+    /// Ordered by generated position.
+    pub mappings: Vec<Mapping>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceMapError {
+    /// The input is not valid JSON; the byte offset of the error.
+    Json(usize),
+    /// The map is not a version 3 map.
+    Version,
+    /// A required field is missing or has the wrong type.
+    MissingField(&'static str),
+    /// The `mappings` string is malformed.
+    Mappings,
+}
+
+impl fmt::Display for SourceMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceMapError::Json(offset) => write!(f, "Invalid JSON at offset {}", offset),
+            SourceMapError::Version => write!(f, "Not a version 3 source map"),
+            SourceMapError::MissingField(field) => {
+                write!(f, "Source map is missing its '{}' field", field)
+            }
+            SourceMapError::Mappings => write!(f, "Malformed source map mappings"),
+        }
+    }
+}
+
+impl error::Error for SourceMapError {}
+
+impl SourceMap {
+    pub fn new(file: Option<String>) -> Self {
+        SourceMap {
+            file,
+            ..SourceMap::default()
+        }
+    }
+
+    /// The index of `source` in `sources`, adding it if needed.
+    pub fn addSource(&mut self, source: &str) -> usize {
+        match self.sources.iter().position(|s| s == source) {
+            Some(index) => index,
+            None => {
+                self.sources.push(String::from(source));
+                self.sourcesContent.push(None);
+                self.sources.len() - 1
+            }
+        }
+    }
+
+    /// The index of `name` in `names`, adding it if needed.
+    pub fn addName(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(String::from(name));
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Record the content of `source`, to be embedded in the map.
+    pub fn setSourceContent(&mut self, source: &str, content: String) {
+        let index = self.addSource(source);
+        self.sourcesContent[index] = Some(content);
+    }
+
+    /// Add a mapping. Mappings must be added in generated order; one at
+    /// the same generated position as the last replaces it, so that the
+    /// innermost node printed there wins.
+    pub fn addMapping(&mut self, mapping: Mapping) {
+        if let Some(last) = self.mappings.last_mut() {
+            if last.line == mapping.line && last.column == mapping.column {
+                *last = mapping;
+                return;
+            }
+        }
+        self.mappings.push(mapping);
+    }
+
+    /// Where the generated position `line`:`column` came from: the
+    /// closest mapping at or before it on the same line.
+    pub fn originalPositionFor(&self, line: usize, column: usize) -> Option<&Original> {
+        let end = self
+            .mappings
+            .partition_point(|m| (m.line, m.column) <= (line, column));
+        let mapping = self.mappings[..end].last()?;
+        if mapping.line != line {
+            return None;
+        }
+        mapping.original.as_ref()
+    }
+
+    /// Compose this map with `input`, the map of the file this map's
+    /// sources were generated from, so that the result points at
+    /// `input`'s sources. Mappings into sources other than `input.file`
+    /// (when it is set) are kept as they are; mappings `input` has no
+    /// position for are dropped.
+    pub fn compose(&self, input: &SourceMap) -> SourceMap {
+        let mut composed = SourceMap::new(self.file.clone());
+        for mapping in &self.mappings {
+            let original = match &mapping.original {
+                Some(original) => original,
+                None => {
+                    composed.addMapping(mapping.clone());
+                    continue;
+                }
+            };
+            let source = &self.sources[original.source];
+            let chained = input.file.as_ref().is_none_or(|file| file == source);
+            let (source, line, column, name, content) = if chained {
+                let traced = match input.originalPositionFor(original.line, original.column) {
+                    Some(traced) => traced,
+                    None => continue,
+                };
+                let name = traced
+                    .name
+                    .map(|name| &input.names[name])
+                    .or(original.name.map(|name| &self.names[name]));
+                (
+                    &input.sources[traced.source],
+                    traced.line,
+                    traced.column,
+                    name,
+                    input.sourcesContent.get(traced.source),
+                )
+            } else {
+                (
+                    source,
+                    original.line,
+                    original.column,
+                    original.name.map(|name| &self.names[name]),
+                    self.sourcesContent.get(original.source),
+                )
+            };
+            let sourceIndex = composed.addSource(source);
+            if let Some(Some(content)) = content {
+                composed.sourcesContent[sourceIndex] = Some(content.clone());
+            }
+            let name = name.map(|name| composed.addName(name));
+            composed.addMapping(Mapping {
+                line: mapping.line,
+                column: mapping.column,
+                original: Some(Original {
+                    source: sourceIndex,
+                    line,
+                    column,
+                    name,
+                }),
+            });
+        }
+        composed
+    }
+
+    /// The `mappings` field: VLQ segments, with lines separated by `;`.
+    pub fn encodeMappings(&self) -> String {
+        let mut out = String::new();
+        let mut line = 1;
+        let mut previous = [0i64; 5];
+        let mut first = true;
+        for mapping in &self.mappings {
+            while line < mapping.line {
+                out.push(';');
+                line += 1;
+                previous[0] = 0;
+                first = true;
+            }
+            if !first {
+                out.push(',');
+            }
+            first = false;
+            let mut fields = vec![mapping.column as i64];
+            if let Some(original) = &mapping.original {
+                fields.push(original.source as i64);
+                fields.push(original.line as i64 - 1);
+                fields.push(original.column as i64);
+                if let Some(name) = original.name {
+                    fields.push(name as i64);
+                }
+            }
+            for (i, value) in fields.into_iter().enumerate() {
+                encodeVlq(&mut out, value - previous[i]);
+                previous[i] = value;
+            }
+        }
+        out
+    }
+
+    pub fn toJson(&self) -> String {
+        let mut out = String::from("{\"version\":3");
+        if let Some(file) = &self.file {
+            out.push_str(",\"file\":");
+            quoteJson(&mut out, file);
+        }
+        if let Some(sourceRoot) = &self.sourceRoot {
+            out.push_str(",\"sourceRoot\":");
+            quoteJson(&mut out, sourceRoot);
+        }
+        out.push_str(",\"sources\":[");
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            quoteJson(&mut out, source);
+        }
+        out.push(']');
+        if self.sourcesContent.iter().any(Option::is_some) {
+            out.push_str(",\"sourcesContent\":[");
+            for (i, content) in self.sourcesContent.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                match content {
+                    Some(content) => quoteJson(&mut out, content),
+                    None => out.push_str("null"),
+                }
+            }
+            out.push(']');
+        }
+        out.push_str(",\"names\":[");
+        for (i, name) in self.names.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            quoteJson(&mut out, name);
+        }
+        out.push_str("],\"mappings\":");
+        quoteJson(&mut out, &self.encodeMappings());
+        out.push('}');
+        out
+    }
+
+    /// Read a map from its JSON text. Index maps (with `sections`) are
+    /// not supported.
+    pub fn fromJson(json: &str) -> Result<SourceMap, SourceMapError> {
+        let mut parser = JsonParser {
+            input: json.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos != json.len() {
+            return Err(SourceMapError::Json(parser.pos));
+        }
+        match value.get("version") {
+            Some(Json::Number(version)) if *version == 3.0 => {}
+            _ => return Err(SourceMapError::Version),
+        }
+        let strings = |field: &'static str| -> Result<Vec<Option<String>>, SourceMapError> {
+            match value.get(field) {
+                None => Ok(Vec::new()),
+                Some(Json::Array(items)) => Ok(items
+                    .iter()
+                    .map(|item| match item {
+                        Json::String(item) => Some(item.clone()),
+                        _ => None,
+                    })
+                    .collect()),
+                Some(_) => Err(SourceMapError::MissingField(field)),
+            }
+        };
+        let string = |field: &'static str| match value.get(field) {
+            Some(Json::String(string)) => Some(string.clone()),
+            _ => None,
+        };
+        let sources: Vec<String> = strings("sources")?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        let mut sourcesContent = strings("sourcesContent")?;
+        sourcesContent.resize(sources.len(), None);
+        let names = strings("names")?
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        let mappings = string("mappings").ok_or(SourceMapError::MissingField("mappings"))?;
+        let mut map = SourceMap {
+            file: string("file"),
+            sourceRoot: string("sourceRoot"),
+            sources,
+            sourcesContent,
+            names,
+            mappings: Vec::new(),
+        };
+        map.mappings = decodeMappings(&mappings, map.sources.len(), map.names.len())?;
+        Ok(map)
+    }
+}
+
+fn encodeVlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (vlq & 0x1f) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0x20;
+        }
+        out.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn decodeVlq(bytes: &[u8], pos: &mut usize) -> Result<i64, SourceMapError> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos).ok_or(SourceMapError::Mappings)?;
+        let digit = BASE64
+            .iter()
+            .position(|&b| b == byte)
+            .ok_or(SourceMapError::Mappings)? as u64;
+        *pos += 1;
+        if shift > 60 {
+            return Err(SourceMapError::Mappings);
+        }
+        value |= (digit & 0x1f) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            break;
+        }
+    }
+    let magnitude = (value >> 1) as i64;
+    Ok(if value & 1 == 1 {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+fn decodeMappings(
+    mappings: &str,
+    sources: usize,
+    names: usize,
+) -> Result<Vec<Mapping>, SourceMapError> {
+    let bytes = mappings.as_bytes();
+    let mut result = Vec::new();
+    let mut previous = [0i64; 5];
+    for (line, segments) in mappings.split(';').enumerate() {
+        previous[0] = 0;
+        for segment in segments.split(',').filter(|segment| !segment.is_empty()) {
+            let start = segment.as_ptr() as usize - bytes.as_ptr() as usize;
+            let end = start + segment.len();
+            let mut pos = start;
+            let mut fields = Vec::with_capacity(5);
+            while pos < end && fields.len() < 5 {
+                let i = fields.len();
+                previous[i] = previous[i]
+                    .checked_add(decodeVlq(&bytes[..end], &mut pos)?)
+                    .ok_or(SourceMapError::Mappings)?;
+                fields.push(previous[i]);
+            }
+            // Sources and names index lists, so must lie within them.
+            let field = |i: usize, limit: Option<usize>| -> Result<usize, SourceMapError> {
+                let value = fields[i];
+                if value < 0 || limit.is_some_and(|limit| value as usize >= limit) {
+                    return Err(SourceMapError::Mappings);
+                }
+                Ok(value as usize)
+            };
+            let original = match fields.len() {
+                1 => None,
+                4 | 5 => Some(Original {
+                    source: field(1, Some(sources))?,
+                    line: field(2, None)? + 1,
+                    column: field(3, None)?,
+                    name: if fields.len() == 5 {
+                        Some(field(4, Some(names))?)
+                    } else {
+                        None
+                    },
+                }),
+                _ => return Err(SourceMapError::Mappings),
+            };
+            result.push(Mapping {
+                line: line + 1,
+                column: field(0, None)?,
+                original,
+            });
+        }
+    }
+    result.sort_by_key(|mapping| (mapping.line, mapping.column));
+    Ok(result)
+}
+
+fn quoteJson(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Just enough JSON to read a source map.
+enum Json {
+    Null,
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// The number of arrays and objects the parser is inside.
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error<T>(&self) -> Result<T, SourceMapError> {
+        Err(SourceMapError::Json(self.pos))
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.whitespace();
+        if self.input.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, SourceMapError> {
+        if self.input[self.pos..].starts_with(keyword.as_bytes()) {
+            self.pos += keyword.len();
+            Ok(value)
+        } else {
+            self.error()
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, SourceMapError> {
+        self.whitespace();
+        if self.depth >= MAX_JSON_DEPTH {
+            return self.error();
+        }
+        match self.input.get(self.pos) {
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(b't') => self.keyword("true", Json::Bool),
+            Some(b'f') => self.keyword("false", Json::Bool),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'[') => {
+                self.pos += 1;
+                self.depth += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        items.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return self.error();
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Array(items))
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                self.depth += 1;
+                let mut entries = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.whitespace();
+                        let key = self.string()?;
+                        if !self.eat(b':') {
+                            return self.error();
+                        }
+                        entries.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return self.error();
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Object(entries))
+            }
+            Some(b'-') | Some(b'0'..=b'9') => {
+                let start = self.pos;
+                while let Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E')
+                | Some(b'0'..=b'9') = self.input.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+                let text = std::str::from_utf8(&self.input[start..self.pos]).unwrap_or("");
+                match text.parse() {
+                    Ok(number) => Ok(Json::Number(number)),
+                    Err(_) => Err(SourceMapError::Json(start)),
+                }
+            }
+            _ => self.error(),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, SourceMapError> {
+        if self.input.get(self.pos) != Some(&b'"') {
+            return self.error();
+        }
+        self.pos += 1;
+        let mut units: Vec<u16> = Vec::new();
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(&byte) = self.input.get(self.pos) {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            if self.pos > start {
+                out.push_str(&String::from_utf16_lossy(&units));
+                units.clear();
+                match std::str::from_utf8(&self.input[start..self.pos]) {
+                    Ok(text) => out.push_str(text),
+                    Err(_) => return Err(SourceMapError::Json(start)),
+                }
+            }
+            match self.input.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    out.push_str(&String::from_utf16_lossy(&units));
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escaped = match self.input.get(self.pos) {
+                        Some(&byte) => byte,
+                        None => return self.error(),
+                    };
+                    self.pos += 1;
+                    let unit = match escaped {
+                        b'"' => b'"' as u16,
+                        b'\\' => b'\\' as u16,
+                        b'/' => b'/' as u16,
+                        b'b' => 8,
+                        b'f' => 12,
+                        b'n' => b'\n' as u16,
+                        b'r' => b'\r' as u16,
+                        b't' => b'\t' as u16,
+                        b'u' => {
+                            let hex = self
+                                .input
+                                .get(self.pos..self.pos + 4)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+                            match hex {
+                                Some(unit) => {
+                                    self.pos += 4;
+                                    unit
+                                }
+                                None => return self.error(),
+                            }
+                        }
+                        _ => return self.error(),
+                    };
+                    // Escapes are collected as UTF-16 so that surrogate
+                    // pairs written as two `\u` escapes combine.
+                    units.push(unit);
+                }
+                _ => return self.error(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: i64) -> String {
+        let mut out = String::new();
+        encodeVlq(&mut out, value);
+        out
+    }
+
+    #[test]
+    fn roundTripsThroughJson() {
+        let mut map = SourceMap::new(Some(String::from("out.js")));
+        let source = map.addSource("in.js");
+        let name = map.addName("x");
+        map.setSourceContent("in.js", String::from("x;\n\"\u{e9}\";"));
+        map.addMapping(Mapping {
+            line: 1,
+            column: 0,
+            original: Some(Original {
+                source,
+                line: 1,
+                column: 0,
+                name: Some(name),
+            }),
+        });
+        map.addMapping(Mapping {
+            line: 2,
+            column: 4,
+            original: Some(Original {
+                source,
+                line: 2,
+                column: 0,
+                name: None,
+            }),
+        });
+        assert_eq!(SourceMap::fromJson(&map.toJson()), Ok(map));
+    }
+
+    #[test]
+    fn rejectsDeeplyNestedJson() {
+        let nested = "[".repeat(100_000);
+        assert!(matches!(
+            SourceMap::fromJson(&nested),
+            Err(SourceMapError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn rejectsMappingsThatOverflow() {
+        let mappings = format!("{},{}", vlq(i64::MAX), vlq(i64::MAX));
+        let json = format!(
+            "{{\"version\":3,\"sources\":[],\"names\":[],\"mappings\":\"{}\"}}",
+            mappings
+        );
+        assert_eq!(SourceMap::fromJson(&json), Err(SourceMapError::Mappings));
+    }
+
+    #[test]
+    fn rejectsMappingsOutsideTheSourcesAndNames() {
+        let map = |sources: &str, names: &str, mappings: &str| {
+            SourceMap::fromJson(&format!(
+                "{{\"version\":3,\"sources\":[{}],\"names\":[{}],\"mappings\":\"{}\"}}",
+                sources, names, mappings
+            ))
+        };
+        assert_eq!(map("", "", "AAAAA"), Err(SourceMapError::Mappings));
+        assert_eq!(map("", "", "AAAA"), Err(SourceMapError::Mappings));
+        assert_eq!(map("\"a.js\"", "", "AAAAA"), Err(SourceMapError::Mappings));
+        assert_eq!(map("\"a.js\"", "", "AAAA,ACAA"), Err(SourceMapError::Mappings));
+        assert!(map("\"a.js\"", "\"x\"", "AAAAA").is_ok());
+        assert!(map("", "", "A").is_ok());
+    }
+}
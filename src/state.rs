@@ -1,19 +1,23 @@
+use crate::errorkind;
 use crate::expression;
+use crate::location;
 use crate::locutil;
 use crate::node;
 use crate::options;
+use crate::parseutil;
 use crate::scope;
 use crate::scopeflags;
 use crate::statement;
 use crate::tokencontext;
 use crate::tokenize;
 use crate::tokentype;
-use crate::whitespace;
 
 use std::collections::HashMap;
 
 use expression::ParserExpression;
+use location::ParserLocation;
 use node::ParserNode;
+use parseutil::ParserParseUtil;
 use scope::ParserScope;
 use scopeflags::Flags;
 use statement::ParserStatement;
@@ -45,6 +49,14 @@ pub struct Parser {
     pub context: Vec<tokencontext::TokContext>,
     pub exprAllowed: bool,
 
+    /// Set while reading the body of a template element, so that a
+    /// bad escape marks the token invalid instead of failing.
+    pub inTemplateElement: bool,
+    pub invalidTemplateEscape: bool,
+    /// Set once iterating the tokenizer has reached the end of the
+    /// input or an error.
+    pub tokenizerDone: bool,
+
     pub r#type: tokentype::TokenType,
     pub undefinedExports: HashMap<String, node::Node>,
     pub inModule: bool,
@@ -60,16 +72,54 @@ pub struct Parser {
     pub value: Option<String>,
     /// Used to signify the start of a potential arrow function
     pub potentialArrowAt: isize,
+    /// Positions to delayed-check that yield/await does not exist in
+    /// default parameters.
+    pub yieldPos: usize,
+    pub awaitPos: usize,
+    /// Labels in scope.
+    pub labels: Vec<Label>,
+    /// How many statements, expressions and binding patterns are open;
+    /// see `parseutil::MAX_NESTING_DEPTH`.
+    pub depth: usize,
     /// Scope tracking for duplicate variable names (see scope.rs)
     pub scopeStack: Vec<scope::Scope>,
 
     pub strict: bool,
+
+    /// Errors collected by `raiseRecoverable` when
+    /// `Options.collectRecoverableErrors` is set.
+    pub recoverableErrors: Vec<location::SyntaxError>,
+
+    /// Comments seen so far, when `Options.collectComments` is set.
+    pub comments: Vec<tokenize::Comment>,
+
+    /// Maps the byte offsets used internally to the unit selected by
+    /// `Options.positionEncoding`.
+    pub offsets: locutil::OffsetMap,
+    pub lineIndex: locutil::LineIndex,
 }
 
+/// A label in scope, and the kind of statement it labels: `"loop"`,
+/// `"switch"`, or `None` for any other statement.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub name: Option<String>,
+    pub kind: Option<&'static str>,
+    pub statementStart: usize,
+}
+
+/// The parameters of the CommonJS module wrapper.
+const COMMONJS_BINDINGS: [&str; 5] = ["exports", "require", "module", "__filename", "__dirname"];
+
 // TODO(ryzokuken): do you need sourceFile?
 impl Parser {
-    fn new(options: options::Options, input: String, startPos: Option<usize>) -> Self {
-        let parser = Parser {
+    /// Build a parser without checking `options`. The entry points go
+    /// through `checked` instead; the loose parser, which never fails,
+    /// uses this directly.
+    pub(crate) fn new(options: options::Options, input: String, startPos: Option<usize>) -> Self {
+        let offsets = locutil::OffsetMap::new(&input);
+        let lineIndex = locutil::LineIndex::new(&input);
+        let mut parser = Parser {
             options,
             input,
             containsEsc: false,
@@ -90,58 +140,363 @@ impl Parser {
             endLoc: None,
             value: None,
             potentialArrowAt: -1,
+            yieldPos: 0,
+            awaitPos: 0,
             exprAllowed: true,
+            inTemplateElement: false,
+            invalidTemplateEscape: false,
+            tokenizerDone: false,
+            labels: Vec::new(),
+            depth: 0,
             scopeStack: Vec::new(),
             strict: false,
+            recoverableErrors: Vec::new(),
+            comments: Vec::new(),
+            offsets,
+            lineIndex,
         };
-        if startPos.is_some() {
-            let pos = startPos.unwrap();
+        if let Some(startPos) = startPos {
+            let mut pos = parser.decodeOffset(startPos).min(parser.input.len());
+            // A position inside a multi-byte character, or between the
+            // halves of a UTF-16 surrogate pair, starts at that character.
+            while !parser.input.is_char_boundary(pos) {
+                pos -= 1;
+            }
+            let loc = parser.lineIndex.position(pos);
             parser.pos = pos;
-            parser.lineStart = input[pos - 1..].rfind('\n').unwrap();
-            let cline: Vec<&str> = whitespace::lineBreak
-                .split(&input[..parser.lineStart])
-                .collect();
-            parser.curLine = cline.len();
+            parser.lineStart = pos - loc.column;
+            parser.curLine = loc.line;
         }
         parser.start = parser.pos;
         parser.end = parser.pos;
+        parser.startLoc = parser.curPosition();
+        parser.endLoc = parser.startLoc;
         parser.lastTokStart = parser.pos;
         parser.lastTokEnd = parser.pos;
-        parser.inModule = options.sourceType != options::SourceType::Module;
+        parser.inModule = parser.options.sourceType == options::SourceType::Module;
         parser.strict = parser.inModule || parser.strictDirective(parser.pos);
+        if parser.options.sourceType == options::SourceType::CommonJS {
+            // The top level is the body of the function Node.js wraps
+            // each module in.
+            parser.enterScope(Flags::Top | Flags::Function);
+            parser.declareImplicit(&COMMONJS_BINDINGS, parser.pos);
+        } else {
+            parser.enterScope(Flags::Top);
+        }
+
+        // Skip a `#!` line at the very start of the input, as Node.js
+        // does for executable scripts.
+        if parser.pos == 0 && parser.options.allowHashBang() && parser.input.starts_with("#!") {
+            parser.skipLineComment(2);
+        }
         parser
     }
 
-    fn parse_inst(&self) -> node::Node {
-        let n = self.options.program.unwrap_or(self.startNode());
-        self.nextToken();
-        self.parseTopLevel(n)
+    /// Build a parser, failing with `ErrorKind::InvalidOptions` when
+    /// `Options::validate` rejects `options`, and with
+    /// `ErrorKind::PositionOutOfRange` when `startPos` lies past the end
+    /// of `input`.
+    fn checked(
+        options: options::Options,
+        input: String,
+        startPos: Option<usize>,
+    ) -> Result<Self, location::SyntaxError> {
+        let invalid = options.validate().err();
+        let parser = Parser::new(options, input, startPos);
+        if let Some(err) = invalid {
+            return Err(parser.error(parser.pos, errorkind::ErrorKind::InvalidOptions(err)));
+        }
+        match startPos {
+            Some(startPos) if parser.decodeOffset(startPos) > parser.input.len() => {
+                Err(parser.error(parser.pos, errorkind::ErrorKind::PositionOutOfRange))
+            }
+            _ => Ok(parser),
+        }
+    }
+
+    fn parse_inst(&mut self) -> Result<node::Node, location::SyntaxError> {
+        let n = match self.options.program.take() {
+            Some(program) => program,
+            None => self.startNode(),
+        };
+        self.nextToken()?;
+        let node = self.parseTopLevel(n)?;
+        Ok(self.encodeNode(node))
+    }
+
+    /// Convert an internal byte offset into the unit selected by
+    /// `Options.positionEncoding`.
+    pub fn encodeOffset(&self, pos: usize) -> usize {
+        match self.options.positionEncoding {
+            options::PositionEncoding::Utf8 => pos,
+            options::PositionEncoding::Utf16 => self.offsets.toUtf16(pos),
+        }
+    }
+
+    /// The inverse of `encodeOffset`.
+    pub fn decodeOffset(&self, pos: usize) -> usize {
+        match self.options.positionEncoding {
+            options::PositionEncoding::Utf8 => pos,
+            options::PositionEncoding::Utf16 => self.offsets.fromUtf16(pos),
+        }
+    }
+
+    /// Convert the column of `loc`, which lies at the internal offset
+    /// `pos`, into the unit selected by `Options.positionEncoding`.
+    pub fn encodePosition(&self, pos: usize, loc: &locutil::Position) -> locutil::Position {
+        let column = self.encodeOffset(pos) - self.encodeOffset(pos - loc.column);
+        locutil::Position::new(loc.line, column)
+    }
+
+    /// Rewrite the offsets of a finished tree from internal byte
+    /// offsets into the unit selected by `Options.positionEncoding`.
+    fn encodeNode(&self, mut node: node::Node) -> node::Node {
+        if self.options.positionEncoding != options::PositionEncoding::Utf8 {
+            node.mapOffsets(&|pos| self.encodeOffset(pos));
+        }
+        node
     }
 
     // TODO(ryzokuken): where do these options come from?
-    pub fn parse(input: String, options: Option<options::Options>) -> node::Node {
-        Parser::new(options.unwrap_or_default(), input, None).parse_inst()
+    pub fn parse(
+        input: String,
+        options: Option<options::Options>,
+    ) -> Result<node::Node, location::SyntaxError> {
+        Parser::checked(options.unwrap_or_default(), input, None)?.parse_inst()
+    }
+
+    /// Parse `input` as `parse` does, but collect recoverable errors
+    /// (duplicate `__proto__`, parenthesized patterns, undefined exports,
+    /// ...) instead of failing on the first one. Only unrecoverable
+    /// errors produce an `Err`.
+    pub fn parseWithErrors(
+        input: String,
+        options: Option<options::Options>,
+    ) -> Result<(node::Node, Vec<location::SyntaxError>), location::SyntaxError> {
+        let mut options = options.unwrap_or_default();
+        options.collectRecoverableErrors = true;
+        let mut parser = Parser::checked(options, input, None)?;
+        let node = parser.parse_inst()?;
+        Ok((node, parser.recoverableErrors))
+    }
+
+    /// Parse `input` as `parse` does, and also return every comment in
+    /// it, in source order.
+    pub fn parseWithComments(
+        input: String,
+        options: Option<options::Options>,
+    ) -> Result<(node::Node, Vec<tokenize::Comment>), location::SyntaxError> {
+        let mut options = options.unwrap_or_default();
+        options.collectComments = true;
+        let mut parser = Parser::checked(options, input, None)?;
+        let node = parser.parse_inst()?;
+        Ok((node, parser.comments))
     }
 
     pub fn parseExpressionAt(
         input: String,
         pos: usize,
         options: Option<options::Options>,
-    ) -> node::Node {
-        let parser = Parser::new(options.unwrap_or_default(), input, Some(pos));
-        parser.nextToken();
-        parser.parseExpression(None, None)
+    ) -> Result<node::Node, location::SyntaxError> {
+        let mut parser = Parser::checked(options.unwrap_or_default(), input, Some(pos))?;
+        parser.nextToken()?;
+        let node = parser.parseExpression(false, None)?;
+        Ok(parser.encodeNode(node))
+    }
+
+    pub fn tokenizer(
+        input: String,
+        options: Option<options::Options>,
+    ) -> Result<Parser, location::SyntaxError> {
+        Parser::checked(options.unwrap_or_default(), input, None)
+    }
+
+    pub fn inFunction(&self) -> bool {
+        self.scopeStack
+            .iter()
+            .rev()
+            .find(|scope| (scope.flags & Flags::Var) != Flags::Zero)
+            .is_some_and(|scope| (scope.flags & Flags::Function) != Flags::Zero)
+    }
+
+    pub fn inGenerator(&self) -> bool {
+        self.currentVarScope()
+            .is_some_and(|scope| (scope.flags & Flags::Generator) != Flags::Zero)
+    }
+
+    pub fn inAsync(&self) -> bool {
+        self.currentVarScope()
+            .is_some_and(|scope| (scope.flags & Flags::Async) != Flags::Zero)
+    }
+
+    pub fn allowSuper(&self) -> bool {
+        self.currentThisScope()
+            .is_some_and(|scope| (scope.flags & Flags::Super) != Flags::Zero)
     }
 
-    pub fn tokenizer(input: String, options: Option<options::Options>) -> Parser {
-        Parser::new(options.unwrap_or_default(), input, None)
+    pub fn allowDirectSuper(&self) -> bool {
+        self.currentThisScope()
+            .is_some_and(|scope| (scope.flags & Flags::DirectSuper) != Flags::Zero)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(input: &str) -> Vec<node::Node> {
+        Parser::parse(String::from(input), None)
+            .expect("input should parse")
+            .body
+            .unwrap_or_default()
     }
 
-    pub fn inGenerator(self) -> bool {
-        (self.currentVarScope().unwrap().flags & Flags::Generator) > Flags::Zero
+    #[test]
+    fn parsesStatementsAndExpressions() {
+        let body = body(
+            "var a = 1, b = [a, , 2];\n\
+             function f(x, y = 2, ...rest) { return x + y * 3; }\n\
+             for (let i = 0; i < 10; i++) { if (i % 2) continue; else break; }\n\
+             for (const k in {a: 1, get b() { return 2; }}) {}\n\
+             class C extends Object { constructor() { super(); } static m() {} }\n\
+             label: while (true) { break label; }\n\
+             try { throw new Error('x'); } catch ({message}) {} finally {}\n\
+             switch (a) { case 1: f(a); break; default: }\n\
+             const g = async (p) => await p, t = `a${b}c`;",
+        );
+        let types: Vec<&str> = body.iter().map(|stmt| stmt.r#type.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "VariableDeclaration",
+                "FunctionDeclaration",
+                "ForStatement",
+                "ForInStatement",
+                "ClassDeclaration",
+                "LabeledStatement",
+                "TryStatement",
+                "SwitchStatement",
+                "VariableDeclaration",
+            ]
+        );
+        let init = body[0].declarations.as_ref().unwrap()[1].init.as_ref().unwrap();
+        assert_eq!(init.r#type, "ArrayExpression");
+        assert!(init.elements.as_ref().unwrap()[1].isHole());
+    }
+
+    #[test]
+    fn respectsOperatorPrecedence() {
+        let body = body("a = b || c && d + e * f ** g;");
+        let assign = body[0].expression.as_ref().unwrap();
+        assert_eq!(assign.r#type, "AssignmentExpression");
+        let or = assign.right.as_ref().unwrap();
+        assert_eq!(or.operator.as_deref(), Some("||"));
+        let and = or.right.as_ref().unwrap();
+        assert_eq!(and.operator.as_deref(), Some("&&"));
+        let plus = and.right.as_ref().unwrap();
+        assert_eq!(plus.operator.as_deref(), Some("+"));
+        let times = plus.right.as_ref().unwrap();
+        assert_eq!(times.operator.as_deref(), Some("*"));
+        assert_eq!(times.right.as_ref().unwrap().operator.as_deref(), Some("**"));
+    }
+
+    #[test]
+    fn reportsErrorsInsteadOfPanicking() {
+        for input in &["(", "a +", "let [", "`${", "/re", "'str", "0x", "a => {", "class {}", "}"] {
+            assert!(
+                Parser::parse(String::from(*input), None).is_err(),
+                "{:?} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn limitsNestingDepth() {
+        // Unoptimized builds use far more stack per level than release
+        // builds, more than the default test thread has room for.
+        std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(|| {
+                let nested = |open: &str, close: &str, depth: usize| {
+                    format!("{}x{}", open.repeat(depth), close.repeat(depth))
+                };
+                for (open, close) in &[("(", ")"), ("{", "}"), ("!", ""), ("new ", ""), ("[", "]")] {
+                    assert!(Parser::parse(nested(open, close, 20), None).is_ok());
+                    let err = Parser::parse(nested(open, close, 1000), None).err();
+                    assert_eq!(err.map(|err| err.kind), Some(errorkind::ErrorKind::NestingTooDeep));
+                }
+                let pattern = format!("let {}x{} = y;", "[".repeat(1000), "]".repeat(1000));
+                let err = Parser::parse(pattern, None).err();
+                assert_eq!(err.map(|err| err.kind), Some(errorkind::ErrorKind::NestingTooDeep));
+                let chain = format!("{}a", "a + ".repeat(10000));
+                assert!(Parser::parse(chain, None).is_ok());
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn tokenizerYieldsEveryToken() {
+        let mut tokenizer = Parser::tokenizer(String::from("let x = /a/g; `t${1}`"), None).unwrap();
+        let labels: Vec<String> = (&mut tokenizer)
+            .map(|token| String::from(token.unwrap().r#type.label))
+            .collect();
+        assert_eq!(
+            labels,
+            ["name", "name", "=", "regexp", ";", "`", "template", "${", "num", "}", "template", "`"]
+        );
+    }
+
+    fn moduleBeforeEcma6() -> options::Options {
+        let mut options = options::Options::default();
+        options.ecmaVersion = options::EcmaVersion::Ecma5;
+        options.sourceType = options::SourceType::Module;
+        options
+    }
+
+    #[test]
+    fn entryPointsRejectInvalidOptions() {
+        let invalid = errorkind::ErrorKind::InvalidOptions(options::OptionsError::ModuleBeforeEcma6);
+        let input = || String::from("x");
+        let kinds = vec![
+            Parser::parse(input(), Some(moduleBeforeEcma6())).err(),
+            Parser::parseWithErrors(input(), Some(moduleBeforeEcma6())).err(),
+            Parser::parseWithComments(input(), Some(moduleBeforeEcma6())).err(),
+            Parser::parseExpressionAt(input(), 0, Some(moduleBeforeEcma6())).err(),
+            Parser::tokenizer(input(), Some(moduleBeforeEcma6())).err(),
+        ];
+        for kind in kinds {
+            assert_eq!(kind.map(|err| err.kind), Some(invalid.clone()));
+        }
+    }
+
+    #[test]
+    fn validatesTheStartPosition() {
+        let at = |input: &str, pos, encoding| {
+            let options = options::Options::builder().positionEncoding(encoding).build().unwrap();
+            Parser::parseExpressionAt(String::from(input), pos, Some(options))
+        };
+        let utf8 = options::PositionEncoding::Utf8;
+        let utf16 = options::PositionEncoding::Utf16;
+        let err = at("a", 2, utf8).err().map(|err| err.kind);
+        assert_eq!(err, Some(errorkind::ErrorKind::PositionOutOfRange));
+        let err = at("\u{1F600}", 3, utf16).err().map(|err| err.kind);
+        assert_eq!(err, Some(errorkind::ErrorKind::PositionOutOfRange));
+        let err = at("a", 1, utf8).err().map(|err| err.kind);
+        assert_eq!(err, Some(errorkind::ErrorKind::UnexpectedToken));
+        // Positions inside a character start at that character.
+        assert_eq!(at("é + 1", 1, utf8).unwrap().start, 0);
+        assert_eq!(at("x, \u{10400}", 4, utf16).unwrap().start, 3);
     }
 
-    pub fn inAsync(self) -> bool {
-        (self.currentVarScope().unwrap().flags & Flags::Async) > Flags::Zero
+    #[test]
+    fn tokenizerStopsAfterAnError() {
+        let mut tokenizer = Parser::tokenizer(String::from("a 'unterminated"), None).unwrap();
+        let tokens: Vec<_> = (&mut tokenizer).collect();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens[0].is_ok());
+        assert!(tokens[1].is_err());
     }
 }
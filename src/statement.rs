@@ -1,13 +1,39 @@
+use crate::ast;
+use crate::errorkind;
+use crate::expression;
+use crate::identifier;
 use crate::location;
+use crate::lval;
 use crate::node;
 use crate::options;
+use crate::parseutil;
+use crate::scope;
+use crate::scopeflags;
 use crate::state;
+use crate::tokenize;
 use crate::tokentype;
+use crate::whitespace;
 
 use std::collections::HashMap;
 
+use errorkind::ErrorKind;
+use expression::ParserExpression;
 use location::ParserLocation;
+use lval::ParserLval;
 use node::ParserNode;
+use parseutil::{DestructuringErrors, ParserParseUtil};
+use scope::ParserScope;
+use scopeflags::{Binds, Flags};
+use tokenize::ParserTokenize;
+use tokentype::TokenType;
+
+/// `parseFunction` is parsing a function declaration.
+pub const FUNC_STATEMENT: u8 = 1;
+/// The declaration is the body of an `if` or a label, not a statement
+/// list item.
+pub const FUNC_HANGING_STATEMENT: u8 = 2;
+/// The declaration may omit its name (`export default function () {}`).
+pub const FUNC_NULLABLE_ID: u8 = 4;
 
 // ### Statement parsing
 
@@ -17,32 +43,1482 @@ use node::ParserNode;
 // to its body instead of creating a new node.
 
 pub trait ParserStatement {
-    fn parseTopLevel(self, node: node::Node) -> node::Node;
+    fn parseTopLevel(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+
+    /// Whether the `let` at the current token starts a declaration
+    /// rather than being an identifier.
+    fn isLet(&self, context: Option<&str>) -> bool;
+
+    /// Whether the current token is an `async` that starts an async
+    /// function declaration.
+    fn isAsyncFunction(&self) -> bool;
+
+    // Parse a single statement.
+    //
+    // If expecting a statement and finding a slash operator, parse a
+    // regular expression literal. This is to handle cases like
+    // `if (foo) /blah/.exec(foo)`, where looking at the previous token
+    // does not help.
+    fn parseStatement(
+        &mut self,
+        context: Option<&str>,
+        topLevel: bool,
+        exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<node::Node, location::SyntaxError>;
+
+    fn parseBreakContinueStatement(
+        &mut self,
+        node: node::Node,
+        keyword: &str,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseDebuggerStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseDoStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+
+    // Disambiguating between a `for` and a `for`/`in` or `for`/`of`
+    // loop is non-trivial. Basically, we have to parse the init `var`
+    // statement or expression, disallowing the `in` operator (see
+    // the second parameter to `parseExpression`), and then check
+    // whether the next token is `in` or `of`. When there is no init
+    // part (semicolon immediately after the opening parenthesis), it
+    // is a regular `for` loop.
+    fn parseForStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+
+    fn parseFunctionStatement(
+        &mut self,
+        node: node::Node,
+        isAsync: bool,
+        declarationPosition: bool,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseIfStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseReturnStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseSwitchStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseThrowStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseTryStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseVarStatement(
+        &mut self,
+        node: node::Node,
+        kind: String,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseWhileStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseWithStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseEmptyStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+    fn parseLabeledStatement(
+        &mut self,
+        node: node::Node,
+        maybeName: String,
+        expr: node::Node,
+        context: Option<&str>,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseExpressionStatement(
+        &mut self,
+        node: node::Node,
+        expr: node::Node,
+    ) -> Result<node::Node, location::SyntaxError>;
+
+    /// Parse a semicolon-enclosed block of statements. The block gets a
+    /// lexical scope of its own unless `createNewLexicalScope` is false,
+    /// as for function bodies and catch clauses, whose caller has
+    /// already entered one.
+    fn parseBlock(&mut self, createNewLexicalScope: bool) -> Result<node::Node, location::SyntaxError>;
+
+    /// Parse a regular `for` loop. The disambiguation code in
+    /// `parseStatement` will already have parsed the init statement or
+    /// expression.
+    fn parseFor(
+        &mut self,
+        node: node::Node,
+        init: Option<node::Node>,
+    ) -> Result<node::Node, location::SyntaxError>;
+
+    /// Parse a `for`/`in` and `for`/`of` loop, which are almost
+    /// same from parser's perspective.
+    fn parseForIn(&mut self, node: node::Node, init: node::Node) -> Result<node::Node, location::SyntaxError>;
+
+    /// Parse a list of variable declarations.
+    fn parseVar(
+        &mut self,
+        node: &mut node::Node,
+        isFor: bool,
+        kind: String,
+    ) -> Result<(), location::SyntaxError>;
+    fn parseVarId(&mut self, decl: &mut node::Node, kind: &str) -> Result<(), location::SyntaxError>;
+
+    /// Parse a function declaration or literal (depending on the
+    /// `statement & FUNC_STATEMENT`).
+    fn parseFunction(
+        &mut self,
+        node: node::Node,
+        statement: u8,
+        allowExpressionBody: bool,
+        isAsync: bool,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseFunctionParams(&mut self, node: &mut node::Node) -> Result<(), location::SyntaxError>;
+
+    /// Parse a class declaration or literal (depending on the
+    /// `isStatement` parameter).
+    fn parseClass(
+        &mut self,
+        node: node::Node,
+        isStatement: bool,
+        nullableId: bool,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn parseClassElement(
+        &mut self,
+        constructorAllowsSuper: bool,
+    ) -> Result<Option<node::Node>, location::SyntaxError>;
+    fn parseClassId(
+        &mut self,
+        node: &mut node::Node,
+        isStatement: bool,
+        nullableId: bool,
+    ) -> Result<(), location::SyntaxError>;
+    fn parseClassSuper(&mut self, node: &mut node::Node) -> Result<(), location::SyntaxError>;
+
+    /// Parses module export declaration.
+    fn parseExport(
+        &mut self,
+        node: node::Node,
+        exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<node::Node, location::SyntaxError>;
+    fn checkExport(
+        &mut self,
+        exports: Option<&mut HashMap<String, bool>>,
+        name: &str,
+        pos: usize,
+    ) -> Result<(), location::SyntaxError>;
+    fn checkPatternExport(
+        &mut self,
+        exports: &mut HashMap<String, bool>,
+        pat: &node::Node,
+    ) -> Result<(), location::SyntaxError>;
+    fn shouldParseExportStatement(&self) -> bool;
+
+    /// Parses a comma-separated list of module exports.
+    fn parseExportSpecifiers(
+        &mut self,
+        exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<Vec<node::Node>, location::SyntaxError>;
+
+    /// Parses import declaration.
+    fn parseImport(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError>;
+
+    /// Parses a comma-separated list of module imports.
+    fn parseImportSpecifiers(&mut self) -> Result<Vec<node::Node>, location::SyntaxError>;
+
+    /// Set `ExpressionStatement#directive` property for directive
+    /// prologues.
+    fn adaptDirectivePrologue(&self, statements: &mut [node::Node]);
+    fn isDirectiveCandidate(&self, statement: &node::Node) -> bool;
 }
 
 impl ParserStatement for state::Parser {
-    fn parseTopLevel(self, node: node::Node) -> node::Node {
-        let exports: HashMap<String, bool> = HashMap::new();
-        if node.body.is_none() {
-            node.body = Some(Vec::new());
-        }
-        while self.r#type != tokentype::TokenType::eof() {
-            let stmt = self.parseStatement(None, true, exports);
-            node.body.expect("").push(stmt);
+    fn parseTopLevel(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        let mut exports: HashMap<String, bool> = HashMap::new();
+        let mut body = node.body.take().unwrap_or_default();
+        while self.r#type != TokenType::eof() {
+            let stmt = self.parseStatement(None, true, Some(&mut exports))?;
+            body.push(stmt);
         }
         if self.inModule {
-            for name in self.undefinedExports.keys() {
-                self.raiseRecoverable(
-                    self.undefinedExports[name].start,
-                    format!("Export #{} is not defined", name),
-                );
+            let mut undefinedExports: Vec<(usize, String)> = self
+                .undefinedExports
+                .iter()
+                .map(|(name, id)| (id.start, name.clone()))
+                .collect();
+            undefinedExports.sort();
+            for (start, name) in undefinedExports {
+                self.raiseRecoverable(start, ErrorKind::UndefinedExport(name))?;
+            }
+        }
+        self.adaptDirectivePrologue(&mut body);
+        node.body = Some(body);
+        self.next()?;
+        if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            node.sourceType = Some(self.options.sourceType.clone());
+        }
+        Ok(self.finishNode(node, "Program"))
+    }
+
+    fn isLet(&self, context: Option<&str>) -> bool {
+        if self.options.ecmaVersion < options::EcmaVersion::Ecma6 || !self.isContextual("let") {
+            return false;
+        }
+        let next = whitespace::skipWhiteSpace(&self.input, self.pos);
+        let nextCh = match self.input[next..].chars().next() {
+            Some(nextCh) => nextCh,
+            None => return false,
+        };
+        // For ambiguous cases, determine if a LexicalDeclaration (or
+        // only a Statement) is allowed here. If context is not empty
+        // then only a Statement is allowed. However, `let [` is an
+        // explicit negative lookahead for ExpressionStatement, so
+        // special-case it first.
+        if nextCh == '[' || nextCh == '\\' {
+            return true;
+        }
+        if context.is_some() {
+            return false;
+        }
+        if nextCh == '{' {
+            return true;
+        }
+        if identifier::isIdentifierStart(nextCh) {
+            let rest = &self.input[next..];
+            let length = rest
+                .find(|ch: char| !identifier::isIdentifierChar(ch))
+                .unwrap_or(rest.len());
+            let ident = &rest[..length];
+            if rest[length..].starts_with('\\') {
+                return true;
             }
+            if ident != "in" && ident != "instanceof" {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn isAsyncFunction(&self) -> bool {
+        if self.options.ecmaVersion < options::EcmaVersion::Ecma8 || !self.isContextual("async") {
+            return false;
+        }
+        let next = whitespace::skipWhiteSpace(&self.input, self.pos);
+        let after = &self.input[next..];
+        !whitespace::hasLineBreak(&self.input[self.pos..next])
+            && after.starts_with("function")
+            && !after["function".len()..]
+                .chars()
+                .next()
+                .is_some_and(identifier::isIdentifierChar)
+    }
+
+    fn parseStatement(
+        &mut self,
+        context: Option<&str>,
+        topLevel: bool,
+        exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.enterNesting()?;
+        let mut starttype = self.r#type.clone();
+        let node = self.startNode();
+        let mut kind = None;
+
+        if self.isLet(context) {
+            starttype = TokenType::_var();
+            kind = Some(String::from("let"));
         }
-        self.adaptDirectivePrologue(node.body);
-        self.next();
+
+        // Most types of statements are recognized by the keyword they
+        // start with. Many are trivial to parse, some require a bit of
+        // complexity.
+
+        let stmt = if starttype == TokenType::_break() || starttype == TokenType::_continue() {
+            self.parseBreakContinueStatement(node, starttype.keyword)
+        } else if starttype == TokenType::_debugger() {
+            self.parseDebuggerStatement(node)
+        } else if starttype == TokenType::_do() {
+            self.parseDoStatement(node)
+        } else if starttype == TokenType::_for() {
+            self.parseForStatement(node)
+        } else if starttype == TokenType::_function() {
+            // Function as sole body of either an if statement or a
+            // labeled statement works, but not when it is part of a
+            // labeled statement that is the sole body of an if
+            // statement.
+            if let Some(context) = context {
+                if (self.strict || context != "if" && context != "label")
+                    && self.options.ecmaVersion >= options::EcmaVersion::Ecma6
+                {
+                    return self.unexpected(None);
+                }
+            }
+            self.parseFunctionStatement(node, false, context.is_none())
+        } else if starttype == TokenType::_class() {
+            if context.is_some() {
+                return self.unexpected(None);
+            }
+            self.parseClass(node, true, false)
+        } else if starttype == TokenType::_if() {
+            self.parseIfStatement(node)
+        } else if starttype == TokenType::_return() {
+            self.parseReturnStatement(node)
+        } else if starttype == TokenType::_switch() {
+            self.parseSwitchStatement(node)
+        } else if starttype == TokenType::_throw() {
+            self.parseThrowStatement(node)
+        } else if starttype == TokenType::_try() {
+            self.parseTryStatement(node)
+        } else if starttype == TokenType::_const() || starttype == TokenType::_var() {
+            let kind = match kind {
+                Some(kind) => kind,
+                None => self.value.clone().unwrap_or_default(),
+            };
+            if context.is_some() && kind != "var" {
+                return self.unexpected(None);
+            }
+            self.parseVarStatement(node, kind)
+        } else if starttype == TokenType::_while() {
+            self.parseWhileStatement(node)
+        } else if starttype == TokenType::_with() {
+            self.parseWithStatement(node)
+        } else if starttype == TokenType::braceL() {
+            self.parseBlock(true)
+        } else if starttype == TokenType::semi() {
+            self.parseEmptyStatement(node)
+        } else if starttype == TokenType::_export() || starttype == TokenType::_import() {
+            if !self.options.allowImportExportEverywhere() {
+                if !topLevel {
+                    self.raise(self.start, ErrorKind::ImportExportTopLevel)?;
+                }
+                if !self.inModule {
+                    self.raise(self.start, ErrorKind::ImportExportOutsideModule)?;
+                }
+            }
+            if starttype == TokenType::_import() {
+                self.parseImport(node)
+            } else {
+                self.parseExport(node, exports)
+            }
+        } else {
+            // If the statement does not start with a statement keyword
+            // or a brace, it's an ExpressionStatement or
+            // LabeledStatement. We simply start parsing an expression,
+            // and afterwards, if the next token is a colon and the
+            // expression was a simple Identifier node, we switch to
+            // interpreting it as a label.
+            if self.isAsyncFunction() {
+                if context.is_some() {
+                    return self.unexpected(None);
+                }
+                self.next()?;
+                self.parseFunctionStatement(node, true, context.is_none())
+            } else {
+                let maybeName = self.value.clone().unwrap_or_default();
+                let expr = self.parseExpression(false, None)?;
+                if starttype == TokenType::name()
+                    && expr.r#type == "Identifier"
+                    && self.eat(TokenType::colon())?
+                {
+                    self.parseLabeledStatement(node, maybeName, expr, context)
+                } else {
+                    self.parseExpressionStatement(node, expr)
+                }
+            }
+        };
+        self.depth -= 1;
+        stmt
+    }
+
+    fn parseBreakContinueStatement(
+        &mut self,
+        mut node: node::Node,
+        keyword: &str,
+    ) -> Result<node::Node, location::SyntaxError> {
+        let isBreak = keyword == "break";
+        self.next()?;
+        if self.eat(TokenType::semi())? || self.insertSemicolon() {
+            node.label = None;
+        } else if self.r#type != TokenType::name() {
+            return self.unexpected(None);
+        } else {
+            node.label = Some(Box::new(self.parseIdent(false)?));
+            self.semicolon()?;
+        }
+
+        // Verify that there is an actual destination to break or
+        // continue to.
+        let labelName = node.label.as_ref().and_then(|label| label.name.clone());
+        let found = self.labels.iter().any(|lab| {
+            if labelName.is_none() || lab.name == labelName {
+                if lab.kind.is_some() && (isBreak || lab.kind == Some("loop")) {
+                    return true;
+                }
+                if labelName.is_some() && isBreak {
+                    return true;
+                }
+            }
+            false
+        });
+        if !found {
+            self.raise(node.start, ErrorKind::UnsyntacticJump(String::from(keyword)))?;
+        }
+        Ok(self.finishNode(
+            node,
+            if isBreak {
+                "BreakStatement"
+            } else {
+                "ContinueStatement"
+            },
+        ))
+    }
+
+    fn parseDebuggerStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        self.semicolon()?;
+        Ok(self.finishNode(node, "DebuggerStatement"))
+    }
+
+    fn parseDoStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        self.labels.push(loopLabel());
+        node.body = Some(vec![self.parseStatement(Some("do"), false, None)?]);
+        self.labels.pop();
+        self.expect(TokenType::_while())?;
+        node.test = Some(Box::new(self.parseParenExpression()?));
         if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
-            node.sourceType = Some(self.options.sourceType);
+            self.eat(TokenType::semi())?;
+        } else {
+            self.semicolon()?;
+        }
+        Ok(self.finishNode(node, "DoWhileStatement"))
+    }
+
+    fn parseForStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        let canAwait = self.inAsync()
+            || (!self.inFunction() && self.options.allowAwaitOutsideFunction());
+        let awaitAt = if self.options.ecmaVersion >= options::EcmaVersion::Ecma9
+            && canAwait
+            && self.eatContextual("await")?
+        {
+            Some(self.lastTokStart)
+        } else {
+            None
+        };
+        self.labels.push(loopLabel());
+        self.enterScope(Flags::Zero);
+        self.expect(TokenType::parenL())?;
+        if self.r#type == TokenType::semi() {
+            if let Some(awaitAt) = awaitAt {
+                return self.unexpected(Some(awaitAt));
+            }
+            return self.parseFor(node, None);
+        }
+        let isLet = self.isLet(None);
+        if self.r#type == TokenType::_var() || self.r#type == TokenType::_const() || isLet {
+            let mut init = self.startNode();
+            let kind = if isLet {
+                String::from("let")
+            } else {
+                self.value.clone().unwrap_or_default()
+            };
+            self.next()?;
+            self.parseVar(&mut init, true, kind)?;
+            let init = self.finishNode(init, "VariableDeclaration");
+            let single = init.declarations.as_ref().is_some_and(|decls| decls.len() == 1);
+            if (self.r#type == TokenType::_in()
+                || self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && self.isContextual("of"))
+                && single
+            {
+                if self.options.ecmaVersion >= options::EcmaVersion::Ecma9 {
+                    if self.r#type == TokenType::_in() {
+                        if let Some(awaitAt) = awaitAt {
+                            return self.unexpected(Some(awaitAt));
+                        }
+                    } else {
+                        node.r#await = Some(awaitAt.is_some());
+                    }
+                }
+                return self.parseForIn(node, init);
+            }
+            if let Some(awaitAt) = awaitAt {
+                return self.unexpected(Some(awaitAt));
+            }
+            return self.parseFor(node, Some(init));
+        }
+        let mut refDestructuringErrors = DestructuringErrors::new();
+        let init = self.parseExpression(true, Some(&mut refDestructuringErrors))?;
+        if self.r#type == TokenType::_in()
+            || self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && self.isContextual("of")
+        {
+            if self.options.ecmaVersion >= options::EcmaVersion::Ecma9 {
+                if self.r#type == TokenType::_in() {
+                    if let Some(awaitAt) = awaitAt {
+                        return self.unexpected(Some(awaitAt));
+                    }
+                } else {
+                    node.r#await = Some(awaitAt.is_some());
+                }
+            }
+            let init = self.toAssignable(init, false, Some(&refDestructuringErrors))?;
+            self.checkLValPattern(&init, Binds::None, None)?;
+            return self.parseForIn(node, init);
+        } else {
+            self.checkExpressionErrors(Some(&refDestructuringErrors), true)?;
+        }
+        if let Some(awaitAt) = awaitAt {
+            return self.unexpected(Some(awaitAt));
+        }
+        self.parseFor(node, Some(init))
+    }
+
+    fn parseFunctionStatement(
+        &mut self,
+        node: node::Node,
+        isAsync: bool,
+        declarationPosition: bool,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        let hanging = if declarationPosition {
+            0
+        } else {
+            FUNC_HANGING_STATEMENT
+        };
+        self.parseFunction(node, FUNC_STATEMENT | hanging, false, isAsync)
+    }
+
+    fn parseIfStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        node.test = Some(Box::new(self.parseParenExpression()?));
+        // allow function declarations in branches, but only in non-strict mode
+        node.consequent = Some(Box::new(self.parseStatement(Some("if"), false, None)?));
+        node.alternate = if self.eat(TokenType::_else())? {
+            Some(Box::new(self.parseStatement(Some("if"), false, None)?))
+        } else {
+            None
+        };
+        Ok(self.finishNode(node, "IfStatement"))
+    }
+
+    fn parseReturnStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        if !self.inFunction() && !self.options.allowReturnOutsideFunction() {
+            self.raise(self.start, ErrorKind::ReturnOutsideFunction)?;
+        }
+        self.next()?;
+
+        // In `return` (and `break`/`continue`), the keywords with
+        // optional arguments, we eagerly look for a semicolon or the
+        // possibility to insert one.
+
+        if self.eat(TokenType::semi())? || self.insertSemicolon() {
+            node.argument = None;
+        } else {
+            node.argument = Some(Box::new(self.parseExpression(false, None)?));
+            self.semicolon()?;
+        }
+        Ok(self.finishNode(node, "ReturnStatement"))
+    }
+
+    fn parseSwitchStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        node.discriminant = Some(Box::new(self.parseParenExpression()?));
+        let mut cases = Vec::new();
+        self.expect(TokenType::braceL())?;
+        self.labels.push(switchLabel());
+        self.enterScope(Flags::Zero);
+
+        // Statements under must be grouped (by label) in SwitchCase
+        // nodes. `cur` is used to keep the node that we are currently
+        // adding statements to.
+
+        let mut cur: Option<node::Node> = None;
+        let mut sawDefault = false;
+        while self.r#type != TokenType::braceR() {
+            if self.r#type == TokenType::_case() || self.r#type == TokenType::_default() {
+                let isCase = self.r#type == TokenType::_case();
+                if let Some(cur) = cur.take() {
+                    cases.push(self.finishNode(cur, "SwitchCase"));
+                }
+                let mut case = self.startNode();
+                case.consequents = Some(Vec::new());
+                self.next()?;
+                if isCase {
+                    case.test = Some(Box::new(self.parseExpression(false, None)?));
+                } else {
+                    if sawDefault {
+                        self.raiseRecoverable(self.lastTokStart, ErrorKind::MultipleDefaults)?;
+                    }
+                    sawDefault = true;
+                    case.test = None;
+                }
+                self.expect(TokenType::colon())?;
+                cur = Some(case);
+            } else {
+                let stmt = match cur {
+                    Some(_) => self.parseStatement(None, false, None)?,
+                    None => return self.unexpected(None),
+                };
+                if let Some(consequents) = cur.as_mut().and_then(|cur| cur.consequents.as_mut()) {
+                    consequents.push(stmt);
+                }
+            }
+        }
+        self.exitScope();
+        if let Some(cur) = cur {
+            cases.push(self.finishNode(cur, "SwitchCase"));
+        }
+        node.cases = Some(cases);
+        self.next()?; // Closing brace
+        self.labels.pop();
+        Ok(self.finishNode(node, "SwitchStatement"))
+    }
+
+    fn parseThrowStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        if whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start]) {
+            self.raise(self.lastTokEnd, ErrorKind::NewlineAfterThrow)?;
+        }
+        node.argument = Some(Box::new(self.parseExpression(false, None)?));
+        self.semicolon()?;
+        Ok(self.finishNode(node, "ThrowStatement"))
+    }
+
+    fn parseTryStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        node.block = Some(Box::new(self.parseBlock(true)?));
+        node.handler = None;
+        if self.r#type == TokenType::_catch() {
+            let mut clause = self.startNode();
+            self.next()?;
+            if self.eat(TokenType::parenL())? {
+                let param = self.parseBindingAtom()?;
+                let simple = param.r#type == "Identifier";
+                self.enterScope(if simple { Flags::SimpleCatch } else { Flags::Zero });
+                self.checkLValPattern(
+                    &param,
+                    if simple { Binds::SimpleCatch } else { Binds::Lexical },
+                    None,
+                )?;
+                clause.param = Some(Box::new(param));
+                self.expect(TokenType::parenR())?;
+            } else {
+                if self.options.ecmaVersion < options::EcmaVersion::Ecma10 {
+                    return self.unexpected(None);
+                }
+                clause.param = None;
+                self.enterScope(Flags::Zero);
+            }
+            clause.body = Some(vec![self.parseBlock(false)?]);
+            self.exitScope();
+            node.handler = Some(Box::new(self.finishNode(clause, "CatchClause")));
+        }
+        node.finalizer = if self.eat(TokenType::_finally())? {
+            Some(Box::new(self.parseBlock(true)?))
+        } else {
+            None
+        };
+        if node.handler.is_none() && node.finalizer.is_none() {
+            self.raise(node.start, ErrorKind::MissingCatchOrFinally)?;
+        }
+        Ok(self.finishNode(node, "TryStatement"))
+    }
+
+    fn parseVarStatement(
+        &mut self,
+        mut node: node::Node,
+        kind: String,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        self.parseVar(&mut node, false, kind)?;
+        self.semicolon()?;
+        Ok(self.finishNode(node, "VariableDeclaration"))
+    }
+
+    fn parseWhileStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        node.test = Some(Box::new(self.parseParenExpression()?));
+        self.labels.push(loopLabel());
+        node.body = Some(vec![self.parseStatement(Some("while"), false, None)?]);
+        self.labels.pop();
+        Ok(self.finishNode(node, "WhileStatement"))
+    }
+
+    fn parseWithStatement(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        if self.strict {
+            self.raise(self.start, ErrorKind::StrictWith)?;
+        }
+        self.next()?;
+        node.object = Some(Box::new(self.parseParenExpression()?));
+        node.body = Some(vec![self.parseStatement(Some("with"), false, None)?]);
+        Ok(self.finishNode(node, "WithStatement"))
+    }
+
+    fn parseEmptyStatement(&mut self, node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        Ok(self.finishNode(node, "EmptyStatement"))
+    }
+
+    fn parseLabeledStatement(
+        &mut self,
+        mut node: node::Node,
+        maybeName: String,
+        expr: node::Node,
+        context: Option<&str>,
+    ) -> Result<node::Node, location::SyntaxError> {
+        if self.labels.iter().any(|label| label.name.as_deref() == Some(maybeName.as_str())) {
+            self.raise(expr.start, ErrorKind::DuplicateLabel(maybeName.clone()))?;
+        }
+        let kind = if self.r#type.isLoop {
+            Some("loop")
+        } else if self.r#type == TokenType::_switch() {
+            Some("switch")
+        } else {
+            None
+        };
+        let start = self.start;
+        for label in self.labels.iter_mut().rev() {
+            if label.statementStart == node.start {
+                // Update information about previous labels on this node
+                label.statementStart = start;
+                label.kind = kind;
+            } else {
+                break;
+            }
+        }
+        self.labels.push(state::Label {
+            name: Some(maybeName),
+            kind,
+            statementStart: start,
+        });
+        let context = match context {
+            Some(context) if context.contains("label") => String::from(context),
+            Some(context) => format!("{}label", context),
+            None => String::from("label"),
+        };
+        node.body = Some(vec![self.parseStatement(Some(&context), false, None)?]);
+        self.labels.pop();
+        node.label = Some(Box::new(expr));
+        Ok(self.finishNode(node, "LabeledStatement"))
+    }
+
+    fn parseExpressionStatement(
+        &mut self,
+        mut node: node::Node,
+        expr: node::Node,
+    ) -> Result<node::Node, location::SyntaxError> {
+        node.expression = Some(Box::new(expr));
+        self.semicolon()?;
+        Ok(self.finishNode(node, "ExpressionStatement"))
+    }
+
+    fn parseBlock(&mut self, createNewLexicalScope: bool) -> Result<node::Node, location::SyntaxError> {
+        let mut node = self.startNode();
+        let mut body = Vec::new();
+        self.expect(TokenType::braceL())?;
+        if createNewLexicalScope {
+            self.enterScope(Flags::Zero);
+        }
+        while self.r#type != TokenType::braceR() {
+            body.push(self.parseStatement(None, false, None)?);
+        }
+        node.body = Some(body);
+        self.next()?;
+        if createNewLexicalScope {
+            self.exitScope();
+        }
+        Ok(self.finishNode(node, "BlockStatement"))
+    }
+
+    fn parseFor(
+        &mut self,
+        mut node: node::Node,
+        init: Option<node::Node>,
+    ) -> Result<node::Node, location::SyntaxError> {
+        node.init = init.map(Box::new);
+        self.expect(TokenType::semi())?;
+        node.test = if self.r#type == TokenType::semi() {
+            None
+        } else {
+            Some(Box::new(self.parseExpression(false, None)?))
+        };
+        self.expect(TokenType::semi())?;
+        node.update = if self.r#type == TokenType::parenR() {
+            None
+        } else {
+            Some(Box::new(self.parseExpression(false, None)?))
+        };
+        self.expect(TokenType::parenR())?;
+        node.body = Some(vec![self.parseStatement(Some("for"), false, None)?]);
+        self.exitScope();
+        self.labels.pop();
+        Ok(self.finishNode(node, "ForStatement"))
+    }
+
+    fn parseForIn(&mut self, mut node: node::Node, init: node::Node) -> Result<node::Node, location::SyntaxError> {
+        let isForIn = self.r#type == TokenType::_in();
+        self.next()?;
+
+        if init.r#type == "VariableDeclaration" {
+            let decl = &init.declarations.as_ref().map_or(&[][..], |decls| decls)[0];
+            let simpleVar = init.kind.as_deref() == Some("var")
+                && decl.id.as_ref().is_some_and(|id| id.r#type == "Identifier");
+            if decl.init.is_some()
+                && (!isForIn
+                    || self.options.ecmaVersion < options::EcmaVersion::Ecma8
+                    || self.strict
+                    || !simpleVar)
+            {
+                let keyword = if isForIn { "in" } else { "of" };
+                self.raise(init.start, ErrorKind::ForInInitializer(String::from(keyword)))?;
+            }
+        }
+        node.left = Some(Box::new(init));
+        node.right = Some(Box::new(if isForIn {
+            self.parseExpression(false, None)?
+        } else {
+            self.parseMaybeAssign(false, None, None)?
+        }));
+        self.expect(TokenType::parenR())?;
+        node.body = Some(vec![self.parseStatement(Some("for"), false, None)?]);
+        self.exitScope();
+        self.labels.pop();
+        Ok(self.finishNode(
+            node,
+            if isForIn {
+                "ForInStatement"
+            } else {
+                "ForOfStatement"
+            },
+        ))
+    }
+
+    fn parseVar(
+        &mut self,
+        node: &mut node::Node,
+        isFor: bool,
+        kind: String,
+    ) -> Result<(), location::SyntaxError> {
+        let mut declarations = Vec::new();
+        loop {
+            let mut decl = self.startNode();
+            self.parseVarId(&mut decl, &kind)?;
+            let forInOf = isFor
+                && (self.r#type == TokenType::_in()
+                    || self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && self.isContextual("of"));
+            if self.eat(TokenType::eq())? {
+                decl.init = Some(Box::new(self.parseMaybeAssign(isFor, None, None)?));
+            } else if kind == "const" && !forInOf {
+                self.raise(self.lastTokEnd, ErrorKind::MissingConstInitializer)?;
+            } else if decl.id.as_ref().is_some_and(|id| id.r#type != "Identifier") && !forInOf {
+                self.raise(self.lastTokEnd, ErrorKind::MissingPatternInitializer)?;
+            } else {
+                decl.init = None;
+            }
+            declarations.push(self.finishNode(decl, "VariableDeclarator"));
+            if !self.eat(TokenType::comma())? {
+                break;
+            }
+        }
+        node.declarations = Some(declarations);
+        node.kind = Some(kind);
+        Ok(())
+    }
+
+    fn parseVarId(&mut self, decl: &mut node::Node, kind: &str) -> Result<(), location::SyntaxError> {
+        let id = self.parseBindingAtom()?;
+        let bindingType = if kind == "var" {
+            Binds::Var
+        } else {
+            Binds::Lexical
+        };
+        self.checkLValPattern(&id, bindingType, None)?;
+        decl.id = Some(Box::new(id));
+        Ok(())
+    }
+
+    fn parseFunction(
+        &mut self,
+        mut node: node::Node,
+        statement: u8,
+        allowExpressionBody: bool,
+        isAsync: bool,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.initFunction(&mut node);
+        let ecmaVersion = self.options.ecmaVersion;
+        if ecmaVersion >= options::EcmaVersion::Ecma9 || ecmaVersion >= options::EcmaVersion::Ecma6 && !isAsync {
+            if self.r#type == TokenType::star() && (statement & FUNC_HANGING_STATEMENT) != 0 {
+                return self.unexpected(None);
+            }
+            node.generator = Some(self.eat(TokenType::star())?);
+        }
+        if ecmaVersion >= options::EcmaVersion::Ecma8 {
+            node.r#async = Some(isAsync);
+        }
+
+        if (statement & FUNC_STATEMENT) != 0 {
+            node.id = if (statement & FUNC_NULLABLE_ID) != 0 && self.r#type != TokenType::name() {
+                None
+            } else {
+                Some(Box::new(self.parseIdent(false)?))
+            };
+            if let Some(id) = node.id.as_deref() {
+                if (statement & FUNC_HANGING_STATEMENT) == 0 {
+                    // If it is a regular function declaration in sloppy
+                    // mode, then it is subject to Annex B semantics
+                    // (BIND_FUNCTION). Otherwise, the binding follows
+                    // standard block scoping rules, so it is a lexical
+                    // binding.
+                    let bindingType = if self.strict || node.generator == Some(true) || isAsync {
+                        if self.treatFunctionsAsVar() {
+                            Binds::Var
+                        } else {
+                            Binds::Lexical
+                        }
+                    } else {
+                        Binds::Function
+                    };
+                    self.checkLValSimple(id, bindingType, None)?;
+                }
+            }
+        }
+
+        let oldYieldPos = self.yieldPos;
+        let oldAwaitPos = self.awaitPos;
+        self.yieldPos = 0;
+        self.awaitPos = 0;
+        let isGenerator = node.generator == Some(true);
+        self.enterScope(scopeflags::function_flags(isAsync, isGenerator));
+
+        if (statement & FUNC_STATEMENT) == 0 {
+            node.id = if self.r#type == TokenType::name() {
+                Some(Box::new(self.parseIdent(false)?))
+            } else {
+                None
+            };
+        }
+
+        self.parseFunctionParams(&mut node)?;
+        self.parseFunctionBody(&mut node, allowExpressionBody, false)?;
+
+        self.yieldPos = oldYieldPos;
+        self.awaitPos = oldAwaitPos;
+        Ok(self.finishNode(
+            node,
+            if (statement & FUNC_STATEMENT) != 0 {
+                "FunctionDeclaration"
+            } else {
+                "FunctionExpression"
+            },
+        ))
+    }
+
+    fn parseFunctionParams(&mut self, node: &mut node::Node) -> Result<(), location::SyntaxError> {
+        self.expect(TokenType::parenL())?;
+        let allowTrailingComma = self.options.ecmaVersion >= options::EcmaVersion::Ecma8;
+        node.params = Some(self.parseBindingList(TokenType::parenR(), false, allowTrailingComma)?);
+        self.checkYieldAwaitInDefaultParams()
+    }
+
+    fn parseClass(
+        &mut self,
+        mut node: node::Node,
+        isStatement: bool,
+        nullableId: bool,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+
+        // ecma-262 14.6 Class Definitions
+        // A class definition is always strict mode code.
+        let oldStrict = self.strict;
+        self.strict = true;
+
+        self.parseClassId(&mut node, isStatement, nullableId)?;
+        self.parseClassSuper(&mut node)?;
+        let mut classBody = self.startNode();
+        let mut hadConstructor = false;
+        let mut body = Vec::new();
+        self.expect(TokenType::braceL())?;
+        let constructorAllowsSuper = node.superClass.is_some();
+        while self.r#type != TokenType::braceR() {
+            if let Some(element) = self.parseClassElement(constructorAllowsSuper)? {
+                if element.r#type == "MethodDefinition" && element.kind.as_deref() == Some("constructor") {
+                    if hadConstructor {
+                        self.raise(element.start, ErrorKind::DuplicateConstructor)?;
+                    }
+                    hadConstructor = true;
+                }
+                body.push(element);
+            }
+        }
+        classBody.body = Some(body);
+        self.strict = oldStrict;
+        self.next()?;
+        node.body = Some(vec![self.finishNode(classBody, "ClassBody")]);
+        Ok(self.finishNode(
+            node,
+            if isStatement {
+                "ClassDeclaration"
+            } else {
+                "ClassExpression"
+            },
+        ))
+    }
+
+    fn parseClassElement(
+        &mut self,
+        constructorAllowsSuper: bool,
+    ) -> Result<Option<node::Node>, location::SyntaxError> {
+        if self.eat(TokenType::semi())? {
+            return Ok(None);
+        }
+
+        let mut method = self.startNode();
+        method.kind = Some(String::from("method"));
+        method.r#static = Some(tryContextual(self, &mut method, "static", false)?);
+        let mut isGenerator = self.eat(TokenType::star())?;
+        let mut isAsync = false;
+        if !isGenerator {
+            if self.options.ecmaVersion >= options::EcmaVersion::Ecma8
+                && tryContextual(self, &mut method, "async", true)?
+            {
+                isAsync = true;
+                isGenerator = self.options.ecmaVersion >= options::EcmaVersion::Ecma9
+                    && self.eat(TokenType::star())?;
+            } else if tryContextual(self, &mut method, "get", false)? {
+                method.kind = Some(String::from("get"));
+            } else if tryContextual(self, &mut method, "set", false)? {
+                method.kind = Some(String::from("set"));
+            }
+        }
+        if method.key.is_none() {
+            self.parsePropertyName(&mut method)?;
+        }
+        let mut allowsDirectSuper = false;
+        let isStatic = method.r#static == Some(true);
+        if let Some(key) = method.key.as_deref() {
+            let keyName = match key.r#type.as_str() {
+                "Identifier" => key.name.clone(),
+                "Literal" => match ast::literalValue(key.raw.as_deref().unwrap_or("")) {
+                    ast::LiteralValue::String(value) => Some(value),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if method.computed != Some(true) && !isStatic && keyName.as_deref() == Some("constructor") {
+                if method.kind.as_deref() != Some("method") {
+                    self.raise(key.start, ErrorKind::SpecialConstructor)?;
+                }
+                if isGenerator {
+                    self.raise(key.start, ErrorKind::GeneratorConstructor)?;
+                }
+                if isAsync {
+                    self.raise(key.start, ErrorKind::AsyncConstructor)?;
+                }
+                method.kind = Some(String::from("constructor"));
+                allowsDirectSuper = constructorAllowsSuper;
+            } else if isStatic && key.r#type == "Identifier" && key.name.as_deref() == Some("prototype") {
+                self.raise(key.start, ErrorKind::StaticPrototype)?;
+            }
+        }
+        let value = self.parseMethod(isGenerator, isAsync, allowsDirectSuper)?;
+        let params = value.params.as_deref().unwrap_or(&[]);
+        match method.kind.as_deref() {
+            Some("get") if !params.is_empty() => {
+                self.raiseRecoverable(value.start, ErrorKind::GetterParams)?;
+            }
+            Some("set") if params.len() != 1 => {
+                self.raiseRecoverable(value.start, ErrorKind::SetterParams)?;
+            }
+            Some("set") if params[0].r#type == "RestElement" => {
+                self.raiseRecoverable(params[0].start, ErrorKind::SetterRest)?;
+            }
+            _ => {}
+        }
+        method.value = Some(Box::new(value));
+        Ok(Some(self.finishNode(method, "MethodDefinition")))
+    }
+
+    fn parseClassId(
+        &mut self,
+        node: &mut node::Node,
+        isStatement: bool,
+        nullableId: bool,
+    ) -> Result<(), location::SyntaxError> {
+        if self.r#type == TokenType::name() {
+            let id = self.parseIdent(false)?;
+            if isStatement {
+                self.checkLValSimple(&id, Binds::Lexical, None)?;
+            }
+            node.id = Some(Box::new(id));
+        } else {
+            if isStatement && !nullableId {
+                return self.unexpected(None);
+            }
+            node.id = None;
+        }
+        Ok(())
+    }
+
+    fn parseClassSuper(&mut self, node: &mut node::Node) -> Result<(), location::SyntaxError> {
+        node.superClass = if self.eat(TokenType::_extends())? {
+            Some(Box::new(self.parseExprSubscripts(None)?))
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    fn parseExport(
+        &mut self,
+        mut node: node::Node,
+        mut exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        // export * from '...'
+        if self.eat(TokenType::star())? {
+            self.expectContextual("from")?;
+            if self.r#type != TokenType::string() {
+                return self.unexpected(None);
+            }
+            node.source = Some(Box::new(self.parseExprAtom(None)?));
+            self.semicolon()?;
+            return Ok(self.finishNode(node, "ExportAllDeclaration"));
+        }
+        if self.eat(TokenType::_default())? {
+            // export default ...
+            let pos = self.lastTokStart;
+            self.checkExport(exports, "default", pos)?;
+            let isAsync = self.isAsyncFunction();
+            if self.r#type == TokenType::_function() || isAsync {
+                let fNode = self.startNode();
+                self.next()?;
+                if isAsync {
+                    self.next()?;
+                }
+                node.declaration = Some(Box::new(self.parseFunction(
+                    fNode,
+                    FUNC_STATEMENT | FUNC_NULLABLE_ID,
+                    false,
+                    isAsync,
+                )?));
+            } else if self.r#type == TokenType::_class() {
+                let cNode = self.startNode();
+                node.declaration = Some(Box::new(self.parseClass(cNode, true, true)?));
+            } else {
+                node.declaration = Some(Box::new(self.parseMaybeAssign(false, None, None)?));
+                self.semicolon()?;
+            }
+            return Ok(self.finishNode(node, "ExportDefaultDeclaration"));
+        }
+        // export var|const|let|function|class ...
+        if self.shouldParseExportStatement() {
+            let declaration = self.parseStatement(None, false, None)?;
+            if let Some(exports) = exports.as_deref_mut() {
+                if declaration.r#type == "VariableDeclaration" {
+                    for decl in declaration.declarations.iter().flatten() {
+                        if let Some(id) = &decl.id {
+                            self.checkPatternExport(exports, id)?;
+                        }
+                    }
+                } else if let Some(id) = &declaration.id {
+                    let name = id.name.clone().unwrap_or_default();
+                    self.checkExport(Some(exports), &name, id.start)?;
+                }
+            }
+            node.declaration = Some(Box::new(declaration));
+            node.specifiers = Some(Vec::new());
+            node.source = None;
+        } else {
+            // export { x, y as z } [from '...']
+            node.declaration = None;
+            let specifiers = self.parseExportSpecifiers(exports)?;
+            if self.eatContextual("from")? {
+                if self.r#type != TokenType::string() {
+                    return self.unexpected(None);
+                }
+                node.source = Some(Box::new(self.parseExprAtom(None)?));
+            } else {
+                for spec in &specifiers {
+                    if let Some(local) = &spec.local {
+                        // check for keywords used as local names
+                        self.checkUnreserved(local)?;
+                        // check if export is defined
+                        self.checkLocalExport(local);
+                    }
+                }
+                node.source = None;
+            }
+            node.specifiers = Some(specifiers);
+            self.semicolon()?;
+        }
+        Ok(self.finishNode(node, "ExportNamedDeclaration"))
+    }
+
+    fn checkExport(
+        &mut self,
+        exports: Option<&mut HashMap<String, bool>>,
+        name: &str,
+        pos: usize,
+    ) -> Result<(), location::SyntaxError> {
+        let exports = match exports {
+            Some(exports) => exports,
+            None => return Ok(()),
+        };
+        if exports.contains_key(name) {
+            self.raiseRecoverable(pos, ErrorKind::DuplicateExport(String::from(name)))?;
+        }
+        exports.insert(String::from(name), true);
+        Ok(())
+    }
+
+    fn checkPatternExport(
+        &mut self,
+        exports: &mut HashMap<String, bool>,
+        pat: &node::Node,
+    ) -> Result<(), location::SyntaxError> {
+        match pat.r#type.as_str() {
+            "Identifier" => {
+                let name = pat.name.clone().unwrap_or_default();
+                self.checkExport(Some(exports), &name, pat.start)?;
+            }
+            "ObjectPattern" => {
+                for prop in pat.properties.iter().flatten() {
+                    self.checkPatternExport(exports, prop)?;
+                }
+            }
+            "ArrayPattern" => {
+                for elt in pat.elements.iter().flatten().filter(|elt| !elt.isHole()) {
+                    self.checkPatternExport(exports, elt)?;
+                }
+            }
+            "Property" => {
+                if let Some(value) = &pat.value {
+                    self.checkPatternExport(exports, value)?;
+                }
+            }
+            "AssignmentPattern" => {
+                if let Some(left) = &pat.left {
+                    self.checkPatternExport(exports, left)?;
+                }
+            }
+            "RestElement" => {
+                if let Some(argument) = &pat.argument {
+                    self.checkPatternExport(exports, argument)?;
+                }
+            }
+            "ParenthesizedExpression" => {
+                if let Some(expression) = &pat.expression {
+                    self.checkPatternExport(exports, expression)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn shouldParseExportStatement(&self) -> bool {
+        let keyword = self.r#type.keyword;
+        keyword == "var"
+            || keyword == "const"
+            || keyword == "class"
+            || keyword == "function"
+            || self.isLet(None)
+            || self.isAsyncFunction()
+    }
+
+    fn parseExportSpecifiers(
+        &mut self,
+        mut exports: Option<&mut HashMap<String, bool>>,
+    ) -> Result<Vec<node::Node>, location::SyntaxError> {
+        let mut nodes = Vec::new();
+        let mut first = true;
+        // export { x, y as z } [from '...']
+        self.expect(TokenType::braceL())?;
+        while !self.eat(TokenType::braceR())? {
+            if !first {
+                self.expect(TokenType::comma())?;
+                if self.afterTrailingComma(TokenType::braceR(), false)? {
+                    break;
+                }
+            } else {
+                first = false;
+            }
+
+            let mut node = self.startNode();
+            let local = self.parseIdent(true)?;
+            let exported = if self.eatContextual("as")? {
+                self.parseIdent(true)?
+            } else {
+                local.clone()
+            };
+            let name = exported.name.clone().unwrap_or_default();
+            self.checkExport(exports.as_deref_mut(), &name, exported.start)?;
+            node.local = Some(Box::new(local));
+            node.exported = Some(Box::new(exported));
+            nodes.push(self.finishNode(node, "ExportSpecifier"));
+        }
+        Ok(nodes)
+    }
+
+    fn parseImport(&mut self, mut node: node::Node) -> Result<node::Node, location::SyntaxError> {
+        self.next()?;
+        // import '...'
+        if self.r#type == TokenType::string() {
+            node.specifiers = Some(Vec::new());
+            node.source = Some(Box::new(self.parseExprAtom(None)?));
+        } else {
+            node.specifiers = Some(self.parseImportSpecifiers()?);
+            self.expectContextual("from")?;
+            if self.r#type != TokenType::string() {
+                return self.unexpected(None);
+            }
+            node.source = Some(Box::new(self.parseExprAtom(None)?));
+        }
+        self.semicolon()?;
+        Ok(self.finishNode(node, "ImportDeclaration"))
+    }
+
+    fn parseImportSpecifiers(&mut self) -> Result<Vec<node::Node>, location::SyntaxError> {
+        let mut nodes = Vec::new();
+        let mut first = true;
+        if self.r#type == TokenType::name() {
+            // import defaultObj, { x, y as z } from '...'
+            let mut node = self.startNode();
+            let local = self.parseIdent(false)?;
+            self.checkLValSimple(&local, Binds::Lexical, None)?;
+            node.local = Some(Box::new(local));
+            nodes.push(self.finishNode(node, "ImportDefaultSpecifier"));
+            if !self.eat(TokenType::comma())? {
+                return Ok(nodes);
+            }
         }
-        self.finishNode(node, String::from("Program"))
+        if self.r#type == TokenType::star() {
+            let mut node = self.startNode();
+            self.next()?;
+            self.expectContextual("as")?;
+            let local = self.parseIdent(false)?;
+            self.checkLValSimple(&local, Binds::Lexical, None)?;
+            node.local = Some(Box::new(local));
+            nodes.push(self.finishNode(node, "ImportNamespaceSpecifier"));
+            return Ok(nodes);
+        }
+        self.expect(TokenType::braceL())?;
+        while !self.eat(TokenType::braceR())? {
+            if !first {
+                self.expect(TokenType::comma())?;
+                if self.afterTrailingComma(TokenType::braceR(), false)? {
+                    break;
+                }
+            } else {
+                first = false;
+            }
+
+            let mut node = self.startNode();
+            let imported = self.parseIdent(true)?;
+            let local = if self.eatContextual("as")? {
+                self.parseIdent(false)?
+            } else {
+                self.checkUnreserved(&imported)?;
+                imported.clone()
+            };
+            self.checkLValSimple(&local, Binds::Lexical, None)?;
+            node.imported = Some(Box::new(imported));
+            node.local = Some(Box::new(local));
+            nodes.push(self.finishNode(node, "ImportSpecifier"));
+        }
+        Ok(nodes)
+    }
+
+    fn adaptDirectivePrologue(&self, statements: &mut [node::Node]) {
+        for statement in statements.iter_mut() {
+            if !self.isDirectiveCandidate(statement) {
+                break;
+            }
+            let raw = statement
+                .expression
+                .as_ref()
+                .and_then(|expression| expression.raw.clone())
+                .unwrap_or_default();
+            statement.directive = raw.get(1..raw.len() - 1).map(String::from);
+        }
+    }
+
+    fn isDirectiveCandidate(&self, statement: &node::Node) -> bool {
+        self.options.ecmaVersion >= options::EcmaVersion::Ecma5
+            && statement.r#type == "ExpressionStatement"
+            && statement.expression.as_ref().is_some_and(|expression| {
+                expression.r#type == "Literal"
+                    && matches!(
+                        ast::literalValue(expression.raw.as_deref().unwrap_or("")),
+                        ast::LiteralValue::String(_)
+                    )
+            })
+            // Reject parenthesized strings.
+            && (self.input.as_bytes().get(statement.start) == Some(&b'"')
+                || self.input.as_bytes().get(statement.start) == Some(&b'\''))
+    }
+}
+
+fn loopLabel() -> state::Label {
+    state::Label {
+        name: None,
+        kind: Some("loop"),
+        statementStart: 0,
+    }
+}
+
+fn switchLabel() -> state::Label {
+    state::Label {
+        name: None,
+        kind: Some("switch"),
+        statementStart: 0,
+    }
+}
+
+/// Consume the contextual keyword `k` as a modifier of a class element
+/// (`static`, `async`, `get` or `set`). When it turns out to be the
+/// element's name instead, store it as `method.key` and return false.
+fn tryContextual(
+    parser: &mut state::Parser,
+    method: &mut node::Node,
+    k: &str,
+    noLineBreak: bool,
+) -> Result<bool, location::SyntaxError> {
+    let start = parser.start;
+    let startLoc = parser.startLoc;
+    if !parser.eatContextual(k)? {
+        return Ok(false);
+    }
+    if parser.r#type != TokenType::parenL() && (!noLineBreak || !parser.canInsertSemicolon()) {
+        return Ok(true);
+    }
+    if method.key.is_some() {
+        return parser.unexpected(None);
+    }
+    method.computed = Some(false);
+    let mut key = parser.startNodeAt(start, startLoc);
+    key.name = Some(String::from(k));
+    method.key = Some(Box::new(parser.finishNode(key, "Identifier")));
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errorkind::ErrorKind;
+    use crate::options;
+    use crate::state;
+
+    fn parse(input: &str, options: options::Options) -> Result<(), ErrorKind> {
+        state::Parser::parse(String::from(input), Some(options))
+            .map(|_| ())
+            .map_err(|err| err.kind)
+    }
+
+    #[test]
+    fn rejectsReturnOutsideFunctionsByDefault() {
+        assert_eq!(
+            parse("return 1;", options::Options::default()),
+            Err(ErrorKind::ReturnOutsideFunction)
+        );
+        assert_eq!(
+            parse("{ if (x) return; }", options::Options::default()),
+            Err(ErrorKind::ReturnOutsideFunction)
+        );
+        assert_eq!(parse("function f() { return 1; }", options::Options::default()), Ok(()));
+        assert_eq!(parse("(() => { return; });", options::Options::default()), Ok(()));
+    }
+
+    #[test]
+    fn allowsReturnOutsideFunctionsWhenAsked() {
+        let allowed = options::Options::builder()
+            .allowReturnOutsideFunction(true)
+            .build()
+            .unwrap();
+        assert_eq!(parse("return 1;", allowed), Ok(()));
+        let commonJS = options::Options::builder()
+            .sourceType(options::SourceType::CommonJS)
+            .build()
+            .unwrap();
+        assert_eq!(parse("if (!module.parent) return;", commonJS), Ok(()));
     }
 }
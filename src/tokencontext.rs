@@ -1,36 +1,216 @@
+// The algorithm used to determine whether a regexp can appear at a
+// given point in the program is loosely based on sweet.js' approach.
+// See https://github.com/mozilla/sweet.js/wiki/design
+
+use crate::options;
 use crate::state;
+use crate::tokentype;
+use crate::whitespace;
+
+use tokentype::TokenType;
 
-#[derive(Default)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TokContext {
-    token: String,
-    isExpr: bool,
+    pub token: &'static str,
+    pub isExpr: bool,
     pub preserveSpace: bool,
-    overrideFn: Option<fn(state::Parser)>,
-    generator: bool,
+    pub generator: bool,
 }
 
 impl TokContext {
-    // fn new(
-    //     token: String,
-    //     isExpr: bool,
-    //     preserveSpace: bool,
-    //     overrideFn: Option<fn(state::Parser)>,
-    //     generator: bool,
-    // ) -> Self {
-    // }
-
-    pub fn b_stat() -> Self {
+    fn new(token: &'static str, isExpr: bool, preserveSpace: bool, generator: bool) -> Self {
         TokContext {
-            token: String::from("{"),
-            isExpr: false,
-            ..Default::default()
+            token,
+            isExpr,
+            preserveSpace,
+            generator,
         }
     }
+
+    pub fn b_stat() -> Self {
+        TokContext::new("{", false, false, false)
+    }
+    pub fn b_expr() -> Self {
+        TokContext::new("{", true, false, false)
+    }
+    pub fn b_tmpl() -> Self {
+        TokContext::new("${", false, false, false)
+    }
+    pub fn p_stat() -> Self {
+        TokContext::new("(", false, false, false)
+    }
+    pub fn p_expr() -> Self {
+        TokContext::new("(", true, false, false)
+    }
+    /// Inside a template, where the tokenizer reads template tokens
+    /// instead of skipping space.
+    pub fn q_tmpl() -> Self {
+        TokContext::new("`", true, true, false)
+    }
+    pub fn f_stat() -> Self {
+        TokContext::new("function", false, false, false)
+    }
+    pub fn f_expr() -> Self {
+        TokContext::new("function", true, false, false)
+    }
+    pub fn f_expr_gen() -> Self {
+        TokContext::new("function", true, false, true)
+    }
+    pub fn f_gen() -> Self {
+        TokContext::new("function", false, false, true)
+    }
 }
 
-// trait ParserTokenContext {
-//     // TODO(ryzokuken): why is this not static again?
-//     fn initialContext(&self) -> Vec<TokContext> {
-//         vec![TokContext::b_stat()]
-//     }
-// }
+pub trait ParserTokenContext {
+    fn initialContext() -> Vec<TokContext>;
+    fn braceIsBlock(&self, prevType: &TokenType) -> bool;
+    fn inGeneratorContext(&self) -> bool;
+
+    /// Update `context` and `exprAllowed` for the token just read, given
+    /// the type of the token before it.
+    fn updateContext(&mut self, prevType: &TokenType);
+}
+
+impl ParserTokenContext for state::Parser {
+    fn initialContext() -> Vec<TokContext> {
+        vec![TokContext::b_stat()]
+    }
+
+    fn braceIsBlock(&self, prevType: &TokenType) -> bool {
+        let parent = self.context.last();
+        if parent == Some(&TokContext::f_expr()) || parent == Some(&TokContext::f_stat()) {
+            return true;
+        }
+        if *prevType == TokenType::colon()
+            && (parent == Some(&TokContext::b_stat()) || parent == Some(&TokContext::b_expr()))
+        {
+            return !parent.is_some_and(|parent| parent.isExpr);
+        }
+        // The check for `name && exprAllowed` detects whether we are
+        // after a `yield` or `of` construct. See the `updateContext`
+        // for `name`.
+        if *prevType == TokenType::_return() || *prevType == TokenType::name() && self.exprAllowed
+        {
+            return whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start]);
+        }
+        if *prevType == TokenType::_else()
+            || *prevType == TokenType::semi()
+            || *prevType == TokenType::eof()
+            || *prevType == TokenType::parenR()
+            || *prevType == TokenType::arrow()
+        {
+            return true;
+        }
+        if *prevType == TokenType::braceL() {
+            return parent == Some(&TokContext::b_stat());
+        }
+        if *prevType == TokenType::_var()
+            || *prevType == TokenType::_const()
+            || *prevType == TokenType::name()
+        {
+            return false;
+        }
+        !self.exprAllowed
+    }
+
+    fn inGeneratorContext(&self) -> bool {
+        for context in self.context.iter().skip(1).rev() {
+            if context.token == "function" {
+                return context.generator;
+            }
+        }
+        false
+    }
+
+    fn updateContext(&mut self, prevType: &TokenType) {
+        let r#type = self.r#type.clone();
+        if !r#type.keyword.is_empty() && *prevType == TokenType::dot() {
+            self.exprAllowed = false;
+        } else if r#type == TokenType::parenR() || r#type == TokenType::braceR() {
+            if self.context.len() == 1 {
+                self.exprAllowed = true;
+                return;
+            }
+            let mut out = self.context.pop();
+            if out == Some(TokContext::b_stat())
+                && self.context.last().is_some_and(|cx| cx.token == "function")
+            {
+                out = self.context.pop();
+            }
+            self.exprAllowed = !out.is_some_and(|out| out.isExpr);
+        } else if r#type == TokenType::braceL() {
+            let context = if self.braceIsBlock(prevType) {
+                TokContext::b_stat()
+            } else {
+                TokContext::b_expr()
+            };
+            self.context.push(context);
+            self.exprAllowed = true;
+        } else if r#type == TokenType::dollarBraceL() {
+            self.context.push(TokContext::b_tmpl());
+            self.exprAllowed = true;
+        } else if r#type == TokenType::parenL() {
+            let statementParens = *prevType == TokenType::_if()
+                || *prevType == TokenType::_for()
+                || *prevType == TokenType::_with()
+                || *prevType == TokenType::_while();
+            self.context.push(if statementParens {
+                TokContext::p_stat()
+            } else {
+                TokContext::p_expr()
+            });
+            self.exprAllowed = true;
+        } else if r#type == TokenType::incDec() {
+            // exprAllowed stays unchanged
+        } else if r#type == TokenType::_function() || r#type == TokenType::_class() {
+            let afterReturnLine = *prevType == TokenType::_return()
+                && whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.start]);
+            let startsStatement = (*prevType == TokenType::colon()
+                || *prevType == TokenType::braceL())
+                && self.context.last() == Some(&TokContext::b_stat());
+            if prevType.beforeExpr
+                && *prevType != TokenType::semi()
+                && *prevType != TokenType::_else()
+                && !afterReturnLine
+                && !startsStatement
+            {
+                self.context.push(TokContext::f_expr());
+            } else {
+                self.context.push(TokContext::f_stat());
+            }
+            self.exprAllowed = false;
+        } else if r#type == TokenType::backQuote() {
+            if self.context.last() == Some(&TokContext::q_tmpl()) {
+                self.context.pop();
+            } else {
+                self.context.push(TokContext::q_tmpl());
+            }
+            self.exprAllowed = false;
+        } else if r#type == TokenType::star() {
+            if *prevType == TokenType::_function() {
+                if let Some(last) = self.context.last_mut() {
+                    *last = if *last == TokContext::f_expr() {
+                        TokContext::f_expr_gen()
+                    } else {
+                        TokContext::f_gen()
+                    };
+                }
+            }
+            self.exprAllowed = true;
+        } else if r#type == TokenType::name() {
+            let mut allowed = false;
+            if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 && *prevType != TokenType::dot()
+            {
+                let value = self.value.as_deref();
+                if value == Some("of") && !self.exprAllowed
+                    || value == Some("yield") && self.inGeneratorContext()
+                {
+                    allowed = true;
+                }
+            }
+            self.exprAllowed = allowed;
+        } else {
+            self.exprAllowed = r#type.beforeExpr;
+        }
+    }
+}
@@ -1,141 +1,1073 @@
+use crate::errorkind;
+use crate::identifier;
+use crate::location;
 use crate::locutil;
+use crate::options;
 use crate::state;
 use crate::tokencontext;
 use crate::tokentype;
 use crate::whitespace;
 
-use std::char;
+use errorkind::ErrorKind;
+use location::ParserLocation;
+use tokencontext::{ParserTokenContext, TokContext};
+use tokentype::TokenType;
 
+#[derive(Clone, Debug)]
 pub struct Token {
-    r#type: tokentype::TokenType,
-    value: Option<String>,
-    start: usize,
-    end: usize,
-    loc: Option<locutil::SourceLocation>,
-    range: Option<(usize, usize)>,
+    pub r#type: tokentype::TokenType,
+    pub value: Option<String>,
+    pub start: usize,
+    pub end: usize,
+    pub loc: Option<locutil::SourceLocation>,
+    pub range: Option<(usize, usize)>,
 }
 
 impl Token {
     fn new(p: &state::Parser) -> Self {
-        let token = Token {
-            r#type: p.r#type,
-            value: p.value,
-            start: p.start,
-            end: p.end,
+        let start = p.encodeOffset(p.start);
+        let end = p.encodeOffset(p.end);
+        let mut token = Token {
+            r#type: p.r#type.clone(),
+            value: p.value.clone(),
+            start,
+            end,
             loc: None,
             range: None,
         };
         if p.options.locations {
-            token.loc = Some(locutil::SourceLocation::from_parser(p));
+            token.loc = Some(locutil::SourceLocation::new(
+                p,
+                p.startLoc.as_ref().map(|loc| p.encodePosition(p.start, loc)),
+                p.endLoc.as_ref().map(|loc| p.encodePosition(p.end, loc)),
+            ));
         };
         if p.options.ranges {
-            token.range = Some((p.start, p.end));
+            token.range = Some((start, end));
         }
         token
     }
 }
 
-pub trait ParserTokenize: std::iter::Iterator<Item = Token> {
-    fn r#next(&self) -> ();
-    fn getToken(&self) -> Token;
+/// A comment, as acorn passes it to `onComment`. Comments are only
+/// collected (into `Parser.comments`) when `Options.collectComments`
+/// is set.
+#[derive(Clone, Debug)]
+pub struct Comment {
+    /// `true` for a `/* */` comment, `false` for a `//` comment.
+    pub block: bool,
+    /// The text of the comment, without its delimiters.
+    pub value: String,
+    pub start: usize,
+    pub end: usize,
+    pub loc: Option<locutil::SourceLocation>,
+    pub range: Option<(usize, usize)>,
+}
+
+impl Comment {
+    fn new(
+        p: &state::Parser,
+        block: bool,
+        start: usize,
+        end: usize,
+        startLoc: Option<locutil::Position>,
+        endLoc: Option<locutil::Position>,
+    ) -> Self {
+        let delimiters = if block { (2, 2) } else { (2, 0) };
+        let value = String::from(&p.input[start + delimiters.0..end - delimiters.1]);
+        let mut comment = Comment {
+            block,
+            value,
+            start: p.encodeOffset(start),
+            end: p.encodeOffset(end),
+            loc: None,
+            range: None,
+        };
+        if p.options.locations {
+            comment.loc = Some(locutil::SourceLocation::new(
+                p,
+                startLoc.as_ref().map(|loc| p.encodePosition(start, loc)),
+                endLoc.as_ref().map(|loc| p.encodePosition(end, loc)),
+            ));
+        }
+        if p.options.ranges {
+            comment.range = Some((comment.start, comment.end));
+        }
+        comment
+    }
+
+    /// The ESTree type of the comment: `"Block"` or `"Line"`.
+    pub fn r#type(&self) -> &'static str {
+        if self.block {
+            "Block"
+        } else {
+            "Line"
+        }
+    }
+}
+
+
+pub trait ParserTokenize {
+    /// Move on to the next token.
+    fn next(&mut self) -> Result<(), location::SyntaxError>;
+    fn getToken(&mut self) -> Result<Token, location::SyntaxError>;
     fn curContext(&self) -> Option<&tokencontext::TokContext>;
-    fn nextToken(&self) -> ();
-    fn skipSpace(&self) -> ();
-    fn skipLineComment(&self, startSkip: usize) -> ();
+
+    /// Read a single token, updating the parser object's token-related
+    /// properties.
+    fn nextToken(&mut self) -> Result<(), location::SyntaxError>;
+    fn readToken(&mut self, ch: char) -> Result<(), location::SyntaxError>;
+    fn fullCharAtPos(&self) -> Option<char>;
+    fn skipBlockComment(&mut self) -> Result<(), location::SyntaxError>;
+    fn skipLineComment(&mut self, startSkip: usize);
+
+    /// Called at the start of the parse and after every token. Skips
+    /// whitespace and comments, and.
+    fn skipSpace(&mut self) -> Result<(), location::SyntaxError>;
+
+    /// Called at the end of every token. Sets `end`, `value`, and
+    /// maintains `context` and `exprAllowed`, and skips the space after
+    /// the token, so that the next one's `start` will point at the
+    /// right position.
+    fn finishToken(&mut self, r#type: TokenType, value: Option<String>);
+
+    fn getTokenFromCode(&mut self, ch: char) -> Result<(), location::SyntaxError>;
+    fn finishOp(&mut self, r#type: TokenType, size: usize);
+    fn readRegexp(&mut self) -> Result<(), location::SyntaxError>;
+
+    /// Read an integer in the given radix. Returns `None` if zero digits
+    /// were read, or if `len` is given and not that many digits were
+    /// read.
+    fn readInt(&mut self, radix: u32, len: Option<usize>) -> Option<u32>;
+    fn readRadixNumber(&mut self, radix: u32) -> Result<(), location::SyntaxError>;
+
+    /// Read an integer, octal integer, or floating-point number.
+    fn readNumber(&mut self, startsWithDot: bool) -> Result<(), location::SyntaxError>;
+
+    /// Read a string value, interpreting backslash-escapes.
+    fn readCodePoint(&mut self) -> Result<u32, location::SyntaxError>;
+    fn readString(&mut self, quote: char) -> Result<(), location::SyntaxError>;
+
+    /// Reads template string tokens. An invalid escape sequence in a
+    /// template only makes it an `invalidTemplate` token, which tagged
+    /// templates allow.
+    fn tryReadTemplateToken(&mut self) -> Result<(), location::SyntaxError>;
+    fn invalidStringToken(&mut self, pos: usize, kind: ErrorKind) -> location::SyntaxError;
+    fn readTmplToken(&mut self) -> Result<(), location::SyntaxError>;
+    fn readInvalidTemplateToken(&mut self) -> Result<(), location::SyntaxError>;
+
+    /// Used to read escaped characters.
+    fn readEscapedChar(&mut self, inTemplate: bool) -> Result<String, location::SyntaxError>;
+
+    /// Used to read character escape sequences ('\x', '\u', '\U').
+    fn readHexChar(&mut self, len: usize) -> Result<u32, location::SyntaxError>;
+
+    /// Read an identifier, and return it as a string. Sets
+    /// `containsEsc` to whether the word contained a '\u' escape.
+    ///
+    /// Incrementally adds only escaped chars, adding other chunks
+    /// as-is as a micro-optimization.
+    fn readWord1(&mut self) -> Result<String, location::SyntaxError>;
+
+    /// Read an identifier or keyword token. Will check for reserved
+    /// words when necessary.
+    fn readWord(&mut self) -> Result<(), location::SyntaxError>;
 }
 
-impl Iterator for state::Parser {
-    type Item = Token;
+/// The byte at `pos`, if any. The tokenizer compares against ASCII
+/// only, so looking at single bytes is enough.
+fn byteAt(input: &str, pos: usize) -> Option<u8> {
+    input.as_bytes().get(pos).cloned()
+}
+
+fn codePointToString(code: u32) -> String {
+    std::char::from_u32(code)
+        .unwrap_or(std::char::REPLACEMENT_CHARACTER)
+        .to_string()
+}
+
+// The tokenizer can be iterated with `for token in &mut parser`; it
+// stops after the last token, or after the first error.
+impl Iterator for &mut state::Parser {
+    type Item = Result<Token, location::SyntaxError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(self.getToken())
+        if self.tokenizerDone {
+            return None;
+        }
+        match self.getToken() {
+            Ok(token) if token.r#type == TokenType::eof() => {
+                self.tokenizerDone = true;
+                None
+            }
+            Ok(token) => Some(Ok(token)),
+            Err(err) => {
+                self.tokenizerDone = true;
+                Some(Err(err))
+            }
+        }
     }
 }
 
 impl ParserTokenize for state::Parser {
+    fn next(&mut self) -> Result<(), location::SyntaxError> {
+        self.lastTokEnd = self.end;
+        self.lastTokStart = self.start;
+        self.lastTokEndLoc = self.endLoc;
+        self.lastTokStartLoc = self.startLoc;
+        self.nextToken()
+    }
+
+    fn getToken(&mut self) -> Result<Token, location::SyntaxError> {
+        self.next()?;
+        Ok(Token::new(self))
+    }
+
     fn curContext(&self) -> Option<&tokencontext::TokContext> {
         self.context.last()
     }
 
-    fn nextToken(&self) {
-        let curContext = self.curContext();
-        if curContext.is_none() || !curContext.unwrap().preserveSpace {
-            self.skipSpace();
+    fn nextToken(&mut self) -> Result<(), location::SyntaxError> {
+        let curContext = self.curContext().cloned();
+        if curContext.as_ref().is_none_or(|cx| !cx.preserveSpace) {
+            self.skipSpace()?;
         }
+
+        self.start = self.pos;
+        if self.options.locations {
+            self.startLoc = self.curPosition();
+        }
+        let ch = match self.fullCharAtPos() {
+            Some(ch) => ch,
+            None => {
+                self.finishToken(TokenType::eof(), None);
+                return Ok(());
+            }
+        };
+
+        if curContext == Some(TokContext::q_tmpl()) {
+            self.tryReadTemplateToken()
+        } else {
+            self.readToken(ch)
+        }
+    }
+
+    fn readToken(&mut self, ch: char) -> Result<(), location::SyntaxError> {
+        // Identifier or keyword. '\uXXXX' sequences are allowed in
+        // identifiers, so '\' also dispatches to that.
+        if identifier::isIdentifierStart(ch) || ch == '\\' {
+            return self.readWord();
+        }
+        self.getTokenFromCode(ch)
+    }
+
+    /// The character at the current position. Positions are always
+    /// byte offsets into `input`, whatever `Options.positionEncoding`
+    /// says; conversion happens only when offsets are reported.
+    fn fullCharAtPos(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
     }
 
-    // TODO(ryzokuken): why do they break and not return?
-    fn skipSpace(&self) {
-        while self.pos < self.input.len() {
-            let chars = self.input.encode_utf16();
-            let ch = chars.nth(self.pos).unwrap();
+    fn skipBlockComment(&mut self) -> Result<(), location::SyntaxError> {
+        let startLoc = self.curPosition();
+        let start = self.pos;
+        let end = match self.input[start + 2..].find("*/") {
+            Some(end) => start + 2 + end,
+            None => return self.raise(self.pos, ErrorKind::UnterminatedComment),
+        };
+        self.pos = end + 2;
+        if self.options.locations {
+            let loc = self.lineIndex.position(self.pos);
+            self.curLine = loc.line;
+            self.lineStart = self.pos - loc.column;
+        }
+        if self.options.collectComments {
+            let comment = Comment::new(self, true, start, self.pos, startLoc, self.curPosition());
+            self.comments.push(comment);
+        }
+        Ok(())
+    }
+
+    fn skipLineComment(&mut self, startSkip: usize) {
+        let start = self.pos;
+        let startLoc = self.curPosition();
+        self.pos += startSkip;
+        while let Some(ch) = self.fullCharAtPos() {
+            if whitespace::isNewLine(ch, false) {
+                break;
+            }
+            self.pos += ch.len_utf8();
+        }
+        if self.options.collectComments {
+            let mut comment = Comment::new(self, false, start, self.pos, startLoc, self.curPosition());
+            // `-->` and `<!--` comments start with more than two
+            // characters that are not part of their text.
+            comment.value = String::from(&self.input[start + startSkip..self.pos]);
+            self.comments.push(comment);
+        }
+    }
+
+    fn skipSpace(&mut self) -> Result<(), location::SyntaxError> {
+        while let Some(ch) = self.fullCharAtPos() {
             match ch {
-                32 | 160 => self.pos += 1,
-                13 => {
-                    if chars.nth(self.pos + 1).unwrap() == 10 {
+                ' ' | '\u{a0}' => self.pos += ch.len_utf8(),
+                '\r' | '\n' | '\u{2028}' | '\u{2029}' => {
+                    if ch == '\r' && byteAt(&self.input, self.pos + 1) == Some(b'\n') {
                         self.pos += 1;
                     }
-                }
-                10 | 8232 | 8233 => {
-                    self.pos += 1;
+                    self.pos += ch.len_utf8();
                     if self.options.locations {
                         self.curLine += 1;
                         self.lineStart = self.pos;
                     }
                 }
-                47 => match chars.nth(self.pos + 1).unwrap() {
-                    42 => self.skipBlockComment(),
-                    47 => self.skipLineComment(2),
-                    _ => return,
+                '/' => match byteAt(&self.input, self.pos + 1) {
+                    Some(b'*') => self.skipBlockComment()?,
+                    Some(b'/') => self.skipLineComment(2),
+                    _ => return Ok(()),
                 },
                 _ => {
-                    let chr = char::from_u32(ch as u32).unwrap();
-                    if ch > 8 && ch < 14
-                        || ch >= 5760 && chr.is_whitespace() && !chr.is_ascii_whitespace()
+                    if whitespace::isNonLineBreakSpace(ch) {
+                        self.pos += ch.len_utf8();
+                    } else {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finishToken(&mut self, r#type: TokenType, value: Option<String>) {
+        self.end = self.pos;
+        if self.options.locations {
+            self.endLoc = self.curPosition();
+        }
+        let prevType = std::mem::replace(&mut self.r#type, r#type);
+        self.value = value;
+        self.updateContext(&prevType);
+    }
+
+    fn getTokenFromCode(&mut self, ch: char) -> Result<(), location::SyntaxError> {
+        let next = byteAt(&self.input, self.pos + 1);
+        let ecma6 = self.options.ecmaVersion >= options::EcmaVersion::Ecma6;
+        let punctuation = match ch {
+            '(' => Some(TokenType::parenL()),
+            ')' => Some(TokenType::parenR()),
+            ';' => Some(TokenType::semi()),
+            ',' => Some(TokenType::comma()),
+            '[' => Some(TokenType::bracketL()),
+            ']' => Some(TokenType::bracketR()),
+            '{' => Some(TokenType::braceL()),
+            '}' => Some(TokenType::braceR()),
+            ':' => Some(TokenType::colon()),
+            '?' => Some(TokenType::question()),
+            '`' if ecma6 => Some(TokenType::backQuote()),
+            _ => None,
+        };
+        if let Some(r#type) = punctuation {
+            self.pos += 1;
+            self.finishToken(r#type, None);
+            return Ok(());
+        }
+        match ch {
+            // The interpretation of a dot depends on whether it is
+            // followed by a digit or another two dots.
+            '.' => {
+                if let Some(b'0'..=b'9') = next {
+                    return self.readNumber(true);
+                }
+                if ecma6 && next == Some(b'.') && byteAt(&self.input, self.pos + 2) == Some(b'.') {
+                    self.pos += 3;
+                    self.finishToken(TokenType::ellipsis(), None);
+                } else {
+                    self.pos += 1;
+                    self.finishToken(TokenType::dot(), None);
+                }
+                Ok(())
+            }
+            '0' => match next {
+                // '0x', '0X' - hex number
+                Some(b'x') | Some(b'X') => self.readRadixNumber(16),
+                // '0o', '0O' - octal number
+                Some(b'o') | Some(b'O') if ecma6 => self.readRadixNumber(8),
+                // '0b', '0B' - binary number
+                Some(b'b') | Some(b'B') if ecma6 => self.readRadixNumber(2),
+                _ => self.readNumber(false),
+            },
+            // Anything else beginning with a digit is an integer, octal
+            // number, or float.
+            '1'..='9' => self.readNumber(false),
+            '"' | '\'' => self.readString(ch),
+
+            // Operators are parsed inline in tiny state machines. '=' is
+            // handled specially, because it can also start an arrow.
+            '/' => {
+                if self.exprAllowed {
+                    self.pos += 1;
+                    return self.readRegexp();
+                }
+                if next == Some(b'=') {
+                    self.finishOp(TokenType::assign(), 2);
+                } else {
+                    self.finishOp(TokenType::slash(), 1);
+                }
+                Ok(())
+            }
+            '%' | '*' => {
+                let mut next = next;
+                let mut size = 1;
+                let mut r#type = if ch == '*' {
+                    TokenType::star()
+                } else {
+                    TokenType::modulo()
+                };
+                // exponentiation operator ** and **=
+                if self.options.ecmaVersion >= options::EcmaVersion::Ecma7
+                    && ch == '*'
+                    && next == Some(b'*')
+                {
+                    size += 1;
+                    r#type = TokenType::starstar();
+                    next = byteAt(&self.input, self.pos + 2);
+                }
+                if next == Some(b'=') {
+                    self.finishOp(TokenType::assign(), size + 1);
+                } else {
+                    self.finishOp(r#type, size);
+                }
+                Ok(())
+            }
+            '|' | '&' => {
+                let code = ch as u8;
+                if next == Some(code) {
+                    self.finishOp(
+                        if ch == '|' {
+                            TokenType::logicalOR()
+                        } else {
+                            TokenType::logicalAND()
+                        },
+                        2,
+                    );
+                } else if next == Some(b'=') {
+                    self.finishOp(TokenType::assign(), 2);
+                } else {
+                    self.finishOp(
+                        if ch == '|' {
+                            TokenType::bitwiseOR()
+                        } else {
+                            TokenType::bitwiseAND()
+                        },
+                        1,
+                    );
+                }
+                Ok(())
+            }
+            '^' => {
+                if next == Some(b'=') {
+                    self.finishOp(TokenType::assign(), 2);
+                } else {
+                    self.finishOp(TokenType::bitwiseXOR(), 1);
+                }
+                Ok(())
+            }
+            '+' | '-' => {
+                if next == Some(ch as u8) {
+                    if ch == '-'
+                        && !self.inModule
+                        && byteAt(&self.input, self.pos + 2) == Some(b'>')
+                        && (self.lastTokEnd == 0
+                            || whitespace::hasLineBreak(&self.input[self.lastTokEnd..self.pos]))
                     {
-                        self.pos += 1;
+                        // A `-->` line comment
+                        self.skipLineComment(3);
+                        self.skipSpace()?;
+                        return self.nextToken();
+                    }
+                    self.finishOp(TokenType::incDec(), 2);
+                } else if next == Some(b'=') {
+                    self.finishOp(TokenType::assign(), 2);
+                } else {
+                    self.finishOp(TokenType::plusMin(), 1);
+                }
+                Ok(())
+            }
+            '<' | '>' => {
+                let code = ch as u8;
+                if next == Some(code) {
+                    let size = if ch == '>' && byteAt(&self.input, self.pos + 2) == Some(b'>') {
+                        3
                     } else {
-                        return;
+                        2
+                    };
+                    if byteAt(&self.input, self.pos + size) == Some(b'=') {
+                        self.finishOp(TokenType::assign(), size + 1);
+                    } else {
+                        self.finishOp(TokenType::bitShift(), size);
                     }
+                    return Ok(());
+                }
+                if next == Some(b'!')
+                    && ch == '<'
+                    && !self.inModule
+                    && self.input[self.pos + 2..].starts_with("--")
+                {
+                    // `<!--`, an XML-style comment that should be
+                    // interpreted as a line comment
+                    self.skipLineComment(4);
+                    self.skipSpace()?;
+                    return self.nextToken();
                 }
+                let size = if next == Some(b'=') { 2 } else { 1 };
+                self.finishOp(TokenType::relational(), size);
+                Ok(())
             }
+            '=' | '!' => {
+                if next == Some(b'=') {
+                    let size = if byteAt(&self.input, self.pos + 2) == Some(b'=') {
+                        3
+                    } else {
+                        2
+                    };
+                    self.finishOp(TokenType::equality(), size);
+                } else if ch == '=' && next == Some(b'>') && ecma6 {
+                    // '=>'
+                    self.pos += 2;
+                    self.finishToken(TokenType::arrow(), None);
+                } else if ch == '=' {
+                    self.finishOp(TokenType::eq(), 1);
+                } else {
+                    self.finishOp(TokenType::prefix(), 1);
+                }
+                Ok(())
+            }
+            '~' => {
+                self.finishOp(TokenType::prefix(), 1);
+                Ok(())
+            }
+            _ => self.raise(self.pos, ErrorKind::UnexpectedCharacter(ch.to_string())),
         }
     }
 
-    // TODO(ryzokuken): contemplate life and decide if onComment is required.
-    fn skipLineComment(&self, startSkip: usize) {
+    fn finishOp(&mut self, r#type: TokenType, size: usize) {
+        let value = String::from(&self.input[self.pos..self.pos + size]);
+        self.pos += size;
+        self.finishToken(r#type, Some(value));
+    }
+
+    fn readRegexp(&mut self) -> Result<(), location::SyntaxError> {
+        let mut escaped = false;
+        let mut inClass = false;
         let start = self.pos;
-        // let startLoc = self.options.onComment && self.curPosition();
-        self.pos += startSkip;
-        let chars = self.input.chars();
-        let ch = chars.nth(self.pos);
-        while self.pos < self.input.len() && !whitespace::is_newline(ch.unwrap(), false) {
+        loop {
+            let ch = match self.fullCharAtPos() {
+                Some(ch) if !whitespace::isNewLine(ch, false) => ch,
+                _ => return self.raise(start, ErrorKind::UnterminatedRegexp),
+            };
+            if !escaped {
+                if ch == '[' {
+                    inClass = true;
+                } else if ch == ']' && inClass {
+                    inClass = false;
+                } else if ch == '/' && !inClass {
+                    break;
+                }
+                escaped = ch == '\\';
+            } else {
+                escaped = false;
+            }
+            self.pos += ch.len_utf8();
+        }
+        self.pos += 1;
+        let flagsStart = self.pos;
+        let flags = self.readWord1()?;
+        if self.containsEsc {
+            return self.raise(flagsStart, ErrorKind::UnexpectedToken);
+        }
+        let mut validFlags = String::from("gim");
+        if self.options.ecmaVersion >= options::EcmaVersion::Ecma6 {
+            validFlags.push_str("uy");
+        }
+        if self.options.ecmaVersion >= options::EcmaVersion::Ecma9 {
+            validFlags.push('s');
+        }
+        for (i, flag) in flags.char_indices() {
+            if !validFlags.contains(flag) || flags[i + flag.len_utf8()..].contains(flag) {
+                return self.raise(start, ErrorKind::InvalidRegexpFlags);
+            }
+        }
+        // The token's value is its source text, as in `Literal.raw`;
+        // `ast::literalValue` splits it into pattern and flags.
+        let value = String::from(&self.input[start - 1..self.pos]);
+        self.finishToken(TokenType::regexp(), Some(value));
+        Ok(())
+    }
+
+    fn readInt(&mut self, radix: u32, len: Option<usize>) -> Option<u32> {
+        let start = self.pos;
+        let mut total: u32 = 0;
+        while len.is_none_or(|len| self.pos - start < len) {
+            let value = match byteAt(&self.input, self.pos).and_then(|b| (b as char).to_digit(36)) {
+                Some(value) if value < radix => value,
+                _ => break,
+            };
             self.pos += 1;
-            ch = chars.nth(self.pos);
+            total = total.saturating_mul(radix).saturating_add(value);
         }
-        // if self.options.onComment.is_some() {
-        //     self.options.onComment(
-        //         false,
-        //         self.input.as_str()[start + startSkip..self.pos],
-        //         start,
-        //         self.pos,
-        //         startLoc,
-        //         self.curPosition(),
-        //     );
-        // }
+        if self.pos == start || len.is_some_and(|len| self.pos - start != len) {
+            return None;
+        }
+        Some(total)
     }
 
-    fn getToken(&self) -> Token {
-        self.r#next();
-        Token::new(self)
+    fn readRadixNumber(&mut self, radix: u32) -> Result<(), location::SyntaxError> {
+        let start = self.pos;
+        self.pos += 2; // 0x
+        if self.readInt(radix, None).is_none() {
+            return self.raise(self.start + 2, ErrorKind::InvalidNumber);
+        }
+        if self.options.ecmaVersion == options::EcmaVersion::Latest
+            && byteAt(&self.input, self.pos) == Some(b'n')
+        {
+            self.pos += 1;
+        } else if self.fullCharAtPos().is_some_and(identifier::isIdentifierStart) {
+            return self.raise(self.pos, ErrorKind::IdentifierAfterNumber);
+        }
+        let value = String::from(&self.input[start..self.pos]);
+        self.finishToken(TokenType::num(), Some(value));
+        Ok(())
     }
 
-    fn r#next(&self) {
-        // if self.options.onToken.is_some() { self.options.onToken(Token::new(self)) }
-        self.lastTokEnd = self.end;
-        self.lastTokStart = self.start;
-        self.lastTokEndLoc = self.endLoc;
-        self.lastTokStartLoc = self.startLoc;
-        self.nextToken();
+    fn readNumber(&mut self, startsWithDot: bool) -> Result<(), location::SyntaxError> {
+        let start = self.pos;
+        if !startsWithDot && self.readInt(10, None).is_none() {
+            return self.raise(start, ErrorKind::InvalidNumber);
+        }
+        let mut octal = self.pos - start >= 2 && byteAt(&self.input, start) == Some(b'0');
+        if octal && self.strict {
+            return self.raise(start, ErrorKind::StrictOctal);
+        }
+        if octal && self.input[start..self.pos].contains(['8', '9']) {
+            octal = false;
+        }
+        let mut next = byteAt(&self.input, self.pos);
+        if !octal
+            && !startsWithDot
+            && self.options.ecmaVersion == options::EcmaVersion::Latest
+            && next == Some(b'n')
+        {
+            self.pos += 1;
+            if self.fullCharAtPos().is_some_and(identifier::isIdentifierStart) {
+                return self.raise(self.pos, ErrorKind::IdentifierAfterNumber);
+            }
+            let value = String::from(&self.input[start..self.pos]);
+            self.finishToken(TokenType::num(), Some(value));
+            return Ok(());
+        }
+        if next == Some(b'.') && !octal {
+            self.pos += 1;
+            self.readInt(10, None);
+            next = byteAt(&self.input, self.pos);
+        }
+        if (next == Some(b'e') || next == Some(b'E')) && !octal {
+            self.pos += 1;
+            if let Some(b'+') | Some(b'-') = byteAt(&self.input, self.pos) {
+                self.pos += 1;
+            }
+            if self.readInt(10, None).is_none() {
+                return self.raise(start, ErrorKind::InvalidNumber);
+            }
+        }
+        if self.fullCharAtPos().is_some_and(identifier::isIdentifierStart) {
+            return self.raise(self.pos, ErrorKind::IdentifierAfterNumber);
+        }
+        let value = String::from(&self.input[start..self.pos]);
+        self.finishToken(TokenType::num(), Some(value));
+        Ok(())
+    }
+
+    fn readCodePoint(&mut self) -> Result<u32, location::SyntaxError> {
+        if byteAt(&self.input, self.pos) == Some(b'{') {
+            if self.options.ecmaVersion < options::EcmaVersion::Ecma6 {
+                return Err(self.error(self.pos, ErrorKind::UnexpectedToken));
+            }
+            self.pos += 1;
+            let codePos = self.pos;
+            let len = self.input[self.pos..].find('}').unwrap_or(0);
+            let code = self.readHexChar(len)?;
+            self.pos += 1;
+            if code > 0x10ffff {
+                return Err(self.invalidStringToken(codePos, ErrorKind::CodePointOutOfBounds));
+            }
+            Ok(code)
+        } else {
+            self.readHexChar(4)
+        }
+    }
+
+    fn readString(&mut self, quote: char) -> Result<(), location::SyntaxError> {
+        let mut out = String::new();
+        self.pos += 1;
+        let mut chunkStart = self.pos;
+        loop {
+            let ch = match self.fullCharAtPos() {
+                Some(ch) => ch,
+                None => return self.raise(self.start, ErrorKind::UnterminatedString),
+            };
+            if ch == quote {
+                break;
+            }
+            if ch == '\\' {
+                out.push_str(&self.input[chunkStart..self.pos]);
+                out.push_str(&self.readEscapedChar(false)?);
+                chunkStart = self.pos;
+            } else {
+                let ecma2019String = self.options.ecmaVersion >= options::EcmaVersion::Ecma10;
+                if whitespace::isNewLine(ch, ecma2019String) {
+                    return self.raise(self.start, ErrorKind::UnterminatedString);
+                }
+                self.pos += ch.len_utf8();
+            }
+        }
+        out.push_str(&self.input[chunkStart..self.pos]);
+        self.pos += 1;
+        self.finishToken(TokenType::string(), Some(out));
+        Ok(())
+    }
+
+    fn tryReadTemplateToken(&mut self) -> Result<(), location::SyntaxError> {
+        self.inTemplateElement = true;
+        self.invalidTemplateEscape = false;
+        let result = self.readTmplToken();
+        self.inTemplateElement = false;
+        match result {
+            Err(_) if self.invalidTemplateEscape => {
+                self.invalidTemplateEscape = false;
+                self.readInvalidTemplateToken()
+            }
+            result => result,
+        }
+    }
+
+    fn invalidStringToken(&mut self, pos: usize, kind: ErrorKind) -> location::SyntaxError {
+        if self.inTemplateElement && self.options.ecmaVersion >= options::EcmaVersion::Ecma9 {
+            self.invalidTemplateEscape = true;
+        }
+        self.error(pos, kind)
+    }
+
+    fn readTmplToken(&mut self) -> Result<(), location::SyntaxError> {
+        let mut out = String::new();
+        let mut chunkStart = self.pos;
+        loop {
+            let ch = match self.fullCharAtPos() {
+                Some(ch) => ch,
+                None => return self.raise(self.start, ErrorKind::UnterminatedTemplate),
+            };
+            if ch == '`' || ch == '$' && byteAt(&self.input, self.pos + 1) == Some(b'{') {
+                if self.pos == self.start
+                    && (self.r#type == TokenType::template()
+                        || self.r#type == TokenType::invalidTemplate())
+                {
+                    if ch == '$' {
+                        self.pos += 2;
+                        self.finishToken(TokenType::dollarBraceL(), None);
+                    } else {
+                        self.pos += 1;
+                        self.finishToken(TokenType::backQuote(), None);
+                    }
+                    return Ok(());
+                }
+                out.push_str(&self.input[chunkStart..self.pos]);
+                self.finishToken(TokenType::template(), Some(out));
+                return Ok(());
+            }
+            if ch == '\\' {
+                out.push_str(&self.input[chunkStart..self.pos]);
+                out.push_str(&self.readEscapedChar(true)?);
+                chunkStart = self.pos;
+            } else if whitespace::isNewLine(ch, false) {
+                out.push_str(&self.input[chunkStart..self.pos]);
+                self.pos += ch.len_utf8();
+                if ch == '\r' {
+                    if byteAt(&self.input, self.pos) == Some(b'\n') {
+                        self.pos += 1;
+                    }
+                    out.push('\n');
+                } else {
+                    out.push(ch);
+                }
+                if self.options.locations {
+                    self.curLine += 1;
+                    self.lineStart = self.pos;
+                }
+                chunkStart = self.pos;
+            } else {
+                self.pos += ch.len_utf8();
+            }
+        }
+    }
+
+    fn readInvalidTemplateToken(&mut self) -> Result<(), location::SyntaxError> {
+        while let Some(ch) = self.fullCharAtPos() {
+            match ch {
+                '\\' => self.pos += 1,
+                '$' if byteAt(&self.input, self.pos + 1) == Some(b'{') => {
+                    let value = String::from(&self.input[self.start..self.pos]);
+                    self.finishToken(TokenType::invalidTemplate(), Some(value));
+                    return Ok(());
+                }
+                '`' => {
+                    let value = String::from(&self.input[self.start..self.pos]);
+                    self.finishToken(TokenType::invalidTemplate(), Some(value));
+                    return Ok(());
+                }
+                _ => {}
+            }
+            // After a backslash, skip whatever character follows it.
+            self.pos += self.fullCharAtPos().map_or(0, char::len_utf8);
+        }
+        self.raise(self.start, ErrorKind::UnterminatedTemplate)
+    }
+
+    fn readEscapedChar(&mut self, inTemplate: bool) -> Result<String, location::SyntaxError> {
+        self.pos += 1;
+        let ch = match self.fullCharAtPos() {
+            Some(ch) => ch,
+            None => {
+                let kind = if inTemplate {
+                    ErrorKind::UnterminatedTemplate
+                } else {
+                    ErrorKind::UnterminatedString
+                };
+                return self.raise(self.start, kind).map(|()| String::new());
+            }
+        };
+        self.pos += ch.len_utf8();
+        Ok(match ch {
+            'n' => String::from("\n"),
+            'r' => String::from("\r"),
+            'x' => codePointToString(self.readHexChar(2)?),
+            'u' => {
+                let mut code = self.readCodePoint()?;
+                // A surrogate pair written as two escapes is one
+                // character.
+                if (0xd800..0xdc00).contains(&code) && self.input[self.pos..].starts_with("\\u") {
+                    let pos = self.pos;
+                    self.pos += 2;
+                    match self.readHexChar(4) {
+                        Ok(low) if (0xdc00..0xe000).contains(&low) => {
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        _ => self.pos = pos,
+                    }
+                }
+                codePointToString(code)
+            }
+            't' => String::from("\t"),
+            'b' => String::from("\u{8}"),
+            'v' => String::from("\u{b}"),
+            'f' => String::from("\u{c}"),
+            '\r' | '\n' => {
+                if ch == '\r' && byteAt(&self.input, self.pos) == Some(b'\n') {
+                    self.pos += 1;
+                }
+                if self.options.locations {
+                    self.lineStart = self.pos;
+                    self.curLine += 1;
+                }
+                String::new()
+            }
+            '0'..='7' => {
+                let digits = self.input[self.pos - 1..]
+                    .bytes()
+                    .take(3)
+                    .take_while(|b| (b'0'..=b'7').contains(b))
+                    .count();
+                let mut octalStr = &self.input[self.pos - 1..self.pos - 1 + digits];
+                let mut octal = u32::from_str_radix(octalStr, 8).unwrap_or(0);
+                if octal > 255 {
+                    octalStr = &octalStr[..octalStr.len() - 1];
+                    octal = u32::from_str_radix(octalStr, 8).unwrap_or(0);
+                }
+                let len = octalStr.len();
+                self.pos += len - 1;
+                let next = byteAt(&self.input, self.pos);
+                if (octalStr != "0" || next == Some(b'8') || next == Some(b'9'))
+                    && (self.strict || inTemplate)
+                {
+                    return Err(self.invalidStringToken(self.pos - 1 - len, ErrorKind::StrictOctal));
+                }
+                codePointToString(octal)
+            }
+            // Escaped U+2028 and U+2029 are line continuations.
+            '\u{2028}' | '\u{2029}' => String::new(),
+            ch => ch.to_string(),
+        })
+    }
+
+    fn readHexChar(&mut self, len: usize) -> Result<u32, location::SyntaxError> {
+        let codePos = self.pos;
+        match self.readInt(16, Some(len)) {
+            Some(n) => Ok(n),
+            None => Err(self.invalidStringToken(codePos, ErrorKind::BadEscape)),
+        }
+    }
+
+    fn readWord1(&mut self) -> Result<String, location::SyntaxError> {
+        self.containsEsc = false;
+        let mut word = String::new();
+        let mut first = true;
+        let mut chunkStart = self.pos;
+        while let Some(ch) = self.fullCharAtPos() {
+            if identifier::isIdentifierChar(ch) {
+                self.pos += ch.len_utf8();
+            } else if ch == '\\' {
+                self.containsEsc = true;
+                word.push_str(&self.input[chunkStart..self.pos]);
+                let escStart = self.pos;
+                self.pos += 1;
+                if byteAt(&self.input, self.pos) != Some(b'u') {
+                    return Err(self.invalidStringToken(self.pos, ErrorKind::InvalidUnicodeEscape));
+                }
+                self.pos += 1;
+                let esc = self.readCodePoint()?;
+                let valid = std::char::from_u32(esc).is_some_and(|esc| {
+                    if first {
+                        identifier::isIdentifierStart(esc)
+                    } else {
+                        identifier::isIdentifierChar(esc)
+                    }
+                });
+                if !valid {
+                    return Err(self.invalidStringToken(escStart, ErrorKind::InvalidUnicodeEscape));
+                }
+                word.push_str(&codePointToString(esc));
+                chunkStart = self.pos;
+            } else {
+                break;
+            }
+            first = false;
+        }
+        word.push_str(&self.input[chunkStart..self.pos]);
+        Ok(word)
+    }
+
+    fn readWord(&mut self) -> Result<(), location::SyntaxError> {
+        let word = self.readWord1()?;
+        let mut r#type = TokenType::name();
+        if identifier::isKeyword(&word, self.options.ecmaVersion, self.inModule) {
+            if self.containsEsc {
+                self.raiseRecoverable(self.start, ErrorKind::EscapedKeyword(word.clone()))?;
+            }
+            if let Some(keyword) = tokentype::keywords(&word) {
+                r#type = keyword;
+            }
+        }
+        self.finishToken(r#type, Some(word));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenizer(input: &str, encoding: options::PositionEncoding) -> state::Parser {
+        let options = options::Options::builder()
+            .locations(true)
+            .positionEncoding(encoding)
+            .build()
+            .unwrap();
+        match state::Parser::tokenizer(String::from(input), Some(options)) {
+            Ok(tokenizer) => tokenizer,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// The label, start and end of every token of `input`.
+    fn spans(
+        input: &str,
+        encoding: options::PositionEncoding,
+    ) -> Vec<(&'static str, usize, usize)> {
+        let mut tokenizer = tokenizer(input, encoding);
+        (&mut tokenizer)
+            .map(|token| {
+                let token = token.unwrap();
+                (token.r#type.label, token.start, token.end)
+            })
+            .collect()
+    }
+
+    /// The line and column each token of `input` starts at.
+    fn starts(input: &str, encoding: options::PositionEncoding) -> Vec<(usize, usize)> {
+        let mut tokenizer = tokenizer(input, encoding);
+        (&mut tokenizer)
+            .map(|token| {
+                let start = token.unwrap().loc.unwrap().start.unwrap();
+                (start.line, start.column)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reportsOffsetsInTheChosenEncoding() {
+        let input = "é = '😀' + ü;";
+        assert_eq!(
+            spans(input, options::PositionEncoding::Utf8),
+            [
+                ("name", 0, 2), ("=", 3, 4), ("string", 5, 11), ("+/-", 12, 13), ("name", 14, 16),
+                (";", 16, 17),
+            ]
+        );
+        assert_eq!(
+            spans(input, options::PositionEncoding::Utf16),
+            [
+                ("name", 0, 1), ("=", 2, 3), ("string", 4, 8), ("+/-", 9, 10), ("name", 11, 12),
+                (";", 12, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn skipsNonAsciiCommentsAndLineBreaks() {
+        let input = "/* ∑ */ a // ü\n\u{2028}b";
+        let utf8 = options::PositionEncoding::Utf8;
+        let utf16 = options::PositionEncoding::Utf16;
+        assert_eq!(spans(input, utf8), [("name", 10, 11), ("name", 21, 22)]);
+        assert_eq!(spans(input, utf16), [("name", 8, 9), ("name", 16, 17)]);
+        assert_eq!(starts(input, utf8), [(1, 10), (3, 0)]);
+        assert_eq!(starts(input, utf16), [(1, 8), (3, 0)]);
+    }
+
+    #[test]
+    fn readsNonAsciiTemplates() {
+        let input = "`😀${ü}`";
+        assert_eq!(
+            spans(input, options::PositionEncoding::Utf8),
+            [
+                ("`", 0, 1), ("template", 1, 5), ("${", 5, 7), ("name", 7, 9), ("}", 9, 10),
+                ("template", 10, 10), ("`", 10, 11),
+            ]
+        );
+        assert_eq!(
+            spans(input, options::PositionEncoding::Utf16),
+            [
+                ("`", 0, 1), ("template", 1, 3), ("${", 3, 5), ("name", 5, 6), ("}", 6, 7),
+                ("template", 7, 7), ("`", 7, 8),
+            ]
+        );
+    }
+
+    #[test]
+    fn reportsErrorsInTheChosenEncoding() {
+        let error = |encoding| {
+            let mut tokenizer = tokenizer("ü\n é '😀", encoding);
+            let err = (&mut tokenizer).find_map(Result::err).unwrap();
+            (err.kind, err.pos, err.loc.line, err.loc.column)
+        };
+        let unterminated = ErrorKind::UnterminatedString;
+        assert_eq!(error(options::PositionEncoding::Utf8), (unterminated.clone(), 7, 2, 4));
+        assert_eq!(error(options::PositionEncoding::Utf16), (unterminated, 5, 2, 3));
     }
 }
@@ -21,22 +21,21 @@
 // to know when parsing a label, in order to allow or disallow
 // continue jumps to that label.
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct TokenType {
-  label: String,
-  keyword: String,
-  beforeExpr: bool,
-  startsExpr: bool,
-  isLoop: bool,
+  pub label: &'static str,
+  pub keyword: &'static str,
+  pub beforeExpr: bool,
+  pub startsExpr: bool,
+  pub isLoop: bool,
   pub isAssign: bool,
   pub prefix: bool,
   pub postfix: bool,
   pub binop: Option<usize>,
 }
 
-#[derive(Default)]
 struct TokenTypeConfig {
-  keyword: String,
+  keyword: &'static str,
   beforeExpr: bool,
   startsExpr: bool,
   isLoop: bool,
@@ -46,41 +45,99 @@ struct TokenTypeConfig {
   binop: Option<usize>,
 }
 
-fn binop(name: &str, prec: usize) -> TokenType {
+const NONE: TokenTypeConfig = TokenTypeConfig {
+  keyword: "",
+  beforeExpr: false,
+  startsExpr: false,
+  isLoop: false,
+  isAssign: false,
+  prefix: false,
+  postfix: false,
+  binop: None,
+};
+
+const beforeExpr: TokenTypeConfig = TokenTypeConfig {
+  beforeExpr: true,
+  ..NONE
+};
+const startsExpr: TokenTypeConfig = TokenTypeConfig {
+  startsExpr: true,
+  ..NONE
+};
+const beforeAndStartsExpr: TokenTypeConfig = TokenTypeConfig {
+  beforeExpr: true,
+  startsExpr: true,
+  ..NONE
+};
+
+fn binop(name: &'static str, prec: usize) -> TokenType {
   TokenType::new(
     name,
     TokenTypeConfig {
       beforeExpr: true,
       binop: Some(prec),
-      ..Default::default()
+      ..NONE
     },
   )
 }
 
-static beforeExpr: TokenTypeConfig = TokenTypeConfig {
-  beforeExpr: true,
-  ..Default::default()
-};
-static startsExpr: TokenTypeConfig = TokenTypeConfig {
-  startsExpr: true,
-  ..Default::default()
-};
+/// Succinct definitions of keyword token types
+fn kw(name: &'static str, options: TokenTypeConfig) -> TokenType {
+  TokenType::new(
+    name,
+    TokenTypeConfig {
+      keyword: name,
+      ..options
+    },
+  )
+}
 
 /// Map keyword names to token types.
-static keywords: std::collections::HashMap<&str, TokenType> = std::collections::HashMap::new();
-
-/// Succinct definitions of keyword token types
-fn kw(name: &str, options: TokenTypeConfig) -> TokenType {
-  options.keyword = String::from(name);
-  let token = TokenType::new(name, options);
-  keywords[name] = token;
-  token
+pub fn keywords(name: &str) -> Option<TokenType> {
+  Some(match name {
+    "break" => TokenType::_break(),
+    "case" => TokenType::_case(),
+    "catch" => TokenType::_catch(),
+    "continue" => TokenType::_continue(),
+    "debugger" => TokenType::_debugger(),
+    "default" => TokenType::_default(),
+    "do" => TokenType::_do(),
+    "else" => TokenType::_else(),
+    "finally" => TokenType::_finally(),
+    "for" => TokenType::_for(),
+    "function" => TokenType::_function(),
+    "if" => TokenType::_if(),
+    "return" => TokenType::_return(),
+    "switch" => TokenType::_switch(),
+    "throw" => TokenType::_throw(),
+    "try" => TokenType::_try(),
+    "var" => TokenType::_var(),
+    "const" => TokenType::_const(),
+    "while" => TokenType::_while(),
+    "with" => TokenType::_with(),
+    "new" => TokenType::_new(),
+    "this" => TokenType::_this(),
+    "super" => TokenType::_super(),
+    "class" => TokenType::_class(),
+    "extends" => TokenType::_extends(),
+    "export" => TokenType::_export(),
+    "import" => TokenType::_import(),
+    "null" => TokenType::_null(),
+    "true" => TokenType::_true(),
+    "false" => TokenType::_false(),
+    "in" => TokenType::_in(),
+    "instanceof" => TokenType::_instanceof(),
+    "typeof" => TokenType::_typeof(),
+    "void" => TokenType::_void(),
+    "delete" => TokenType::_delete(),
+    _ => return None,
+  })
 }
 
 impl TokenType {
-  fn new(label: &str, conf: TokenTypeConfig) -> Self {
+  fn new(label: &'static str, conf: TokenTypeConfig) -> Self {
     TokenType {
-      label: String::from(label),
+      label,
       keyword: conf.keyword,
       beforeExpr: conf.beforeExpr,
       startsExpr: conf.startsExpr,
@@ -92,35 +149,73 @@ impl TokenType {
     }
   }
 
+  pub fn num() -> Self {
+    TokenType::new("num", startsExpr)
+  }
+  pub fn regexp() -> Self {
+    TokenType::new("regexp", startsExpr)
+  }
+  pub fn string() -> Self {
+    TokenType::new("string", startsExpr)
+  }
   pub fn name() -> Self {
     TokenType::new("name", startsExpr)
   }
   pub fn eof() -> Self {
-    TokenType::new("eof", Default::default())
+    TokenType::new("eof", NONE)
   }
 
   // Punctuation token types.
+  pub fn bracketL() -> Self {
+    TokenType::new("[", beforeAndStartsExpr)
+  }
+  pub fn bracketR() -> Self {
+    TokenType::new("]", NONE)
+  }
+  pub fn braceL() -> Self {
+    TokenType::new("{", beforeAndStartsExpr)
+  }
+  pub fn braceR() -> Self {
+    TokenType::new("}", NONE)
+  }
   pub fn parenL() -> Self {
-    TokenType::new(
-      "(",
-      TokenTypeConfig {
-        beforeExpr: true,
-        startsExpr: true,
-        ..Default::default()
-      },
-    )
+    TokenType::new("(", beforeAndStartsExpr)
+  }
+  pub fn parenR() -> Self {
+    TokenType::new(")", NONE)
   }
   pub fn comma() -> Self {
     TokenType::new(",", beforeExpr)
   }
+  pub fn semi() -> Self {
+    TokenType::new(";", beforeExpr)
+  }
   pub fn colon() -> Self {
     TokenType::new(":", beforeExpr)
   }
+  pub fn dot() -> Self {
+    TokenType::new(".", NONE)
+  }
   pub fn question() -> Self {
     TokenType::new("?", beforeExpr)
   }
-  pub fn semi() -> Self {
-    TokenType::new(";", beforeExpr)
+  pub fn arrow() -> Self {
+    TokenType::new("=>", beforeExpr)
+  }
+  pub fn template() -> Self {
+    TokenType::new("template", NONE)
+  }
+  pub fn invalidTemplate() -> Self {
+    TokenType::new("invalidTemplate", NONE)
+  }
+  pub fn ellipsis() -> Self {
+    TokenType::new("...", beforeExpr)
+  }
+  pub fn backQuote() -> Self {
+    TokenType::new("`", startsExpr)
+  }
+  pub fn dollarBraceL() -> Self {
+    TokenType::new("${", beforeAndStartsExpr)
   }
 
   // Operators. These carry several kinds of properties to help the
@@ -142,7 +237,17 @@ impl TokenType {
       TokenTypeConfig {
         beforeExpr: true,
         isAssign: true,
-        ..Default::default()
+        ..NONE
+      },
+    )
+  }
+  pub fn assign() -> Self {
+    TokenType::new(
+      "_=",
+      TokenTypeConfig {
+        beforeExpr: true,
+        isAssign: true,
+        ..NONE
       },
     )
   }
@@ -153,7 +258,18 @@ impl TokenType {
         prefix: true,
         postfix: true,
         startsExpr: true,
-        ..Default::default()
+        ..NONE
+      },
+    )
+  }
+  pub fn prefix() -> Self {
+    TokenType::new(
+      "!/~",
+      TokenTypeConfig {
+        beforeExpr: true,
+        prefix: true,
+        startsExpr: true,
+        ..NONE
       },
     )
   }
@@ -163,21 +279,209 @@ impl TokenType {
   pub fn logicalAND() -> Self {
     binop("&&", 2)
   }
-  pub fn starstar() -> Self {
-    TokenType::new("**", beforeExpr)
+  pub fn bitwiseOR() -> Self {
+    binop("|", 3)
+  }
+  pub fn bitwiseXOR() -> Self {
+    binop("^", 4)
+  }
+  pub fn bitwiseAND() -> Self {
+    binop("&", 5)
+  }
+  pub fn equality() -> Self {
+    binop("==/!=/===/!==", 6)
+  }
+  pub fn relational() -> Self {
+    binop("</>/<=/>=", 7)
+  }
+  pub fn bitShift() -> Self {
+    binop("<</>>/>>>", 8)
+  }
+  pub fn plusMin() -> Self {
+    TokenType::new(
+      "+/-",
+      TokenTypeConfig {
+        beforeExpr: true,
+        binop: Some(9),
+        prefix: true,
+        startsExpr: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn modulo() -> Self {
+    binop("%", 10)
   }
   pub fn star() -> Self {
-      binop("*", 10)
+    binop("*", 10)
+  }
+  pub fn slash() -> Self {
+    binop("/", 10)
+  }
+  pub fn starstar() -> Self {
+    TokenType::new("**", beforeExpr)
   }
 
   // Keyword token types.
+  pub fn _break() -> Self {
+    kw("break", NONE)
+  }
+  pub fn _case() -> Self {
+    kw("case", beforeExpr)
+  }
+  pub fn _catch() -> Self {
+    kw("catch", NONE)
+  }
+  pub fn _continue() -> Self {
+    kw("continue", NONE)
+  }
+  pub fn _debugger() -> Self {
+    kw("debugger", NONE)
+  }
+  pub fn _default() -> Self {
+    kw("default", beforeExpr)
+  }
+  pub fn _do() -> Self {
+    kw(
+      "do",
+      TokenTypeConfig {
+        isLoop: true,
+        beforeExpr: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn _else() -> Self {
+    kw("else", beforeExpr)
+  }
+  pub fn _finally() -> Self {
+    kw("finally", NONE)
+  }
+  pub fn _for() -> Self {
+    kw(
+      "for",
+      TokenTypeConfig {
+        isLoop: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn _function() -> Self {
+    kw("function", startsExpr)
+  }
+  pub fn _if() -> Self {
+    kw("if", NONE)
+  }
+  pub fn _return() -> Self {
+    kw("return", beforeExpr)
+  }
+  pub fn _switch() -> Self {
+    kw("switch", NONE)
+  }
+  pub fn _throw() -> Self {
+    kw("throw", beforeExpr)
+  }
+  pub fn _try() -> Self {
+    kw("try", NONE)
+  }
+  pub fn _var() -> Self {
+    kw("var", NONE)
+  }
+  pub fn _const() -> Self {
+    kw("const", NONE)
+  }
+  pub fn _while() -> Self {
+    kw(
+      "while",
+      TokenTypeConfig {
+        isLoop: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn _with() -> Self {
+    kw("with", NONE)
+  }
+  pub fn _new() -> Self {
+    kw("new", beforeAndStartsExpr)
+  }
+  pub fn _this() -> Self {
+    kw("this", startsExpr)
+  }
+  pub fn _super() -> Self {
+    kw("super", startsExpr)
+  }
+  pub fn _class() -> Self {
+    kw("class", startsExpr)
+  }
+  pub fn _extends() -> Self {
+    kw("extends", beforeExpr)
+  }
+  pub fn _export() -> Self {
+    kw("export", NONE)
+  }
+  pub fn _import() -> Self {
+    kw("import", startsExpr)
+  }
+  pub fn _null() -> Self {
+    kw("null", startsExpr)
+  }
+  pub fn _true() -> Self {
+    kw("true", startsExpr)
+  }
+  pub fn _false() -> Self {
+    kw("false", startsExpr)
+  }
   pub fn _in() -> Self {
     kw(
       "in",
       TokenTypeConfig {
         beforeExpr: true,
         binop: Some(7),
-        ..Default::default()
+        ..NONE
+      },
+    )
+  }
+  pub fn _instanceof() -> Self {
+    kw(
+      "instanceof",
+      TokenTypeConfig {
+        beforeExpr: true,
+        binop: Some(7),
+        ..NONE
+      },
+    )
+  }
+  pub fn _typeof() -> Self {
+    kw(
+      "typeof",
+      TokenTypeConfig {
+        beforeExpr: true,
+        prefix: true,
+        startsExpr: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn _void() -> Self {
+    kw(
+      "void",
+      TokenTypeConfig {
+        beforeExpr: true,
+        prefix: true,
+        startsExpr: true,
+        ..NONE
+      },
+    )
+  }
+  pub fn _delete() -> Self {
+    kw(
+      "delete",
+      TokenTypeConfig {
+        beforeExpr: true,
+        prefix: true,
+        startsExpr: true,
+        ..NONE
       },
     )
   }
@@ -0,0 +1,415 @@
+// Mutable traversal for codemods. `visitMut` walks a tree calling the
+// `ast::VisitMut` hook of each node's type; a hook can edit the node in
+// place and decide, through the `Change` it returns, whether the node
+// stays, goes, or is replaced by any number of nodes. Changes are
+// applied by the parent, so removing a statement from a `body` or
+// inserting one before it is just a matter of returning `Remove` or
+// `Replace(vec![new, old])`.
+
+use crate::ast;
+use crate::node;
+
+pub use ast::VisitMut;
+
+/// What to do with a visited node.
+pub enum Change {
+    /// Leave the node where it is, with any edits made to it.
+    Keep,
+    /// Take the node out of its parent. A position that must hold a
+    /// node gets an `EmptyStatement` instead if it holds a statement,
+    /// and an empty `BlockStatement` if it holds a block; in any other
+    /// required position, such as the operand of an expression, the
+    /// node is kept.
+    Remove,
+    /// Put these nodes in the node's place. In a list (`body`,
+    /// `arguments`, ...) they are spliced in. In a position that holds
+    /// a single node, several statements are wrapped in a
+    /// `BlockStatement`, several expressions in a `SequenceExpression`
+    /// and several `VariableDeclaration`s of one kind in a
+    /// `for` initializer are merged; elsewhere only the first is used.
+    /// No nodes at all is the same as `Remove`.
+    Replace(Vec<node::Node>),
+}
+
+/// Visit `node` with `visitor`, returning what its hook decided. The
+/// caller owns `node`, so applying that change is up to it.
+pub fn visitMut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut node::Node) -> Change {
+    visit(visitor, node, None)
+}
+
+fn visit<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut node::Node,
+    category: Option<&str>,
+) -> Change {
+    let hook = if node.r#type == ast::Property::TYPE && category == Some("ObjectPatternProperty") {
+        "AssignmentProperty"
+    } else {
+        node.r#type.as_str()
+    };
+    let hook = String::from(hook);
+    ast::dispatchMut(visitor, &hook, node)
+}
+
+/// A child field of a node, borrowed mutably.
+enum Slot<'a> {
+    One(&'a mut Option<Box<node::Node>>),
+    Many(&'a mut Vec<node::Node>),
+    /// A single node stored as the only element of a list; see
+    /// `node::Node.body`.
+    Single(&'a mut Vec<node::Node>),
+}
+
+/// Visit every child of `node`, applying the changes the hooks ask for.
+pub fn walkMut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut node::Node) {
+    // Each child field, with its category and whether it must hold a
+    // node. The fields of a type not in the node table are all taken to
+    // be optional.
+    let fields: Vec<(&'static str, &'static str, bool)> = match ast::fieldsOf(&node.r#type) {
+        Some(fields) => fields
+            .childFields()
+            .map(|(name, category)| (name, category, fields.shape(name) == Some(ast::Shape::Node)))
+            .collect(),
+        None => ast::NODE_FIELDS
+            .iter()
+            .map(|&name| (name, "", false))
+            .collect(),
+    };
+    let singleBody = matches!(ast::field(node, "body"), ast::FieldRef::Node(_));
+    for (name, category, required) in fields {
+        let slot = match slot(node, name, singleBody) {
+            Some(slot) => slot,
+            None => continue,
+        };
+        let category = if category.is_empty() {
+            None
+        } else {
+            Some(category)
+        };
+        match slot {
+            Slot::One(child) => {
+                if let Some(current) = child {
+                    let change = visit(visitor, current, category);
+                    apply(change, child, category, required);
+                }
+            }
+            Slot::Single(children) => {
+                let mut child = children.pop().map(Box::new);
+                if let Some(current) = &mut child {
+                    let change = visit(visitor, current, category);
+                    apply(change, &mut child, category, required);
+                }
+                // A single body is always required, so `child` is still
+                // there and the list does not end up empty.
+                children.extend(child.map(|child| *child));
+            }
+            Slot::Many(children) => {
+                let mut i = 0;
+                while i < children.len() {
+                    if children[i].isHole() {
+                        i += 1;
+                        continue;
+                    }
+                    match visit(visitor, &mut children[i], category) {
+                        Change::Keep => i += 1,
+                        Change::Remove => {
+                            children.remove(i);
+                        }
+                        Change::Replace(nodes) => {
+                            let len = nodes.len();
+                            children.splice(i..=i, nodes);
+                            i += len;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Apply `change` to a position holding a single node, which must
+/// hold one if `required`.
+fn apply(
+    change: Change,
+    child: &mut Option<Box<node::Node>>,
+    category: Option<&str>,
+    required: bool,
+) {
+    let mut nodes = match change {
+        Change::Keep => return,
+        Change::Remove => Vec::new(),
+        Change::Replace(nodes) => nodes,
+    };
+    if nodes.is_empty() && required {
+        // Leave a node that does nothing, or else the node itself.
+        if let (Some(category), Some(current)) = (category, child.as_deref()) {
+            if let Some(empty) = empty(category, current) {
+                *child = Some(Box::new(empty));
+            }
+        }
+        return;
+    }
+    *child = match nodes.len() {
+        0 => None,
+        1 => nodes.pop().map(Box::new),
+        _ => Some(Box::new(combine(nodes, category))),
+    }
+}
+
+/// A node doing nothing that can take the place of `current` in a
+/// position of `category`, if there is one.
+fn empty(category: &str, current: &node::Node) -> Option<node::Node> {
+    let r#type = match category {
+        "Statement" => ast::EmptyStatement::TYPE,
+        "BlockStatement" => ast::BlockStatement::TYPE,
+        _ => return None,
+    };
+    Some(node::Node {
+        r#type: String::from(r#type),
+        start: current.start,
+        end: current.end,
+        body: (r#type == ast::BlockStatement::TYPE).then(Vec::new),
+        ..node::Node::default()
+    })
+}
+
+/// Merge several nodes into one that can take a single node's place.
+fn combine(mut nodes: Vec<node::Node>, category: Option<&str>) -> node::Node {
+    let start = nodes[0].start;
+    let end = nodes[nodes.len() - 1].end;
+    let all = |category: &str| {
+        nodes
+            .iter()
+            .all(|node| ast::belongsTo(&node.r#type, category))
+    };
+    match category {
+        Some("Statement") => node::Node {
+            r#type: String::from(ast::BlockStatement::TYPE),
+            start,
+            end,
+            body: Some(nodes),
+            ..node::Node::default()
+        },
+        Some("Expression") | Some("ArrowBody") | Some("ForInit") | Some("ExpressionOrSpread")
+            if all("Expression") =>
+        {
+            node::Node {
+                r#type: String::from(ast::SequenceExpression::TYPE),
+                start,
+                end,
+                expressions: nodes,
+                ..node::Node::default()
+            }
+        }
+        Some("ForInit")
+            if all(ast::VariableDeclaration::TYPE)
+                && nodes.iter().all(|node| node.kind == nodes[0].kind) =>
+        {
+            let mut declarations = Vec::new();
+            for node in &mut nodes {
+                declarations.append(node.declarations.get_or_insert_with(Vec::new));
+            }
+            let mut merged = nodes.swap_remove(0);
+            merged.end = end;
+            merged.declarations = Some(declarations);
+            merged
+        }
+        _ => nodes.swap_remove(0),
+    }
+}
+
+/// Borrow the child field `name` of `node`.
+fn slot<'a>(node: &'a mut node::Node, name: &str, singleBody: bool) -> Option<Slot<'a>> {
+    let list = |children: &'a mut Option<Vec<node::Node>>| children.as_mut().map(Slot::Many);
+    Some(match name {
+        "body" if singleBody => Slot::Single(node.body.as_mut()?),
+        "body" => return list(&mut node.body),
+        "expressions" => Slot::Many(&mut node.expressions),
+        "properties" => return list(&mut node.properties),
+        "elements" => return list(&mut node.elements),
+        "declarations" => return list(&mut node.declarations),
+        "arguments" => return list(&mut node.arguments),
+        "params" => return list(&mut node.params),
+        "cases" => return list(&mut node.cases),
+        "quasis" => return list(&mut node.quasis),
+        "specifiers" => return list(&mut node.specifiers),
+        "local" => Slot::One(&mut node.local),
+        "left" => Slot::One(&mut node.left),
+        "right" => Slot::One(&mut node.right),
+        "argument" => Slot::One(&mut node.argument),
+        "value" => Slot::One(&mut node.value),
+        "key" => Slot::One(&mut node.key),
+        "expression" => Slot::One(&mut node.expression),
+        "test" => Slot::One(&mut node.test),
+        "consequent" if node.r#type == "SwitchCase" => return list(&mut node.consequents),
+        "consequent" => Slot::One(&mut node.consequent),
+        "alternate" => Slot::One(&mut node.alternate),
+        "id" => Slot::One(&mut node.id),
+        "init" => Slot::One(&mut node.init),
+        "object" => Slot::One(&mut node.object),
+        "property" => Slot::One(&mut node.property),
+        "callee" => Slot::One(&mut node.callee),
+        "label" => Slot::One(&mut node.label),
+        "block" => Slot::One(&mut node.block),
+        "handler" => Slot::One(&mut node.handler),
+        "finalizer" => Slot::One(&mut node.finalizer),
+        "param" => Slot::One(&mut node.param),
+        "discriminant" => Slot::One(&mut node.discriminant),
+        "update" => Slot::One(&mut node.update),
+        "tag" => Slot::One(&mut node.tag),
+        "quasi" => Slot::One(&mut node.quasi),
+        "superClass" => Slot::One(&mut node.superClass),
+        "meta" => Slot::One(&mut node.meta),
+        "source" => Slot::One(&mut node.source),
+        "imported" => Slot::One(&mut node.imported),
+        "exported" => Slot::One(&mut node.exported),
+        "declaration" => Slot::One(&mut node.declaration),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+
+    fn parse(input: &str) -> node::Node {
+        match state::Parser::parse(String::from(input), None) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// The statements of `input`.
+    fn statements(input: &str) -> Vec<node::Node> {
+        parse(input).body.unwrap_or_default()
+    }
+
+    /// The types of `node` and its descendants, with their names,
+    /// literal values and declaration kinds, nested as in the tree.
+    fn shape(node: &node::Node) -> String {
+        let mut label = node.r#type.clone();
+        for detail in [&node.name, &node.raw, &node.kind].iter().copied().flatten() {
+            label.push(' ');
+            label.push_str(detail);
+        }
+        let children: Vec<String> = node.children().into_iter().map(shape).collect();
+        if children.is_empty() {
+            label
+        } else {
+            format!("{}({})", label, children.join(", "))
+        }
+    }
+
+    /// The shape of `input` after `visitor` has run over it.
+    fn transform<V: VisitMut>(mut visitor: V, input: &str) -> String {
+        let mut program = parse(input);
+        walkMut(&mut visitor, &mut program);
+        shape(&program)
+    }
+
+    /// The shape of `input` as parsed.
+    fn expected(input: &str) -> String {
+        shape(&parse(input))
+    }
+
+    /// Removes every `debug(...)` statement and identifier `a`.
+    struct Strip;
+
+    impl VisitMut for Strip {
+        fn ExpressionStatement(&mut self, node: &mut node::Node) -> Change {
+            let callee = node
+                .expression
+                .as_ref()
+                .and_then(|call| call.callee.as_ref());
+            if callee.and_then(|callee| callee.name.as_deref()) == Some("debug") {
+                return Change::Remove;
+            }
+            walkMut(self, node);
+            Change::Keep
+        }
+
+        fn Identifier(&mut self, node: &mut node::Node) -> Change {
+            if node.name.as_deref() == Some("a") {
+                Change::Remove
+            } else {
+                Change::Keep
+            }
+        }
+    }
+
+    #[test]
+    fn removesFromListsAndOptionalPositions() {
+        assert_eq!(transform(Strip, "debug(); x(); debug();"), expected("x();"));
+        assert_eq!(transform(Strip, "f(a, b); [a, b];"), expected("f(b); [b];"));
+        assert_eq!(
+            transform(Strip, "function f() { return a; }"),
+            expected("function f() { return; }")
+        );
+    }
+
+    #[test]
+    fn removingARequiredStatementLeavesAnEmptyOne() {
+        assert_eq!(
+            transform(Strip, "if (x) debug(); else debug();"),
+            expected("if (x) ;")
+        );
+        assert_eq!(transform(Strip, "while (x) debug();"), expected("while (x) ;"));
+        assert_eq!(transform(Strip, "for (;;) debug();"), expected("for (;;) ;"));
+    }
+
+    #[test]
+    fn requiredExpressionsAreKept() {
+        assert_eq!(transform(Strip, "a + b;"), expected("a + b;"));
+        assert_eq!(transform(Strip, "() => a;"), expected("() => a;"));
+    }
+
+    /// Replaces each `for` initializer with `nodes`.
+    struct ReplaceInit {
+        nodes: Vec<node::Node>,
+    }
+
+    impl VisitMut for ReplaceInit {
+        fn ForStatement(&mut self, node: &mut node::Node) -> Change {
+            if let Some(init) = &mut node.init {
+                let mut init = Some(init.clone());
+                apply(
+                    Change::Replace(self.nodes.clone()),
+                    &mut init,
+                    Some("ForInit"),
+                    false,
+                );
+                node.init = init;
+            }
+            Change::Keep
+        }
+    }
+
+    #[test]
+    fn combinesForInitializers() {
+        let declarations = statements("let i = 0; let j = 1;");
+        assert_eq!(
+            transform(
+                ReplaceInit {
+                    nodes: declarations
+                },
+                "for (x;;) {}"
+            ),
+            expected("for (let i = 0, j = 1;;) {}")
+        );
+        let expressions = statements("i = 0; j = 1;")
+            .into_iter()
+            .filter_map(|statement| statement.expression.map(|expression| *expression))
+            .collect();
+        assert_eq!(
+            transform(ReplaceInit { nodes: expressions }, "for (x;;) {}"),
+            expected("for (i = 0, j = 1;;) {}")
+        );
+        // Declarations of different kinds cannot be merged.
+        let mixed = statements("let i = 0; var j = 1;");
+        assert_eq!(
+            transform(ReplaceInit { nodes: mixed }, "for (x;;) {}"),
+            expected("for (let i = 0;;) {}")
+        );
+    }
+}
@@ -0,0 +1,542 @@
+// AST walkers, after acorn-walk. Every walker is built on `base`,
+// which knows the children of every node type from the node table in
+// `ast.rs`, so adding a node type there is enough to have it walked.
+//
+// As in acorn-walk, a child is visited together with the category of
+// the position it appears in (`"Statement"`, `"Expression"`,
+// `"Pattern"`, `"ForInit"`, ...), and visitors can be registered for
+// categories as well as for node types: a visitor for `"Expression"`
+// is called for every node in expression position, after the visitor
+// for the node's own type.
+
+use crate::ast;
+use crate::node;
+
+use std::collections::HashMap;
+
+use ast::FieldRef;
+
+type Callback<'a, S> = Box<dyn FnMut(&node::Node, &mut S) + 'a>;
+type AncestorCallback<'a, S> = Box<dyn FnMut(&node::Node, &mut S, &[&node::Node]) + 'a>;
+type RecursiveFunc<'a, S> = Box<dyn Fn(&node::Node, &mut S, &Walker<S>) + 'a>;
+
+/// Call `c` on each child of `node`, with the category of the
+/// position it appears in. When `category` names a category rather
+/// than a node type, `node` itself is passed through to `c` instead:
+/// with its own type, as acorn-walk's `skipThrough` does, or, for the
+/// categories that unite others, with the one it belongs to.
+///
+/// Node types that are not in the node table have their children
+/// visited in the order of `node::Node::children`, without a category.
+pub fn base<'n, F>(node: &'n node::Node, category: Option<&str>, mut c: F)
+where
+    F: FnMut(&'n node::Node, Option<&'static str>),
+{
+    if let Some(category) = category.filter(|category| ast::isCategory(category)) {
+        c(node, narrow(category, &node.r#type));
+        return;
+    }
+    match ast::fieldsOf(&node.r#type) {
+        Some(fields) => {
+            for (name, category) in fields.childFields() {
+                match ast::field(node, name) {
+                    FieldRef::Node(child) => c(child, Some(category)),
+                    FieldRef::Nodes(children) => {
+                        for child in children.iter().filter(|child| !child.isHole()) {
+                            c(child, Some(category));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None => {
+            for child in node.children() {
+                c(child, None);
+            }
+        }
+    }
+}
+
+/// The category a node of type `r#type` in a position of `category`
+/// is visited as next, as acorn-walk's `ForInit` and `Program` do:
+/// `Statement`, `Expression` or `Pattern` for the categories that unite
+/// them with others, and `None`, its own type, otherwise.
+fn narrow(category: &str, r#type: &str) -> Option<&'static str> {
+    match (category, r#type) {
+        ("ModuleItem", _) => Some("Statement"),
+        ("ForInit", "VariableDeclaration") | ("ForInLeft", "VariableDeclaration") => None,
+        ("ForInit", _) => Some("Expression"),
+        ("ForInLeft", _) => Some("Pattern"),
+        ("ArrowBody", "BlockStatement") => Some("Statement"),
+        ("ArrowBody", _) => Some("Expression"),
+        ("ExpressionOrSpread", "SpreadElement") => None,
+        ("ExpressionOrSpread", _) => Some("Expression"),
+        ("ExportDefaultKind", "FunctionDeclaration")
+        | ("ExportDefaultKind", "ClassDeclaration") => Some("Statement"),
+        ("ExportDefaultKind", _) => Some("Expression"),
+        _ => None,
+    }
+}
+
+/// The type a node is visited as: its category, if it has one, or its
+/// own type.
+fn typeOf<'n>(node: &'n node::Node, category: Option<&'static str>) -> &'n str {
+    category.unwrap_or(&node.r#type)
+}
+
+/// Callbacks for `simple`, keyed by node type or category.
+pub struct Visitors<'a, S> {
+    callbacks: HashMap<&'static str, Callback<'a, S>>,
+}
+
+impl<'a, S> Default for Visitors<'a, S> {
+    fn default() -> Self {
+        Visitors {
+            callbacks: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, S> Visitors<'a, S> {
+    pub fn new() -> Self {
+        Visitors::default()
+    }
+
+    /// Call `callback` for every node of type or category `r#type`.
+    pub fn on<F>(mut self, r#type: &'static str, callback: F) -> Self
+    where
+        F: FnMut(&node::Node, &mut S) + 'a,
+    {
+        self.callbacks.insert(r#type, Box::new(callback));
+        self
+    }
+}
+
+/// Callbacks for `ancestor`, keyed by node type or category.
+pub struct AncestorVisitors<'a, S> {
+    callbacks: HashMap<&'static str, AncestorCallback<'a, S>>,
+}
+
+impl<'a, S> Default for AncestorVisitors<'a, S> {
+    fn default() -> Self {
+        AncestorVisitors {
+            callbacks: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, S> AncestorVisitors<'a, S> {
+    pub fn new() -> Self {
+        AncestorVisitors::default()
+    }
+
+    /// Call `callback` for every node of type or category `r#type`.
+    pub fn on<F>(mut self, r#type: &'static str, callback: F) -> Self
+    where
+        F: FnMut(&node::Node, &mut S, &[&node::Node]) + 'a,
+    {
+        self.callbacks.insert(r#type, Box::new(callback));
+        self
+    }
+}
+
+/// A simple walk is one where you simply specify callbacks to be
+/// called on specific nodes. A callback is called after the node's
+/// children have been walked.
+pub fn simple<S>(node: &node::Node, visitors: &mut Visitors<S>, state: &mut S) {
+    simpleAt(node, None, visitors, state);
+}
+
+fn simpleAt<S>(
+    node: &node::Node,
+    category: Option<&'static str>,
+    visitors: &mut Visitors<S>,
+    state: &mut S,
+) {
+    base(node, category, |child, category| {
+        simpleAt(child, category, visitors, state)
+    });
+    if let Some(callback) = visitors.callbacks.get_mut(typeOf(node, category)) {
+        callback(node, state);
+    }
+}
+
+/// An ancestor walk keeps an array of ancestor nodes (including the
+/// current node) and passes them to the callback as third parameter.
+pub fn ancestor<S>(node: &node::Node, visitors: &mut AncestorVisitors<S>, state: &mut S) {
+    let mut ancestors = Vec::new();
+    ancestorAt(node, None, visitors, state, &mut ancestors);
+}
+
+fn ancestorAt<'n, S>(
+    node: &'n node::Node,
+    category: Option<&'static str>,
+    visitors: &mut AncestorVisitors<S>,
+    state: &mut S,
+    ancestors: &mut Vec<&'n node::Node>,
+) {
+    // A node passed through from its category is already on the stack.
+    let isNew = ancestors
+        .last()
+        .is_none_or(|&last| !std::ptr::eq(last, node));
+    if isNew {
+        ancestors.push(node);
+    }
+    base(node, category, |child, category| {
+        ancestorAt(child, category, visitors, state, ancestors)
+    });
+    if let Some(callback) = visitors.callbacks.get_mut(typeOf(node, category)) {
+        callback(node, state, ancestors);
+    }
+    if isNew {
+        ancestors.pop();
+    }
+}
+
+/// Functions for `recursive`, keyed by node type or category. Each
+/// function is responsible for continuing the walk into the children
+/// it wants visited, through the `Walker` it is given.
+pub struct RecursiveVisitors<'a, S> {
+    funcs: HashMap<&'static str, RecursiveFunc<'a, S>>,
+}
+
+impl<'a, S> Default for RecursiveVisitors<'a, S> {
+    fn default() -> Self {
+        RecursiveVisitors {
+            funcs: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, S> RecursiveVisitors<'a, S> {
+    pub fn new() -> Self {
+        RecursiveVisitors::default()
+    }
+
+    /// Handle nodes of type or category `r#type` with `func` instead
+    /// of `base`.
+    pub fn on<F>(mut self, r#type: &'static str, func: F) -> Self
+    where
+        F: Fn(&node::Node, &mut S, &Walker<S>) + 'a,
+    {
+        self.funcs.insert(r#type, Box::new(func));
+        self
+    }
+}
+
+/// Continues a `recursive` walk.
+pub struct Walker<'w, 'a, S> {
+    visitors: &'w RecursiveVisitors<'a, S>,
+}
+
+impl<'w, 'a, S> Walker<'w, 'a, S> {
+    /// Walk `node`, as a node of `category` when one is given.
+    pub fn walk(&self, node: &node::Node, state: &mut S, category: Option<&'static str>) {
+        match self.visitors.funcs.get(typeOf(node, category)) {
+            Some(func) => func(node, state, self),
+            None => self.children(node, state, category),
+        }
+    }
+
+    /// Walk the children of `node`, as `base` would.
+    pub fn children(&self, node: &node::Node, state: &mut S, category: Option<&'static str>) {
+        base(node, category, |child, category| {
+            self.walk(child, state, category)
+        });
+    }
+}
+
+/// A recursive walk is one where your functions override the default
+/// walkers. They can modify and replace the state parameter that's
+/// threaded through the walk, and can opt how and whether to walk
+/// their child nodes (by calling their third argument on these nodes).
+pub fn recursive<S>(node: &node::Node, state: &mut S, visitors: &RecursiveVisitors<S>) {
+    Walker { visitors }.walk(node, state, None);
+}
+
+/// A full walk triggers the callback on each node, with its type.
+pub fn full<S, F>(node: &node::Node, mut callback: F, state: &mut S)
+where
+    F: FnMut(&node::Node, &mut S, &str),
+{
+    fullAt(node, None, &mut callback, state);
+}
+
+fn fullAt<S, F>(node: &node::Node, category: Option<&'static str>, callback: &mut F, state: &mut S)
+where
+    F: FnMut(&node::Node, &mut S, &str),
+{
+    base(node, category, |child, category| {
+        fullAt(child, category, callback, state)
+    });
+    // Passing through a category visits the node again with its own
+    // type; only report it once.
+    if !category.is_some_and(ast::isCategory) {
+        callback(node, state, &node.r#type);
+    }
+}
+
+/// A full walk that also passes the ancestors of each node, including
+/// the node itself.
+pub fn fullAncestor<S, F>(node: &node::Node, mut callback: F, state: &mut S)
+where
+    F: FnMut(&node::Node, &mut S, &[&node::Node]),
+{
+    let mut ancestors = Vec::new();
+    fullAncestorAt(node, None, &mut callback, state, &mut ancestors);
+}
+
+fn fullAncestorAt<'n, S, F>(
+    node: &'n node::Node,
+    category: Option<&'static str>,
+    callback: &mut F,
+    state: &mut S,
+    ancestors: &mut Vec<&'n node::Node>,
+) where
+    F: FnMut(&node::Node, &mut S, &[&node::Node]),
+{
+    let passThrough = category.is_some_and(ast::isCategory);
+    if !passThrough {
+        ancestors.push(node);
+    }
+    base(node, category, |child, category| {
+        fullAncestorAt(child, category, callback, state, ancestors)
+    });
+    if !passThrough {
+        callback(node, state, ancestors);
+        ancestors.pop();
+    }
+}
+
+/// Find a node with a given start, end, and type (all are optional,
+/// `None` can be used as wildcard). Returns the innermost matching
+/// node. `test` is given the type or category the node is visited as,
+/// and the node.
+pub fn findNodeAt<F>(
+    node: &node::Node,
+    start: Option<usize>,
+    end: Option<usize>,
+    mut test: F,
+) -> Option<&node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    findAt(node, None, start, end, &mut test)
+}
+
+fn findAt<'n, F>(
+    node: &'n node::Node,
+    category: Option<&'static str>,
+    start: Option<usize>,
+    end: Option<usize>,
+    test: &mut F,
+) -> Option<&'n node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    let mut found = None;
+    if start.is_none_or(|start| node.start <= start) && end.is_none_or(|end| node.end >= end) {
+        base(node, category, |child, category| {
+            if found.is_none() {
+                found = findAt(child, category, start, end, test);
+            }
+        });
+    }
+    if found.is_none()
+        && start.is_none_or(|start| node.start == start)
+        && end.is_none_or(|end| node.end == end)
+        && test(typeOf(node, category), node)
+    {
+        found = Some(node);
+    }
+    found
+}
+
+/// Find the innermost node of a given type that contains the given
+/// position.
+pub fn findNodeAround<F>(
+    node: &node::Node,
+    pos: usize,
+    mut test: F,
+) -> Option<&node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    findAround(node, None, pos, &mut test)
+}
+
+fn findAround<'n, F>(
+    node: &'n node::Node,
+    category: Option<&'static str>,
+    pos: usize,
+    test: &mut F,
+) -> Option<&'n node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    if node.start > pos || node.end < pos {
+        return None;
+    }
+    let mut found = None;
+    base(node, category, |child, category| {
+        if found.is_none() {
+            found = findAround(child, category, pos, test);
+        }
+    });
+    if found.is_none() && test(typeOf(node, category), node) {
+        found = Some(node);
+    }
+    found
+}
+
+/// Find the outermost matching node after a given position.
+pub fn findNodeAfter<F>(node: &node::Node, pos: usize, mut test: F) -> Option<&node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    findAfter(node, None, pos, &mut test)
+}
+
+fn findAfter<'n, F>(
+    node: &'n node::Node,
+    category: Option<&'static str>,
+    pos: usize,
+    test: &mut F,
+) -> Option<&'n node::Node>
+where
+    F: FnMut(&str, &node::Node) -> bool,
+{
+    if node.end < pos {
+        return None;
+    }
+    if node.start >= pos && test(typeOf(node, category), node) {
+        return Some(node);
+    }
+    let mut found = None;
+    base(node, category, |child, category| {
+        if found.is_none() {
+            found = findAfter(child, category, pos, test);
+        }
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options;
+    use crate::state;
+
+    fn parse(input: &str) -> node::Node {
+        let options = options::Options::builder()
+            .sourceType(options::SourceType::Module)
+            .build()
+            .unwrap();
+        match state::Parser::parse(String::from(input), Some(options)) {
+            Ok(program) => program,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// The types of the nodes `simple` visits as `category`, in order.
+    fn visitedAs(input: &str, category: &'static str) -> Vec<String> {
+        let program = parse(input);
+        let mut visitors = Visitors::new().on(category, |node, types: &mut Vec<String>| {
+            types.push(node.r#type.clone())
+        });
+        let mut types = Vec::new();
+        simple(&program, &mut visitors, &mut types);
+        types
+    }
+
+    #[test]
+    fn unionCategoriesAreVisitedAsWhatTheyHold() {
+        assert_eq!(
+            visitedAs("import a from 'a'; a;", "Statement"),
+            ["ImportDeclaration", "ExpressionStatement"]
+        );
+        assert_eq!(visitedAs("for (x in y);", "Pattern"), ["Identifier"]);
+        assert_eq!(
+            visitedAs("for (x = 0;;);", "Expression"),
+            ["Literal", "AssignmentExpression"]
+        );
+        assert_eq!(
+            visitedAs("() => a;", "Expression"),
+            ["Identifier", "ArrowFunctionExpression"]
+        );
+        assert_eq!(
+            visitedAs("() => {};", "Statement"),
+            ["BlockStatement", "ExpressionStatement"]
+        );
+        assert_eq!(
+            visitedAs("f(a, ...b);", "Expression"),
+            ["Identifier", "Identifier", "Identifier", "CallExpression"]
+        );
+        assert_eq!(
+            visitedAs("export default function () {}", "Statement"),
+            ["FunctionDeclaration", "ExportDefaultDeclaration"]
+        );
+        assert_eq!(visitedAs("export default 1;", "Expression"), ["Literal"]);
+    }
+
+    #[test]
+    fn fullReportsEachNodeOnce() {
+        let program = parse("for (let i of a) f(i, ...b);");
+        let mut types = Vec::new();
+        full(
+            &program,
+            |_, types: &mut Vec<String>, r#type| types.push(String::from(r#type)),
+            &mut types,
+        );
+        assert_eq!(
+            types,
+            [
+                "Identifier",
+                "VariableDeclarator",
+                "VariableDeclaration",
+                "Identifier",
+                "Identifier",
+                "Identifier",
+                "Identifier",
+                "SpreadElement",
+                "CallExpression",
+                "ExpressionStatement",
+                "ForOfStatement",
+                "Program",
+            ]
+        );
+    }
+
+    #[test]
+    fn ancestorsIncludeTheNode() {
+        let program = parse("a + b;");
+        let mut visitors = AncestorVisitors::new().on(
+            "Identifier",
+            |node, depths: &mut Vec<(String, usize)>, ancestors| {
+                assert!(std::ptr::eq(*ancestors.last().unwrap(), node));
+                depths.push((node.name.clone().unwrap(), ancestors.len()))
+            },
+        );
+        let mut depths = Vec::new();
+        ancestor(&program, &mut visitors, &mut depths);
+        assert_eq!(depths, [(String::from("a"), 4), (String::from("b"), 4)]);
+    }
+
+    #[test]
+    fn findsNodes() {
+        let program = parse("let x = a + b;");
+        let any = |_: &str, _: &node::Node| true;
+        assert_eq!(
+            findNodeAt(&program, Some(8), Some(13), any).map(|node| node.r#type.as_str()),
+            Some("BinaryExpression")
+        );
+        let found = findNodeAround(&program, 12, |r#type, _| r#type == "Expression");
+        assert_eq!(found.and_then(|node| node.name.as_deref()), Some("b"));
+        let found = findNodeAfter(&program, 5, |r#type, _| r#type == "Expression");
+        assert_eq!(
+            found.map(|node| node.r#type.as_str()),
+            Some("BinaryExpression")
+        );
+    }
+}
@@ -1,8 +1,72 @@
-use regex::Regex;
+// Matches a whole line break (where CRLF is considered a single
+// line break). Used to count lines.
 
-pub const lineBreak: Regex = Regex::new(r"/\r\n?|\n|\u2028|\u2029/").unwrap();
+pub fn isNewLine(ch: char, ecma2019String: bool) -> bool {
+    ch == '\n' || ch == '\r' || (!ecma2019String && (ch == '\u{2028}' || ch == '\u{2029}'))
+}
+
+/// Whether `text` contains a line break, as acorn's `lineBreak`
+/// regular expression tests.
+pub fn hasLineBreak(text: &str) -> bool {
+    text.chars().any(|ch| isNewLine(ch, false))
+}
+
+/// The length of the line break starting at the beginning of `text`,
+/// counting `\r\n` as one.
+pub fn lineBreakLength(text: &str) -> Option<usize> {
+    let ch = text.chars().next()?;
+    if !isNewLine(ch, false) {
+        return None;
+    }
+    if text.starts_with("\r\n") {
+        return Some(2);
+    }
+    Some(ch.len_utf8())
+}
+
+/// Whitespace that is not a line break.
+pub fn isNonLineBreakSpace(ch: char) -> bool {
+    matches!(
+        ch,
+        '\t' | '\u{b}'
+            | '\u{c}'
+            | ' '
+            | '\u{a0}'
+            | '\u{feff}'
+            | '\u{1680}'
+            | '\u{2000}'..='\u{200a}'
+            | '\u{202f}'
+            | '\u{205f}'
+            | '\u{3000}'
+    )
+}
 
-pub fn is_newline(ch: char, ecma_2019_string: bool) -> bool {
-    let code = ch as u16;
-    code == 10 || code == 13 || (!ecma_2019_string && (code == 0x2028 || code == 0x2029))
+/// Skip whitespace and comments from `pos`, as acorn's
+/// `skipWhiteSpace` regular expression does, and return the offset of
+/// the first character after them.
+pub fn skipWhiteSpace(input: &str, mut pos: usize) -> usize {
+    loop {
+        let rest = &input[pos..];
+        if let Some(ch) = rest.chars().next() {
+            if isNonLineBreakSpace(ch) || isNewLine(ch, false) {
+                pos += ch.len_utf8();
+                continue;
+            }
+        }
+        if rest.starts_with("//") {
+            pos += rest
+                .char_indices()
+                .find(|&(_, ch)| isNewLine(ch, false))
+                .map_or(rest.len(), |(i, _)| i);
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(end) => pos += end + 4,
+                None => return input.len(),
+            }
+            continue;
+        }
+        return pos;
+    }
 }